@@ -0,0 +1,156 @@
+use crate::capture::CAPTURE_NAME;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+/// what to do about other agents changing the same volumes
+///
+/// `warn` logs them and carries on. `refuse` exits at startup if one is
+/// running. `defer` keeps hands off while one is present. `coordinate`
+/// leaves streams of WirePlumber's ducked roles alone and yields to a
+/// pw-duck that was started first.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictPolicy {
+    #[default]
+    Warn,
+    Refuse,
+    Defer,
+    Coordinate,
+}
+
+/// another volume-changing agent, seen in the registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Agent {
+    /// capture stream of another pw-duck
+    PwDuck { pid: String, serial: u64 },
+    /// WirePlumber role loopback, ducks by media.role
+    RoleDucking { node: String, roles: Vec<String> },
+}
+
+impl Agent {
+    /// from node properties, None for anything else
+    pub fn from_props(
+        media_class: &str,
+        own_pid: &str,
+        prop: impl Fn(&str) -> Option<String>,
+    ) -> Option<Self> {
+        let node = prop("node.name").unwrap_or_default();
+        match media_class {
+            "Stream/Input/Audio" if prop("media.name").as_deref() == Some(CAPTURE_NAME) => {
+                let pid = prop("application.process.id").unwrap_or_else(|| "-".into());
+                (pid != own_pid).then(|| Self::PwDuck {
+                    pid,
+                    serial: serial(&prop),
+                })
+            }
+            "Audio/Sink"
+                if node.starts_with("loopback.sink.role.")
+                    || prop("policy.role-based.priority").is_some() =>
+            {
+                let roles = prop("device.intended-roles")
+                    .map(|r| {
+                        r.split([' ', ','])
+                            .filter(|r| !r.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_else(|| {
+                        node.strip_prefix("loopback.sink.role.")
+                            .map(|r| vec![r.to_string()])
+                            .unwrap_or_default()
+                    });
+                Some(Self::RoleDucking { node, roles })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::PwDuck { pid, .. } => format!("another pw-duck (pid {pid})"),
+            Self::RoleDucking { node, .. } => format!("WirePlumber role ducking ({node})"),
+        }
+    }
+}
+
+fn serial(prop: &impl Fn(&str) -> Option<String>) -> u64 {
+    prop("object.serial")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(u64::MAX)
+}
+
+/// agents present, by global id
+#[derive(Debug, Default)]
+pub struct Agents {
+    seen: RefCell<BTreeMap<u32, Agent>>,
+    /// serial of our own capture stream
+    own_serial: Cell<Option<u64>>,
+    changed: Cell<bool>,
+}
+
+impl Agents {
+    pub fn add(&self, id: u32, agent: Agent) {
+        warn!("{} also changes volumes, see --conflict", agent.describe());
+        self.seen.borrow_mut().insert(id, agent);
+        self.changed.set(true);
+    }
+
+    pub fn remove(&self, id: u32) {
+        if let Some(agent) = self.seen.borrow_mut().remove(&id) {
+            info!("{} gone", agent.describe());
+            self.changed.set(true);
+        }
+    }
+
+    pub fn set_own_serial(&self, prop: impl Fn(&str) -> Option<String>) {
+        self.own_serial.set(Some(serial(&prop)));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.borrow().is_empty()
+    }
+
+    /// one line for logs and errors
+    pub fn describe(&self) -> String {
+        self.seen
+            .borrow()
+            .values()
+            .map(Agent::describe)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// set or removed since the last call
+    pub fn take_changed(&self) -> bool {
+        self.changed.replace(false)
+    }
+
+    /// hands off under this policy
+    pub fn defers(&self, policy: ConflictPolicy) -> bool {
+        let seen = self.seen.borrow();
+        match policy {
+            ConflictPolicy::Defer => !seen.is_empty(),
+            // the instance started first keeps ducking
+            ConflictPolicy::Coordinate => {
+                let own = self.own_serial.get().unwrap_or(u64::MAX);
+                seen.values()
+                    .any(|a| matches!(a, Agent::PwDuck { serial, .. } if *serial < own))
+            }
+            ConflictPolicy::Warn | ConflictPolicy::Refuse => false,
+        }
+    }
+
+    /// media roles WirePlumber ducks itself
+    pub fn ducked_roles(&self) -> Vec<String> {
+        self.seen
+            .borrow()
+            .values()
+            .filter_map(|a| match a {
+                Agent::RoleDucking { roles, .. } => Some(roles.clone()),
+                Agent::PwDuck { .. } => None,
+            })
+            .flatten()
+            .collect()
+    }
+}
@@ -1,7 +1,10 @@
 use crate::ducking::RestoreGuard;
+use crate::events::{self, Event};
+use crate::messages;
 use crate::ControlMode;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// atomic f32 bits
 #[derive(Debug)]
@@ -25,6 +28,32 @@ pub struct VadState {
     pub last_above: Option<Instant>,
     pub above_start: Option<Instant>,
     pub voice_active: bool,
+    /// soft-knee duck depth (0..1)
+    pub duck_depth: f32,
+    pub applied_depth: f32,
+    /// per-rule timing by (attack, hold)
+    gates: BTreeMap<(u64, u64), Gate>,
+    /// last duck/restore
+    last_change: Option<Instant>,
+    /// speech band share of the capture, set by the caller when known
+    pub speech_ratio: Option<f32>,
+    /// zero crossings per second, set by the caller when the ZCR gate is on
+    pub zcr: Option<f32>,
+    /// recent pauses between words, ms
+    word_gaps: VecDeque<u64>,
+    /// last tick above threshold, kept across voice stops
+    last_loud: Option<Instant>,
+    was_above: bool,
+    /// n of the last m ticks above before voice starts, set by the caller
+    pub onset_votes: Option<(usize, usize)>,
+    /// recent ticks above threshold, for `onset_votes`
+    votes: VecDeque<bool>,
+    /// fastest volume change in dB/s, set by the caller
+    pub gain_rate: Option<f32>,
+    /// level voice has to fall below to stop, set by the caller
+    pub release: Option<f32>,
+    /// duck or restore in progress under `gain_rate`
+    ramp: Option<Ramp>,
 }
 
 impl VadState {
@@ -33,8 +62,304 @@ impl VadState {
             last_above: None,
             above_start: None,
             voice_active: active,
+            duck_depth: 0.0,
+            applied_depth: 0.0,
+            gates: BTreeMap::new(),
+            last_change: None,
+            speech_ratio: None,
+            zcr: None,
+            word_gaps: VecDeque::new(),
+            last_loud: None,
+            was_above: false,
+            onset_votes: None,
+            votes: VecDeque::new(),
+            gain_rate: None,
+            release: None,
+            ramp: None,
         }
     }
+
+    /// forget a ramp, for restores outside `auto_vad_step`
+    pub fn stop_ramp(&mut self) {
+        self.ramp = None;
+    }
+
+    /// record a tick, true once enough recent ticks were above
+    fn vote(&mut self, above: bool) -> bool {
+        let Some((n, m)) = self.onset_votes else {
+            self.votes.clear();
+            return true;
+        };
+        self.votes.push_back(above);
+        while self.votes.len() > m {
+            self.votes.pop_front();
+        }
+        self.votes.iter().filter(|v| **v).count() >= n
+    }
+
+    /// a vote is still open, keep the onset time across short dips
+    fn voting(&self) -> bool {
+        self.onset_votes.is_some() && self.votes.contains(&true)
+    }
+
+    /// hold from the current speaker's pauses between words
+    ///
+    /// None until enough pauses have been seen.
+    pub fn adaptive_hold(&self) -> Option<u64> {
+        (self.word_gaps.len() >= HOLD_GAPS_MIN)
+            .then(|| hold_for_gaps(self.word_gaps.iter().copied().collect()))
+    }
+
+    /// new speaker, forget cadence
+    pub fn reset_cadence(&mut self) {
+        self.word_gaps.clear();
+        self.last_loud = None;
+        self.was_above = false;
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn track_cadence(&mut self, above: bool, now: Instant) {
+        if above {
+            if let Some(last) = self.last_loud.filter(|_| !self.was_above) {
+                let gap = now.duration_since(last).as_millis();
+                if gap <= u128::from(MAX_WORD_GAP_MS) {
+                    if self.word_gaps.len() == HOLD_GAPS {
+                        self.word_gaps.pop_front();
+                    }
+                    self.word_gaps.push_back(gap as u64);
+                }
+            }
+            self.last_loud = Some(now);
+        }
+        self.was_above = above;
+    }
+}
+
+/// quietest level a ramp passes through, below is silence
+const RAMP_FLOOR_DB: f32 = -60.0;
+
+/// duck factor moving at a limited dB/s
+#[derive(Debug, Copy, Clone)]
+struct Ramp {
+    current: f32,
+    target: f32,
+    last: Instant,
+}
+
+impl Ramp {
+    /// starts moving on the first step
+    fn new(from: f32, target: f32, now: Instant) -> Self {
+        Self {
+            current: from,
+            target,
+            last: now
+                .checked_sub(Duration::from_millis(VAD_TICK_MS))
+                .unwrap_or(now),
+        }
+    }
+
+    fn step(&mut self, now: Instant, rate_db: f32) -> f32 {
+        let db = |f: f32| {
+            if f > 0.001 {
+                20.0 * f.log10()
+            } else {
+                RAMP_FLOOR_DB
+            }
+        };
+        let dt = now.duration_since(self.last).as_secs_f32().min(0.25);
+        self.last = now;
+        let (from, to) = (db(self.current), db(self.target));
+        let max = rate_db * dt;
+        let next = from + (to - from).clamp(-max, max);
+        self.current = if (next - to).abs() < 0.01 {
+            self.target
+        } else {
+            10f32.powf(next / 20.0)
+        };
+        self.current
+    }
+
+    fn done(&self) -> bool {
+        (self.current - self.target).abs() < f32::EPSILON
+    }
+
+    fn releasing(&self) -> bool {
+        self.target >= 0.999
+    }
+}
+
+/// next step of the ramp, restoring at its end
+fn advance_ramp(state: &mut VadState, guard: &mut RestoreGuard, now: Instant) {
+    // restored elsewhere, nothing left to fade
+    if !guard.ducked {
+        state.ramp = None;
+        return;
+    }
+    let (Some(rate), Some(ramp)) = (state.gain_rate, state.ramp.as_mut()) else {
+        return;
+    };
+    if ramp.done() {
+        return;
+    }
+    let factor = ramp.step(now, rate);
+    if !ramp.done() {
+        guard.ramp(factor.min(0.998));
+    } else if ramp.releasing() {
+        state.ramp = None;
+        guard.restore();
+    } else {
+        guard.ramp(factor);
+    }
+}
+
+/// voice gate with own attack/hold
+#[derive(Debug, Default)]
+struct Gate {
+    last_above: Option<Instant>,
+    above_start: Option<Instant>,
+    active: bool,
+}
+
+impl Gate {
+    #[allow(clippy::cast_possible_truncation)]
+    fn step(&mut self, above: bool, now: Instant, attack_ms: u64, hold_ms: u64) {
+        if above {
+            self.last_above = Some(now);
+            if !self.active {
+                let start = *self.above_start.get_or_insert(now);
+                if now.duration_since(start).as_millis() as u64 >= attack_ms {
+                    self.active = true;
+                    self.above_start = None;
+                }
+            }
+        } else {
+            self.above_start = None;
+            let held = self
+                .last_above
+                .is_some_and(|last| (now.duration_since(last).as_millis() as u64) < hold_ms);
+            if self.active && !held {
+                self.active = false;
+                self.last_above = None;
+            }
+        }
+    }
+}
+
+/// shortest effective hold
+pub const MIN_HOLD_MS: u64 = 300;
+/// VAD timer period
+pub const VAD_TICK_MS: u64 = 50;
+/// shortest time between duck and restore
+const MIN_CHANGE_MS: u64 = 250;
+/// pauses longer than this end a sentence instead of separating words
+pub const MAX_WORD_GAP_MS: u64 = 1500;
+/// hold on top of the p90 pause
+const WORD_GAP_MARGIN_MS: u64 = 100;
+/// pauses kept for adaptive hold
+const HOLD_GAPS: usize = 20;
+/// pauses needed before adapting
+const HOLD_GAPS_MIN: usize = 5;
+
+/// hold covering nine in ten pauses between words
+pub fn hold_for_gaps(mut gaps: Vec<u64>) -> u64 {
+    gaps.sort_unstable();
+    let p90 = gaps
+        .get((gaps.len() * 9).div_ceil(10).saturating_sub(1))
+        .copied()
+        .unwrap_or_default();
+    (p90 + WORD_GAP_MARGIN_MS).max(MIN_HOLD_MS)
+}
+
+/// zero crossing rates of speech, per second
+///
+/// Voiced speech sits around a few hundred to two thousand crossings per
+/// second; hum and bass-heavy music stay below, broadband noise and cymbals
+/// go far above.
+pub const SPEECH_ZCR_MIN: f32 = 200.0;
+pub const SPEECH_ZCR_MAX: f32 = 5000.0;
+
+/// zero crossings per second of the first channel
+#[allow(clippy::cast_precision_loss)]
+pub fn zero_crossing_rate(samples: &[f32], channels: usize, rate: u32) -> f32 {
+    let mut first = samples.iter().step_by(channels.max(1));
+    let Some(mut prev) = first.next().copied() else {
+        return 0.0;
+    };
+    let mut crossings = 0usize;
+    let mut frames = 1usize;
+    for s in first {
+        if (*s >= 0.0) != (prev >= 0.0) {
+            crossings += 1;
+        }
+        prev = *s;
+        frames += 1;
+    }
+    crossings as f32 * rate as f32 / frames as f32
+}
+
+/// above threshold and, with the ZCR gate, speech-like
+fn is_above(state: &VadState, energy: f32, thr: f32) -> bool {
+    energy > thr
+        && state
+            .zcr
+            .is_none_or(|z| (SPEECH_ZCR_MIN..=SPEECH_ZCR_MAX).contains(&z))
+}
+
+/// level over threshold for a full margin score
+const MARGIN_FULL_DB: f32 = 12.0;
+/// time above threshold for a full duration score, unless attack is longer
+const DURATION_FULL_MS: u64 = 200;
+
+/// voice confidence, 0..100
+///
+/// Weighs the level margin over the threshold, how long the level has been
+/// above it and, when known, the share of energy in the speech band.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn confidence(state: &VadState, energy: f32, thr: f32, now: Instant, attack_ms: u64) -> u8 {
+    let margin = if energy > thr && thr > 0.0 {
+        (20.0 * (energy / thr).log10() / MARGIN_FULL_DB).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let duration = if state.voice_active {
+        1.0
+    } else {
+        state.above_start.map_or(0.0, |start| {
+            let full = attack_ms.max(DURATION_FULL_MS) as f32;
+            (now.duration_since(start).as_millis() as f32 / full).clamp(0.0, 1.0)
+        })
+    };
+    let score = match state.speech_ratio {
+        Some(ratio) => 0.45 * margin + 0.3 * duration + 0.25 * ratio,
+        None => 0.6 * margin + 0.4 * duration,
+    };
+    (score * 100.0).round() as u8
+}
+
+/// depth at threshold crossing
+const KNEE_MIN_DEPTH: f32 = 0.3;
+/// per-tick release towards lower depth
+const KNEE_RELEASE: f32 = 0.1;
+/// min depth change before touching volumes
+const KNEE_STEP: f32 = 0.05;
+
+/// soft-knee depth
+///
+/// Ramps from `KNEE_MIN_DEPTH` at the threshold to full depth at `thr * knee`.
+fn knee_depth(energy: f32, thr: f32, knee: f32) -> f32 {
+    let top = thr * knee.max(1.0);
+    let t = if top > thr {
+        ((energy - thr) / (top - thr)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    KNEE_MIN_DEPTH + (1.0 - KNEE_MIN_DEPTH) * t
+}
+
+fn apply_depth(state: &mut VadState, guard: &mut RestoreGuard, duck_factor: f32) {
+    let factor = 1.0 - (1.0 - duck_factor) * state.duck_depth;
+    guard.apply_duck(factor);
+    state.applied_depth = state.duck_depth;
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -43,6 +368,24 @@ pub struct VadSnapshot {
     #[cfg(feature = "dev-tools")]
     pub desired_duck: bool,
     pub applied_duck: bool,
+    /// ducked, but nothing else plays
+    pub armed: bool,
+    /// 0..100
+    pub confidence: u8,
+}
+
+impl VadSnapshot {
+    /// snapshot from another instance's status
+    pub const fn reported(voice_active: bool, ducked: bool, armed: bool, confidence: u8) -> Self {
+        Self {
+            voice_active,
+            #[cfg(feature = "dev-tools")]
+            desired_duck: ducked,
+            applied_duck: ducked,
+            armed,
+            confidence,
+        }
+    }
 }
 
 #[allow(clippy::cast_possible_truncation, clippy::too_many_arguments)]
@@ -57,8 +400,12 @@ pub fn auto_vad_step(
     log: &mut dyn FnMut(String),
     attack_ms: u64,
     hold_ms: u64,
+    soft_knee: Option<f32>,
+    on_transition: &mut dyn FnMut(&mut RestoreGuard, bool, f32),
 ) -> VadSnapshot {
     if mode != ControlMode::AutoVad {
+        state.ramp = None;
+        let confidence = confidence(state, energy, thr, now, attack_ms);
         #[cfg(feature = "dev-tools")]
         let desired_duck = matches!(mode, ControlMode::ManualDucked);
         #[cfg(not(feature = "dev-tools"))]
@@ -68,15 +415,27 @@ pub fn auto_vad_step(
             voice_active: state.voice_active,
             desired_duck,
             applied_duck: guard.ducked,
+            armed: guard.is_armed(),
+            confidence,
         };
         #[cfg(not(feature = "dev-tools"))]
         return VadSnapshot {
             voice_active: state.voice_active,
             applied_duck: guard.ducked,
+            armed: guard.is_armed(),
+            confidence,
         };
     }
 
-    if energy > thr {
+    // between release and threshold voice keeps going
+    let gate = match state.release {
+        Some(release) if state.voice_active => release.min(thr),
+        _ => thr,
+    };
+    let above = is_above(state, energy, gate);
+    state.track_cadence(above, now);
+    let voted = state.vote(above);
+    if above {
         state.last_above = Some(now);
         if !state.voice_active {
             match state.above_start {
@@ -84,38 +443,140 @@ pub fn auto_vad_step(
                     state.above_start = Some(now);
                 }
                 Some(start) => {
-                    if attack_ms == 0 || now.duration_since(start).as_millis() as u64 >= attack_ms {
+                    let attacked =
+                        attack_ms == 0 || now.duration_since(start).as_millis() as u64 >= attack_ms;
+                    if attacked && voted {
                         state.voice_active = true;
                         state.above_start = None;
+                        events::emit(Event::VoiceStart {
+                            level: energy,
+                            confidence: confidence(state, energy, thr, now, attack_ms),
+                        });
                     }
                 }
             }
         }
     } else {
-        state.above_start = None;
+        if !state.voting() {
+            state.above_start = None;
+        }
         if state.voice_active {
             if let Some(last) = state.last_above {
                 if now.duration_since(last).as_millis() as u64 >= hold_ms {
                     state.voice_active = false;
                     state.last_above = None;
+                    events::emit(Event::VoiceStop {
+                        level: energy,
+                        confidence: confidence(state, energy, thr, now, attack_ms),
+                    });
                 }
             }
         }
     }
 
-    let desired_duck = state.voice_active;
-    if desired_duck != guard.ducked {
+    let confidence = confidence(state, energy, thr, now, attack_ms);
+
+    // per-rule attack/hold
+    let timings: HashMap<u32, (u64, u64)> = guard
+        .stream_timings()
+        .map(|(id, t)| {
+            let hold = t.hold.map_or(hold_ms, |h| h.max(MIN_HOLD_MS));
+            (id, (t.attack.unwrap_or(attack_ms), hold))
+        })
+        .collect();
+    state
+        .gates
+        .retain(|key, _| timings.values().any(|k| k == key));
+    for key in timings.values() {
+        state.gates.entry(*key).or_default();
+    }
+    for (&(attack, hold), gate) in &mut state.gates {
+        gate.step(above, now, attack, hold);
+    }
+    let desired_duck = state.voice_active || state.gates.values().any(|g| g.active);
+    if desired_duck {
+        let gates = &state.gates;
+        let voice_active = state.voice_active;
+        guard.update_held(|id| {
+            !timings
+                .get(&id)
+                .map_or(voice_active, |key| gates[key].active)
+        });
+    }
+    // no mixer thrashing on borderline levels, retried next tick
+    let settling = state
+        .last_change
+        .is_some_and(|t| now.duration_since(t).as_millis() < u128::from(MIN_CHANGE_MS));
+    // releasing under a gain rate counts as restored
+    let ducked = guard.ducked && !state.ramp.is_some_and(|r| r.releasing());
+    if desired_duck != ducked && !settling {
+        state.last_change = Some(now);
+        #[cfg(feature = "metrics")]
+        crate::metrics::METRICS
+            .transitions_total
+            .fetch_add(1, Ordering::Relaxed);
+        on_transition(guard, desired_duck, energy);
         if desired_duck {
-            log(format!(
-                "VOICE ACTIVE (level={energy:.4}) → Ducking einschalten"
-            ));
-            guard.apply_duck(duck_factor);
+            if let Some(rate) = state.gain_rate {
+                let target = match soft_knee {
+                    Some(knee) => {
+                        state.duck_depth = knee_depth(energy, thr, knee);
+                        state.applied_depth = state.duck_depth;
+                        1.0 - (1.0 - duck_factor) * state.duck_depth
+                    }
+                    None => duck_factor,
+                };
+                let from = match state.ramp {
+                    Some(ramp) if guard.ducked => ramp.current,
+                    _ => 1.0,
+                };
+                let mut ramp = Ramp::new(from, target, now);
+                guard.apply_duck(ramp.step(now, rate).min(0.998));
+                state.ramp = Some(ramp);
+            } else if let Some(knee) = soft_knee {
+                state.duck_depth = knee_depth(energy, thr, knee);
+                apply_depth(state, guard, duck_factor);
+            } else {
+                guard.apply_duck(duck_factor);
+            }
+            // armed: nothing to report
+            if !guard.is_armed() {
+                log(messages::voice_changed(true, energy));
+            }
         } else {
-            log(format!(
-                "VOICE INACTIVE (level={energy:.4}) → Ducking ausschalten"
-            ));
-            guard.restore();
+            if !guard.is_armed() {
+                log(messages::voice_changed(false, energy));
+            }
+            match state.ramp {
+                Some(ramp) if state.gain_rate.is_some() && guard.ducked => {
+                    state.ramp = Some(Ramp::new(ramp.current, 1.0, now));
+                    advance_ramp(state, guard, now);
+                }
+                _ => {
+                    state.ramp = None;
+                    guard.restore();
+                }
+            }
+            state.duck_depth = 0.0;
+            state.applied_depth = 0.0;
+        }
+    } else {
+        if let (true, Some(knee)) = (desired_duck && guard.ducked, soft_knee) {
+            // follow level
+            let target = knee_depth(energy, thr, knee);
+            state.duck_depth = if target >= state.duck_depth {
+                target
+            } else {
+                state.duck_depth + (target - state.duck_depth) * KNEE_RELEASE
+            };
+            if let Some(ramp) = state.ramp.as_mut() {
+                ramp.target = 1.0 - (1.0 - duck_factor) * state.duck_depth;
+                state.applied_depth = state.duck_depth;
+            } else if (state.duck_depth - state.applied_depth).abs() >= KNEE_STEP {
+                apply_depth(state, guard, duck_factor);
+            }
         }
+        advance_ramp(state, guard, now);
     }
 
     #[cfg(feature = "dev-tools")]
@@ -124,6 +585,8 @@ pub fn auto_vad_step(
             voice_active: state.voice_active,
             desired_duck,
             applied_duck: guard.ducked,
+            armed: guard.is_armed(),
+            confidence,
         }
     }
     #[cfg(not(feature = "dev-tools"))]
@@ -131,6 +594,178 @@ pub fn auto_vad_step(
         VadSnapshot {
             voice_active: state.voice_active,
             applied_duck: guard.ducked,
+            armed: guard.is_armed(),
+            confidence,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(f: f32) -> f32 {
+        20.0 * f.log10()
+    }
+
+    #[test]
+    fn ramp_follows_the_rate() {
+        let start = Instant::now();
+        let mut ramp = Ramp::new(1.0, 0.1, start);
+        // one tick since new, 20 dB/s over 50 ms
+        assert!((db(ramp.step(start, 20.0)) + 1.0).abs() < 0.01);
+        let at = start + Duration::from_millis(100);
+        assert!((db(ramp.step(at, 20.0)) + 3.0).abs() < 0.01);
+        // long gaps count as 250 ms at most
+        let at = at + Duration::from_secs(5);
+        assert!((db(ramp.step(at, 20.0)) + 8.0).abs() < 0.01);
+        assert!(!ramp.done() && !ramp.releasing());
+    }
+
+    #[test]
+    fn ramp_reaches_its_target() {
+        let start = Instant::now();
+        let mut ramp = Ramp::new(1.0, 0.5, start);
+        let mut ticks = 0;
+        while !ramp.done() {
+            ramp.step(start + Duration::from_millis(VAD_TICK_MS * ticks), 20.0);
+            ticks += 1;
+            assert!(ticks < 20);
+        }
+        // -6 dB at 1 dB per tick
+        assert_eq!(ticks, 7);
+        assert!((ramp.current - 0.5).abs() < f32::EPSILON);
+        // and back up
+        let mut ramp = Ramp::new(0.5, 1.0, start);
+        assert!(ramp.releasing());
+        for n in 0..7 {
+            ramp.step(start + Duration::from_millis(VAD_TICK_MS * n), 20.0);
+        }
+        assert!(ramp.done());
+        assert!((ramp.current - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn hold_covers_nine_in_ten_gaps() {
+        let gaps: Vec<u64> = (1..=10).map(|n| n * 100).collect();
+        // p90 of 100..1000 is 900, plus the margin
+        assert_eq!(hold_for_gaps(gaps), 1000);
+        // one long pause out of ten does not count
+        let mut gaps = vec![200; 9];
+        gaps.push(1400);
+        assert_eq!(hold_for_gaps(gaps), 300);
+        assert_eq!(hold_for_gaps(vec![50; 5]), MIN_HOLD_MS);
+    }
+
+    #[test]
+    fn cadence_skips_sentence_breaks() {
+        let start = Instant::now();
+        let mut vad = VadState::new(false);
+        let mut at = start;
+        // a word, then a pause of `gap` ms
+        let mut word = |vad: &mut VadState, gap: u64| {
+            vad.track_cadence(true, at);
+            vad.track_cadence(false, at + Duration::from_millis(VAD_TICK_MS));
+            at += Duration::from_millis(gap);
+        };
+        for gap in [300, 400, 500, 600] {
+            word(&mut vad, gap);
+        }
+        // four pauses so far, the fifth one ends a sentence
+        word(&mut vad, 2000);
+        assert_eq!(vad.adaptive_hold(), None);
+        word(&mut vad, MAX_WORD_GAP_MS);
+        assert_eq!(vad.adaptive_hold(), None);
+        // the cutoff itself still counts
+        word(&mut vad, 700);
+        vad.track_cadence(true, at);
+        // p90 of 300, 400, 500, 600, 1500 and 700
+        assert_eq!(vad.adaptive_hold(), Some(1600));
+        vad.reset_cadence();
+        assert_eq!(vad.adaptive_hold(), None);
+    }
+
+    #[test]
+    fn zcr_gate_passes_voice_band_only() {
+        let rate = 48_000;
+        let tone = |hz: f32| -> Vec<f32> {
+            (0..4800_u16)
+                .map(|i| (std::f32::consts::TAU * hz * f32::from(i) / 48_000.0).sin() * 0.1)
+                .collect()
+        };
+        // two crossings per period
+        let voiced = zero_crossing_rate(&tone(440.0), 1, rate);
+        assert!((voiced - 880.0).abs() < 20.0, "{voiced}");
+        let hum = zero_crossing_rate(&tone(50.0), 1, rate);
+        assert!(hum < SPEECH_ZCR_MIN, "{hum}");
+        let mut x = 1_u32;
+        let noise: Vec<f32> = (0..4800)
+            .map(|_| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                f32::from(u16::try_from((x >> 16) & 0x7fff).unwrap()) / 16_384.0 - 1.0
+            })
+            .collect();
+        let hiss = zero_crossing_rate(&noise, 1, rate);
+        assert!(hiss > SPEECH_ZCR_MAX, "{hiss}");
+        // first channel only
+        let stereo: Vec<f32> = tone(440.0)
+            .iter()
+            .zip(&noise)
+            .flat_map(|(l, r)| [*l, *r])
+            .collect();
+        assert!((zero_crossing_rate(&stereo, 2, rate) - voiced).abs() < 1.0);
+        assert!(zero_crossing_rate(&[], 2, rate).abs() < f32::EPSILON);
+
+        let mut state = VadState::new(false);
+        assert!(is_above(&state, 0.1, 0.01));
+        state.zcr = Some(voiced);
+        assert!(is_above(&state, 0.1, 0.01));
+        assert!(!is_above(&state, 0.005, 0.01));
+        state.zcr = Some(hiss);
+        assert!(!is_above(&state, 0.1, 0.01));
+        state.zcr = Some(hum);
+        assert!(!is_above(&state, 0.1, 0.01));
+    }
+
+    #[test]
+    fn knee_depth_ramps_up_to_full() {
+        let (thr, knee) = (0.01, 4.0);
+        // minimum at and below the threshold, full from thr * knee
+        assert!((knee_depth(0.0, thr, knee) - KNEE_MIN_DEPTH).abs() < f32::EPSILON);
+        assert!((knee_depth(thr, thr, knee) - KNEE_MIN_DEPTH).abs() < f32::EPSILON);
+        assert!((knee_depth(0.04, thr, knee) - 1.0).abs() < f32::EPSILON);
+        assert!((knee_depth(1.0, thr, knee) - 1.0).abs() < f32::EPSILON);
+        let mut prev = 0.0;
+        for i in 0..=50_u8 {
+            let depth = knee_depth(f32::from(i) * 0.001, thr, knee);
+            assert!(depth >= prev, "not monotonic at {i}");
+            prev = depth;
+        }
+        // no knee, full depth right away
+        assert!((knee_depth(0.011, thr, 1.0) - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn confidence_grows_with_margin_and_time() {
+        let start = Instant::now();
+        let mut state = VadState::new(false);
+        assert_eq!(confidence(&state, 0.0, 0.01, start, 0), 0);
+        // at the threshold, nothing yet
+        assert_eq!(confidence(&state, 0.01, 0.01, start, 0), 0);
+        // halfway through a 200 ms attack, 6 dB over
+        state.above_start = Some(start);
+        let at = start + Duration::from_millis(100);
+        assert_eq!(confidence(&state, 0.02, 0.01, at, 200), 50);
+        let later = confidence(&state, 0.02, 0.01, at + Duration::from_millis(50), 200);
+        assert!(later > 50 && later < 100, "{later}");
+        // a longer attack fills the duration score more slowly
+        assert!(confidence(&state, 0.02, 0.01, at, 400) < 50);
+        // sustained, 12 dB over
+        state.voice_active = true;
+        assert_eq!(confidence(&state, 0.04, 0.01, at, 200), 100);
+        state.speech_ratio = Some(1.0);
+        assert_eq!(confidence(&state, 0.04, 0.01, at, 200), 100);
+        state.speech_ratio = Some(0.0);
+        assert_eq!(confidence(&state, 0.04, 0.01, at, 200), 75);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::analysis::VadSnapshot;
+use crate::control;
+use crate::ipc::{self, Status};
+use crate::messages::Msg;
+use crate::ui::{self, DUCK_STEP_PCT, HOLD_MAX_MS, HOLD_STEP_MS, SENS_MAX, SENS_MIN, SENS_STEP};
+use crate::ControlMode;
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::debug;
+
+/// status poll period
+const REFRESH: Duration = Duration::from_millis(100);
+
+/// `pw-duck attach`
+///
+/// Drives the TUI from the running instance's status. Leaving only detaches,
+/// the instance keeps running.
+pub fn run() -> Result<()> {
+    let mut status = fetch_status().context("pw-duck is not running")?;
+    let log = follow_log();
+    let _gui = ui::enter_gui_mode()?;
+    let mut show_spectrum = false;
+    let mut prompt: Option<String> = None;
+    let view = ui::View::default();
+    loop {
+        if event::poll(REFRESH)? {
+            while event::poll(Duration::ZERO)? {
+                let Event::Key(key) = event::read()? else {
+                    continue;
+                };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let command = if prompt.is_some() {
+                    let Some(line) = ui::edit_prompt(&mut prompt, key.code) else {
+                        continue;
+                    };
+                    // checked here, so a typo never reaches the instance
+                    if let Err(err) = control::parse(&line) {
+                        log.lock().unwrap().push(format!(":{line}: {err}"));
+                        continue;
+                    }
+                    Some(line)
+                } else if view.handle_key(key.code) {
+                    continue;
+                } else if key.code == KeyCode::Enter {
+                    if let Some(param) = view.setting_param() {
+                        prompt = Some(format!("set {param} "));
+                    }
+                    continue;
+                } else {
+                    match view.setting_key(key.code).map_or(key.code, KeyCode::Char) {
+                        _ if ui::is_save_key(key) => Some("save".to_string()),
+                        _ if ui::is_suspend_key(key) => {
+                            ui::suspend(true)?;
+                            None
+                        }
+                        KeyCode::Esc => return Ok(()),
+                        KeyCode::Char(':') => {
+                            prompt = Some(String::new());
+                            None
+                        }
+                        KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                            'x' => return Ok(()),
+                            'f' => {
+                                show_spectrum = !show_spectrum;
+                                None
+                            }
+                            other => key_command(other, &status),
+                        },
+                        _ => None,
+                    }
+                };
+                if let Some(command) = command {
+                    match ipc::request(&command) {
+                        Ok(reply) => {
+                            if let Some(err) = reply.strip_prefix("error: ") {
+                                log.lock().unwrap().push(format!(":{command}: {err}"));
+                            }
+                        }
+                        Err(err) => return Err(anyhow!("pw-duck went away ({err})")),
+                    }
+                    // next key steps from the new value
+                    status = fetch_status().context("pw-duck went away")?;
+                }
+            }
+        }
+        status = fetch_status().context("pw-duck went away")?;
+        let bands = if show_spectrum {
+            let reply = ipc::request(&format!("spectrum {}", ui::spectrum_bands()))?;
+            serde_json::from_str::<Vec<f32>>(&reply).ok()
+        } else {
+            None
+        };
+        ui::render_gui(
+            status.voice_source.clone(),
+            status.reason.clone(),
+            &status.scene,
+            ControlMode::from_name(&status.mode).unwrap_or(ControlMode::AutoVad),
+            &VadSnapshot::reported(
+                status.voice_active,
+                status.ducked,
+                status.armed,
+                status.confidence,
+            ),
+            status.level,
+            status.threshold,
+            status.duck_factor,
+            status.hold_ms,
+            &status.groups,
+            bands.as_deref(),
+            &view,
+            None,
+            Msg::AttachKeys,
+            prompt.as_deref(),
+            &log.lock().unwrap(),
+        );
+    }
+}
+
+/// the instance's TUI log, from `log` events
+///
+/// Starts empty, lines logged before attaching are not replayed.
+fn follow_log() -> Arc<Mutex<Vec<String>>> {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let lines = Arc::clone(&log);
+    let spawned = thread::Builder::new()
+        .name("pw-duck-attach-log".into())
+        .spawn(move || {
+            let followed = ipc::subscribe(|line| {
+                if let Some(message) = log_message(line) {
+                    lines.lock().unwrap().push(message);
+                }
+            });
+            if let Err(err) = followed {
+                debug!("log subscription: {err}");
+            }
+        });
+    if let Err(err) = spawned {
+        debug!("log thread: {err}");
+    }
+    log
+}
+
+/// message of a `log` event line
+fn log_message(line: &str) -> Option<String> {
+    let event: serde_json::Value = serde_json::from_str(line).ok()?;
+    if event["event"] != "log" {
+        return None;
+    }
+    event["message"].as_str().map(String::from)
+}
+
+fn fetch_status() -> Result<Status> {
+    let reply = ipc::request("status")?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+/// ipc command for a TUI key, same steps as the local TUI
+fn key_command(key: char, status: &Status) -> Option<String> {
+    let duck = |step: f32| {
+        let pct = ((1.0 - status.duck_factor) * 100.0 + step).clamp(0.0, 100.0);
+        format!("set duck_factor {}", 1.0 - pct / 100.0)
+    };
+    let command = match key {
+        ' ' => "toggle".to_string(),
+        'o' => "focus".to_string(),
+        'w' => format!(
+            "set threshold {}",
+            (status.threshold + SENS_STEP).clamp(SENS_MIN, SENS_MAX)
+        ),
+        's' => format!(
+            "set threshold {}",
+            (status.threshold - SENS_STEP).clamp(SENS_MIN, SENS_MAX)
+        ),
+        'a' => duck(-DUCK_STEP_PCT),
+        'd' => duck(DUCK_STEP_PCT),
+        'q' => format!("set hold {}", status.hold_ms.saturating_sub(HOLD_STEP_MS)),
+        'e' => format!(
+            "set hold {}",
+            (status.hold_ms + HOLD_STEP_MS).min(HOLD_MAX_MS)
+        ),
+        _ => return None,
+    };
+    Some(command)
+}
@@ -0,0 +1,220 @@
+use crate::ducking::{contains_ci, OutputStream};
+use anyhow::{anyhow, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+
+/// browser binaries
+const BROWSERS: &[&str] = &["firefox", "chrome", "chromium", "brave", "vivaldi", "opera"];
+
+/// call-like tab titles
+const CALL_TITLES: &[&str] = &["call", "meet", "jitsi", "zoom", "teams", "whereby"];
+
+/// known voice client
+struct Client {
+    name: &'static str,
+    score: i32,
+    matches: fn(&OutputStream) -> bool,
+}
+
+fn any_of(s: &OutputStream, needles: &[&str]) -> bool {
+    needles
+        .iter()
+        .any(|n| contains_ci(&s.app, n) || contains_ci(&s.bin, n) || contains_ci(&s.node, n))
+}
+
+const CLIENTS: &[Client] = &[
+    Client {
+        name: "webrtc",
+        score: 100,
+        matches: |s| contains_ci(&s.app, "voiceengine") || contains_ci(&s.node, "voiceengine"),
+    },
+    Client {
+        name: "discord",
+        score: 90,
+        matches: |s| contains_ci(&s.bin, "discord"),
+    },
+    Client {
+        name: "zoom",
+        score: 90,
+        matches: |s| any_of(s, &["zoom"]),
+    },
+    Client {
+        name: "mumble",
+        score: 90,
+        matches: |s| any_of(s, &["mumble"]),
+    },
+    Client {
+        name: "teamspeak",
+        score: 90,
+        matches: |s| any_of(s, &["teamspeak", "ts3client"]),
+    },
+    Client {
+        name: "teams",
+        score: 80,
+        matches: |s| any_of(s, &["teams"]),
+    },
+    Client {
+        name: "telegram",
+        score: 80,
+        matches: |s| any_of(s, &["telegram"]),
+    },
+    Client {
+        name: "element",
+        score: 70,
+        matches: |s| any_of(s, &["element"]),
+    },
+    Client {
+        name: "jitsi",
+        score: 70,
+        matches: |s| any_of(s, &["jitsi"]),
+    },
+    Client {
+        name: "browser-call",
+        score: 60,
+        matches: |s| {
+            BROWSERS.iter().any(|b| contains_ci(&s.bin, b))
+                && CALL_TITLES.iter().any(|t| contains_ci(&s.media, t))
+        },
+    },
+    Client {
+        name: "communication",
+        score: 50,
+        matches: |s| contains_ci(&s.role, "communication"),
+    },
+    Client {
+        name: "playstream",
+        score: 40,
+        matches: |s| contains_ci(&s.media, "playstream"),
+    },
+];
+
+/// bonus for echo-cancelled sources over raw mics
+const ECHO_CANCEL_SCORE: i32 = 20;
+
+/// source of PipeWire's echo-cancel module, e.g. `echo-cancel-source`
+pub fn is_echo_cancelled(node: &str) -> bool {
+    contains_ci(node, "echo-cancel")
+}
+
+/// voice source scoring
+///
+/// Each known client has a score that can be changed (or set to 0 to ignore
+/// the client) with the `[voice_scores]` config table. Echo-cancelled sources
+/// rank above raw mics, whose speaker bleed-through reads as voice; with
+/// `require_echo_cancel` raw mics are not offered at all.
+#[derive(Debug, Clone)]
+pub struct Scorer {
+    scores: Vec<i32>,
+    require_echo_cancel: bool,
+}
+
+impl Scorer {
+    pub fn new(overrides: &BTreeMap<String, i32>, require_echo_cancel: bool) -> Result<Self> {
+        let mut scores: Vec<i32> = CLIENTS.iter().map(|c| c.score).collect();
+        for (name, score) in overrides {
+            let idx = CLIENTS
+                .iter()
+                .position(|c| c.name == name)
+                .ok_or_else(|| anyhow!("unknown voice client '{name}' in [voice_scores]"))?;
+            scores[idx] = *score;
+        }
+        Ok(Self {
+            scores,
+            require_echo_cancel,
+        })
+    }
+
+    /// offered as a voice source at all
+    pub fn allows(&self, s: &OutputStream) -> bool {
+        !self.require_echo_cancel || s.media_class != "Audio/Source" || is_echo_cancelled(&s.node)
+    }
+
+    /// best matching client, remote voice is always an output stream
+    pub fn client(&self, s: &OutputStream) -> Option<(&'static str, i32)> {
+        if s.media_class != "Stream/Output/Audio" {
+            return None;
+        }
+        CLIENTS
+            .iter()
+            .zip(&self.scores)
+            .filter(|(c, score)| **score > 0 && (c.matches)(s))
+            .map(|(c, score)| (c.name, *score))
+            .max_by_key(|(_, score)| *score)
+    }
+
+    /// likely remote voice
+    pub fn is_candidate(&self, s: &OutputStream) -> bool {
+        self.client(s).is_some()
+    }
+
+    /// selection order
+    pub fn score(&self, s: &OutputStream) -> i32 {
+        let mut score = self.client(s).map_or(0, |(_, score)| score);
+        if s.node != "unknown-node" {
+            score += 5;
+        }
+        if s.client != "-" {
+            score += 3;
+        }
+        if s.media != "unknown-media" {
+            score += 1;
+        }
+        if s.role != "-" {
+            score += 1;
+        }
+        if s.media_class == "Audio/Source" && is_echo_cancelled(&s.node) {
+            score += ECHO_CANCEL_SCORE;
+        }
+        score
+    }
+}
+
+/// ordered voice source patterns
+///
+/// Each pattern is a case-insensitive substring of the app name, binary, node
+/// name or media name; earlier patterns win.
+#[derive(Debug, Default)]
+pub struct Failover {
+    patterns: RefCell<Vec<String>>,
+    /// replaced since the last `take_replaced`
+    replaced: Cell<bool>,
+}
+
+impl Failover {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self {
+            patterns: RefCell::new(patterns),
+            replaced: Cell::new(false),
+        }
+    }
+
+    /// new patterns, e.g. from a scene
+    pub fn replace(&self, patterns: Vec<String>) {
+        *self.patterns.borrow_mut() = patterns;
+        self.replaced.set(true);
+    }
+
+    pub fn take_replaced(&self) -> bool {
+        self.replaced.replace(false)
+    }
+
+    /// index of first matching pattern
+    pub fn rank(&self, s: &OutputStream) -> Option<usize> {
+        self.patterns.borrow().iter().position(|p| {
+            contains_ci(&s.app, p)
+                || contains_ci(&s.bin, p)
+                || contains_ci(&s.node, p)
+                || contains_ci(&s.media, p)
+        })
+    }
+
+    /// best available source
+    pub fn pick(&self, outputs: &HashMap<u32, OutputStream>) -> Option<(usize, OutputStream)> {
+        outputs
+            .values()
+            .filter(|s| s.media_class == "Stream/Output/Audio")
+            .filter_map(|s| self.rank(s).map(|rank| (rank, s)))
+            .min_by_key(|(rank, s)| (*rank, s.id))
+            .map(|(rank, s)| (rank, s.clone()))
+    }
+}
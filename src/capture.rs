@@ -1,5 +1,7 @@
 use crate::analysis::AtomicF32;
-use crate::logging::logln;
+use crate::messages;
+use crate::spectrum::Spectrum;
+use crate::tap::Tap;
 use pipewire as pw;
 use pw::spa::param::audio::{AudioFormat, AudioInfoRaw};
 use pw::spa::param::format::{MediaSubtype, MediaType};
@@ -7,10 +9,130 @@ use pw::spa::param::format_utils;
 use pw::spa::pod::Pod;
 use pw::{properties::properties, spa};
 use std::cell::RefCell;
+use std::process::{Command, Stdio};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// capture stream name
+pub const CAPTURE_NAME: &str = "voice-capture";
+
+/// move the capture onto another source
+pub fn retarget(capture_node: u32, serial: &str, node: &str) -> bool {
+    let object = if serial == "-" { node } else { serial };
+    let ok = Command::new("pw-metadata")
+        .args([&capture_node.to_string(), "target.object", object])
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !ok {
+        warn!("retargeting capture id={capture_node} to {object} failed");
+    }
+    ok
+}
+
+/// how the capture names its voice source in `target.object`
+///
+/// Tried in this order when a capture stays idle or fails.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Targeting {
+    Serial,
+    NodeName,
+    NodeId,
+}
+
+impl Targeting {
+    pub const ALL: [Self; 3] = [Self::Serial, Self::NodeName, Self::NodeId];
+
+    /// strategy after this one, None once all were tried
+    pub fn next(self) -> Option<Self> {
+        Self::ALL.get(self as usize + 1).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Serial => "object.serial",
+            Self::NodeName => "node.name",
+            Self::NodeId => "node id",
+        }
+    }
+
+    /// target for this strategy, None if the source has no such value
+    fn object(self, id: u32, node: Option<&str>, serial: Option<&str>) -> Option<String> {
+        match self {
+            Self::Serial => serial.filter(|v| *v != "-").map(str::to_string),
+            Self::NodeName => node.filter(|v| *v != "unknown-node").map(str::to_string),
+            Self::NodeId => Some(id.to_string()),
+        }
+    }
+}
+
+/// fixed format for the capture, None negotiates the graph's
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CaptureFormat {
+    pub rate: Option<u32>,
+    pub channels: Option<u32>,
+}
+
+/// channels the VAD level is taken from
+///
+/// `mix` is the level of all channels together, `max` the loudest channel,
+/// a number that channel alone (from 1). With the voice on one side only,
+/// `mix` reads it 3 dB low.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum ChannelSelect {
+    #[default]
+    Mix,
+    Max,
+    Channel(u32),
+}
+
+impl std::str::FromStr for ChannelSelect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "mix" => Ok(Self::Mix),
+            "max" => Ok(Self::Max),
+            n => match n.parse::<u32>() {
+                Ok(n) if (1..=64).contains(&n) => Ok(Self::Channel(n)),
+                _ => Err(format!("'{s}' is not mix, max or a channel from 1 to 64")),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for ChannelSelect {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl ChannelSelect {
+    /// RMS from per-channel sums of squares over `frames`
+    #[allow(clippy::cast_precision_loss)]
+    fn level(self, sums: &[f32], frames: usize) -> f32 {
+        let rms = |sum: f32| (sum / frames as f32).sqrt();
+        match self {
+            Self::Channel(n) if (n as usize) <= sums.len() => rms(sums[n as usize - 1]),
+            Self::Max => sums.iter().copied().map(rms).fold(0.0, f32::max),
+            Self::Mix | Self::Channel(_) => rms(sums.iter().sum::<f32>() / sums.len() as f32),
+        }
+    }
+
+    /// channel for zero crossings
+    fn zcr_channel(self, channels: usize) -> usize {
+        match self {
+            Self::Channel(n) if (n as usize) <= channels => n as usize - 1,
+            _ => 0,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct CaptureData {
@@ -29,16 +151,25 @@ pub struct CaptureData {
 )]
 pub fn setup_capture(
     core: &pw::core::CoreRc,
+    name: &str,
     voice_id_opt: Option<u32>,
     voice_node_opt: Option<String>,
     voice_serial_opt: Option<String>,
+    targeting: Targeting,
+    quantum: Option<&str>,
+    fixed: CaptureFormat,
+    select: ChannelSelect,
+    monitor: bool,
     energy_atomic: Arc<AtomicF32>,
+    zcr_atomic: Arc<AtomicF32>,
     audio_seen: Arc<AtomicBool>,
     capture_frames: Arc<AtomicU64>,
-    gui_enabled: bool,
+    failed: Arc<AtomicBool>,
+    tap: Option<Tap>,
+    spectrum: Option<Spectrum>,
 ) -> Result<
     Option<(
-        pw::stream::StreamBox<'_>,
+        pw::stream::StreamRc,
         pw::stream::StreamListener<CaptureData>,
     )>,
     pw::Error,
@@ -51,23 +182,43 @@ pub fn setup_capture(
                 *pw::keys::MEDIA_ROLE => "Communication",
                 *pw::keys::MEDIA_CLASS => "Stream/Input/Audio",
             };
-            // target id
-            if let Some(serial) = voice_serial_opt.clone().filter(|v| v != "-") {
-                props.insert("target.object", serial);
-            } else if let Some(node_name) = voice_node_opt.clone().filter(|v| v != "unknown-node") {
-                props.insert("target.object", node_name);
+            // target id, first strategy the source has a value for
+            let target = Targeting::ALL[targeting as usize..].iter().find_map(|t| {
+                t.object(
+                    voice_id,
+                    voice_node_opt.as_deref(),
+                    voice_serial_opt.as_deref(),
+                )
+            });
+            if let Some(target) = target {
+                props.insert("target.object", target);
             }
-            // monitor capture
-            props.insert(*pw::keys::STREAM_CAPTURE_SINK, "true");
-            let stream = pw::stream::StreamBox::new(core, "voice-capture", props)?;
+            // smaller quantum, quicker attack for more wakeups
+            if let Some(quantum) = quantum {
+                props.insert(*pw::keys::NODE_LATENCY, quantum);
+            }
+            // monitor capture, sources are captured directly
+            if monitor {
+                props.insert(*pw::keys::STREAM_CAPTURE_SINK, "true");
+            }
+            let stream = pw::stream::StreamRc::new(core.clone(), name, props)?;
             let user_data = CaptureData {
                 format: Default::default(),
             };
             let energy_clone = energy_atomic.clone();
+            let zcr_clone = zcr_atomic.clone();
             let audio_seen_rt = audio_seen.clone();
             let capture_frames_rt = capture_frames.clone();
+            let tap_fmt = tap.clone();
+            let spectrum_fmt = spectrum.clone();
             let stream_listener = stream
                 .add_local_listener_with_user_data(user_data)
+                .state_changed(move |_, _, _, new| {
+                    if let pw::stream::StreamState::Error(err) = new {
+                        warn!("capture stream error: {err}");
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                })
                 .param_changed(move |_, user_data, id, param| {
                     let Some(param) = param else {
                         return;
@@ -85,15 +236,20 @@ pub fn setup_capture(
                         .format
                         .parse(param)
                         .expect("Failed to parse audio format");
-                    logln(
-                        gui_enabled,
-                        format!(
-                            "Überwachung gestartet: rate={} channels={} format={:?}",
+                    info!(
+                        "{}",
+                        messages::capture_started(
                             user_data.format.rate(),
                             user_data.format.channels(),
                             user_data.format.format()
-                        ),
+                        )
                     );
+                    if let Some(tap) = tap_fmt.as_ref() {
+                        tap.format(user_data.format.rate(), user_data.format.channels());
+                    }
+                    if let Some(spectrum) = spectrum_fmt.as_ref() {
+                        spectrum.format(user_data.format.rate(), user_data.format.channels());
+                    }
                 })
                 .process(move |stream, user_data| match stream.dequeue_buffer() {
                     None => (),
@@ -127,40 +283,56 @@ pub fn setup_capture(
 
                             let slice = &samples[offset..end];
 
-                            let mut sum_sq: f32 = 0.0;
+                            // sums of squares per channel
+                            let channels = (n_channels as usize).min(64);
+                            let mut sums = [0f32; 64];
                             let mut count: usize = 0;
+                            // zero crossings on the selected channel
+                            let zcr_channel = select.zcr_channel(channels);
+                            let mut crossings: usize = 0;
+                            let mut prev: Option<f32> = None;
+                            let mut tap_buf = (tap.is_some() || spectrum.is_some())
+                                .then(|| Vec::with_capacity(slice.len() / 2));
+                            let mut visit = |sample: f32| {
+                                let channel = count % channels;
+                                if channel == zcr_channel {
+                                    if prev.is_some_and(|p| (p >= 0.0) != (sample >= 0.0)) {
+                                        crossings += 1;
+                                    }
+                                    prev = Some(sample);
+                                }
+                                sums[channel] += sample * sample;
+                                count += 1;
+                                if let Some(buf) = tap_buf.as_mut() {
+                                    buf.push(sample);
+                                }
+                            };
 
                             match user_data.format.format() {
                                 AudioFormat::F32LE => {
                                     for chunk in slice.chunks_exact(std::mem::size_of::<f32>()) {
-                                        let sample = f32::from_le_bytes(chunk.try_into().unwrap());
-                                        sum_sq += sample * sample;
-                                        count += 1;
+                                        visit(f32::from_le_bytes(chunk.try_into().unwrap()));
                                     }
                                 }
                                 AudioFormat::F32BE => {
                                     for chunk in slice.chunks_exact(std::mem::size_of::<f32>()) {
-                                        let sample = f32::from_be_bytes(chunk.try_into().unwrap());
-                                        sum_sq += sample * sample;
-                                        count += 1;
+                                        visit(f32::from_be_bytes(chunk.try_into().unwrap()));
                                     }
                                 }
                                 AudioFormat::S16LE => {
                                     for chunk in slice.chunks_exact(std::mem::size_of::<i16>()) {
-                                        let sample = i16::from_le_bytes(chunk.try_into().unwrap())
-                                            as f32
-                                            / i16::MAX as f32;
-                                        sum_sq += sample * sample;
-                                        count += 1;
+                                        visit(
+                                            i16::from_le_bytes(chunk.try_into().unwrap()) as f32
+                                                / i16::MAX as f32,
+                                        );
                                     }
                                 }
                                 AudioFormat::S16BE => {
                                     for chunk in slice.chunks_exact(std::mem::size_of::<i16>()) {
-                                        let sample = i16::from_be_bytes(chunk.try_into().unwrap())
-                                            as f32
-                                            / i16::MAX as f32;
-                                        sum_sq += sample * sample;
-                                        count += 1;
+                                        visit(
+                                            i16::from_be_bytes(chunk.try_into().unwrap()) as f32
+                                                / i16::MAX as f32,
+                                        );
                                     }
                                 }
                                 _ => {
@@ -168,9 +340,19 @@ pub fn setup_capture(
                                 }
                             }
 
+                            if let (Some(spectrum), Some(buf)) = (spectrum.as_ref(), &tap_buf) {
+                                spectrum.samples(buf);
+                            }
+                            if let (Some(tap), Some(buf)) = (tap.as_ref(), tap_buf) {
+                                tap.samples(buf);
+                            }
                             if count > 0 {
-                                let rms = (sum_sq / (count as f32)).sqrt();
-                                energy_clone.store(rms);
+                                let frames = count.div_ceil(channels);
+                                energy_clone.store(select.level(&sums[..channels], frames));
+                                zcr_clone.store(
+                                    crossings as f32 * user_data.format.rate() as f32
+                                        / frames as f32,
+                                );
                             }
 
                             audio_seen_rt.store(true, Ordering::Relaxed);
@@ -182,6 +364,22 @@ pub fn setup_capture(
 
             let mut audio_info = AudioInfoRaw::new();
             audio_info.set_format(AudioFormat::F32LE);
+            if let Some(rate) = fixed.rate {
+                audio_info.set_rate(rate);
+            }
+            if let Some(channels) = fixed.channels {
+                audio_info.set_channels(channels);
+                let mut position = [0; 64];
+                match channels {
+                    1 => position[0] = spa::sys::SPA_AUDIO_CHANNEL_MONO,
+                    2 => {
+                        position[0] = spa::sys::SPA_AUDIO_CHANNEL_FL;
+                        position[1] = spa::sys::SPA_AUDIO_CHANNEL_FR;
+                    }
+                    _ => {}
+                }
+                audio_info.set_position(position);
+            }
             let obj = pw::spa::pod::Object {
                 type_: pw::spa::utils::SpaTypes::ObjectParamFormat.as_raw(),
                 id: pw::spa::param::ParamType::EnumFormat.as_raw(),
@@ -197,10 +395,7 @@ pub fn setup_capture(
 
             let mut params = [Pod::from_bytes(&serialized).unwrap()];
 
-            logln(
-                gui_enabled,
-                format!("Capture-Stream verbunden mit Voice-Node id={}", voice_id),
-            );
+            info!("{}", messages::capture_connected(voice_id));
 
             stream.connect(
                 spa::utils::Direction::Input,
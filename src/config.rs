@@ -0,0 +1,264 @@
+use crate::agents::ConflictPolicy;
+use crate::capture::ChannelSelect;
+use crate::discord::DiscordConfig;
+use crate::ducking::{NewStreamPolicy, VolumeScope};
+use crate::hid::HidConfig;
+use crate::midi::MidiConfig;
+use crate::mqtt::MqttConfig;
+use crate::overlay::OverlayConfig;
+use crate::profile::Profile;
+use crate::rules::AppRule;
+use crate::scene::Scene;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, Value};
+
+/// config file contents
+///
+/// Every key is optional; command line flags take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// saved from the TUI, below flags and above profiles
+    pub threshold: Option<f32>,
+    pub duck_factor: Option<f32>,
+    pub hold: Option<u64>,
+    pub attack: Option<u64>,
+    pub hooks: HooksConfig,
+    /// OSC listen addr
+    pub osc_addr: Option<String>,
+    pub midi: MidiConfig,
+    pub hid: HidConfig,
+    pub mqtt: MqttConfig,
+    pub discord: DiscordConfig,
+    pub overlay: OverlayConfig,
+    /// rhai rules script
+    #[cfg(feature = "rules")]
+    pub rules: Option<PathBuf>,
+    /// per-app rules
+    pub rule: Vec<AppRule>,
+    /// never duck screen readers (default true)
+    pub protect_accessibility: Option<bool>,
+    /// ordered voice source patterns
+    pub voice_sources: Vec<String>,
+    /// left alone in focus mode
+    pub focus_allow: Vec<String>,
+    /// only these media roles are ducked
+    pub duck_roles: Vec<String>,
+    /// voice client scores
+    pub voice_scores: BTreeMap<String, i32>,
+    /// voice client profiles
+    pub profile: BTreeMap<String, Profile>,
+    /// named scenes
+    pub scene: BTreeMap<String, Scene>,
+    /// stuck duck watchdog, minutes
+    pub max_duck: Option<u64>,
+    /// leave AutoVad when the watchdog fires
+    pub max_duck_pause: bool,
+    /// live parameters in PipeWire metadata
+    pub metadata: bool,
+    /// other ducking agents running
+    pub conflict: Option<ConflictPolicy>,
+    /// restore and pause while the session is locked or idle
+    pub pause_on_lock: bool,
+    /// re-read baselines while restored, seconds
+    pub baseline_refresh: Option<u64>,
+    /// capture latency request, e.g. "256/48000"
+    pub capture_quantum: Option<String>,
+    /// capture sample rate, Hz
+    pub capture_rate: Option<u32>,
+    /// capture channels
+    pub capture_channels: Option<u32>,
+    /// VAD level from these capture channels
+    pub capture_select: Option<ChannelSelect>,
+    /// ignore the voice source while it echoes the local mic
+    pub echo_reject: bool,
+    /// mic for echo_reject, node.name
+    pub echo_mic: Option<String>,
+    /// only offer echo-cancelled sources, never raw mics
+    pub require_echo_cancel: bool,
+    /// capture retry after this long without audio, seconds
+    pub capture_timeout: Option<u64>,
+    /// streams that appear while ducked
+    pub new_stream_policy: Option<NewStreamPolicy>,
+    /// set volumes per node or per client
+    pub volume_scope: Option<VolumeScope>,
+    /// only count speech-like zero crossing rates as voice
+    pub zcr_gate: bool,
+    /// fastest volume change, dB/s
+    pub gain_rate: Option<f32>,
+    /// level voice has to fall below to stop
+    pub release: Option<f32>,
+    /// voice onset vote, e.g. "3/5"
+    pub onset_votes: Option<String>,
+    /// hold from the speaker's pauses between words
+    pub adaptive_hold: bool,
+    /// duck only the "pw-duck Music" sink
+    pub music_sink: bool,
+    /// streams moved onto the music sink
+    pub music_route: Option<String>,
+}
+
+/// [hooks] table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub on_voice_start: Option<String>,
+    pub on_voice_stop: Option<String>,
+}
+
+/// default config path
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|d| d.join("pw-duck").join("config.toml"))
+}
+
+/// explicit or default config path
+pub fn path(explicit: Option<&Path>) -> Option<PathBuf> {
+    explicit.map(Path::to_path_buf).or_else(default_path)
+}
+
+/// load config
+///
+/// An explicit path must exist; a missing default file yields the defaults.
+pub fn load(explicit: Option<&Path>) -> Result<Config> {
+    let path = match path(explicit) {
+        Some(p) if explicit.is_some() || p.exists() => p,
+        _ => return Ok(Config::default()),
+    };
+    let text = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// live settings written by `save`
+///
+/// `None` leaves the key in the file alone, e.g. for a value that came from a
+/// profile.
+#[derive(Debug, Clone, Default)]
+pub struct Live {
+    pub threshold: Option<f32>,
+    pub duck_factor: Option<f32>,
+    pub hold: Option<u64>,
+    pub attack: Option<u64>,
+    /// apps excluded at runtime, saved as `[[rule]]` tables
+    pub exclude: Vec<String>,
+}
+
+/// write live settings into the config file
+///
+/// Only the saved keys change; comments, formatting and other keys are kept.
+pub fn save(path: &Path, live: &Live) -> Result<()> {
+    let mut doc: DocumentMut = if path.exists() {
+        fs::read_to_string(path)
+            .with_context(|| format!("reading {}", path.display()))?
+            .parse()
+            .with_context(|| format!("parsing {}", path.display()))?
+    } else {
+        DocumentMut::new()
+    };
+    // 4 decimals, not f32 noise
+    let float = |v: f32| Value::from((f64::from(v) * 1e4).round() / 1e4);
+    let int = |v: u64| Value::from(i64::try_from(v).unwrap_or(i64::MAX));
+    if let Some(v) = live.threshold {
+        set_value(&mut doc, "threshold", float(v));
+    }
+    if let Some(v) = live.duck_factor {
+        set_value(&mut doc, "duck_factor", float(v));
+    }
+    if let Some(v) = live.hold {
+        set_value(&mut doc, "hold", int(v));
+    }
+    if let Some(v) = live.attack {
+        set_value(&mut doc, "attack", int(v));
+    }
+    let new: Vec<&String> = live
+        .exclude
+        .iter()
+        .filter(|app| !excluded(&doc, app))
+        .collect();
+    if !new.is_empty() {
+        let rules = doc
+            .entry("rule")
+            .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()));
+        let Some(rules) = rules.as_array_of_tables_mut() else {
+            bail!("{}: rule is not an array of tables", path.display());
+        };
+        for app in new {
+            let mut rule = Table::new();
+            rule["app"] = toml_edit::value(app.as_str());
+            rule["exclude"] = toml_edit::value(true);
+            rules.push(rule);
+        }
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    fs::write(path, doc.to_string()).with_context(|| format!("writing {}", path.display()))
+}
+
+/// replace a top-level value, keeping its comments
+fn set_value(doc: &mut DocumentMut, key: &str, mut value: Value) {
+    match doc.get_mut(key).and_then(Item::as_value_mut) {
+        Some(old) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        None => doc[key] = Item::Value(value),
+    }
+}
+
+/// `[[rule]]` excluding `app` already in the file
+fn excluded(doc: &DocumentMut, app: &str) -> bool {
+    doc.get("rule")
+        .and_then(Item::as_array_of_tables)
+        .is_some_and(|rules| {
+            rules.iter().any(|r| {
+                r.get("app").and_then(Item::as_str) == Some(app)
+                    && r.get("exclude").and_then(Item::as_bool) == Some(true)
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_keeps_comments() {
+        let dir = std::env::temp_dir().join(format!("pw-duck-config-test-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            &path,
+            "# my settings\nthreshold = 0.02 # tuned by ear\nhold = 300\n\n[[rule]]\n# keep the game\napp = \"game\"\nfactor = 0.8\n",
+        )
+        .unwrap();
+        let live = Live {
+            threshold: Some(0.031_25),
+            hold: None,
+            attack: Some(40),
+            exclude: vec!["game".into(), "spotify".into()],
+            ..Live::default()
+        };
+        save(&path, &live).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("# my settings"));
+        assert!(text.contains("threshold = 0.0313 # tuned by ear"));
+        assert!(text.contains("hold = 300"));
+        assert!(text.contains("attack = 40"));
+        assert!(!text.contains("duck_factor"));
+        assert!(text.contains("# keep the game"));
+        let config: Config = toml::from_str(&text).unwrap();
+        assert_eq!(config.rule.len(), 3);
+        assert!(config.rule[2].exclude);
+        // saving again adds nothing
+        save(&path, &live).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
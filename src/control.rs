@@ -0,0 +1,424 @@
+use crate::analysis::VadState;
+use crate::candidate::Failover;
+use crate::config;
+use crate::ducking::{OutputStream, RestoreGuard};
+use crate::messages;
+use crate::profile::{Profile, Profiles};
+use crate::rules::{self, RuleSet};
+use crate::scene::Scene;
+use crate::units;
+use crate::ControlMode;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// remote control request
+///
+/// Sent from background threads and applied on the main loop by the VAD timer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Control {
+    Threshold(f32),
+    DuckFactor(f32),
+    Hold(u64),
+    /// level voice has to fall below to stop
+    Release(f32),
+    Duck,
+    Restore,
+    Auto,
+    Toggle,
+    /// duck everything but the allowlist, or leave focus mode
+    Focus,
+    /// leave matching apps alone until restart
+    Exclude(String),
+    /// switch to a voice client profile
+    Profile(String),
+    /// switch to a scene
+    Scene(String),
+    /// write live settings to the config file
+    Save,
+}
+
+/// what `exclude`, `profile`, `scene` and `save` act on
+pub struct Session {
+    pub rules: Rc<RuleSet>,
+    pub outputs: Rc<RefCell<HashMap<u32, OutputStream>>>,
+    pub profiles: Profiles,
+    pub scenes: BTreeMap<String, Scene>,
+    /// active scene
+    pub scene: RefCell<Option<String>>,
+    pub failover: Rc<Failover>,
+    pub attack_ms: Rc<Cell<u64>>,
+    /// `--release`, None to stop below the threshold
+    pub release: Cell<Option<f32>>,
+    pub config_path: Option<PathBuf>,
+    /// values the active profile set, not saved unless changed since
+    pub profiled: RefCell<Profile>,
+}
+
+/// one command line, as typed at the TUI prompt or sent over the socket
+///
+/// `set <param> <value>`, `exclude <app>`, `profile <name>`, `scene <name>`,
+/// `save` and the mode commands, including `focus`.
+pub fn parse(line: &str) -> Result<Control, String> {
+    let line = line.trim();
+    let (cmd, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    match cmd {
+        "exclude" if !rest.is_empty() => return Ok(Control::Exclude(rest.to_string())),
+        "exclude" => return Err(messages::usage("exclude", "app")),
+        "profile" if !rest.is_empty() => return Ok(Control::Profile(rest.to_string())),
+        "profile" => return Err(messages::usage("profile", "name")),
+        "scene" if !rest.is_empty() => return Ok(Control::Scene(rest.to_string())),
+        "scene" => return Err(messages::usage("scene", "name")),
+        _ => {}
+    }
+    let mut words = line.split_whitespace();
+    let ctl = match (words.next(), words.next(), words.next()) {
+        (Some("duck"), None, None) => Control::Duck,
+        (Some("restore"), None, None) => Control::Restore,
+        (Some("auto"), None, None) => Control::Auto,
+        (Some("toggle"), None, None) => Control::Toggle,
+        (Some("focus"), None, None) => Control::Focus,
+        (Some("save"), None, None) => Control::Save,
+        (Some("set"), Some(param), Some(value)) => {
+            let bad = |err: String| format!("{param}: {err}");
+            match param {
+                "threshold" => Control::Threshold(units::threshold(value).map_err(bad)?),
+                "duck_factor" | "duck" => {
+                    Control::DuckFactor(units::duck_factor(value).map_err(bad)?)
+                }
+                "hold" => Control::Hold(units::millis(value).map_err(bad)?),
+                "release" => Control::Release(units::threshold(value).map_err(bad)?),
+                _ => return Err(messages::unknown_parameter(param)),
+            }
+        }
+        _ => return Err(messages::unknown_command(line)),
+    };
+    if words.next().is_some() {
+        return Err(messages::unknown_command(line));
+    }
+    Ok(ctl)
+}
+
+/// apply on main loop
+#[allow(clippy::too_many_arguments)]
+pub fn apply(
+    ctl: Control,
+    guard_t: &Rc<RefCell<Option<Arc<Mutex<RestoreGuard>>>>>,
+    mode_t: &Rc<RefCell<ControlMode>>,
+    vad_t: &Rc<RefCell<VadState>>,
+    threshold_live: &Rc<RefCell<f32>>,
+    duck_factor_live: &Rc<RefCell<f32>>,
+    hold_live: &Rc<RefCell<u64>>,
+    session: &Session,
+) -> String {
+    let with_guard = |f: &dyn Fn(&mut RestoreGuard)| {
+        if let Some(guard) = guard_t.borrow().as_ref() {
+            f(&mut guard.lock().unwrap());
+        }
+    };
+    // rules see the allowlist only in focus mode
+    let set_focus = |on: bool| {
+        session.rules.set_focus(on);
+        let vad = rules::VadInfo {
+            active: vad_t.borrow().voice_active,
+            level: 0.0,
+        };
+        with_guard(&|g| session.rules.apply(g, &session.outputs.borrow(), vad));
+    };
+    let set_mode = |mode: ControlMode| {
+        *mode_t.borrow_mut() = mode;
+        messages::mode_set(mode.as_str())
+    };
+    if matches!(ctl, Control::Duck | Control::Restore | Control::Auto)
+        && *mode_t.borrow() == ControlMode::Focus
+    {
+        set_focus(false);
+    }
+    match ctl {
+        Control::Threshold(thr) => {
+            *threshold_live.borrow_mut() = thr.max(0.0);
+            messages::threshold_set(*threshold_live.borrow())
+        }
+        Control::DuckFactor(factor) => {
+            let factor = factor.clamp(0.0, 1.0);
+            *duck_factor_live.borrow_mut() = factor;
+            if matches!(
+                *mode_t.borrow(),
+                ControlMode::ManualDucked | ControlMode::Focus
+            ) {
+                with_guard(&|g| {
+                    g.apply_duck(factor);
+                });
+            }
+            messages::duck_set((1.0 - factor) * 100.0)
+        }
+        Control::Hold(ms) => {
+            *hold_live.borrow_mut() = ms;
+            messages::hold_set(ms)
+        }
+        Control::Release(level) => {
+            session.release.set(Some(level));
+            messages::release_set(level)
+        }
+        Control::Duck => {
+            let factor = *duck_factor_live.borrow();
+            with_guard(&|g| {
+                g.apply_duck(factor);
+            });
+            set_mode(ControlMode::ManualDucked)
+        }
+        Control::Restore => {
+            with_guard(&|g| {
+                if g.ducked {
+                    g.restore();
+                }
+            });
+            set_mode(ControlMode::ManualRestored)
+        }
+        Control::Auto => {
+            // reset VAD
+            {
+                let mut vad = vad_t.borrow_mut();
+                vad.voice_active = false;
+                vad.above_start = None;
+                vad.last_above = None;
+                vad.stop_ramp();
+            }
+            with_guard(&|g| {
+                if g.ducked {
+                    g.restore();
+                }
+            });
+            set_mode(ControlMode::AutoVad)
+        }
+        Control::Toggle => {
+            let next = if *mode_t.borrow() == ControlMode::AutoVad {
+                Control::Restore
+            } else {
+                Control::Auto
+            };
+            apply(
+                next,
+                guard_t,
+                mode_t,
+                vad_t,
+                threshold_live,
+                duck_factor_live,
+                hold_live,
+                session,
+            )
+        }
+        Control::Focus if *mode_t.borrow() == ControlMode::Focus => apply(
+            Control::Auto,
+            guard_t,
+            mode_t,
+            vad_t,
+            threshold_live,
+            duck_factor_live,
+            hold_live,
+            session,
+        ),
+        Control::Focus => {
+            set_focus(true);
+            let factor = *duck_factor_live.borrow();
+            with_guard(&|g| {
+                g.apply_duck(factor);
+            });
+            set_mode(ControlMode::Focus)
+        }
+        Control::Exclude(app) => {
+            session.rules.exclude(&app);
+            let vad = rules::VadInfo {
+                active: vad_t.borrow().voice_active,
+                level: 0.0,
+            };
+            let factor = *duck_factor_live.borrow();
+            with_guard(&|g| {
+                session.rules.apply(g, &session.outputs.borrow(), vad);
+                if g.ducked {
+                    g.apply_duck(factor);
+                }
+            });
+            messages::excluded(&app)
+        }
+        Control::Profile(name) => {
+            let Some(p) = session.profiles.get(&name) else {
+                return messages::unknown_profile(&name);
+            };
+            *session.profiled.borrow_mut() = p.clone();
+            if let Some(v) = p.threshold {
+                *threshold_live.borrow_mut() = v;
+            }
+            if let Some(v) = p.hold {
+                *hold_live.borrow_mut() = v;
+            }
+            if let Some(v) = p.attack {
+                session.attack_ms.set(v);
+            }
+            if let Some(v) = p.duck_factor {
+                apply(
+                    Control::DuckFactor(v),
+                    guard_t,
+                    mode_t,
+                    vad_t,
+                    threshold_live,
+                    duck_factor_live,
+                    hold_live,
+                    session,
+                );
+            }
+            messages::settings_switched(
+                false,
+                &name,
+                *threshold_live.borrow(),
+                *hold_live.borrow(),
+                session.attack_ms.get(),
+                (1.0 - *duck_factor_live.borrow()) * 100.0,
+            )
+        }
+        Control::Scene(name) => {
+            let Some(scene) = session.scenes.get(&name) else {
+                return messages::unknown_scene(&name);
+            };
+            session.rules.set_scene(scene.rule.clone());
+            let vad = rules::VadInfo {
+                active: vad_t.borrow().voice_active,
+                level: 0.0,
+            };
+            with_guard(&|g| session.rules.apply(g, &session.outputs.borrow(), vad));
+            if !scene.voice_source.is_empty() {
+                session.failover.replace(scene.voice_source.clone());
+            }
+            if let Some(v) = scene.threshold {
+                *threshold_live.borrow_mut() = v;
+            }
+            if let Some(v) = scene.hold {
+                *hold_live.borrow_mut() = v;
+            }
+            if let Some(v) = scene.attack {
+                session.attack_ms.set(v);
+            }
+            if let Some(v) = scene.duck_factor {
+                *duck_factor_live.borrow_mut() = v.clamp(0.0, 1.0);
+            }
+            let factor = *duck_factor_live.borrow();
+            with_guard(&|g| {
+                if g.ducked {
+                    g.apply_duck(factor);
+                }
+            });
+            *session.scene.borrow_mut() = Some(name.clone());
+            messages::settings_switched(
+                true,
+                &name,
+                *threshold_live.borrow(),
+                *hold_live.borrow(),
+                session.attack_ms.get(),
+                (1.0 - *duck_factor_live.borrow()) * 100.0,
+            )
+        }
+        Control::Save => {
+            let Some(path) = session.config_path.as_deref() else {
+                return messages::save_no_path();
+            };
+            // profile values stay with the profile
+            let profiled = session.profiled.borrow();
+            let live = config::Live {
+                threshold: Some(*threshold_live.borrow())
+                    .filter(|v| profiled.threshold != Some(*v)),
+                duck_factor: Some(*duck_factor_live.borrow())
+                    .filter(|v| profiled.duck_factor != Some(*v)),
+                hold: Some(*hold_live.borrow()).filter(|v| profiled.hold != Some(*v)),
+                attack: Some(session.attack_ms.get()).filter(|v| profiled.attack != Some(*v)),
+                exclude: session.rules.exclusions(),
+            };
+            match config::save(path, &live) {
+                Ok(()) => messages::saved(path),
+                Err(err) => messages::save_failed(&err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mode_words() {
+        for (line, ctl) in [
+            ("duck", Control::Duck),
+            ("restore", Control::Restore),
+            ("auto", Control::Auto),
+            ("toggle", Control::Toggle),
+            ("focus", Control::Focus),
+            ("save", Control::Save),
+            ("  toggle \n", Control::Toggle),
+        ] {
+            assert_eq!(parse(line), Ok(ctl), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn parses_set() {
+        for (line, ctl) in [
+            ("set threshold 0.02", Control::Threshold(0.02)),
+            ("set duck 0.5", Control::DuckFactor(0.5)),
+            ("set duck_factor 75%", Control::DuckFactor(0.25)),
+            ("set hold 1.5s", Control::Hold(1500)),
+            ("set  hold   300", Control::Hold(300)),
+            ("set release 0.01", Control::Release(0.01)),
+        ] {
+            assert_eq!(parse(line), Ok(ctl), "{line:?}");
+        }
+        for line in [
+            "set threshold 0",
+            "set threshold 2",
+            "set threshold loud",
+            "set duck 1.5",
+            "set duck_factor 3dB",
+            "set hold -5",
+            "set hold 90s",
+            "set attack 10",
+            "set threshold",
+            "set",
+        ] {
+            assert!(parse(line).is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn parses_arguments() {
+        assert_eq!(
+            parse("exclude Firefox"),
+            Ok(Control::Exclude("Firefox".into()))
+        );
+        // names may contain spaces
+        assert_eq!(
+            parse("profile  team speak "),
+            Ok(Control::Profile("team speak".into()))
+        );
+        assert_eq!(parse("scene gaming"), Ok(Control::Scene("gaming".into())));
+        for line in ["exclude", "profile", "scene  "] {
+            assert!(parse(line).is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_extra_and_unknown_words() {
+        for line in [
+            "duck now",
+            "auto auto",
+            "set hold 300 ms",
+            "set threshold 0.02 extra",
+            "quit",
+            "Duck",
+            "",
+        ] {
+            assert!(parse(line).is_err(), "{line:?}");
+        }
+    }
+}
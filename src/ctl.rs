@@ -0,0 +1,147 @@
+use crate::ipc::{self, Status};
+use anyhow::Result;
+use clap::{Subcommand, ValueEnum};
+
+/// no instance listening
+const EXIT_NOT_RUNNING: i32 = 2;
+/// instance rejected the command
+const EXIT_REJECTED: i32 = 1;
+
+/// ctl args
+#[derive(clap::Args, Debug)]
+pub struct CtlArgs {
+    #[command(subcommand)]
+    action: CtlAction,
+    /// JSON output
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum CtlAction {
+    /// print live state
+    Status,
+    /// print one parameter
+    Get { param: Param },
+    /// change one parameter
+    Set { param: Param, value: String },
+    /// duck manually
+    Duck,
+    /// restore manually
+    Restore,
+    /// back to automatic ducking
+    Auto,
+    /// toggle automatic ducking
+    Toggle,
+    /// duck everything but the focus allowlist, again to leave
+    Focus,
+    /// switch to a scene from the config file
+    Scene { name: String },
+    /// print events as they happen, one JSON object per line
+    Events,
+}
+
+/// live parameter
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum Param {
+    Threshold,
+    DuckFactor,
+    Hold,
+}
+
+impl Param {
+    const fn wire_name(self) -> &'static str {
+        match self {
+            Self::Threshold => "threshold",
+            Self::DuckFactor => "duck_factor",
+            Self::Hold => "hold",
+        }
+    }
+}
+
+/// `pw-duck ctl`
+///
+/// Exits 0 on success, 1 when the instance rejects the command and 2 when no
+/// instance is running.
+pub fn run(args: &CtlArgs) -> Result<()> {
+    let command = match &args.action {
+        CtlAction::Status | CtlAction::Get { .. } => "status".to_string(),
+        CtlAction::Set { param, value } => format!("set {} {value}", param.wire_name()),
+        CtlAction::Duck => "duck".to_string(),
+        CtlAction::Restore => "restore".to_string(),
+        CtlAction::Auto => "auto".to_string(),
+        CtlAction::Toggle => "toggle".to_string(),
+        CtlAction::Focus => "focus".to_string(),
+        CtlAction::Scene { name } => format!("scene {name}"),
+        CtlAction::Events => return events(),
+    };
+    let reply = match ipc::request(&command) {
+        Ok(reply) => reply,
+        Err(err) => {
+            eprintln!("pw-duck is not running ({err})");
+            std::process::exit(EXIT_NOT_RUNNING);
+        }
+    };
+    if let Some(err) = reply.strip_prefix("error: ") {
+        eprintln!("{err}");
+        std::process::exit(EXIT_REJECTED);
+    }
+    match &args.action {
+        CtlAction::Status => {
+            let status: Status = serde_json::from_str(&reply)?;
+            if args.json {
+                println!("{reply}");
+            } else {
+                print_status(&status);
+            }
+        }
+        CtlAction::Get { param } => {
+            let status: Status = serde_json::from_str(&reply)?;
+            let value = match param {
+                Param::Threshold => serde_json::json!(status.threshold),
+                Param::DuckFactor => serde_json::json!(status.duck_factor),
+                Param::Hold => serde_json::json!(status.hold_ms),
+            };
+            if args.json {
+                println!("{}", serde_json::json!({ param.wire_name(): value }));
+            } else {
+                println!("{value}");
+            }
+        }
+        _ => {
+            if args.json {
+                println!("{}", serde_json::json!({ "ok": true }));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// follow pushed events
+fn events() -> Result<()> {
+    if let Err(err) = ipc::subscribe(|line| println!("{line}")) {
+        eprintln!("pw-duck is not running ({err})");
+        std::process::exit(EXIT_NOT_RUNNING);
+    }
+    Ok(())
+}
+
+fn print_status(s: &Status) {
+    println!("mode:         {}", s.mode);
+    if !s.scene.is_empty() {
+        println!("scene:        {}", s.scene);
+    }
+    println!("voice source: {}", s.voice_source);
+    println!("voice active: {}", s.voice_active);
+    println!("ducked:       {}", s.ducked);
+    println!("armed:        {}", s.armed);
+    println!("level:        {:.4}", s.level);
+    println!("confidence:   {}%", s.confidence);
+    println!("threshold:    {:.4}", s.threshold);
+    println!("duck factor:  {:.2}", s.duck_factor);
+    println!("hold:         {} ms", s.hold_ms);
+    println!("streams:      {}", s.streams);
+    if !s.session_manager.is_empty() {
+        println!("session:      {}", s.session_manager);
+    }
+}
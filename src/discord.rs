@@ -0,0 +1,303 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// wait before reconnecting
+const RECONNECT: Duration = Duration::from_secs(10);
+
+/// frame opcodes
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+const OP_CLOSE: u32 = 2;
+const OP_PING: u32 = 3;
+const OP_PONG: u32 = 4;
+
+/// [discord] table
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    /// only duck automatically while in a voice channel
+    pub gate: bool,
+    /// application id of your Discord app
+    pub client_id: Option<String>,
+    /// OAuth2 token with the `rpc` scope for that app
+    pub access_token: Option<String>,
+    /// gate closed while Discord cannot be reached
+    pub closed_when_unreachable: bool,
+}
+
+/// Discord RPC thread
+///
+/// Connects to the local Discord client over its IPC socket and keeps the
+/// returned flag set while the user is in a voice channel. By default the
+/// flag stays set while Discord cannot be reached, so a closed client or a
+/// bad token does not silently turn ducking off; `closed_when_unreachable`
+/// clears it instead.
+pub fn spawn_gate(config: &DiscordConfig) -> Result<Arc<AtomicBool>> {
+    let (Some(client_id), Some(token)) = (config.client_id.clone(), config.access_token.clone())
+    else {
+        bail!("discord gate needs client_id and access_token in [discord]");
+    };
+    let unreachable = !config.closed_when_unreachable;
+    let in_voice = Arc::new(AtomicBool::new(unreachable));
+    let in_voice_t = in_voice.clone();
+    thread::Builder::new()
+        .name("pw-duck-discord".into())
+        .spawn(move || loop {
+            if let Err(err) = session(&client_id, &token, &in_voice_t) {
+                debug!("discord rpc: {err}");
+            }
+            if in_voice_t.swap(unreachable, Ordering::Relaxed) != unreachable {
+                info!(
+                    "discord unreachable, automatic ducking {}",
+                    if unreachable { "not gated" } else { "off" }
+                );
+            }
+            thread::sleep(RECONNECT);
+        })?;
+    Ok(in_voice)
+}
+
+/// one connection to the client, until it goes away
+fn session(client_id: &str, token: &str, in_voice: &AtomicBool) -> io::Result<()> {
+    let mut conn = connect()?;
+    send(
+        &mut conn,
+        OP_HANDSHAKE,
+        &json!({ "v": 1, "client_id": client_id }),
+    )?;
+    expect_reply(&mut conn, None)?;
+    let reply = command(
+        &mut conn,
+        "AUTHENTICATE",
+        json!({ "access_token": token }),
+        None,
+    );
+    if let Err(err) = reply {
+        warn!("discord rpc: authentication failed: {err}");
+        return Err(err);
+    }
+    let channel = command(&mut conn, "GET_SELECTED_VOICE_CHANNEL", json!({}), None)?;
+    set(in_voice, !channel.is_null());
+    command(
+        &mut conn,
+        "SUBSCRIBE",
+        json!({}),
+        Some("VOICE_CHANNEL_SELECT"),
+    )?;
+    info!("discord rpc connected");
+    loop {
+        if let Some(now) = voice_channel_select(&next_frame(&mut conn)?) {
+            set(in_voice, now);
+        }
+    }
+}
+
+/// in a voice channel after a `VOICE_CHANNEL_SELECT` event, None for others
+fn voice_channel_select(msg: &Value) -> Option<bool> {
+    (msg["evt"] == "VOICE_CHANNEL_SELECT").then(|| !msg["data"]["channel_id"].is_null())
+}
+
+fn set(in_voice: &AtomicBool, now: bool) {
+    if in_voice.swap(now, Ordering::Relaxed) != now {
+        info!(
+            "discord: {}",
+            if now {
+                "in a voice channel, automatic ducking on"
+            } else {
+                "not in a voice channel, automatic ducking off"
+            }
+        );
+    }
+}
+
+/// `discord-ipc-N` in the runtime dir, also for Flatpak and Snap installs
+fn connect() -> io::Result<UnixStream> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    let dirs = [
+        base.clone(),
+        base.join("app/com.discordapp.Discord"),
+        base.join("snap.discord"),
+    ];
+    for dir in &dirs {
+        for n in 0..10 {
+            if let Ok(conn) = UnixStream::connect(dir.join(format!("discord-ipc-{n}"))) {
+                return Ok(conn);
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no discord-ipc socket",
+    ))
+}
+
+/// send a command and wait for its reply, the reply's `data`
+fn command(conn: &mut UnixStream, cmd: &str, args: Value, evt: Option<&str>) -> io::Result<Value> {
+    let nonce = format!("pw-duck-{cmd}");
+    let mut payload = json!({ "cmd": cmd, "args": args, "nonce": nonce });
+    if let Some(evt) = evt {
+        payload["evt"] = evt.into();
+    }
+    send(conn, OP_FRAME, &payload)?;
+    expect_reply(conn, Some(&nonce))
+}
+
+/// next frame answering `nonce`, or the READY dispatch after the handshake
+fn expect_reply(conn: &mut UnixStream, nonce: Option<&str>) -> io::Result<Value> {
+    loop {
+        let msg = next_frame(conn)?;
+        if msg["evt"] == "ERROR" {
+            return Err(io::Error::other(
+                msg["data"]["message"]
+                    .as_str()
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            ));
+        }
+        let matched = match nonce {
+            Some(nonce) => msg["nonce"] == nonce,
+            None => msg["evt"] == "READY",
+        };
+        if matched {
+            return Ok(msg["data"].clone());
+        }
+    }
+}
+
+fn send(conn: &mut UnixStream, op: u32, payload: &Value) -> io::Result<()> {
+    let body = payload.to_string();
+    let mut frame = Vec::with_capacity(8 + body.len());
+    frame.extend_from_slice(&op.to_le_bytes());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.extend_from_slice(body.as_bytes());
+    conn.write_all(&frame)
+}
+
+/// next JSON frame, answering pings on the way
+fn next_frame(conn: &mut UnixStream) -> io::Result<Value> {
+    loop {
+        let mut header = [0u8; 8];
+        conn.read_exact(&mut header)?;
+        let op = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let mut body = vec![0u8; len];
+        conn.read_exact(&mut body)?;
+        let msg: Value = serde_json::from_slice(&body).map_err(io::Error::other)?;
+        match op {
+            OP_FRAME => return Ok(msg),
+            OP_PING => {
+                let mut pong = Vec::with_capacity(8 + len);
+                pong.extend_from_slice(&OP_PONG.to_le_bytes());
+                pong.extend_from_slice(&header[4..]);
+                pong.extend_from_slice(&body);
+                conn.write_all(&pong)?;
+            }
+            OP_CLOSE => {
+                return Err(io::Error::other(
+                    msg["message"].as_str().unwrap_or("closed").to_string(),
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(op: u32, body: &str) -> Vec<u8> {
+        let mut frame = op.to_le_bytes().to_vec();
+        frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        frame.extend_from_slice(body.as_bytes());
+        frame
+    }
+
+    #[test]
+    fn frames_are_little_endian_with_length() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        let payload = json!({ "v": 1, "client_id": "42" });
+        send(&mut a, OP_HANDSHAKE, &payload).unwrap();
+        let body = payload.to_string();
+        let mut raw = vec![0u8; 8 + body.len()];
+        b.read_exact(&mut raw).unwrap();
+        assert_eq!(raw, frame(OP_HANDSHAKE, &body));
+
+        send(&mut a, OP_FRAME, &payload).unwrap();
+        assert_eq!(next_frame(&mut b).unwrap(), payload);
+    }
+
+    #[test]
+    fn pings_are_answered() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        a.write_all(&frame(OP_PING, r#"{"n":7}"#)).unwrap();
+        a.write_all(&frame(OP_FRAME, r#"{"evt":"READY"}"#)).unwrap();
+        assert_eq!(next_frame(&mut b).unwrap(), json!({ "evt": "READY" }));
+        let pong = frame(OP_PONG, r#"{"n":7}"#);
+        let mut raw = vec![0u8; pong.len()];
+        a.read_exact(&mut raw).unwrap();
+        assert_eq!(raw, pong);
+    }
+
+    #[test]
+    fn close_and_garbage_are_errors() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        a.write_all(&frame(
+            OP_CLOSE,
+            r#"{"code":4000,"message":"Invalid Client ID"}"#,
+        ))
+        .unwrap();
+        let err = next_frame(&mut b).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid Client ID");
+        a.write_all(&frame(OP_FRAME, "not json")).unwrap();
+        assert!(next_frame(&mut b).is_err());
+        // cut off inside the body
+        a.write_all(&frame(OP_FRAME, "{}")[..9]).unwrap();
+        drop(a);
+        assert!(next_frame(&mut b).is_err());
+    }
+
+    #[test]
+    fn replies_match_the_nonce() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        for body in [
+            r#"{"cmd":"DISPATCH","evt":"VOICE_CHANNEL_SELECT","data":{}}"#,
+            r#"{"cmd":"GET_SELECTED_VOICE_CHANNEL","nonce":"n1","data":null}"#,
+            r#"{"cmd":"AUTHENTICATE","evt":"ERROR","nonce":"n2","data":{"code":4009,"message":"Invalid token"}}"#,
+        ] {
+            a.write_all(&frame(OP_FRAME, body)).unwrap();
+        }
+        assert_eq!(expect_reply(&mut b, Some("n1")).unwrap(), Value::Null);
+        let err = expect_reply(&mut b, Some("n2")).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid token");
+    }
+
+    #[test]
+    fn voice_channel_events() {
+        let select =
+            |data: Value| json!({ "cmd": "DISPATCH", "evt": "VOICE_CHANNEL_SELECT", "data": data });
+        assert_eq!(
+            voice_channel_select(&select(json!({ "channel_id": "1234", "guild_id": "99" }))),
+            Some(true)
+        );
+        assert_eq!(
+            voice_channel_select(&select(json!({ "channel_id": null, "guild_id": null }))),
+            Some(false)
+        );
+        assert_eq!(
+            voice_channel_select(&json!({ "cmd": "DISPATCH", "evt": "READY", "data": {} })),
+            None
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::ducking::{detect_backend, parse_wpctl_volume, tool_available};
+use crate::session::SessionManager;
+use crate::teststream::test_stream;
+use anyhow::Result;
+use pipewire as pw;
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::process::Command;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// doctor test node
+const TEST_NODE: &str = "pw-duck-doctor";
+
+/// check outcome
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    worst: Option<Level>,
+}
+
+impl Report {
+    fn line(&mut self, level: Level, what: &str, hint: Option<&str>) {
+        let tag = match level {
+            Level::Ok => "[ok]  ",
+            Level::Warn => "[warn]",
+            Level::Fail => "[fail]",
+        };
+        println!("{tag} {what}");
+        if let Some(hint) = hint {
+            println!("       -> {hint}");
+        }
+        self.worst = self.worst.max(Some(level));
+    }
+
+    fn ok(&mut self, what: &str) {
+        self.line(Level::Ok, what, None);
+    }
+
+    fn warn(&mut self, what: &str, hint: &str) {
+        self.line(Level::Warn, what, Some(hint));
+    }
+
+    fn fail(&mut self, what: &str, hint: &str) {
+        self.line(Level::Fail, what, Some(hint));
+    }
+}
+
+/// `pw-duck doctor`
+///
+/// Checks the environment pw-duck relies on and explains what to fix. Exits
+/// with 1 if a required check fails.
+pub fn run() -> Result<()> {
+    let mut report = Report::default();
+    check_tools(&mut report);
+    if let Err(err) = check_pipewire(&mut report) {
+        report.fail(
+            &format!("PipeWire connection failed: {err}"),
+            "is PipeWire running? try `systemctl --user status pipewire`",
+        );
+    }
+    if report.worst == Some(Level::Fail) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// run `tool args`, None if not installed
+fn probe_tool(tool: &str, args: &[&str]) -> Option<io::Result<std::process::Output>> {
+    match Command::new(tool).args(args).output() {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        other => Some(other),
+    }
+}
+
+fn check_tools(report: &mut Report) {
+    match probe_tool("wpctl", &["status"]) {
+        None if tool_available("pactl") => report.warn(
+            "wpctl not found, falling back to pactl",
+            "install WirePlumber for faster volume changes",
+        ),
+        None => report.fail(
+            "neither wpctl nor pactl found",
+            "install WirePlumber, which provides wpctl; volumes cannot be read or set without it",
+        ),
+        Some(Ok(out)) if out.status.success() => report.ok("wpctl available"),
+        Some(Ok(out)) => report.fail(
+            &format!(
+                "wpctl status failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            ),
+            "check that WirePlumber is running: `systemctl --user status wireplumber`",
+        ),
+        Some(Err(err)) => report.fail(&format!("wpctl could not run: {err}"), "check PATH"),
+    }
+    // optional helpers
+    for (tool, args, used_for) in [
+        ("pw-link", &["--version"][..], "--engine sidechain"),
+        (
+            "pw-metadata",
+            &["--version"][..],
+            "--engine sidechain and voice switching",
+        ),
+        ("pw-record", &["--version"][..], "background re-probing"),
+        ("notify-send", &["--version"][..], "--notify"),
+        ("loginctl", &["--version"][..], "--pause-on-lock"),
+    ] {
+        if probe_tool(tool, args).is_some() {
+            report.ok(&format!("{tool} available"));
+        } else {
+            report.warn(
+                &format!("{tool} not found"),
+                &format!("only needed for {used_for}"),
+            );
+        }
+    }
+}
+
+fn check_pipewire(report: &mut Report) -> Result<()> {
+    pw::init();
+    let mainloop = pw::main_loop::MainLoopRc::new(None)?;
+    let context = pw::context::ContextRc::new(&mainloop, None)?;
+    let core = crate::connect_core(&context)?;
+    let registry = core.get_registry_rc()?;
+    report.ok("connected to PipeWire");
+
+    let clients: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+    let test_id: Rc<Cell<Option<u32>>> = Rc::new(Cell::new(None));
+    let _listener = {
+        let clients = clients.clone();
+        let test_id = test_id.clone();
+        registry
+            .add_listener_local()
+            .global(move |global| {
+                let Some(props) = global.props.as_ref() else {
+                    return;
+                };
+                if global.type_ == pw::types::ObjectType::Client {
+                    if let Some(name) = props.get("application.name") {
+                        clients.borrow_mut().push(name.to_string());
+                    }
+                } else if props.get("node.name") == Some(TEST_NODE) {
+                    test_id.set(Some(global.id));
+                }
+            })
+            .register()
+    };
+    let (_stream, _stream_listener) = test_stream(&core, TEST_NODE, |out: &mut [f32]| {
+        out.fill(0.0);
+    })?;
+    let run_for = |d: Duration| -> Result<()> {
+        let quit = mainloop.clone();
+        let timer = mainloop.loop_().add_timer(move |_| quit.quit());
+        timer.update_timer(Some(d), None).into_result()?;
+        mainloop.run();
+        Ok(())
+    };
+    for _ in 0..30 {
+        run_for(Duration::from_millis(100))?;
+        if test_id.get().is_some() {
+            break;
+        }
+    }
+
+    // session manager
+    let found = clients
+        .borrow()
+        .iter()
+        .find_map(|c| SessionManager::from_client(c));
+    match found {
+        Some(SessionManager::WirePlumber) => report.ok("WirePlumber session manager running"),
+        Some(SessionManager::MediaSession) => report.warn(
+            "pipewire-media-session instead of WirePlumber",
+            "wpctl needs WirePlumber; pw-duck sets volumes in-process instead",
+        ),
+        _ => report.fail(
+            "no session manager found",
+            "start WirePlumber: `systemctl --user enable --now wireplumber`",
+        ),
+    }
+    match found.unwrap_or_default().default_node(false) {
+        Some(sink) => report.ok(&format!("default sink: {sink}")),
+        None => report.warn(
+            "no default sink",
+            "the session manager has not picked an output yet",
+        ),
+    }
+
+    let Some(id) = test_id.get() else {
+        report.fail(
+            "test stream did not appear in the registry",
+            "PipeWire accepted the connection but does not create streams; check its logs",
+        );
+        return Ok(());
+    };
+    // wpctl round trip, not under media-session
+    if found != Some(SessionManager::MediaSession) {
+        check_volume(report, id);
+    }
+    Ok(())
+}
+
+fn check_volume(report: &mut Report, id: u32) {
+    // output format
+    match probe_tool("wpctl", &["get-volume", &id.to_string()]) {
+        Some(Ok(out)) if out.status.success() => {
+            let text = String::from_utf8_lossy(&out.stdout);
+            match parse_wpctl_volume(&text) {
+                Some(v) => report.ok(&format!(
+                    "wpctl output format: {} (read as {:.2}{})",
+                    text.trim(),
+                    v.volume,
+                    if v.muted { ", muted" } else { "" }
+                )),
+                None => report.warn(
+                    &format!("unexpected wpctl output: {}", text.trim()),
+                    "pw-duck cannot read a volume from it; please report this wpctl version",
+                ),
+            }
+        }
+        Some(Ok(out)) => {
+            report.fail(
+                &format!(
+                    "wpctl get-volume {id} failed: {}",
+                    String::from_utf8_lossy(&out.stderr).trim()
+                ),
+                "WirePlumber may not manage this node yet; check `wpctl status`",
+            );
+            return;
+        }
+        Some(Err(_)) => return,
+        // pactl fallback
+        None => {}
+    }
+    // round trip
+    let Ok(backend) = detect_backend() else {
+        report.fail(
+            "neither wpctl nor pactl found",
+            "install WirePlumber (wpctl) or pipewire-pulse (pactl)",
+        );
+        return;
+    };
+    let Some(base) = backend.get(id) else {
+        report.fail(
+            "reading the test stream volume failed",
+            "see wpctl output above",
+        );
+        return;
+    };
+    let probe = if (base - 0.5).abs() < 0.05 { 0.8 } else { 0.5 };
+    let ok = backend.set(id, probe) && backend.get(id).is_some_and(|v| (v - probe).abs() < 0.01);
+    backend.set(id, base);
+    if ok {
+        report.ok("volume get/set round trip on test stream");
+    } else {
+        report.fail(
+            "volume set on the test stream did not read back",
+            "ducking will not work; check WirePlumber logs with `journalctl --user -u wireplumber`",
+        );
+    }
+}
@@ -1,7 +1,16 @@
-use crate::logging::logln;
+use crate::error::Error;
+use crate::events::{self, Event};
+use crate::helper::{Changed, RestoreHelper};
+use crate::rules::Timing;
 use regex::Regex;
-use std::collections::HashMap;
-use std::process::Command;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
 
 /// output stream info
 #[derive(Debug, Clone)]
@@ -18,6 +27,123 @@ pub struct OutputStream {
     pub client: String,
 }
 
+impl OutputStream {
+    /// from node properties
+    pub fn from_props(id: u32, media_class: String, prop: impl Fn(&str) -> Option<String>) -> Self {
+        let or = |key: &str, default: &str| prop(key).unwrap_or_else(|| default.into());
+        Self {
+            id,
+            serial: or("object.serial", "-"),
+            app: or("application.name", "unknown-app"),
+            bin: or("application.process.binary", "-"),
+            pid: or("application.process.id", "-"),
+            role: or("media.role", "-"),
+            media: or("media.name", "unknown-media"),
+            media_class,
+            node: or("node.name", "unknown-node"),
+            client: or("client.id", "-"),
+        }
+    }
+
+    /// identity across id changes
+    ///
+    /// A stream recreated after a route switch gets a new id and serial but
+    /// keeps its process, app and node name.
+    pub fn identity(&self) -> String {
+        format!("{}|{}|{}", self.pid, self.app, self.node)
+    }
+
+    /// per-app baseline key
+    pub fn app_key(&self) -> String {
+        format!("{}|{}", self.app, self.node)
+    }
+
+    /// application unit
+    ///
+    /// Streams sharing `application.process.id` are grouped; streams without
+    /// a pid stand alone.
+    pub fn group(&self) -> String {
+        if self.pid == "-" {
+            format!("id:{}", self.id)
+        } else {
+            format!("pid:{}", self.pid)
+        }
+    }
+}
+
+/// ducking implementation
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DuckEngine {
+    /// wpctl volume changes
+    #[default]
+    Volume,
+    /// filter-chain sidechain compressor
+    Sidechain,
+}
+
+/// duck factor mapping
+///
+/// wpctl volumes are cubic, so `linear` (multiply the wpctl value) ducks much
+/// harder than the factor suggests. `cubic` scales the signal amplitude by the
+/// factor; `db` maps the duck amount onto a 0..-60 dB attenuation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DuckCurve {
+    #[default]
+    Linear,
+    Cubic,
+    Db,
+}
+
+/// streams that appear while ducked
+///
+/// `duck-after-baseline` reads the stream's volume first and ducks it from
+/// there; if the read fails it waits for the first Props update.
+/// `cap-at-duck-level` ducks right away, assuming full volume when the read
+/// fails. `leave-alone` does not touch new streams until the next restore.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NewStreamPolicy {
+    CapAtDuckLevel,
+    LeaveAlone,
+    #[default]
+    DuckAfterBaseline,
+}
+
+impl DuckCurve {
+    /// attenuation range for db
+    const DB_RANGE: f32 = 60.0;
+
+    /// target volume
+    pub fn apply(self, base: f32, factor: f32) -> f32 {
+        let factor = factor.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => base * factor,
+            Self::Cubic => base * factor.cbrt(),
+            Self::Db => {
+                if factor <= 0.0 {
+                    return 0.0;
+                }
+                let gain = 10f32.powf(-(1.0 - factor) * Self::DB_RANGE / 20.0);
+                base * gain.cbrt()
+            }
+        }
+    }
+}
+
+/// unit of volume control
+///
+/// `client` treats all streams of one process as a single volume: a new
+/// stream of an app that already plays takes the app's baseline without a
+/// read, so apps that open a node per sound are ducked right away, and the
+/// client's streams are written with one backend call.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VolumeScope {
+    #[default]
+    Node,
+    Client,
+}
+
 /// case-insensitive contains
 pub fn contains_ci(haystack: &str, needle: &str) -> bool {
     haystack
@@ -25,19 +151,49 @@ pub fn contains_ci(haystack: &str, needle: &str) -> bool {
         .contains(&needle.to_ascii_lowercase())
 }
 
-/// voice heuristic
-pub fn is_voice_candidate(s: &OutputStream) -> bool {
-    // keywords
-    s.app == "WEBRTC VoiceEngine"
-        || contains_ci(&s.app, "voiceengine")
-        || contains_ci(&s.node, "voiceengine")
-        || contains_ci(&s.media, "playstream")
-        || contains_ci(&s.bin, "discord")
-        || contains_ci(&s.role, "communication")
+/// nodes fed by `node`
+pub fn linked_targets(links: &HashMap<u32, (u32, u32)>, node: u32) -> Vec<u32> {
+    let mut targets: Vec<u32> = links
+        .values()
+        .filter(|(out, _)| *out == node)
+        .map(|(_, inp)| *inp)
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+    targets
 }
 
-/// get volume
-pub fn wpctl_get_volume(id: u32) -> Option<f32> {
+/// `wpctl get-volume` output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WpctlVolume {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+/// parse `wpctl get-volume` output
+///
+/// Takes `Volume: 0.65`, `Volume: 0.65 [MUTED]`, decimal commas and
+/// per-channel lists like `Volume: FL: 0.40 FR: 0.60`, which are mixed.
+pub fn parse_wpctl_volume(text: &str) -> Option<WpctlVolume> {
+    let rest = text.split_once("Volume:").map_or(text, |(_, rest)| rest);
+    let re = Regex::new(r"\b[0-9]+(?:[.,][0-9]+)?\b").ok()?;
+    let channels: Vec<f32> = re
+        .find_iter(rest)
+        .filter_map(|m| m.as_str().replace(',', ".").parse().ok())
+        .collect();
+    let volume = match channels.as_slice() {
+        [] => return None,
+        [v] => *v,
+        many => mix_channels(many),
+    };
+    Some(WpctlVolume {
+        volume,
+        muted: rest.contains("[MUTED]"),
+    })
+}
+
+/// get volume and mute
+pub fn wpctl_get(id: u32) -> Option<WpctlVolume> {
     let out = Command::new("wpctl")
         .args(["get-volume", &id.to_string()])
         .output()
@@ -45,11 +201,12 @@ pub fn wpctl_get_volume(id: u32) -> Option<f32> {
     if !out.status.success() {
         return None;
     }
-    let s = String::from_utf8_lossy(&out.stdout);
-    // first float
-    let re = Regex::new(r"([0-9]+(?:\.[0-9]+)?)").ok()?;
-    let cap = re.captures(&s)?;
-    cap.get(1)?.as_str().parse::<f32>().ok()
+    parse_wpctl_volume(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// get volume
+pub fn wpctl_get_volume(id: u32) -> Option<f32> {
+    wpctl_get(id).map(|v| v.volume)
 }
 
 /// set volume
@@ -62,29 +219,715 @@ pub fn wpctl_set_volume(id: u32, vol: f32) -> bool {
         .unwrap_or(false)
 }
 
+/// overall volume of channel volumes
+///
+/// Channel gains are averaged, like WirePlumber does for `wpctl get-volume`.
+pub fn mix_channels(channels: &[f32]) -> f32 {
+    if channels.is_empty() {
+        return 0.0;
+    }
+    (channels.iter().map(|c| c.powi(3)).sum::<f32>() / channels.len() as f32).cbrt()
+}
+
+/// all channels at the same level
+pub fn is_balanced(channels: &[f32]) -> bool {
+    channels.windows(2).all(|w| (w[0] - w[1]).abs() < 0.001)
+}
+
+/// volume control
+pub trait VolumeBackend: Send + Sync + fmt::Debug {
+    fn get(&self, id: u32) -> Option<f32>;
+    fn set(&self, id: u32, vol: f32) -> bool;
+
+    /// per-channel volumes
+    fn get_channels(&self, id: u32) -> Option<Vec<f32>> {
+        self.get(id).map(|v| vec![v])
+    }
+
+    /// set per-channel volumes
+    fn set_channels(&self, id: u32, channels: &[f32]) -> bool {
+        self.set(id, mix_channels(channels))
+    }
+
+    /// channel volumes of several streams, e.g. all streams of a client
+    ///
+    /// A balanced stream may be given a single channel, set on all of them.
+    fn set_group(&self, streams: &[(u32, Vec<f32>)]) -> bool {
+        let mut ok = true;
+        for (id, channels) in streams {
+            ok &= self.set_channels(*id, channels);
+        }
+        ok
+    }
+
+    fn is_muted(&self, _id: u32) -> bool {
+        false
+    }
+
+    /// problem to show once, e.g. in the TUI log
+    fn take_alert(&self) -> Option<String> {
+        None
+    }
+}
+
+/// channelVolumes from `pw-dump <id>`, wpctl scale
+fn pw_dump_channels(id: u32) -> Option<Vec<f32>> {
+    pw_dump(Some(id)).remove(&id)
+}
+
+/// channelVolumes of every node in `pw-dump [id]`, wpctl scale
+fn pw_dump(id: Option<u32>) -> HashMap<u32, Vec<f32>> {
+    let mut cmd = Command::new("pw-dump");
+    if let Some(id) = id {
+        cmd.arg(id.to_string());
+    }
+    let Some(out) = cmd.output().ok().filter(|out| out.status.success()) else {
+        return HashMap::new();
+    };
+    serde_json::from_slice::<serde_json::Value>(&out.stdout)
+        .map(|dump| parse_pw_dump(&dump))
+        .unwrap_or_default()
+}
+
+fn parse_pw_dump(dump: &serde_json::Value) -> HashMap<u32, Vec<f32>> {
+    let Some(objects) = dump.as_array() else {
+        return HashMap::new();
+    };
+    objects
+        .iter()
+        .filter_map(|o| {
+            let id = u32::try_from(o["id"].as_u64()?).ok()?;
+            let props = o.pointer("/info/params/Props")?.as_array()?;
+            let channels = props.iter().find_map(|p| {
+                let channels: Vec<f32> = p["channelVolumes"]
+                    .as_array()?
+                    .iter()
+                    .filter_map(serde_json::Value::as_f64)
+                    .map(|v| (v as f32).cbrt())
+                    .collect();
+                (!channels.is_empty()).then_some(channels)
+            })?;
+            Some((id, channels))
+        })
+        .collect()
+}
+
+/// `pw-cli set-param` Props with channel gains, wpctl scale in
+fn channel_props(channels: &[f32]) -> String {
+    let gains: Vec<String> = channels
+        .iter()
+        .map(|c| c.clamp(0.0, 1.5).powi(3).to_string())
+        .collect();
+    format!("{{ channelVolumes: [ {} ] }}", gains.join(", "))
+}
+
+/// wpctl subprocess backend
+///
+/// wpctl only sets one volume for all channels; unbalanced channels are
+/// set through `pw-cli set-param`, and so are groups, all in one `pw-cli`.
+#[derive(Debug, Default)]
+pub struct Wpctl {
+    /// channel count per node, for group writes
+    widths: Mutex<HashMap<u32, usize>>,
+}
+
+impl VolumeBackend for Wpctl {
+    fn get(&self, id: u32) -> Option<f32> {
+        wpctl_get_volume(id)
+    }
+
+    fn set(&self, id: u32, vol: f32) -> bool {
+        wpctl_set_volume(id, vol)
+    }
+
+    fn get_channels(&self, id: u32) -> Option<Vec<f32>> {
+        pw_dump_channels(id).or_else(|| self.get(id).map(|v| vec![v]))
+    }
+
+    fn is_muted(&self, id: u32) -> bool {
+        wpctl_get(id).is_some_and(|v| v.muted)
+    }
+
+    fn set_channels(&self, id: u32, channels: &[f32]) -> bool {
+        if is_balanced(channels) {
+            return self.set(id, channels.first().copied().unwrap_or(0.0));
+        }
+        Command::new("pw-cli")
+            .args([
+                "set-param",
+                &id.to_string(),
+                "Props",
+                &channel_props(channels),
+            ])
+            .output()
+            .map(|out| out.status.success())
+            .unwrap_or(false)
+    }
+
+    /// one `pw-cli` reading a `set-param` per stream from stdin
+    fn set_group(&self, streams: &[(u32, Vec<f32>)]) -> bool {
+        if streams.len() < 2 {
+            return streams.iter().all(|(id, ch)| self.set_channels(*id, ch));
+        }
+        let mut widths = self.widths.lock().unwrap_or_else(|e| e.into_inner());
+        if streams
+            .iter()
+            .any(|(id, ch)| is_balanced(ch) && !widths.contains_key(id))
+        {
+            widths.extend(pw_dump(None).into_iter().map(|(id, ch)| (id, ch.len())));
+        }
+        let mut script = String::new();
+        let mut ok = true;
+        for (id, channels) in streams {
+            let channels = match (is_balanced(channels), widths.get(id)) {
+                (false, _) => channels.clone(),
+                (true, Some(n)) => vec![channels.first().copied().unwrap_or(0.0); *n],
+                // not in the dump, set on its own
+                (true, None) => {
+                    ok &= self.set_channels(*id, channels);
+                    continue;
+                }
+            };
+            script.push_str(&format!(
+                "set-param {id} Props {}\n",
+                channel_props(&channels)
+            ));
+        }
+        drop(widths);
+        if script.is_empty() {
+            return ok;
+        }
+        let child = Command::new("pw-cli")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            return false;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(script.as_bytes()).is_ok());
+        let exited = child.wait().is_ok_and(|status| status.success());
+        ok && written && exited
+    }
+}
+
+/// pulse volume norm
+const PA_VOLUME_NORM: f32 = 65536.0;
+
+/// pactl node kind
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PaKind {
+    SinkInput,
+    Sink,
+}
+
+/// pactl subprocess backend
+///
+/// Fallback for systems without WirePlumber. pipewire-pulse lists the
+/// PipeWire id as `object.id`, which is mapped to the pulse index.
+#[derive(Debug, Default)]
+pub struct Pactl {
+    index: std::sync::Mutex<HashMap<u32, (PaKind, u32)>>,
+}
+
+/// one `pactl list` entry
+#[derive(Debug, Clone, PartialEq)]
+struct PaEntry {
+    index: u32,
+    object_id: Option<u32>,
+    volumes: Vec<f32>,
+    muted: bool,
+}
+
+/// parse `LC_ALL=C pactl list sinks|sink-inputs`
+fn parse_pactl_list(text: &str) -> Vec<PaEntry> {
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with(char::is_whitespace) {
+            if let Some((_, idx)) = trimmed.rsplit_once('#') {
+                if let Ok(index) = idx.parse() {
+                    entries.push(PaEntry {
+                        index,
+                        object_id: None,
+                        volumes: Vec::new(),
+                        muted: false,
+                    });
+                }
+            }
+            continue;
+        }
+        let Some(entry) = entries.last_mut() else {
+            continue;
+        };
+        if let Some(rest) = trimmed.strip_prefix("Volume:") {
+            // "front-left: 65536 / 100% / 0.00 dB,   front-right: ..."
+            entry.volumes = rest
+                .split(',')
+                .filter_map(|ch| ch.split_once(':'))
+                .filter_map(|(_, v)| v.split('/').next())
+                .filter_map(|raw| raw.trim().parse::<f32>().ok())
+                .map(|raw| raw / PA_VOLUME_NORM)
+                .collect();
+        } else if let Some(mute) = trimmed.strip_prefix("Mute:") {
+            entry.muted = mute.trim() == "yes";
+        } else if let Some(id) = trimmed.strip_prefix("object.id = ") {
+            entry.object_id = id.trim_matches('"').parse().ok();
+        }
+    }
+    entries
+}
+
+impl Pactl {
+    fn list(kind: PaKind) -> Vec<PaEntry> {
+        let what = match kind {
+            PaKind::SinkInput => "sink-inputs",
+            PaKind::Sink => "sinks",
+        };
+        Command::new("pactl")
+            .env("LC_ALL", "C")
+            .args(["list", what])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| parse_pactl_list(&String::from_utf8_lossy(&out.stdout)))
+            .unwrap_or_default()
+    }
+
+    /// look up `id`, refreshing the index map
+    fn find(&self, id: u32) -> Option<(PaKind, PaEntry)> {
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let kinds = match index.get(&id) {
+            Some((PaKind::Sink, _)) => [PaKind::Sink, PaKind::SinkInput],
+            _ => [PaKind::SinkInput, PaKind::Sink],
+        };
+        for kind in kinds {
+            for entry in Self::list(kind) {
+                if let Some(object_id) = entry.object_id {
+                    index.insert(object_id, (kind, entry.index));
+                }
+                if entry.object_id == Some(id) {
+                    return Some((kind, entry));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl VolumeBackend for Pactl {
+    fn get(&self, id: u32) -> Option<f32> {
+        self.get_channels(id).map(|ch| mix_channels(&ch))
+    }
+
+    fn set(&self, id: u32, vol: f32) -> bool {
+        self.set_channels(id, &[vol])
+    }
+
+    fn get_channels(&self, id: u32) -> Option<Vec<f32>> {
+        Some(self.find(id)?.1.volumes).filter(|v| !v.is_empty())
+    }
+
+    fn is_muted(&self, id: u32) -> bool {
+        self.find(id).is_some_and(|(_, e)| e.muted)
+    }
+
+    fn set_channels(&self, id: u32, channels: &[f32]) -> bool {
+        let cached = self
+            .index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+            .copied();
+        let Some((kind, index)) = cached.or_else(|| self.find(id).map(|(k, e)| (k, e.index)))
+        else {
+            return false;
+        };
+        let cmd = match kind {
+            PaKind::SinkInput => "set-sink-input-volume",
+            PaKind::Sink => "set-sink-volume",
+        };
+        let raw: Vec<String> = channels
+            .iter()
+            .map(|c| ((c.clamp(0.0, 1.5) * PA_VOLUME_NORM).round() as u32).to_string())
+            .collect();
+        Command::new("pactl")
+            .args([cmd, &index.to_string()])
+            .args(&raw)
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+}
+
+/// installed check
+pub fn tool_available(tool: &str) -> bool {
+    match Command::new(tool).arg("--version").output() {
+        Err(err) => err.kind() != std::io::ErrorKind::NotFound,
+        Ok(_) => true,
+    }
+}
+
+/// wpctl answers with a volume
+///
+/// False if it runs but prints something else, e.g. an unsupported version.
+fn wpctl_healthy() -> bool {
+    match Command::new("wpctl")
+        .args(["get-volume", "@DEFAULT_AUDIO_SINK@"])
+        .output()
+    {
+        // no default sink is not wpctl's fault
+        Ok(out) if out.status.success() => {
+            parse_wpctl_volume(&String::from_utf8_lossy(&out.stdout)).is_some()
+        }
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
+/// wpctl, else pactl
+///
+/// wpctl keeps pactl as fallback if it starts failing at runtime.
+pub fn detect_backend() -> Result<Arc<dyn VolumeBackend>, Error> {
+    let pactl = tool_available("pactl");
+    if tool_available("wpctl") {
+        if wpctl_healthy() || !pactl {
+            let fallback = pactl.then(|| Fallback::new("pactl", Box::new(Pactl::default())));
+            return Ok(Arc::new(Checked::new(
+                "wpctl",
+                Box::new(Wpctl::default()),
+                fallback,
+            )));
+        }
+        warn!("wpctl returned unexpected output, using pactl for volumes");
+    } else if pactl {
+        warn!("wpctl not found, using pactl for volumes");
+    }
+    if pactl {
+        return Ok(Arc::new(Checked::new(
+            "pactl",
+            Box::new(Pactl::default()),
+            None,
+        )));
+    }
+    Err(Error::VolumeBackend)
+}
+
+/// failed calls in a row before a backend counts as broken
+const BACKEND_FAILURES: u32 = 8;
+
+/// backend to switch to
+#[derive(Debug)]
+pub struct Fallback {
+    name: &'static str,
+    backend: Box<dyn VolumeBackend>,
+}
+
+impl Fallback {
+    pub fn new(name: &'static str, backend: Box<dyn VolumeBackend>) -> Self {
+        Self { name, backend }
+    }
+}
+
+/// backend that notices when every call fails
+///
+/// After `BACKEND_FAILURES` failed reads or writes in a row it switches to
+/// the fallback, or raises an alert once if there is none, so baselines and
+/// ducks do not fail silently.
+#[derive(Debug)]
+pub struct Checked {
+    name: &'static str,
+    primary: Box<dyn VolumeBackend>,
+    fallback: Option<Fallback>,
+    switched: AtomicBool,
+    failures: AtomicU32,
+    alerted: AtomicBool,
+    alert: Mutex<Option<String>>,
+}
+
+impl Checked {
+    pub fn new(
+        name: &'static str,
+        primary: Box<dyn VolumeBackend>,
+        fallback: Option<Fallback>,
+    ) -> Self {
+        Self {
+            name,
+            primary,
+            fallback,
+            switched: AtomicBool::new(false),
+            failures: AtomicU32::new(0),
+            alerted: AtomicBool::new(false),
+            alert: Mutex::new(None),
+        }
+    }
+
+    fn active(&self) -> &dyn VolumeBackend {
+        match &self.fallback {
+            Some(fb) if self.switched.load(Ordering::Relaxed) => fb.backend.as_ref(),
+            _ => self.primary.as_ref(),
+        }
+    }
+
+    fn track(&self, ok: bool) {
+        if ok {
+            self.failures.store(0, Ordering::Relaxed);
+            return;
+        }
+        if self.failures.fetch_add(1, Ordering::Relaxed) + 1 < BACKEND_FAILURES {
+            return;
+        }
+        self.failures.store(0, Ordering::Relaxed);
+        let msg = match &self.fallback {
+            Some(fb) if !self.switched.swap(true, Ordering::Relaxed) => {
+                crate::messages::backend_switched(self.name, fb.name)
+            }
+            _ if self.alerted.swap(true, Ordering::Relaxed) => return,
+            _ => crate::messages::backend_broken(self.active_name()),
+        };
+        error!("{msg}");
+        events::emit(Event::Error {
+            message: msg.clone(),
+        });
+        *self.alert.lock().unwrap() = Some(msg);
+    }
+
+    fn active_name(&self) -> &'static str {
+        match &self.fallback {
+            Some(fb) if self.switched.load(Ordering::Relaxed) => fb.name,
+            _ => self.name,
+        }
+    }
+}
+
+impl VolumeBackend for Checked {
+    fn get(&self, id: u32) -> Option<f32> {
+        let v = self.active().get(id);
+        self.track(v.is_some());
+        v
+    }
+
+    fn set(&self, id: u32, vol: f32) -> bool {
+        let ok = self.active().set(id, vol);
+        self.track(ok);
+        ok
+    }
+
+    fn get_channels(&self, id: u32) -> Option<Vec<f32>> {
+        let v = self.active().get_channels(id);
+        self.track(v.is_some());
+        v
+    }
+
+    fn set_channels(&self, id: u32, channels: &[f32]) -> bool {
+        let ok = self.active().set_channels(id, channels);
+        self.track(ok);
+        ok
+    }
+
+    fn set_group(&self, streams: &[(u32, Vec<f32>)]) -> bool {
+        let ok = self.active().set_group(streams);
+        self.track(ok);
+        ok
+    }
+
+    fn is_muted(&self, id: u32) -> bool {
+        self.active().is_muted(id)
+    }
+
+    fn take_alert(&self) -> Option<String> {
+        self.alert.lock().unwrap().take()
+    }
+}
+
+/// recent volumes set per stream
+const ECHO_HISTORY: usize = 4;
+/// first restore retry, doubled per attempt
+const RETRY_BASE: Duration = Duration::from_millis(100);
+/// restore attempts before giving up
+const RETRY_MAX: u32 = 6;
+
+/// pending restore retry
+#[derive(Debug, Copy, Clone)]
+struct Retry {
+    attempts: u32,
+    next: Instant,
+}
+
+/// volumes the guard set on one stream
+#[derive(Debug, Clone, Default)]
+struct Applied {
+    recent: Vec<f32>,
+    /// volume / baseline
+    gain: f32,
+}
+
+/// planned volume change of one stream
+#[derive(Debug, Clone)]
+struct Write {
+    id: u32,
+    base: f32,
+    vol: f32,
+    /// unbalanced streams, set per channel
+    channels: Option<Vec<f32>>,
+    /// volume / baseline once set
+    gain: f32,
+}
+
+/// volume of one stream as the guard sees it
+#[derive(Debug, Copy, Clone)]
+pub struct StreamVolume {
+    pub baseline: f32,
+    /// None until read back
+    pub current: Option<f32>,
+    /// differs from what pw-duck set last
+    pub overridden: bool,
+}
+
 #[derive(Debug)]
 pub struct RestoreGuard {
+    backend: Arc<dyn VolumeBackend>,
     baselines: HashMap<u32, f32>,
     ids: Vec<u32>,
     voice_id: Option<u32>,
+    voice_baseline: Option<f32>,
+    voice_boost: f32,
+    voice_boosted: bool,
+    curve: DuckCurve,
+    factors: HashMap<u32, f32>,
+    /// unbalanced streams, channel volumes at baseline
+    channels: HashMap<u32, Vec<f32>>,
+    /// left alone while ducking
+    muted: HashSet<u32>,
+    /// per-rule attack/hold
+    timings: HashMap<u32, Timing>,
+    /// per-rule lowest volume
+    floors: HashMap<u32, f32>,
+    /// client scope, streams of one client written together
+    clients: HashMap<u32, String>,
+    /// outside their own attack/hold, left alone while ducked
+    held: HashSet<u32>,
+    /// appeared mid-duck, left alone until restored
+    late: HashSet<u32>,
+    /// last applied duck factor
+    factor: f32,
+    /// restores that did not read back
+    pending: HashMap<u32, Retry>,
+    /// ducked with nothing to duck
+    armed: bool,
+    applied: HashMap<u32, Applied>,
+    /// last volume read back or reported by PipeWire
+    observed: HashMap<u32, f32>,
+    /// restores if pw-duck is killed
+    helper: Option<RestoreHelper>,
     pub ducked: bool,
-    gui_enabled: bool,
 }
 
 impl RestoreGuard {
-    pub fn new(baselines: &HashMap<u32, f32>, voice_id: Option<u32>, gui_enabled: bool) -> Self {
+    pub fn new(
+        backend: Arc<dyn VolumeBackend>,
+        baselines: &HashMap<u32, f32>,
+        voice_id: Option<u32>,
+        curve: DuckCurve,
+    ) -> Self {
         let mut ids: Vec<u32> = baselines
             .keys()
             .copied()
             .filter(|id| Some(*id) != voice_id)
             .collect();
         ids.sort_unstable();
-        Self {
+        let mut guard = Self {
+            backend,
             baselines: baselines.clone(),
             ids,
             voice_id,
+            voice_baseline: None,
+            voice_boost: 1.0,
+            voice_boosted: false,
+            curve,
+            factors: HashMap::new(),
+            channels: HashMap::new(),
+            muted: HashSet::new(),
+            timings: HashMap::new(),
+            floors: HashMap::new(),
+            clients: HashMap::new(),
+            held: HashSet::new(),
+            late: HashSet::new(),
+            factor: 1.0,
+            pending: HashMap::new(),
+            armed: false,
+            applied: HashMap::new(),
+            observed: HashMap::new(),
+            helper: None,
             ducked: false,
-            gui_enabled,
+        };
+        for id in guard.ids.clone() {
+            guard.capture_channels(id, guard.baselines[&id]);
+            guard.capture_muted(id);
+        }
+        guard
+    }
+
+    /// keep the helper up to date with every change
+    pub fn set_helper(&mut self, helper: Option<RestoreHelper>) {
+        self.helper = helper;
+        self.sync_helper();
+    }
+
+    /// streams changed from their baseline, for the helper
+    fn sync_helper(&mut self) {
+        let Some(helper) = self.helper.as_mut() else {
+            return;
+        };
+        let mut changed: Vec<Changed> = self
+            .ids
+            .iter()
+            .filter_map(|id| {
+                let applied = self.applied.get(id)?;
+                if applied.gain >= 0.999 && !self.pending.contains_key(id) {
+                    return None;
+                }
+                Some(Changed {
+                    id: *id,
+                    baseline: *self.baselines.get(id)?,
+                    channels: self.channels.get(id).cloned(),
+                    set: *applied.recent.last()?,
+                })
+            })
+            .collect();
+        if let (true, Some(id), Some(base)) =
+            (self.voice_boosted, self.voice_id, self.voice_baseline)
+        {
+            changed.push(Changed {
+                id,
+                baseline: base,
+                channels: None,
+                set: base * self.voice_boost,
+            });
+        }
+        helper.update(changed);
+    }
+
+    /// raise voice while ducked
+    pub fn set_voice_boost(&mut self, baseline: f32, boost: f32) {
+        self.voice_baseline = Some(baseline);
+        self.voice_boost = boost;
+    }
+
+    /// voice stream gone
+    pub fn clear_voice_boost(&mut self) {
+        self.voice_baseline = None;
+        self.voice_boosted = false;
+    }
+
+    /// voice source changed
+    pub fn set_voice(&mut self, id: Option<u32>) {
+        self.clear_voice_boost();
+        self.voice_id = id;
+        if let Some(id) = id {
+            self.remove_stream(id);
         }
     }
 
@@ -96,74 +939,625 @@ impl RestoreGuard {
         if !self.ids.contains(&id) {
             self.ids.push(id);
         }
+        self.capture_channels(id, baseline);
+        self.capture_muted(id);
+    }
+
+    /// client scope: baseline shared with the client, nothing read
+    ///
+    /// Channels are treated as balanced; mute comes with the next Props update.
+    pub fn add_client_stream(&mut self, id: u32, baseline: f32) {
+        if Some(id) == self.voice_id {
+            return;
+        }
+        self.baselines.insert(id, baseline);
+        if !self.ids.contains(&id) {
+            self.ids.push(id);
+        }
+    }
+
+    fn capture_muted(&mut self, id: u32) {
+        let muted = self.backend.is_muted(id);
+        self.set_muted(id, muted);
+    }
+
+    /// mute state changed, true if it differs
+    pub fn set_muted(&mut self, id: u32, muted: bool) -> bool {
+        if !self.baselines.contains_key(&id) {
+            return false;
+        }
+        if muted {
+            self.muted.insert(id)
+        } else {
+            self.muted.remove(&id)
+        }
+    }
+
+    /// re-read every baseline, unless streams are lowered
+    ///
+    /// Picks up changes PipeWire did not report, e.g. made while pw-duck was
+    /// suspended. Returns how many baselines changed.
+    pub fn refresh_baselines(&mut self) -> usize {
+        if self.ducked && !self.armed {
+            return 0;
+        }
+        let mut changed = 0;
+        for id in self.ids.clone() {
+            if self.pending.contains_key(&id) {
+                continue;
+            }
+            let Some(vol) = self.backend.get_channels(id).map(|ch| mix_channels(&ch)) else {
+                continue;
+            };
+            if self
+                .baselines
+                .get(&id)
+                .is_none_or(|b| (b - vol).abs() >= 0.01)
+            {
+                debug!("baseline refreshed: id={id} -> {vol}");
+                self.baselines.insert(id, vol);
+                self.applied.remove(&id);
+                changed += 1;
+            }
+            self.observed.insert(id, vol);
+            self.capture_channels(id, vol);
+            self.capture_muted(id);
+        }
+        if changed > 0 {
+            self.sync_helper();
+        }
+        changed
+    }
+
+    /// remember unbalanced channels, scaled to `baseline`
+    fn capture_channels(&mut self, id: u32, baseline: f32) {
+        let channels = self.backend.get_channels(id).filter(|ch| !is_balanced(ch));
+        let current = channels.as_deref().map_or(0.0, mix_channels);
+        match channels {
+            Some(ch) if current > 0.001 => {
+                let scaled = ch.iter().map(|c| c * baseline / current).collect();
+                self.channels.insert(id, scaled);
+            }
+            _ => {
+                self.channels.remove(&id);
+            }
+        }
+    }
+
+    /// per-stream duck factor, 1.0 leaves the stream alone
+    pub fn set_stream_factor(&mut self, id: u32, factor: Option<f32>) {
+        if !self.baselines.contains_key(&id) {
+            return;
+        }
+        match factor {
+            Some(f) => self.factors.insert(id, f),
+            None => self.factors.remove(&id),
+        };
+    }
+
+    pub fn stream_factor(&self, id: u32) -> Option<f32> {
+        self.factors.get(&id).copied()
+    }
+
+    /// per-stream attack/hold
+    pub fn set_stream_timing(&mut self, id: u32, timing: Timing) {
+        if !self.baselines.contains_key(&id) {
+            return;
+        }
+        if timing.is_default() {
+            self.timings.remove(&id);
+        } else {
+            self.timings.insert(id, timing);
+        }
+    }
+
+    pub fn set_stream_floor(&mut self, id: u32, floor: Option<f32>) {
+        if !self.baselines.contains_key(&id) {
+            return;
+        }
+        match floor {
+            Some(f) => self.floors.insert(id, f),
+            None => self.floors.remove(&id),
+        };
+    }
+
+    /// client of a stream under client scope
+    pub fn set_stream_client(&mut self, id: u32, client: Option<String>) {
+        if !self.baselines.contains_key(&id) {
+            return;
+        }
+        match client {
+            Some(c) => self.clients.insert(id, c),
+            None => self.clients.remove(&id),
+        };
+    }
+
+    pub fn stream_timings(&self) -> impl Iterator<Item = (u32, Timing)> + '_ {
+        self.timings.iter().map(|(id, t)| (*id, *t))
+    }
+
+    /// hold back streams whose own attack/hold says so
+    ///
+    /// While ducked, streams entering the held set are restored and streams
+    /// leaving it are ducked right away.
+    pub fn update_held(&mut self, held: impl Fn(u32) -> bool) -> usize {
+        let mut failures = 0;
+        for id in self.ids.clone() {
+            let now_held = held(id);
+            if now_held == self.held.contains(&id) {
+                continue;
+            }
+            if now_held {
+                self.held.insert(id);
+            } else {
+                self.held.remove(&id);
+            }
+            if self.ducked {
+                let factor = if now_held { 1.0 } else { self.factor };
+                if let Some((_, _, false)) = self.apply_one(id, factor) {
+                    failures += 1;
+                }
+            }
+        }
+        failures
+    }
+
+    /// leave-alone: not ducked until the next restore
+    pub fn leave_until_restore(&mut self, id: u32) {
+        if self.baselines.contains_key(&id) {
+            self.late.insert(id);
+        }
+    }
+
+    pub fn is_tracked(&self, id: u32) -> bool {
+        self.baselines.contains_key(&id)
+    }
+
+    /// any stream a duck would lower
+    pub fn has_duckable(&self) -> bool {
+        self.ids
+            .iter()
+            .any(|id| !self.muted.contains(id) && self.factors.get(id).is_none_or(|f| *f < 0.999))
     }
 
     pub fn remove_stream(&mut self, id: u32) {
         self.baselines.remove(&id);
+        self.factors.remove(&id);
+        self.channels.remove(&id);
+        self.muted.remove(&id);
+        self.timings.remove(&id);
+        self.floors.remove(&id);
+        self.clients.remove(&id);
+        self.held.remove(&id);
+        self.late.remove(&id);
+        self.pending.remove(&id);
+        self.applied.remove(&id);
+        self.observed.remove(&id);
         self.ids.retain(|v| *v != id);
+        self.sync_helper();
+    }
+
+    /// volume or balance changed outside pw-duck
+    ///
+    /// Echoes of the guard's own changes are ignored. A change while ducked is
+    /// mapped back through the duck gain, so the stream restores to the level
+    /// the user picked. Returns the new baseline.
+    pub fn external_change(&mut self, id: u32, channels: &[f32]) -> Option<f32> {
+        let base = *self.baselines.get(&id)?;
+        let vol = mix_channels(channels);
+        self.observed.insert(id, vol);
+        let applied = self.applied.entry(id).or_insert_with(|| Applied {
+            recent: vec![base],
+            gain: 1.0,
+        });
+        let gain = applied.gain;
+        if gain < 0.01 {
+            return None;
+        }
+        let echo = applied.recent.iter().any(|v| (v - vol).abs() < 0.01);
+        let same_balance = match self.channels.get(&id) {
+            Some(ch) => {
+                ch.len() == channels.len()
+                    && ch
+                        .iter()
+                        .zip(channels)
+                        .all(|(c, v)| (c * gain - v).abs() < 0.01)
+            }
+            None => is_balanced(channels),
+        };
+        if echo && same_balance {
+            return None;
+        }
+        applied.recent = vec![vol];
+        let new_base = (vol / gain).clamp(0.0, 1.5);
+        self.baselines.insert(id, new_base);
+        if is_balanced(channels) {
+            self.channels.remove(&id);
+        } else {
+            let scaled = channels.iter().map(|c| c / gain).collect();
+            self.channels.insert(id, scaled);
+        }
+        self.sync_helper();
+        Some(new_base)
     }
 
     pub fn apply_duck(&mut self, factor: f32) -> usize {
-        let failures = self.apply_factor(factor, None, false, true);
+        let mut failures = self.apply_factor(factor, None, false, true);
         self.ducked = factor < 0.999;
+        if self.update_armed() {
+            return failures;
+        }
+        failures += self.apply_voice_boost(self.ducked);
+        self.emit_duck(factor, failures);
         failures
     }
 
+    /// step of a gain ramp, no events
+    pub fn ramp(&mut self, factor: f32) -> usize {
+        let failures = self.apply_factor(factor, None, false, false);
+        self.ducked = true;
+        failures
+    }
+
+    /// ducked, but no other stream is actually lowered
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    /// arm instead of ducking when nothing else plays
+    ///
+    /// Streams appearing while armed are ducked by the next `apply_duck`.
+    fn update_armed(&mut self) -> bool {
+        let lowered = self
+            .ids
+            .iter()
+            .any(|id| self.applied.get(id).is_some_and(|a| a.gain < 0.999));
+        let armed = self.ducked && !lowered;
+        if armed && !self.armed {
+            debug!("armed: no other stream to duck");
+        }
+        self.armed = armed;
+        if armed {
+            self.apply_voice_boost(false);
+        }
+        armed
+    }
+
     pub fn restore(&mut self) -> usize {
-        let failures = self.apply_factor(1.0, None, false, false);
+        if std::mem::take(&mut self.armed) {
+            // nothing was changed
+            self.ducked = false;
+            self.held.clear();
+            self.late.clear();
+            return 0;
+        }
+        let mut failures = self.apply_factor(1.0, None, false, false);
         self.ducked = false;
+        self.held.clear();
+        self.late.clear();
+        failures += self.apply_voice_boost(false);
+        self.emit_duck(1.0, failures);
         failures
     }
 
     pub fn apply_duck_logged(&mut self, factor: f32, prefix: &str, log_per_stream: bool) -> usize {
-        let failures = self.apply_factor(factor, Some(prefix), log_per_stream, true);
+        let mut failures = self.apply_factor(factor, Some(prefix), log_per_stream, true);
         self.ducked = factor < 0.999;
+        self.armed = false;
+        failures += self.apply_voice_boost(self.ducked);
+        self.emit_duck(factor, failures);
         failures
     }
 
     #[cfg(feature = "dev-tools")]
     pub fn restore_logged(&mut self, prefix: &str, log_per_stream: bool) -> usize {
-        let failures = self.apply_factor(1.0, Some(prefix), log_per_stream, true);
+        let mut failures = self.apply_factor(1.0, Some(prefix), log_per_stream, true);
         self.ducked = false;
+        self.held.clear();
+        self.late.clear();
+        failures += self.apply_voice_boost(false);
+        self.emit_duck(1.0, failures);
         failures
     }
 
+    fn apply_voice_boost(&mut self, on: bool) -> usize {
+        let (Some(id), Some(base)) = (self.voice_id, self.voice_baseline) else {
+            return 0;
+        };
+        if on == self.voice_boosted {
+            return 0;
+        }
+        let vol = if on { base * self.voice_boost } else { base };
+        if self.backend.set(id, vol) {
+            self.voice_boosted = on;
+            self.sync_helper();
+            0
+        } else {
+            1
+        }
+    }
+
+    fn emit_duck(&self, factor: f32, failures: usize) {
+        let streams = self.ids.len();
+        events::emit(if self.ducked {
+            Event::DuckApplied {
+                factor,
+                streams,
+                failures,
+            }
+        } else {
+            Event::DuckRestored { streams, failures }
+        });
+    }
+
+    /// set one stream, None if left alone
+    fn apply_one(&mut self, id: u32, factor: f32) -> Option<(f32, f32, bool)> {
+        let write = self.plan(id, factor)?;
+        let ok = match &write.channels {
+            Some(ch) => self.backend.set_channels(id, ch),
+            None => self.backend.set(id, write.vol),
+        };
+        if ok {
+            self.record(&write);
+        }
+        Some((write.base, write.vol, ok))
+    }
+
+    /// what `factor` sets on one stream, None if left alone
+    fn plan(&self, id: u32, factor: f32) -> Option<Write> {
+        let base = *self.baselines.get(&id)?;
+        // silent at baseline, nothing to restore to
+        if base < 0.001 {
+            return None;
+        }
+        let ducked_by_us = self.applied.get(&id).is_some_and(|a| a.gain < 0.999);
+        // muted: not ducked, but undone if ducked before the mute
+        if self.muted.contains(&id) && (factor < 0.999 || !ducked_by_us) {
+            return None;
+        }
+        // excluded and never ducked, left as the user set it
+        let excluded = self.factors.get(&id).is_some_and(|f| *f >= 0.999);
+        if factor >= 0.999 && excluded && !ducked_by_us {
+            return None;
+        }
+        let mut factor = factor;
+        if factor < 0.999 {
+            if self.held.contains(&id) || self.late.contains(&id) {
+                return None;
+            }
+            match self.factors.get(&id) {
+                // excluded since, undo our own duck
+                Some(f) if *f >= 0.999 && ducked_by_us => factor = 1.0,
+                Some(f) if *f >= 0.999 => return None,
+                Some(f) => factor = *f,
+                None => {}
+            }
+            // already quieter than a full-volume stream ducked to the target
+            if base < self.curve.apply(1.0, factor) {
+                if !ducked_by_us {
+                    return None;
+                }
+                factor = 1.0;
+            }
+        }
+        let mut new_vol = self.curve.apply(base, factor).clamp(0.0, 1.5);
+        // rule floor, never above the baseline
+        let floor = self
+            .floors
+            .get(&id)
+            .map(|f| f.min(base))
+            .filter(|f| factor < 0.999 && new_vol < *f);
+        if let Some(floor) = floor {
+            new_vol = floor;
+        }
+        // already ducked there
+        let last = self.applied.get(&id).and_then(|a| a.recent.last().copied());
+        if factor < 0.999 && last.is_some_and(|v| (v - new_vol).abs() < 0.001) {
+            return None;
+        }
+        let channels = self.channels.get(&id).map(|ch| {
+            ch.iter()
+                .map(|c| match floor {
+                    Some(_) => c * new_vol / base,
+                    None => self.curve.apply(*c, factor).clamp(0.0, 1.5),
+                })
+                .collect()
+        });
+        Some(Write {
+            id,
+            base,
+            vol: new_vol,
+            channels,
+            gain: match floor {
+                Some(_) => new_vol / base,
+                None => self.curve.apply(1.0, factor),
+            },
+        })
+    }
+
+    /// remember a write that went through, for echoes and restores
+    fn record(&mut self, write: &Write) {
+        let applied = self.applied.entry(write.id).or_default();
+        if applied.recent.len() >= ECHO_HISTORY {
+            applied.recent.remove(0);
+        }
+        applied.recent.push(write.vol);
+        applied.gain = write.gain;
+    }
+
+    /// one backend call per client under client scope, one per stream
+    /// otherwise; unbalanced streams keep their balance either way
+    fn send(&self, writes: &[Write]) -> Vec<bool> {
+        let mut ok = vec![false; writes.len()];
+        let mut groups: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (i, write) in writes.iter().enumerate() {
+            match (self.clients.get(&write.id), &write.channels) {
+                (Some(client), _) => groups.entry(client.as_str()).or_default().push(i),
+                (None, Some(ch)) => ok[i] = self.backend.set_channels(write.id, ch),
+                (None, None) => ok[i] = self.backend.set(write.id, write.vol),
+            }
+        }
+        for members in groups.values() {
+            let streams: Vec<(u32, Vec<f32>)> = members
+                .iter()
+                .map(|i| {
+                    let write = &writes[*i];
+                    let channels = write.channels.clone();
+                    (write.id, channels.unwrap_or_else(|| vec![write.vol]))
+                })
+                .collect();
+            let group_ok = self.backend.set_group(&streams);
+            for i in members {
+                ok[*i] = group_ok;
+            }
+        }
+        ok
+    }
+
+    /// volume reads back as `vol`
+    fn reads_back(&mut self, id: u32, vol: f32) -> bool {
+        self.read_back(id).is_some_and(|v| (v - vol).abs() < 0.01)
+    }
+
+    /// current volume from the backend
+    fn read_back(&mut self, id: u32) -> Option<f32> {
+        let current = if self.channels.contains_key(&id) {
+            self.backend.get_channels(id).map(|ch| mix_channels(&ch))
+        } else {
+            self.backend.get(id)
+        };
+        if let Some(v) = current {
+            self.observed.insert(id, v);
+        }
+        current
+    }
+
+    /// baseline and live volume for the stream pane
+    pub fn stream_volume(&self, id: u32) -> Option<StreamVolume> {
+        let baseline = *self.baselines.get(&id)?;
+        let current = self.observed.get(&id).copied();
+        let set = self.applied.get(&id).and_then(|a| a.recent.last().copied());
+        Some(StreamVolume {
+            baseline,
+            current,
+            overridden: matches!((current, set), (Some(c), Some(s)) if (c - s).abs() >= 0.01),
+        })
+    }
+
+    /// queue restores that failed or did not read back
+    fn verify_restore(&mut self, restored: &[(u32, f32, bool)]) {
+        let next = Instant::now() + RETRY_BASE;
+        for &(id, vol, ok) in restored {
+            if ok && self.reads_back(id, vol) {
+                self.pending.remove(&id);
+            } else {
+                debug!("restore not verified: id={id}, retrying");
+                self.pending.insert(id, Retry { attempts: 0, next });
+            }
+        }
+    }
+
+    /// retry failed restores whose backoff elapsed
+    ///
+    /// Returns the streams given up on.
+    pub fn retry_restores(&mut self, now: Instant) -> Vec<u32> {
+        if self.ducked || self.pending.is_empty() {
+            return Vec::new();
+        }
+        let due: Vec<u32> = self
+            .pending
+            .iter()
+            .filter(|(_, r)| r.next <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut gave_up = Vec::new();
+        for id in due {
+            let verified = match self.apply_one(id, 1.0) {
+                Some((_, vol, ok)) => ok && self.reads_back(id, vol),
+                // nothing to restore any more
+                None => true,
+            };
+            if verified {
+                info!("restore retry ok: id={id}");
+                self.pending.remove(&id);
+                continue;
+            }
+            let Some(retry) = self.pending.get_mut(&id) else {
+                continue;
+            };
+            retry.attempts += 1;
+            if retry.attempts >= RETRY_MAX {
+                self.pending.remove(&id);
+                warn!("restore failed for id={id} after {RETRY_MAX} attempts");
+                events::emit(Event::Error {
+                    message: format!("restore failed for id={id}"),
+                });
+                gave_up.push(id);
+            } else {
+                retry.next = now + RETRY_BASE * 2u32.pow(retry.attempts);
+            }
+        }
+        self.sync_helper();
+        gave_up
+    }
+
+    /// restores not verified yet
+    pub fn pending_restores(&self) -> usize {
+        self.pending.len()
+    }
+
     fn apply_factor(
-        &self,
+        &mut self,
         factor: f32,
         prefix: Option<&str>,
         log_per_stream: bool,
         warn_summary: bool,
     ) -> usize {
+        self.factor = factor;
+        let restoring = factor >= 0.999;
+        if !restoring {
+            self.pending.clear();
+        }
         let mut failures = 0;
-        for id in self.ids.iter().copied() {
-            let Some(base) = self.baselines.get(&id) else {
-                continue;
-            };
-            let new_vol = (*base * factor).clamp(0.0, 1.5);
-            let ok = wpctl_set_volume(id, new_vol);
+        let mut restored = Vec::new();
+        let writes: Vec<Write> = self
+            .ids
+            .iter()
+            .filter_map(|id| self.plan(*id, factor))
+            .collect();
+        let sent = self.send(&writes);
+        for (write, ok) in writes.iter().zip(sent) {
+            if ok {
+                self.record(write);
+            }
+            if restoring {
+                restored.push((write.id, write.vol, ok));
+            }
             if log_per_stream {
-                logln(
-                    self.gui_enabled,
-                    format!(
-                        "{}: id={} base={} -> {} {}",
-                        prefix.unwrap_or(""),
-                        id,
-                        base,
-                        new_vol,
-                        if ok { "ok" } else { "FAIL" }
-                    ),
+                info!(
+                    "{}: id={} base={} -> {} {}",
+                    prefix.unwrap_or(""),
+                    write.id,
+                    write.base,
+                    write.vol,
+                    if ok { "ok" } else { "FAIL" }
                 );
             }
             if !ok {
                 failures += 1;
             }
         }
+        #[cfg(feature = "metrics")]
+        crate::metrics::METRICS
+            .wpctl_failures_total
+            .fetch_add(failures as u64, std::sync::atomic::Ordering::Relaxed);
+        self.verify_restore(&restored);
+        self.sync_helper();
         if warn_summary && failures > 0 {
-            logln(
-                self.gui_enabled,
-                format!("warning: wpctl_set_volume failed for {failures} streams"),
-            );
+            warn!("set volume failed for {failures} streams");
+            events::emit(Event::Error {
+                message: format!("set volume failed for {failures} streams"),
+            });
         }
         failures
     }
@@ -174,13 +1568,719 @@ impl Drop for RestoreGuard {
         if self.ducked {
             let failures = self.restore();
             if failures > 0 {
-                logln(
-                    self.gui_enabled,
-                    format!("restore: failed for {failures} streams"),
-                );
+                warn!("restore: failed for {failures} streams");
             } else {
-                logln(self.gui_enabled, "restore: ok");
+                info!("restore: ok");
             }
         }
+        // last chance, one immediate retry so shutdown is not held up
+        let now = Instant::now();
+        for retry in self.pending.values_mut() {
+            retry.next = now;
+        }
+        self.retry_restores(now);
+        if !self.pending.is_empty() {
+            warn!("restore: {} streams may stay ducked", self.pending.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// in-memory volumes
+    #[derive(Debug, Default)]
+    struct MockBackend {
+        volumes: Mutex<HashMap<u32, f32>>,
+        channels: Mutex<HashMap<u32, Vec<f32>>>,
+        muted: Mutex<HashSet<u32>>,
+        failing: Mutex<HashSet<u32>>,
+        sets: Mutex<usize>,
+        groups: Mutex<Vec<Vec<u32>>>,
+    }
+
+    impl MockBackend {
+        fn with(volumes: &[(u32, f32)]) -> Arc<Self> {
+            let mock = Self::default();
+            mock.volumes.lock().unwrap().extend(volumes.iter().copied());
+            Arc::new(mock)
+        }
+
+        fn vol(&self, id: u32) -> f32 {
+            self.volumes.lock().unwrap()[&id]
+        }
+    }
+
+    impl VolumeBackend for MockBackend {
+        fn get(&self, id: u32) -> Option<f32> {
+            self.volumes.lock().unwrap().get(&id).copied()
+        }
+
+        fn set(&self, id: u32, vol: f32) -> bool {
+            if self.failing.lock().unwrap().contains(&id) {
+                return false;
+            }
+            *self.sets.lock().unwrap() += 1;
+            self.volumes.lock().unwrap().insert(id, vol);
+            true
+        }
+
+        fn get_channels(&self, id: u32) -> Option<Vec<f32>> {
+            let channels = self.channels.lock().unwrap().get(&id).cloned();
+            channels.or_else(|| self.get(id).map(|v| vec![v]))
+        }
+
+        fn set_channels(&self, id: u32, channels: &[f32]) -> bool {
+            self.channels.lock().unwrap().insert(id, channels.to_vec());
+            self.set(id, mix_channels(channels))
+        }
+
+        fn set_group(&self, streams: &[(u32, Vec<f32>)]) -> bool {
+            let ids = streams.iter().map(|(id, _)| *id).collect();
+            self.groups.lock().unwrap().push(ids);
+            for (id, channels) in streams {
+                self.channels.lock().unwrap().insert(*id, channels.clone());
+                self.volumes
+                    .lock()
+                    .unwrap()
+                    .insert(*id, mix_channels(channels));
+            }
+            true
+        }
+
+        fn is_muted(&self, id: u32) -> bool {
+            self.muted.lock().unwrap().contains(&id)
+        }
+    }
+
+    fn guard(mock: &Arc<MockBackend>, voice: Option<u32>) -> RestoreGuard {
+        let baselines: HashMap<u32, f32> = mock.volumes.lock().unwrap().clone();
+        RestoreGuard::new(mock.clone(), &baselines, voice, DuckCurve::Linear)
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn duck_and_restore() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        assert_eq!(g.apply_duck(0.5), 0);
+        assert!(g.ducked);
+        assert!(close(mock.vol(1), 0.4));
+        assert!(close(mock.vol(2), 0.25));
+        assert_eq!(g.restore(), 0);
+        assert!(!g.ducked);
+        assert!(close(mock.vol(1), 0.8));
+        assert!(close(mock.vol(2), 0.5));
+    }
+
+    #[test]
+    fn voice_is_never_ducked() {
+        let mock = MockBackend::with(&[(1, 0.8), (7, 1.0)]);
+        let mut g = guard(&mock, Some(7));
+        g.apply_duck(0.2);
+        assert!(close(mock.vol(7), 1.0));
+        g.add_stream(7, 1.0);
+        g.apply_duck(0.2);
+        assert!(close(mock.vol(7), 1.0));
+    }
+
+    #[test]
+    fn added_and_removed_streams() {
+        let mock = MockBackend::with(&[(1, 0.8)]);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        mock.volumes.lock().unwrap().insert(3, 0.6);
+        g.add_stream(3, 0.6);
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(3), 0.3));
+        g.remove_stream(1);
+        mock.volumes.lock().unwrap().insert(1, 0.1);
+        g.restore();
+        // removed stream is left alone
+        assert!(close(mock.vol(1), 0.1));
+        assert!(close(mock.vol(3), 0.6));
+    }
+
+    #[test]
+    fn failures_are_counted() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        mock.failing.lock().unwrap().insert(2);
+        let mut g = guard(&mock, None);
+        assert_eq!(g.apply_duck(0.5), 1);
+        assert_eq!(g.restore(), 1);
+    }
+
+    #[test]
+    fn stream_factor_overrides() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        g.set_stream_factor(1, Some(1.0));
+        g.set_stream_factor(2, Some(0.1));
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(1), 0.8));
+        assert!(close(mock.vol(2), 0.05));
+    }
+
+    #[test]
+    fn drop_restores() {
+        let mock = MockBackend::with(&[(1, 0.8)]);
+        {
+            let mut g = guard(&mock, None);
+            g.apply_duck(0.0);
+            assert!(close(mock.vol(1), 0.0));
+        }
+        assert!(close(mock.vol(1), 0.8));
+    }
+
+    #[test]
+    fn external_changes() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        // own change is an echo
+        g.apply_duck(0.5);
+        assert_eq!(g.external_change(1, &[0.4, 0.4]), None);
+        // raised while ducked
+        assert!(close(g.external_change(1, &[0.3, 0.3]).unwrap(), 0.6));
+        g.restore();
+        assert!(close(mock.vol(1), 0.6));
+        // changed while restored
+        assert!(close(g.external_change(2, &[0.7]).unwrap(), 0.7));
+        assert_eq!(g.external_change(3, &[0.7]), None);
+        // balance changed
+        assert!(g.external_change(2, &[0.8, 0.6]).is_some());
+        g.apply_duck(0.5);
+        let ch = mock.channels.lock().unwrap()[&2].clone();
+        assert!(close(ch[0], 0.4) && close(ch[1], 0.3));
+    }
+
+    #[test]
+    fn muted_and_silent_streams_are_skipped() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.0), (3, 0.6)]);
+        mock.muted.lock().unwrap().insert(1);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(1), 0.8));
+        assert!(close(mock.vol(2), 0.0));
+        assert!(close(mock.vol(3), 0.3));
+        // muted while ducked is still restored
+        assert!(g.set_muted(3, true));
+        g.restore();
+        assert!(close(mock.vol(3), 0.6));
+        assert!(mock.muted.lock().unwrap().contains(&1));
+    }
+
+    #[test]
+    fn refresh_baselines_only_while_restored() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        mock.set(2, 0.2);
+        assert_eq!(g.refresh_baselines(), 0);
+        g.restore();
+        mock.set(1, 0.6);
+        assert_eq!(g.refresh_baselines(), 1);
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(1), 0.3));
+        g.restore();
+        assert!(close(mock.vol(1), 0.6));
+    }
+
+    #[test]
+    fn excluded_while_ducked_is_restored() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        g.set_stream_factor(1, Some(1.0));
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(1), 0.8));
+        assert!(close(mock.vol(2), 0.25));
+        g.restore();
+        assert!(close(mock.vol(1), 0.8));
+        assert!(close(mock.vol(2), 0.5));
+    }
+
+    #[test]
+    fn restore_leaves_excluded_alone() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        g.set_stream_factor(1, Some(1.0));
+        g.apply_duck(0.5);
+        mock.volumes.lock().unwrap().insert(1, 0.3);
+        g.restore();
+        assert!(close(mock.vol(1), 0.3));
+        assert!(close(mock.vol(2), 0.5));
+    }
+
+    #[test]
+    fn wpctl_output_forms() {
+        let read = |text| parse_wpctl_volume(text).map(|v| (v.volume, v.muted));
+        assert_eq!(read("Volume: 0.65\n"), Some((0.65, false)));
+        assert_eq!(read("Volume: 0.65 [MUTED]\n"), Some((0.65, true)));
+        assert_eq!(read("Volume: 0,65\n"), Some((0.65, false)));
+        let (v, muted) = read("Volume: FL: 0.40 FR: 0.40 [MUTED]\n").unwrap();
+        assert!(close(v, 0.40) && muted);
+        assert_eq!(read("Volume: AUX0: 0.50\n"), Some((0.5, false)));
+        assert_eq!(read("Could not connect\n"), None);
+    }
+
+    #[test]
+    fn checked_backend_switches_to_fallback() {
+        let working = MockBackend::default();
+        working.volumes.lock().unwrap().insert(1, 0.5);
+        let checked = Checked::new(
+            "broken",
+            Box::new(MockBackend::default()),
+            Some(Fallback::new("working", Box::new(working))),
+        );
+        for _ in 0..BACKEND_FAILURES {
+            assert_eq!(checked.get(1), None);
+        }
+        assert!(checked.take_alert().is_some());
+        assert!(checked.take_alert().is_none());
+        assert_eq!(checked.get(1), Some(0.5));
+    }
+
+    #[test]
+    fn duckable_ignores_excluded_and_muted() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        assert!(g.has_duckable());
+        g.set_stream_factor(1, Some(1.0));
+        g.set_muted(2, true);
+        assert!(!g.has_duckable());
+        g.set_muted(2, false);
+        assert!(g.has_duckable());
+    }
+
+    #[test]
+    fn held_streams() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        g.update_held(|id| id == 2);
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(1), 0.4));
+        assert!(close(mock.vol(2), 0.5));
+        // attack reached
+        g.update_held(|_| false);
+        assert!(close(mock.vol(2), 0.25));
+        // own hold over
+        g.update_held(|id| id == 1);
+        assert!(close(mock.vol(1), 0.8));
+        g.restore();
+        assert!(close(mock.vol(2), 0.5));
+    }
+
+    #[test]
+    fn late_streams_left_alone() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.6)]);
+        let mut g = guard(&mock, None);
+        g.remove_stream(2);
+        g.apply_duck(0.5);
+        g.add_stream(2, 0.6);
+        g.leave_until_restore(2);
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(2), 0.6));
+        g.restore();
+        assert!(close(mock.vol(1), 0.8));
+        // ducked with the rest next time
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(2), 0.3));
+    }
+
+    #[test]
+    fn client_streams_share_baseline() {
+        let mock = MockBackend::with(&[(1, 0.8)]);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        g.add_client_stream(2, 0.8);
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(2), 0.4));
+        g.restore();
+        assert!(close(mock.vol(2), 0.8));
+    }
+
+    #[test]
+    fn client_streams_are_written_together() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.8), (3, 0.5)]);
+        let mut g = guard(&mock, None);
+        g.set_stream_client(1, Some("pid:10".into()));
+        g.set_stream_client(2, Some("pid:10".into()));
+        g.apply_duck(0.5);
+        assert_eq!(*mock.groups.lock().unwrap(), [vec![1, 2]]);
+        assert_eq!(*mock.sets.lock().unwrap(), 1);
+        assert!(close(mock.vol(1), 0.4) && close(mock.vol(2), 0.4));
+        assert!(close(mock.vol(3), 0.25));
+        g.restore();
+        assert_eq!(mock.groups.lock().unwrap().len(), 2);
+        assert!(close(mock.vol(1), 0.8) && close(mock.vol(2), 0.8));
+    }
+
+    #[test]
+    fn client_groups_keep_channel_balance() {
+        let mock = MockBackend::with(&[(1, mix_channels(&[0.8, 0.4])), (2, 0.8)]);
+        mock.channels.lock().unwrap().insert(1, vec![0.8, 0.4]);
+        let mut g = guard(&mock, None);
+        g.set_stream_client(1, Some("pid:10".into()));
+        g.set_stream_client(2, Some("pid:10".into()));
+        g.apply_duck(0.5);
+        assert_eq!(*mock.groups.lock().unwrap(), [vec![1, 2]]);
+        let ducked = mock.channels.lock().unwrap()[&1].clone();
+        assert!(close(ducked[0], 0.4) && close(ducked[1], 0.2));
+        assert!(close(mock.vol(2), 0.4));
+        g.restore();
+        let restored = mock.channels.lock().unwrap()[&1].clone();
+        assert!(close(restored[0], 0.8) && close(restored[1], 0.4));
+        assert!(close(mock.vol(2), 0.8));
+        assert_eq!(*mock.sets.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn restore_is_retried() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.5)]);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        mock.failing.lock().unwrap().insert(2);
+        assert_eq!(g.restore(), 1);
+        assert!(close(mock.vol(2), 0.25));
+        // not due yet
+        let now = Instant::now();
+        assert!(g.retry_restores(now).is_empty());
+        assert!(close(mock.vol(2), 0.25));
+        mock.failing.lock().unwrap().clear();
+        assert!(g.retry_restores(now + Duration::from_secs(1)).is_empty());
+        assert!(close(mock.vol(2), 0.5));
+        assert!(g.pending.is_empty());
+    }
+
+    #[test]
+    fn restore_retries_give_up() {
+        let mock = MockBackend::with(&[(1, 0.8)]);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        mock.failing.lock().unwrap().insert(1);
+        g.restore();
+        let mut gave_up = Vec::new();
+        let mut now = Instant::now();
+        for _ in 0..RETRY_MAX {
+            now += Duration::from_secs(10);
+            gave_up.extend(g.retry_restores(now));
+        }
+        assert_eq!(gave_up, vec![1]);
+        assert!(g.pending.is_empty());
+        mock.failing.lock().unwrap().clear();
+    }
+
+    #[test]
+    fn armed_without_other_streams() {
+        let mock = MockBackend::with(&[(7, 1.0)]);
+        let mut g = guard(&mock, Some(7));
+        g.set_voice_boost(1.0, 1.2);
+        g.apply_duck(0.5);
+        assert!(g.ducked && g.is_armed());
+        assert_eq!(*mock.sets.lock().unwrap(), 0);
+        // stream appears while armed
+        mock.volumes.lock().unwrap().insert(1, 0.8);
+        g.add_stream(1, 0.8);
+        g.apply_duck(0.5);
+        assert!(!g.is_armed());
+        assert!(close(mock.vol(1), 0.4));
+        // no-op sets are skipped
+        let sets = *mock.sets.lock().unwrap();
+        g.apply_duck(0.5);
+        assert_eq!(*mock.sets.lock().unwrap(), sets);
+        g.restore();
+        assert!(close(mock.vol(1), 0.8));
+    }
+
+    #[test]
+    fn gain_rate_ramps_duck_and_restore() {
+        use crate::analysis::{auto_vad_step, VadState};
+        use std::time::{Duration, Instant};
+        let mock = MockBackend::with(&[(1, 1.0), (2, 1.0)]);
+        let mut g = guard(&mock, Some(2));
+        let mut vad = VadState::new(false);
+        vad.gain_rate = Some(20.0);
+        let start = Instant::now();
+        let mut tick = |n: u64, energy: f32, g: &mut RestoreGuard| {
+            auto_vad_step(
+                crate::ControlMode::AutoVad,
+                energy,
+                0.01,
+                start + Duration::from_millis(50 * n),
+                &mut vad,
+                g,
+                0.5,
+                &mut |_| {},
+                0,
+                300,
+                None,
+                &mut |_, _, _| {},
+            )
+        };
+        tick(0, 0.1, &mut g);
+        tick(1, 0.1, &mut g);
+        // 1 dB per 50 ms tick
+        assert!(g.ducked);
+        assert!(close(mock.vol(1), 10f32.powf(-1.0 / 20.0)));
+        for n in 2..10 {
+            tick(n, 0.1, &mut g);
+        }
+        assert!(close(mock.vol(1), 0.5));
+        // hold, then fade back up
+        for n in 10..18 {
+            tick(n, 0.0, &mut g);
+        }
+        assert!(g.ducked && mock.vol(1) > 0.5 && mock.vol(1) < 1.0);
+        for n in 18..30 {
+            tick(n, 0.0, &mut g);
+        }
+        assert!(!g.ducked);
+        assert!(close(mock.vol(1), 1.0));
+    }
+
+    #[test]
+    fn ramp_stops_after_outside_restore() {
+        use crate::analysis::{auto_vad_step, VadState};
+        use std::time::{Duration, Instant};
+        let mock = MockBackend::with(&[(1, 1.0), (2, 1.0)]);
+        let mut g = guard(&mock, Some(2));
+        let mut vad = VadState::new(false);
+        vad.gain_rate = Some(20.0);
+        let start = Instant::now();
+        let tick = |n: u64, energy: f32, g: &mut RestoreGuard, vad: &mut VadState| {
+            auto_vad_step(
+                crate::ControlMode::AutoVad,
+                energy,
+                0.01,
+                start + Duration::from_millis(50 * n),
+                vad,
+                g,
+                0.5,
+                &mut |_| {},
+                0,
+                300,
+                None,
+                &mut |_, _, _| {},
+            )
+        };
+        for n in 0..4 {
+            tick(n, 0.1, &mut g, &mut vad);
+        }
+        assert!(g.ducked && mock.vol(1) < 1.0);
+        // e.g. suspend, without stop_ramp
+        g.restore();
+        vad.voice_active = false;
+        for n in 4..10 {
+            tick(n, 0.0, &mut g, &mut vad);
+        }
+        assert!(!g.ducked);
+        assert!(close(mock.vol(1), 1.0));
+    }
+
+    #[test]
+    fn transitions_settle_before_flipping() {
+        use crate::analysis::{auto_vad_step, VadState};
+        use std::time::{Duration, Instant};
+        let mock = MockBackend::with(&[(1, 1.0), (2, 1.0)]);
+        let mut g = guard(&mock, Some(2));
+        let mut vad = VadState::new(false);
+        let start = Instant::now();
+        // no attack or hold, only the settle time keeps the state
+        let mut tick = |n: u64, energy: f32, g: &mut RestoreGuard| {
+            auto_vad_step(
+                crate::ControlMode::AutoVad,
+                energy,
+                0.01,
+                start + Duration::from_millis(50 * n),
+                &mut vad,
+                g,
+                0.5,
+                &mut |_| {},
+                0,
+                0,
+                None,
+                &mut |_, _, _| {},
+            );
+            g.ducked
+        };
+        tick(0, 0.1, &mut g);
+        assert!(tick(1, 0.1, &mut g));
+        // flickering within 250 ms of the duck
+        for n in 2..6 {
+            let energy = if n % 2 == 0 { 0.0 } else { 0.1 };
+            assert!(tick(n, energy, &mut g), "tick {n}");
+        }
+        assert!(!tick(6, 0.0, &mut g));
+        // and within 250 ms of the restore
+        for n in 7..11 {
+            assert!(!tick(n, 0.1, &mut g), "tick {n}");
+        }
+        assert!(tick(11, 0.1, &mut g));
+        assert!(close(mock.vol(1), 0.5));
+    }
+
+    #[test]
+    fn onset_votes_need_n_of_m() {
+        use crate::analysis::{auto_vad_step, VadState};
+        use std::time::{Duration, Instant};
+        let mock = MockBackend::with(&[(1, 1.0), (2, 1.0)]);
+        let mut g = guard(&mock, Some(2));
+        let mut vad = VadState::new(false);
+        vad.onset_votes = Some((3, 5));
+        let start = Instant::now();
+        let mut tick = |n: u64, above: bool, g: &mut RestoreGuard| {
+            auto_vad_step(
+                crate::ControlMode::AutoVad,
+                if above { 0.1 } else { 0.0 },
+                0.01,
+                start + Duration::from_millis(50 * n),
+                &mut vad,
+                g,
+                0.5,
+                &mut |_| {},
+                0,
+                300,
+                None,
+                &mut |_, _, _| {},
+            );
+            g.ducked
+        };
+        // a single pop
+        for n in 0..6 {
+            assert!(!tick(n, n == 0, &mut g), "tick {n}");
+        }
+        // 2 of the last 5
+        assert!(!tick(6, true, &mut g));
+        assert!(!tick(7, false, &mut g));
+        assert!(!tick(8, true, &mut g));
+        // 3 of the last 5, with a dip in between
+        assert!(tick(9, true, &mut g));
+        assert!(close(mock.vol(1), 0.5));
+    }
+
+    #[test]
+    fn release_keeps_voice_above_it() {
+        use crate::analysis::{auto_vad_step, VadState};
+        use std::time::{Duration, Instant};
+        let mock = MockBackend::with(&[(1, 1.0), (2, 1.0)]);
+        let mut g = guard(&mock, Some(2));
+        let mut vad = VadState::new(false);
+        vad.release = Some(0.005);
+        let start = Instant::now();
+        let mut tick = |n: u64, energy: f32, g: &mut RestoreGuard| {
+            auto_vad_step(
+                crate::ControlMode::AutoVad,
+                energy,
+                0.01,
+                start + Duration::from_millis(50 * n),
+                &mut vad,
+                g,
+                0.5,
+                &mut |_| {},
+                0,
+                300,
+                None,
+                &mut |_, _, _| {},
+            );
+            g.ducked
+        };
+        // the release level does not start voice
+        assert!(!tick(0, 0.007, &mut g));
+        assert!(!tick(1, 0.007, &mut g));
+        tick(2, 0.1, &mut g);
+        assert!(tick(3, 0.1, &mut g));
+        // below the threshold, above release, for longer than hold
+        for n in 4..24 {
+            assert!(tick(n, 0.007, &mut g), "tick {n}");
+        }
+        // below release for hold
+        for n in 24..29 {
+            assert!(tick(n, 0.002, &mut g), "tick {n}");
+        }
+        assert!(!tick(29, 0.002, &mut g));
+        assert!(close(mock.vol(1), 1.0));
+    }
+
+    #[test]
+    fn quiet_streams_are_skipped() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.3)]);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(1), 0.4));
+        assert!(close(mock.vol(2), 0.3));
+        // deeper, below the quiet stream too
+        g.apply_duck(0.2);
+        assert!(close(mock.vol(2), 0.06));
+        // shallower again, its duck is undone
+        g.apply_duck(0.5);
+        assert!(close(mock.vol(2), 0.3));
+        g.restore();
+        assert!(close(mock.vol(1), 0.8));
+    }
+
+    #[test]
+    fn min_volume_floor() {
+        let mock = MockBackend::with(&[(1, 0.8), (2, 0.2)]);
+        let mut g = guard(&mock, None);
+        g.set_stream_floor(1, Some(0.3));
+        g.set_stream_floor(2, Some(0.3));
+        g.apply_duck(0.1);
+        assert!(close(mock.vol(1), 0.3));
+        // a stream already below its floor is left alone
+        assert!(close(mock.vol(2), 0.2));
+        g.restore();
+        assert!(close(mock.vol(1), 0.8));
+    }
+
+    #[test]
+    fn channel_balance_is_kept() {
+        let mock = MockBackend::with(&[]);
+        mock.channels.lock().unwrap().insert(1, vec![0.8, 0.4]);
+        let base = mix_channels(&[0.8, 0.4]);
+        mock.volumes.lock().unwrap().insert(1, base);
+        let mut g = guard(&mock, None);
+        g.apply_duck(0.5);
+        let ch = mock.channels.lock().unwrap()[&1].clone();
+        assert!(close(ch[0], 0.4) && close(ch[1], 0.2));
+        g.restore();
+        let ch = mock.channels.lock().unwrap()[&1].clone();
+        assert!(close(ch[0], 0.8) && close(ch[1], 0.4));
+    }
+
+    #[test]
+    fn curves() {
+        assert!(close(DuckCurve::Linear.apply(0.8, 0.5), 0.4));
+        assert!(close(DuckCurve::Cubic.apply(1.0, 0.125), 0.5));
+        assert!(close(DuckCurve::Db.apply(1.0, 1.0), 1.0));
+        assert!(close(DuckCurve::Db.apply(1.0, 0.0), 0.0));
+    }
+
+    #[test]
+    fn pactl_list() {
+        let text = "Sink Input #45
+\tDriver: PipeWire
+\tVolume: front-left: 32768 /  50% / -18.06 dB,   front-right: 32768 /  50% / -18.06 dB
+\t        balance 0.00
+\tProperties:
+\t\tobject.id = \"87\"
+\t\tobject.serial = \"45\"
+
+Sink Input #46
+\tVolume: mono: 65536 / 100% / 0.00 dB
+\tProperties:
+\t\tobject.id = \"90\"
+";
+        let entries = parse_pactl_list(text);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].index, 45);
+        assert_eq!(entries[0].object_id, Some(87));
+        assert_eq!(entries[0].volumes, vec![0.5, 0.5]);
+        assert_eq!(entries[1].object_id, Some(90));
+        assert_eq!(entries[1].volumes, vec![1.0]);
     }
 }
@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use tracing::info;
+
+/// mic capture stream name
+pub const MIC_CAPTURE_NAME: &str = "voice-capture-mic";
+
+/// envelope ticks compared, 2 s at the VAD tick
+const WINDOW: usize = 40;
+/// echo delays tried, up to 500 ms
+const MAX_LAG: usize = 10;
+/// correlation from which the voice counts as an echo
+const THRESHOLD: f32 = 0.7;
+/// mic level below which nobody is talking locally
+const MIC_FLOOR: f32 = 0.002;
+
+/// own voice coming back on the voice source
+///
+/// Compares the level envelope of the local mic with the voice source's at
+/// every delay up to half a second. While the voice follows the mic that
+/// closely, it is the user's own voice echoed by a client without echo
+/// cancellation, and it should not duck anything.
+#[derive(Debug, Default)]
+pub struct EchoReject {
+    mic: VecDeque<f32>,
+    voice: VecDeque<f32>,
+    echo: bool,
+}
+
+impl EchoReject {
+    /// one VAD tick, true while the voice is an echo
+    pub fn step(&mut self, mic: f32, voice: f32) -> bool {
+        self.mic.push_back(mic);
+        self.voice.push_back(voice);
+        if self.mic.len() > WINDOW + MAX_LAG {
+            self.mic.pop_front();
+        }
+        if self.voice.len() > WINDOW {
+            self.voice.pop_front();
+        }
+        let echo = self.mic.len() == WINDOW + MAX_LAG
+            && self.mic.iter().rev().take(WINDOW).any(|m| *m > MIC_FLOOR)
+            && self.best_correlation() >= THRESHOLD;
+        if echo != self.echo {
+            info!(
+                "voice source {}",
+                if echo {
+                    "follows the mic, ignoring own voice echo"
+                } else {
+                    "independent of the mic again"
+                }
+            );
+            self.echo = echo;
+        }
+        echo
+    }
+
+    /// highest correlation over all delays of the voice behind the mic
+    fn best_correlation(&self) -> f32 {
+        let mic: Vec<f32> = self.mic.iter().copied().collect();
+        let voice: Vec<f32> = self.voice.iter().copied().collect();
+        (0..=MAX_LAG)
+            .map(|lag| {
+                let end = mic.len() - lag;
+                correlation(&mic[end - WINDOW..end], &voice)
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Pearson correlation, 0 for a flat signal
+#[allow(clippy::cast_precision_loss)]
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let mean_a = a.iter().sum::<f32>() / n;
+    let mean_b = b.iter().sum::<f32>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let (dx, dy) = (x - mean_a, y - mean_b);
+        cov += dx * dy;
+        var_a += dx * dx;
+        var_b += dy * dy;
+    }
+    if var_a <= f32::EPSILON || var_b <= f32::EPSILON {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// speech-like envelope, fixed per seed
+    fn envelope(seed: u32, len: usize) -> Vec<f32> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                f32::from(u16::try_from((x >> 16) & 0x7fff).unwrap()) / 32_768.0 * 0.05
+            })
+            .collect()
+    }
+
+    fn run(mic: &[f32], voice: &[f32]) -> bool {
+        let mut echo = EchoReject::default();
+        mic.iter()
+            .zip(voice)
+            .map(|(m, v)| echo.step(*m, *v))
+            .last()
+            .unwrap()
+    }
+
+    #[test]
+    fn delayed_copy_is_an_echo() {
+        let mic = envelope(1, 80);
+        // 150 ms later and quieter
+        let voice: Vec<f32> = (0..80_usize)
+            .map(|i| i.checked_sub(3).map_or(0.0, |j| mic[j] * 0.4))
+            .collect();
+        assert!(run(&mic, &voice));
+    }
+
+    #[test]
+    fn independent_voice_is_not() {
+        assert!(!run(&envelope(1, 80), &envelope(2, 80)));
+    }
+
+    #[test]
+    fn silent_mic_is_not() {
+        let mic = vec![0.0; 80];
+        assert!(!run(&mic, &envelope(2, 80)));
+        // below the floor, even if it follows
+        let voice = envelope(2, 80);
+        let mic: Vec<f32> = voice.iter().map(|v| v / 50.0).collect();
+        assert!(!run(&mic, &voice));
+    }
+}
@@ -0,0 +1,74 @@
+use pipewire as pw;
+use std::fmt;
+use std::process::ExitCode;
+
+/// any other error
+const EXIT_FAILURE: u8 = 1;
+
+/// failures scripts may want to tell apart
+///
+/// Each maps to its own exit code; everything else stays an `anyhow` error
+/// and exits with 1.
+#[derive(Debug)]
+pub enum Error {
+    /// nothing to pick as voice source
+    NoCandidates(String),
+    /// chooser closed without a pick
+    SelectionCancelled,
+    /// PipeWire main loop, context or core
+    PipeWireConnect(pw::Error),
+    /// neither wpctl nor pactl
+    VolumeBackend,
+    /// capture stream on the voice source
+    CaptureSetup(pw::Error),
+    /// another ducking agent with `--conflict refuse`
+    Conflict(String),
+}
+
+impl Error {
+    pub const fn exit_code(&self) -> u8 {
+        match self {
+            Self::NoCandidates(_) => 3,
+            Self::SelectionCancelled => 4,
+            Self::PipeWireConnect(_) => 5,
+            Self::VolumeBackend => 6,
+            Self::CaptureSetup(_) => 7,
+            Self::Conflict(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCandidates(detail) => f.write_str(detail),
+            Self::SelectionCancelled => f.write_str("voice source selection cancelled"),
+            Self::PipeWireConnect(_) => f.write_str("cannot connect to PipeWire"),
+            Self::VolumeBackend => {
+                f.write_str("neither wpctl nor pactl found, volumes cannot be changed")
+            }
+            Self::CaptureSetup(_) => f.write_str("capture on the voice source failed"),
+            Self::Conflict(agents) => write!(f, "not starting next to {agents}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::PipeWireConnect(err) | Self::CaptureSetup(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// exit code for a failed run
+///
+/// Prints the error chain, except for a cancelled selection.
+pub fn report(err: &anyhow::Error) -> ExitCode {
+    let known = err.downcast_ref::<Error>();
+    if !matches!(known, Some(Error::SelectionCancelled)) {
+        eprintln!("Error: {err:?}");
+    }
+    ExitCode::from(known.map_or(EXIT_FAILURE, Error::exit_code))
+}
@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// integration events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    VoiceStart {
+        level: f32,
+        /// 0..100
+        confidence: u8,
+    },
+    VoiceStop {
+        level: f32,
+        confidence: u8,
+    },
+    DuckApplied {
+        factor: f32,
+        streams: usize,
+        failures: usize,
+    },
+    DuckRestored {
+        streams: usize,
+        failures: usize,
+    },
+    StreamAdded {
+        id: u32,
+        app: String,
+        node: String,
+        media: String,
+    },
+    StreamRemoved {
+        id: u32,
+    },
+    VoiceSourceLost {
+        id: u32,
+    },
+    /// ducked for too long, volumes restored
+    Watchdog {
+        minutes: u64,
+    },
+    Error {
+        message: String,
+    },
+    /// line shown in the TUI log
+    Log {
+        message: String,
+    },
+}
+
+type Sink = Arc<dyn Fn(&Event) + Send + Sync>;
+
+static SINKS: Mutex<Vec<Sink>> = Mutex::new(Vec::new());
+static CHANNELS: Mutex<Vec<SyncSender<Event>>> = Mutex::new(Vec::new());
+
+/// queued events per channel before dropping
+const CHANNEL_QUEUE: usize = 256;
+
+/// register consumer
+pub fn subscribe(sink: impl Fn(&Event) + Send + Sync + 'static) {
+    SINKS.lock().unwrap().push(Arc::new(sink));
+}
+
+/// removable consumer
+///
+/// Dropped from the list once the receiver is gone; events are dropped when
+/// the receiver lags.
+pub fn channel() -> Receiver<Event> {
+    let (tx, rx) = mpsc::sync_channel(CHANNEL_QUEUE);
+    CHANNELS.lock().unwrap().push(tx);
+    rx
+}
+
+/// dispatch to all sinks
+///
+/// Sinks run without the lock held, so one may emit or block on I/O.
+pub fn emit(event: Event) {
+    let sinks = SINKS.lock().unwrap().clone();
+    for sink in &sinks {
+        sink(&event);
+    }
+    CHANNELS.lock().unwrap().retain(|tx| {
+        !matches!(
+            tx.try_send(event.clone()),
+            Err(TrySendError::Disconnected(_))
+        )
+    });
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    ts_ms: u128,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// one JSON object per line
+pub fn to_json_line(event: &Event) -> String {
+    let ts_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    serde_json::to_string(&Envelope { ts_ms, event }).unwrap_or_default()
+}
+
+/// stdout JSON sink
+pub fn subscribe_json_stdout() {
+    subscribe(|event| {
+        let mut out = io::stdout().lock();
+        let _ = writeln!(out, "{}", to_json_line(event));
+        let _ = out.flush();
+    });
+}
@@ -0,0 +1,175 @@
+use crate::capture::CAPTURE_NAME;
+use crate::echo::MIC_CAPTURE_NAME;
+use crate::ipc::{self, Status};
+use crate::snapshot::{self, Global};
+use anyhow::Result;
+use pipewire::types::ObjectType;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// graph args
+#[derive(clap::Args, Debug)]
+pub struct GraphArgs {
+    /// print a Graphviz DOT graph instead of a list
+    #[arg(long)]
+    dot: bool,
+}
+
+/// what a node is to pw-duck
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Role {
+    Voice,
+    Capture,
+    Sink,
+    Source,
+    Stream,
+}
+
+impl Role {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Voice => "voice source",
+            Self::Capture => "capture",
+            Self::Sink => "sink",
+            Self::Source => "source",
+            Self::Stream => "stream",
+        }
+    }
+
+    /// DOT node attributes
+    fn style(self) -> &'static str {
+        match self {
+            Self::Voice => "shape=ellipse, style=filled, fillcolor=palegreen",
+            Self::Capture => "shape=ellipse, style=filled, fillcolor=lightblue",
+            Self::Sink => "shape=box, style=filled, fillcolor=lightgray",
+            Self::Source => "shape=box",
+            Self::Stream => "shape=ellipse",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    role: Role,
+    name: String,
+}
+
+/// `pw-duck graph`
+///
+/// Voice source, capture streams, sinks and duckable streams with the links
+/// between them. The voice source is taken from the running instance, if any.
+pub fn run(args: &GraphArgs) -> Result<()> {
+    let globals = snapshot::collect()?;
+    let voice = ipc::request("status")
+        .ok()
+        .and_then(|reply| serde_json::from_str::<Status>(&reply).ok())
+        .and_then(|s| s.voice_source_id);
+    let nodes: BTreeMap<u32, Node> = globals
+        .iter()
+        .filter(|g| g.kind == ObjectType::Node)
+        .filter_map(|g| {
+            let role = role(g, voice)?;
+            Some((
+                g.id,
+                Node {
+                    role,
+                    name: name(g),
+                },
+            ))
+        })
+        .collect();
+    // one edge per node pair, links are per port
+    let edges: BTreeSet<(u32, u32)> = globals
+        .iter()
+        .filter(|g| g.kind == ObjectType::Link)
+        .filter_map(|g| {
+            let node = |key| g.prop(key).and_then(|v| v.parse::<u32>().ok());
+            Some((node("link.output.node")?, node("link.input.node")?))
+        })
+        .filter(|(out, inp)| nodes.contains_key(out) && nodes.contains_key(inp))
+        .collect();
+    if args.dot {
+        print!("{}", dot(&nodes, &edges));
+    } else {
+        print!("{}", list(&nodes, &edges));
+    }
+    Ok(())
+}
+
+fn role(g: &Global, voice: Option<u32>) -> Option<Role> {
+    let node = g.prop("node.name").unwrap_or_default();
+    if Some(g.id) == voice {
+        return Some(Role::Voice);
+    }
+    if node.starts_with(CAPTURE_NAME) || node == MIC_CAPTURE_NAME {
+        return Some(Role::Capture);
+    }
+    match g.media_class() {
+        "Audio/Sink" => Some(Role::Sink),
+        "Audio/Source" => Some(Role::Source),
+        "Stream/Output/Audio" => Some(Role::Stream),
+        _ => None,
+    }
+}
+
+/// app for streams, description for devices
+fn name(g: &Global) -> String {
+    [
+        "application.name",
+        "node.description",
+        "node.nick",
+        "node.name",
+    ]
+    .iter()
+    .find_map(|key| g.prop(key))
+    .unwrap_or_else(|| format!("node {}", g.id))
+}
+
+fn list(nodes: &BTreeMap<u32, Node>, edges: &BTreeSet<(u32, u32)>) -> String {
+    let mut out = String::new();
+    for (id, node) in nodes {
+        let targets: Vec<String> = edges
+            .iter()
+            .filter(|(from, _)| from == id)
+            .map(|(_, to)| format!("{} ({to})", nodes[to].name))
+            .collect();
+        let linked = edges.iter().any(|(from, to)| from == id || to == id);
+        out.push_str(&format!(
+            "{:<12} {id:>5} {}{}\n",
+            node.role.label(),
+            node.name,
+            match (targets.is_empty(), linked) {
+                (false, _) => format!(" -> {}", targets.join(", ")),
+                (true, false) if node.role == Role::Capture => " (not linked)".to_string(),
+                _ => String::new(),
+            }
+        ));
+    }
+    out
+}
+
+fn dot(nodes: &BTreeMap<u32, Node>, edges: &BTreeSet<(u32, u32)>) -> String {
+    let mut out = String::from("digraph pw_duck {\n    rankdir=LR;\n");
+    for (id, node) in nodes {
+        let linked = edges.iter().any(|(from, to)| from == id || to == id);
+        let note = if node.role == Role::Capture && !linked {
+            "\\nnot linked"
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "    n{id} [label=\"{}\\n{} {id}{note}\", {}];\n",
+            escape(&node.name),
+            node.role.label(),
+            node.role.style()
+        ));
+    }
+    for (from, to) in edges {
+        out.push_str(&format!("    n{from} -> n{to};\n"));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
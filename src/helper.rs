@@ -0,0 +1,213 @@
+use crate::ducking::{detect_backend, mix_channels, VolumeBackend};
+use crate::volumes::VolumeWatch;
+use anyhow::Result;
+use pipewire as pw;
+use serde::{Deserialize, Serialize};
+use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::flag;
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// sent by the parent before a clean exit
+const DONE: &str = "done";
+/// longest wait for Props of the ducked nodes, then for the writes
+const NATIVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// restore-helper args
+#[derive(clap::Args, Debug)]
+pub struct HelperArgs {
+    /// set volumes in-process, like the parent without wpctl or pactl
+    #[arg(long)]
+    native: bool,
+}
+
+/// one stream pw-duck changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Changed {
+    pub id: u32,
+    /// volume to restore
+    pub baseline: f32,
+    /// per-channel volumes to restore, unbalanced streams only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<f32>>,
+    /// volume pw-duck set last
+    pub set: f32,
+}
+
+/// `pw-duck restore-helper` child
+///
+/// Holds the volumes to restore, one JSON line per change. If the parent
+/// goes away without saying `done` (SIGKILL, OOM kill), the helper restores
+/// them itself.
+#[derive(Debug)]
+pub struct RestoreHelper {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    last: Vec<Changed>,
+}
+
+impl RestoreHelper {
+    /// None if the helper could not be started
+    ///
+    /// `native` when the parent sets volumes in-process.
+    pub fn spawn(native: bool) -> Option<Self> {
+        let spawned = std::env::current_exe().and_then(|exe| {
+            Command::new(exe)
+                .arg("restore-helper")
+                .args(native.then_some("--native"))
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                // out of reach of Ctrl+C on the terminal
+                .process_group(0)
+                .spawn()
+        });
+        match spawned {
+            Ok(mut child) => {
+                debug!("restore helper started: pid={}", child.id());
+                let stdin = child.stdin.take();
+                Some(Self {
+                    child,
+                    stdin,
+                    last: Vec::new(),
+                })
+            }
+            Err(err) => {
+                warn!("restore helper: {err}, volumes stay ducked if pw-duck is killed");
+                None
+            }
+        }
+    }
+
+    /// hand over the streams to restore, if changed
+    pub fn update(&mut self, changed: Vec<Changed>) {
+        if changed == self.last {
+            return;
+        }
+        let Some(stdin) = self.stdin.as_mut() else {
+            return;
+        };
+        let line = serde_json::to_string(&changed).unwrap_or_default();
+        if let Err(err) = writeln!(stdin, "{line}") {
+            warn!("restore helper gone: {err}");
+            self.stdin = None;
+        }
+        self.last = changed;
+    }
+}
+
+impl Drop for RestoreHelper {
+    fn drop(&mut self) {
+        if let Some(mut stdin) = self.stdin.take() {
+            let _ = writeln!(stdin, "{DONE}");
+        }
+        let _ = self.child.wait();
+    }
+}
+
+/// helper side, runs until the parent's stdin closes
+pub fn run(args: &HelperArgs) -> Result<()> {
+    // exit is driven by stdin only
+    for sig in [SIGHUP, SIGINT, SIGTERM] {
+        flag::register(sig, Arc::new(AtomicBool::new(false)))?;
+    }
+    let mut pending: Vec<Changed> = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line == DONE {
+            return Ok(());
+        }
+        match serde_json::from_str(&line) {
+            Ok(changed) => pending = changed,
+            Err(err) => warn!("restore helper: bad line: {err}"),
+        }
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+    info!(
+        "pw-duck exited without restoring, restoring {} streams",
+        pending.len()
+    );
+    if args.native {
+        return restore_native(&pending);
+    }
+    restore(&*detect_backend()?, &pending);
+    Ok(())
+}
+
+/// back to the baselines, unless changed since
+fn restore(backend: &dyn VolumeBackend, pending: &[Changed]) {
+    for c in pending {
+        // changed since, or the id was reused
+        let current = match &c.channels {
+            Some(_) => backend.get_channels(c.id).map(|ch| mix_channels(&ch)),
+            None => backend.get(c.id),
+        };
+        if !current.is_some_and(|v| (v - c.set).abs() < 0.01) {
+            debug!(
+                "restore helper: id={} not at the ducked volume, left alone",
+                c.id
+            );
+            continue;
+        }
+        let ok = match &c.channels {
+            Some(ch) => backend.set_channels(c.id, ch),
+            None => backend.set(c.id, c.baseline),
+        };
+        if !ok {
+            warn!("restore helper: set volume failed for id={}", c.id);
+        }
+    }
+}
+
+/// restore through Props, without wpctl or pactl
+///
+/// Binds the ducked nodes like the parent's volume watch, waits for their
+/// current Props and for the writes to be confirmed.
+fn restore_native(pending: &[Changed]) -> Result<()> {
+    pw::init();
+    let mainloop = pw::main_loop::MainLoopRc::new(None)?;
+    let context = pw::context::ContextRc::new(&mainloop, None)?;
+    let core = crate::connect_core(&context)?;
+    let registry = core.get_registry_rc()?;
+    let watch = Rc::new(VolumeWatch::new(|_, _| {}));
+    let _listener = {
+        let watch = watch.clone();
+        let registry_w = registry.downgrade();
+        let ids: HashSet<u32> = pending.iter().map(|c| c.id).collect();
+        registry
+            .add_listener_local()
+            .global(move |global| {
+                if global.type_ != pw::types::ObjectType::Node || !ids.contains(&global.id) {
+                    return;
+                }
+                if let Some(registry) = registry_w.upgrade() {
+                    watch.watch(&registry, global);
+                }
+            })
+            .register()
+    };
+    let backend = watch.native();
+    // gone nodes never answer
+    let deadline = Instant::now() + NATIVE_TIMEOUT;
+    while Instant::now() < deadline && pending.iter().any(|c| backend.get(c.id).is_none()) {
+        mainloop.loop_().iterate(Duration::from_millis(50));
+    }
+    restore(&backend, pending);
+    watch.flush();
+    let deadline = Instant::now() + NATIVE_TIMEOUT;
+    while !watch.settled() && Instant::now() < deadline {
+        mainloop.loop_().iterate(Duration::from_millis(50));
+    }
+    if !watch.settled() {
+        warn!("restore helper: some volumes were not confirmed");
+    }
+    Ok(())
+}
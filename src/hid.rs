@@ -0,0 +1,174 @@
+use crate::control::{self, Control};
+use crate::events::{self, Event};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use tracing::{debug, info, warn};
+
+/// [hid] table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HidConfig {
+    /// hidraw device
+    pub device: Option<PathBuf>,
+    /// report byte of the first button
+    pub offset: usize,
+    /// one bit per button instead of one byte
+    pub bitmap: bool,
+    /// command per button, "" for none
+    pub buttons: Vec<String>,
+    /// output report written when ducking starts
+    pub led_on: Vec<u8>,
+    /// output report written when volumes are restored
+    pub led_off: Vec<u8>,
+}
+
+impl Default for HidConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            // Stream Deck MK.2, XL and V2
+            offset: 4,
+            bitmap: false,
+            buttons: vec!["toggle".into(), "duck".into(), "restore".into()],
+            led_on: Vec::new(),
+            led_off: Vec::new(),
+        }
+    }
+}
+
+/// HID button thread
+///
+/// Reads input reports from a hidraw device (e.g. `/dev/hidraw3`) and sends
+/// the command of every button that goes down, parsed like the `:` prompt.
+/// With `led_on`/`led_off` the ducking state is written back as output
+/// reports.
+pub fn spawn_reader(device: &Path, map: HidConfig, tx: Sender<Control>) -> Result<()> {
+    let commands = map
+        .buttons
+        .iter()
+        .map(|cmd| {
+            (!cmd.trim().is_empty())
+                .then(|| control::parse(cmd).map_err(|err| anyhow!("hid button '{cmd}': {err}")))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(!map.led_on.is_empty() || !map.led_off.is_empty())
+        .open(device)
+        .map_err(|err| anyhow!("{}: {err}", device.display()))?;
+    info!("hid input: {}", device.display());
+    if !map.led_on.is_empty() || !map.led_off.is_empty() {
+        subscribe_leds(file.try_clone()?, map.led_on.clone(), map.led_off.clone());
+    }
+    thread::Builder::new()
+        .name("pw-duck-hid".into())
+        .spawn(move || {
+            let mut down = vec![false; commands.len()];
+            let mut buf = [0u8; 1024];
+            loop {
+                let len = match file.read(&mut buf) {
+                    Ok(0) => {
+                        warn!("hid device closed");
+                        return;
+                    }
+                    Ok(len) => len,
+                    Err(err) => {
+                        warn!("hid read failed: {err}");
+                        return;
+                    }
+                };
+                let report = &buf[..len];
+                for (i, command) in commands.iter().enumerate() {
+                    let pressed = button(report, map.offset, map.bitmap, i);
+                    // press edge only
+                    if pressed && !down[i] {
+                        if let Some(ctl) = command {
+                            debug!("hid: button {i} -> {ctl:?}");
+                            if tx.send(ctl.clone()).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    down[i] = pressed;
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// state of button `i` in one input report, up when the report is too short
+fn button(report: &[u8], offset: usize, bitmap: bool, i: usize) -> bool {
+    if bitmap {
+        offset
+            .checked_add(i / 8)
+            .and_then(|at| report.get(at))
+            .is_some_and(|b| b & (1 << (i % 8)) != 0)
+    } else {
+        offset
+            .checked_add(i)
+            .and_then(|at| report.get(at))
+            .is_some_and(|b| *b != 0)
+    }
+}
+
+/// ducking state as output reports
+fn subscribe_leds(file: File, on: Vec<u8>, off: Vec<u8>) {
+    let ducked = AtomicBool::new(false);
+    if !off.is_empty() {
+        let _ = (&file).write_all(&off);
+    }
+    let write = move |report: &[u8]| {
+        if report.is_empty() {
+            return;
+        }
+        if let Err(err) = (&file).write_all(report) {
+            warn!("hid led report failed: {err}");
+        }
+    };
+    events::subscribe(move |event| match event {
+        Event::DuckApplied { .. } if !ducked.swap(true, Ordering::Relaxed) => write(&on),
+        Event::DuckRestored { .. } if ducked.swap(false, Ordering::Relaxed) => write(&off),
+        _ => {}
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_per_button() {
+        // Stream Deck: report id, 3 header bytes, then one byte per key
+        let report = [0x01, 0x00, 0x0f, 0x00, 0x00, 0x01, 0x00, 0x01];
+        let down: Vec<bool> = (0..6).map(|i| button(&report, 4, false, i)).collect();
+        assert_eq!(down, [false, true, false, true, false, false]);
+    }
+
+    #[test]
+    fn bit_per_button() {
+        let report = [0x02, 0b0000_0101, 0b1000_0000];
+        let down: Vec<bool> = (0..17).map(|i| button(&report, 1, true, i)).collect();
+        assert!(down[0] && !down[1] && down[2]);
+        assert!(down[15]);
+        assert_eq!(down.iter().filter(|d| **d).count(), 3);
+    }
+
+    #[test]
+    fn short_or_garbage_reports() {
+        for bitmap in [false, true] {
+            assert!(!button(&[], 0, bitmap, 0));
+            assert!(!button(&[0xff; 4], 4, bitmap, 0));
+            assert!(!button(&[0xff; 4], usize::MAX, bitmap, 9));
+        }
+        // every bit set only presses buttons inside the report
+        assert!(button(&[0xff; 2], 1, true, 7));
+        assert!(!button(&[0xff; 2], 1, true, 8));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::events::{self, Event};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// user commands on voice events
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    pub on_voice_start: Option<String>,
+    pub on_voice_stop: Option<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.on_voice_start.is_none() && self.on_voice_stop.is_none()
+    }
+}
+
+/// hook sink
+///
+/// Commands run through `sh -c` without blocking the main loop. The event is
+/// described by `PW_DUCK_EVENT`, `PW_DUCK_LEVEL` and `PW_DUCK_TS_MS`.
+pub fn subscribe(hooks: Hooks) {
+    events::subscribe(move |event| {
+        let (name, cmd, level) = match event {
+            Event::VoiceStart { level, .. } => ("voice_start", &hooks.on_voice_start, *level),
+            Event::VoiceStop { level, .. } => ("voice_stop", &hooks.on_voice_stop, *level),
+            _ => return,
+        };
+        if let Some(cmd) = cmd {
+            run(name, cmd, level);
+        }
+    });
+}
+
+fn run(name: &'static str, cmd: &str, level: f32) {
+    let ts_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let child = Command::new("sh")
+        .args(["-c", cmd])
+        .env("PW_DUCK_EVENT", name)
+        .env("PW_DUCK_LEVEL", level.to_string())
+        .env("PW_DUCK_TS_MS", ts_ms.to_string())
+        .stdin(Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            debug!("hook {name}: started pid={}", child.id());
+            // reap off the main loop
+            thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => warn!("hook {name}: exited with {status}"),
+                Err(err) => warn!("hook {name}: {err}"),
+                _ => {}
+            });
+        }
+        Err(err) => warn!("hook {name}: failed to start: {err}"),
+    }
+}
@@ -0,0 +1,421 @@
+use crate::control::{self, Control};
+use crate::events;
+use crate::spectrum::Spectrum;
+use crate::ui::GroupRow;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// per-user runtime dir, one per `--remote`
+///
+/// Without `XDG_RUNTIME_DIR` it lives in the shared temp dir, so the name
+/// carries the uid.
+pub fn runtime_dir() -> PathBuf {
+    let name = match std::env::var("PIPEWIRE_REMOTE") {
+        Ok(remote) if !remote.is_empty() => format!(
+            "pw-duck-{}",
+            remote.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_")
+        ),
+        _ => "pw-duck".into(),
+    };
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join(name),
+        None => std::env::temp_dir().join(format!("{name}-{}", uid())),
+    }
+}
+
+/// `runtime_dir`, created if missing
+pub fn create_runtime_dir() -> io::Result<PathBuf> {
+    let dir = runtime_dir();
+    private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// directory only the user can enter
+///
+/// A directory of the user that others can read is tightened to 0700; one
+/// owned by someone else, writable by others or a symlink is refused.
+fn private_dir(dir: &Path) -> io::Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let meta = fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != uid() || meta.mode() & 0o022 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a private directory of this user, remove it or set XDG_RUNTIME_DIR",
+                dir.display()
+            ),
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// uid of this process, `u32::MAX` when /proc is missing
+fn uid() -> u32 {
+    fs::metadata("/proc/self").map_or(u32::MAX, |m| m.uid())
+}
+
+/// control socket
+pub fn socket_path() -> PathBuf {
+    runtime_dir().join("pw-duck.sock")
+}
+
+/// live state file
+pub fn state_path() -> PathBuf {
+    runtime_dir().join("state.json")
+}
+
+/// shortest time between state file writes
+const STATE_INTERVAL: Duration = Duration::from_millis(500);
+/// most bands a `spectrum` request may ask for
+const MAX_BANDS: usize = 512;
+
+/// `state.json` writer
+///
+/// Mirrors `Status` for bars and scripts that poll a file instead of the
+/// socket. Written atomically, only when changed, and removed on drop.
+#[derive(Debug, Default)]
+pub struct StateFile {
+    last: String,
+    written: Option<Instant>,
+}
+
+impl StateFile {
+    pub fn update(&mut self, status: &Status, now: Instant) {
+        if self
+            .written
+            .is_some_and(|t| now.duration_since(t) < STATE_INTERVAL)
+        {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(status) else {
+            return;
+        };
+        if json == self.last {
+            return;
+        }
+        let path = state_path();
+        let tmp = path.with_extension("json.tmp");
+        match fs::write(&tmp, &json).and_then(|()| fs::rename(&tmp, &path)) {
+            Ok(()) => {
+                self.last = json;
+                self.written = Some(now);
+            }
+            Err(err) => {
+                if self.written.is_none() {
+                    warn!("state file {}: {err}", path.display());
+                }
+                self.written = Some(now);
+            }
+        }
+    }
+}
+
+impl Drop for StateFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(state_path());
+    }
+}
+
+/// held for process lifetime
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+/// single instance lock
+///
+/// With `takeover` a running instance is asked to restore and exit first.
+pub fn acquire_instance_lock(takeover: bool) -> Result<InstanceLock> {
+    let dir = create_runtime_dir()?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join("pw-duck.lock"))?;
+    match file.try_lock() {
+        Ok(()) => return Ok(InstanceLock { _file: file }),
+        Err(TryLockError::WouldBlock) => {}
+        Err(TryLockError::Error(err)) => return Err(err.into()),
+    }
+    if !takeover {
+        return Err(anyhow!(
+            "another pw-duck instance is running (use --takeover to replace it)"
+        ));
+    }
+    info!("asking running instance to restore and exit");
+    let reply = request("quit")?;
+    debug!("takeover reply: {reply}");
+    // wait for exit
+    for _ in 0..50 {
+        thread::sleep(Duration::from_millis(100));
+        match file.try_lock() {
+            Ok(()) => return Ok(InstanceLock { _file: file }),
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+    }
+    Err(anyhow!(
+        "running instance did not exit within 5s (a text voice source prompt does not see --takeover, answer or quit it first)"
+    ))
+}
+
+/// stream pushed events, one JSON line each
+pub fn subscribe(mut on_line: impl FnMut(&str)) -> io::Result<()> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    writeln!(stream, "subscribe")?;
+    let mut lines = BufReader::new(&stream).lines();
+    match lines.next().transpose()? {
+        Some(reply) if reply == "ok" => {}
+        reply => {
+            return Err(io::Error::other(format!(
+                "unexpected reply: {}",
+                reply.unwrap_or_default()
+            )))
+        }
+    }
+    for line in lines {
+        on_line(&line?);
+    }
+    Ok(())
+}
+
+/// send one command
+pub fn request(command: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    writeln!(stream, "{command}")?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    Ok(reply.trim_end().to_string())
+}
+
+/// live state for `status`
+///
+/// Updated by the VAD timer on every tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Status {
+    pub mode: String,
+    pub voice_source: String,
+    #[serde(default)]
+    pub voice_source_id: Option<u32>,
+    pub voice_active: bool,
+    pub ducked: bool,
+    /// ducked, but nothing else plays
+    #[serde(default)]
+    pub armed: bool,
+    pub level: f32,
+    /// voice confidence, 0..100
+    #[serde(default)]
+    pub confidence: u8,
+    pub threshold: f32,
+    pub duck_factor: f32,
+    pub hold_ms: u64,
+    pub streams: usize,
+    /// why the last duck or restore happened
+    #[serde(default)]
+    pub reason: String,
+    /// active scene, empty for none
+    #[serde(default)]
+    pub scene: String,
+    /// wireplumber, pipewire-media-session or unknown
+    #[serde(default)]
+    pub session_manager: String,
+    /// per-group duck state, as shown in the TUI
+    #[serde(default)]
+    pub groups: Vec<GroupRow>,
+}
+
+/// control socket server
+///
+/// Must only be called while holding the instance lock, since a leftover
+/// socket file is replaced unconditionally.
+pub fn spawn_server(
+    quit_requested: Arc<AtomicBool>,
+    control_tx: Sender<Control>,
+    status: Arc<Mutex<Status>>,
+    spectrum: Spectrum,
+) -> io::Result<()> {
+    let path = socket_path();
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    thread::Builder::new()
+        .name("pw-duck-ipc".into())
+        .spawn(move || serve(&listener, &quit_requested, &control_tx, &status, &spectrum))?;
+    Ok(())
+}
+
+/// one thread per client, so a subscriber does not block commands
+fn serve(
+    listener: &UnixListener,
+    quit_requested: &Arc<AtomicBool>,
+    control_tx: &Sender<Control>,
+    status: &Arc<Mutex<Status>>,
+    spectrum: &Spectrum,
+) {
+    for stream in listener.incoming().flatten() {
+        let quit_requested = Arc::clone(quit_requested);
+        let control_tx = control_tx.clone();
+        let status = Arc::clone(status);
+        let spectrum = spectrum.clone();
+        let spawned = thread::Builder::new()
+            .name("pw-duck-ipc-client".into())
+            .spawn(move || {
+                if let Err(err) =
+                    handle_client(stream, &quit_requested, &control_tx, &status, &spectrum)
+                {
+                    warn!("ipc client error: {err}");
+                }
+            });
+        if let Err(err) = spawned {
+            warn!("ipc client thread: {err}");
+        }
+    }
+}
+
+fn handle_client(
+    stream: UnixStream,
+    quit_requested: &AtomicBool,
+    control_tx: &Sender<Control>,
+    status: &Mutex<Status>,
+    spectrum: &Spectrum,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let reply = match line.trim() {
+            "" => continue,
+            "quit" => {
+                info!("quit requested via ipc");
+                quit_requested.store(true, Ordering::Relaxed);
+                "ok".to_string()
+            }
+            "status" => serde_json::to_string(&*status.lock().unwrap())
+                .unwrap_or_else(|err| format!("error: {err}")),
+            cmd if cmd.starts_with("spectrum") => match cmd["spectrum".len()..].trim().parse() {
+                Ok(n) if n <= MAX_BANDS => serde_json::to_string(&spectrum.bands(n))
+                    .unwrap_or_else(|err| format!("error: {err}")),
+                _ => format!("error: usage: spectrum <1..{MAX_BANDS}>"),
+            },
+            "subscribe" => {
+                debug!("ipc client subscribed");
+                return push_events(writer);
+            }
+            other => match control::parse(other) {
+                Ok(ctl) => {
+                    debug!("ipc control: {ctl:?}");
+                    if control_tx.send(ctl).is_ok() {
+                        "ok".to_string()
+                    } else {
+                        "error: main loop gone".to_string()
+                    }
+                }
+                Err(err) => format!("error: {err}"),
+            },
+        };
+        writeln!(writer, "{reply}")?;
+    }
+    Ok(())
+}
+
+/// one JSON event per line until the client goes away
+fn push_events(mut writer: UnixStream) -> io::Result<()> {
+    let rx = events::channel();
+    writeln!(writer, "ok")?;
+    for event in rx {
+        if let Err(err) = writeln!(writer, "{}", events::to_json_line(&event)) {
+            return match err.kind() {
+                io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset => Ok(()),
+                _ => Err(err),
+            };
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn runtime_dir_is_private() {
+        let base = std::env::temp_dir().join(format!("pw-duck-dir-test-{}", std::process::id()));
+        let dir = base.join("run");
+        let mode = |dir: &Path| fs::metadata(dir).unwrap().mode() & 0o777;
+        private_dir(&dir).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+        // readable for others, as older versions left it
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        private_dir(&dir).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(private_dir(&dir).is_err());
+        let link = base.join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(private_dir(&link).is_err());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn commands_while_subscribed() {
+        let dir = std::env::temp_dir().join(format!("pw-duck-ipc-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.sock");
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let (control_tx, control_rx) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status {
+            mode: "auto".into(),
+            ..Status::default()
+        }));
+        thread::spawn(move || {
+            serve(
+                &listener,
+                &Arc::new(AtomicBool::new(false)),
+                &control_tx,
+                &status,
+                &Spectrum::default(),
+            );
+        });
+
+        let mut subscriber = UnixStream::connect(&path).unwrap();
+        writeln!(subscriber, "subscribe").unwrap();
+        let mut ok = String::new();
+        BufReader::new(&subscriber).read_line(&mut ok).unwrap();
+        assert_eq!(ok.trim(), "ok");
+
+        let ask = |command: &str| {
+            let mut stream = UnixStream::connect(&path).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .unwrap();
+            writeln!(stream, "{command}").unwrap();
+            let mut reply = String::new();
+            BufReader::new(&stream).read_line(&mut reply).unwrap();
+            reply
+        };
+        let status: Status = serde_json::from_str(&ask("status")).unwrap();
+        assert_eq!(status.mode, "auto");
+        assert_eq!(ask("duck").trim(), "ok");
+        assert!(control_rx.try_recv().is_ok());
+        drop(subscriber);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::analysis::{AtomicF32, VAD_TICK_MS};
+use crate::capture::{setup_capture, CaptureFormat, ChannelSelect, Targeting, CAPTURE_NAME};
+use crate::ducking::{detect_backend, VolumeBackend};
+use crate::teststream::{test_stream, RATE};
+use anyhow::{anyhow, Result};
+use pipewire as pw;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+const TONE_NODE: &str = "pw-duck-latency-tone";
+const TARGET_NODE: &str = "pw-duck-latency-target";
+/// silence between bursts
+const GAP: Duration = Duration::from_secs(1);
+/// give up on a burst
+const BURST_TIMEOUT: Duration = Duration::from_secs(3);
+/// missed bursts in a row before giving up
+const MAX_MISSES: usize = 3;
+/// between volume reads
+const POLL: Duration = Duration::from_millis(5);
+
+/// latency args
+#[derive(clap::Args, Debug)]
+pub struct LatencyArgs {
+    /// bursts to measure
+    #[arg(long, default_value_t = 20)]
+    bursts: usize,
+    /// vad threshold
+    #[arg(long, default_value_t = 0.02)]
+    threshold: f32,
+    /// duck factor
+    #[arg(long, default_value_t = 0.45)]
+    duck_factor: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Phase {
+    /// silent until
+    Gap(Instant),
+    /// tone requested at
+    Tone(Instant),
+}
+
+/// one measured burst
+#[derive(Debug, Copy, Clone)]
+struct Sample {
+    /// first tone buffer -> threshold crossing
+    detect: Duration,
+    /// crossing -> volume read back
+    apply: Duration,
+}
+
+/// `pw-duck latency`
+///
+/// Plays tone bursts on a test stream, captures it like a voice source and
+/// ducks a second, silent test stream through the volume backend. Reports
+/// how long detection and the verified volume change take.
+#[allow(clippy::too_many_lines)]
+pub fn run(args: &LatencyArgs) -> Result<()> {
+    pw::init();
+    let mainloop = pw::main_loop::MainLoopRc::new(None)?;
+    let context = pw::context::ContextRc::new(&mainloop, None)?;
+    let core = crate::connect_core(&context)?;
+    let registry = core.get_registry_rc()?;
+
+    let nodes: Rc<RefCell<HashMap<String, u32>>> = Rc::new(RefCell::new(HashMap::new()));
+    let _registry_listener = {
+        let nodes = nodes.clone();
+        registry
+            .add_listener_local()
+            .global(move |global| {
+                let Some(name) = global.props.as_ref().and_then(|p| p.get("node.name")) else {
+                    return;
+                };
+                if name == TONE_NODE || name == TARGET_NODE {
+                    nodes.borrow_mut().insert(name.to_string(), global.id);
+                }
+            })
+            .register()
+    };
+
+    // test streams
+    let tone_on = Rc::new(Cell::new(false));
+    let tone_started: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+    let (tone, _tone_listener) = test_stream(&core, TONE_NODE, {
+        let tone_on = tone_on.clone();
+        let tone_started = tone_started.clone();
+        let phase = Cell::new(0.0f32);
+        move |out: &mut [f32]| {
+            let on = tone_on.get();
+            if on && tone_started.get().is_none() {
+                tone_started.set(Some(Instant::now()));
+            }
+            for s in out.iter_mut() {
+                *s = if on { 0.3 * phase.get().sin() } else { 0.0 };
+                phase.set((phase.get() + TAU * 440.0 / RATE as f32) % TAU);
+            }
+        }
+    })?;
+    let (target, _target_listener) = test_stream(&core, TARGET_NODE, |out: &mut [f32]| {
+        out.fill(0.0);
+    })?;
+
+    let run_for = |d: Duration| -> Result<()> {
+        let quit = mainloop.clone();
+        let timer = mainloop.loop_().add_timer(move |_| quit.quit());
+        timer.update_timer(Some(d), None).into_result()?;
+        mainloop.run();
+        Ok(())
+    };
+    for _ in 0..30 {
+        run_for(Duration::from_millis(100))?;
+        if nodes.borrow().len() == 2 {
+            break;
+        }
+    }
+    let (Some(tone_id), Some(target_id)) = (
+        nodes.borrow().get(TONE_NODE).copied(),
+        nodes.borrow().get(TARGET_NODE).copied(),
+    ) else {
+        return Err(anyhow!("test streams did not appear in the registry"));
+    };
+    let backend: Arc<dyn VolumeBackend> = detect_backend()?;
+    let base = backend
+        .get(target_id)
+        .ok_or_else(|| anyhow!("get volume failed for test stream id={target_id}"))?;
+    info!("tone id={tone_id} target id={target_id} base volume={base}");
+
+    let energy = Arc::new(AtomicF32::new(0.0));
+    let _capture = setup_capture(
+        &core,
+        CAPTURE_NAME,
+        Some(tone_id),
+        Some(TONE_NODE.to_string()),
+        None,
+        Targeting::NodeName,
+        None,
+        CaptureFormat::default(),
+        ChannelSelect::Mix,
+        true,
+        energy.clone(),
+        Arc::new(AtomicF32::new(0.0)),
+        Arc::new(AtomicBool::new(false)),
+        Arc::new(AtomicU64::new(0)),
+        Arc::new(AtomicBool::new(false)),
+        None,
+        None,
+    )?;
+
+    let samples: Rc<RefCell<Vec<Sample>>> = Rc::new(RefCell::new(Vec::new()));
+    let phase = Rc::new(Cell::new(Phase::Gap(Instant::now() + GAP)));
+    let ducked_vol = base * args.duck_factor.clamp(0.0, 1.0);
+    let misses = Rc::new(Cell::new(0usize));
+    let timer = {
+        let samples = samples.clone();
+        let misses = misses.clone();
+        let quit = mainloop.clone();
+        let backend = backend.clone();
+        let bursts = args.bursts;
+        let threshold = args.threshold;
+        mainloop.loop_().add_timer(move |_| {
+            let now = Instant::now();
+            match phase.get() {
+                Phase::Gap(until) if now >= until => {
+                    if samples.borrow().len() >= bursts || misses.get() >= MAX_MISSES {
+                        quit.quit();
+                        return;
+                    }
+                    tone_started.set(None);
+                    tone_on.set(true);
+                    phase.set(Phase::Tone(now));
+                }
+                Phase::Gap(_) => {}
+                Phase::Tone(requested) => {
+                    if now.duration_since(requested) > BURST_TIMEOUT {
+                        warn!("burst not detected (energy={:.4})", energy.load());
+                        misses.set(misses.get() + 1);
+                    } else if energy.load() > threshold {
+                        misses.set(0);
+                        let crossed = Instant::now();
+                        if !backend.set(target_id, ducked_vol) {
+                            warn!("set volume failed");
+                        }
+                        // verified by reading back
+                        while crossed.elapsed() < BURST_TIMEOUT {
+                            match backend.get(target_id) {
+                                Some(v) if (v - ducked_vol).abs() < 0.01 => break,
+                                _ => std::thread::sleep(POLL),
+                            }
+                        }
+                        let applied = Instant::now();
+                        let started = tone_started.get().unwrap_or(requested);
+                        let sample = Sample {
+                            detect: crossed.saturating_duration_since(started),
+                            apply: applied.duration_since(crossed),
+                        };
+                        debug!("burst {}: {:?}", samples.borrow().len() + 1, sample);
+                        samples.borrow_mut().push(sample);
+                    } else {
+                        return;
+                    }
+                    tone_on.set(false);
+                    backend.set(target_id, base);
+                    phase.set(Phase::Gap(Instant::now() + GAP));
+                }
+            }
+        })
+    };
+    timer
+        .update_timer(
+            Some(Duration::from_millis(VAD_TICK_MS)),
+            Some(Duration::from_millis(VAD_TICK_MS)),
+        )
+        .into_result()?;
+    println!(
+        "measuring {} bursts (tick {} ms, threshold {})",
+        args.bursts, VAD_TICK_MS, args.threshold
+    );
+    mainloop.run();
+    backend.set(target_id, base);
+    drop((tone, target));
+
+    if misses.get() >= MAX_MISSES {
+        return Err(anyhow!(
+            "no burst detected in {MAX_MISSES} tries, capture energy stayed below {}; \
+             check that the capture stream is linked to {TONE_NODE}",
+            args.threshold
+        ));
+    }
+    let samples = samples.borrow();
+    if samples.is_empty() {
+        return Err(anyhow!("no bursts measured"));
+    }
+    let detect: Vec<Duration> = samples.iter().map(|s| s.detect).collect();
+    let apply: Vec<Duration> = samples.iter().map(|s| s.apply).collect();
+    let total: Vec<Duration> = samples.iter().map(|s| s.detect + s.apply).collect();
+    println!(
+        "{:<8} {:>8} {:>8} {:>8} {:>8}",
+        "", "p50", "p90", "p99", "max"
+    );
+    for (name, v) in [("detect", detect), ("apply", apply), ("total", total)] {
+        println!(
+            "{:<8} {:>8} {:>8} {:>8} {:>8}",
+            name,
+            ms(percentile(&v, 50)),
+            ms(percentile(&v, 90)),
+            ms(percentile(&v, 99)),
+            ms(percentile(&v, 100))
+        );
+    }
+    Ok(())
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+}
+
+/// nearest-rank percentile
+fn percentile(values: &[Duration], p: usize) -> Duration {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank.min(sorted.len()) - 1]
+}
@@ -0,0 +1,297 @@
+use crate::analysis::{self, MAX_WORD_GAP_MS, VAD_TICK_MS};
+use crate::ipc::{self, Status};
+use crate::ui::{HOLD_MAX_MS, HOLD_STEP_MS, SENS_MAX, SENS_MIN};
+use anyhow::{anyhow, bail, Result};
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// level floor, dBFS
+const FLOOR_DB: f32 = -100.0;
+/// speech needed for a full confidence
+const FULL_SPEECH_S: f32 = 30.0;
+/// least time per cluster to propose anything
+const MIN_CLUSTER_S: f32 = 1.0;
+/// least level gap between silence and speech for a release level
+const MIN_GAP_DB: f32 = 3.0;
+
+/// learn args
+#[derive(clap::Args, Debug)]
+pub struct LearnArgs {
+    /// observation time
+    #[arg(long, default_value_t = 3, value_name = "MIN")]
+    minutes: u64,
+    /// set the proposed threshold, release and hold on the instance
+    #[arg(long)]
+    apply: bool,
+}
+
+/// proposed settings
+#[derive(Debug)]
+struct Proposal {
+    threshold: f32,
+    /// None when silence and speech are too close
+    release: Option<f32>,
+    /// None without pauses between words
+    hold_ms: Option<u64>,
+    /// speech p10 over noise p95, negative when they overlap
+    gap_db: f32,
+    speech_share: f32,
+    /// 0..100
+    confidence: u8,
+}
+
+/// `pw-duck learn`
+///
+/// Samples the level of the running instance for a few minutes of normal
+/// use, splits it into a silence and a speech cluster and proposes settings.
+pub fn run(args: &LearnArgs) -> Result<()> {
+    let current = status().map_err(|err| anyhow!("pw-duck is not running ({err})"))?;
+    let span = Duration::from_secs(args.minutes.max(1) * 60);
+    let tick = Duration::from_millis(VAD_TICK_MS);
+    eprintln!(
+        "listening to {} for {} min, talk and pause as usual...",
+        current.voice_source,
+        span.as_secs() / 60
+    );
+    let start = Instant::now();
+    let mut levels = Vec::new();
+    let mut shown = 0;
+    while start.elapsed() < span {
+        levels.push(status()?.level);
+        let secs = start.elapsed().as_secs();
+        if secs != shown {
+            shown = secs;
+            eprint!("\r{secs:>4} / {} s", span.as_secs());
+            let _ = io::stderr().flush();
+        }
+        thread::sleep(tick);
+    }
+    eprintln!();
+
+    let Some(p) = propose(&levels) else {
+        bail!("could not tell speech from silence, try again with more talking and more pauses");
+    };
+    println!(
+        "observed:   {} s, {:.0}% speech",
+        span.as_secs(),
+        p.speech_share * 100.0
+    );
+    println!(
+        "threshold:  {:.4}   (now {:.4})",
+        p.threshold, current.threshold
+    );
+    match p.release {
+        Some(release) => println!("release:    {release:.4}"),
+        None => println!("release:    none, silence and speech are too close"),
+    }
+    match p.hold_ms {
+        Some(hold) => println!("hold:       {hold} ms   (now {} ms)", current.hold_ms),
+        None => println!(
+            "hold:       no pauses between words seen, keep {} ms",
+            current.hold_ms
+        ),
+    }
+    println!(
+        "hysteresis: {:.1} dB between silence and speech{}",
+        p.gap_db,
+        if p.gap_db < MIN_GAP_DB {
+            " (too little, expect false triggers)"
+        } else {
+            ""
+        }
+    );
+    println!("confidence: {}%", p.confidence);
+
+    if args.apply {
+        let mut commands = vec![format!("set threshold {}", p.threshold)];
+        commands.extend(p.release.map(|r| format!("set release {r}")));
+        commands.extend(p.hold_ms.map(|h| format!("set hold {h}")));
+        for command in commands {
+            let reply = ipc::request(&command)?;
+            if let Some(err) = reply.strip_prefix("error: ") {
+                bail!("{command}: {err}");
+            }
+        }
+        println!("applied");
+    } else {
+        println!(
+            "apply with --apply, or start with --threshold {:.4}{}{}",
+            p.threshold,
+            p.release
+                .map(|r| format!(" --release {r:.4}"))
+                .unwrap_or_default(),
+            p.hold_ms
+                .map(|h| format!(" --hold {h}"))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+fn status() -> Result<Status> {
+    Ok(serde_json::from_str(&ipc::request("status")?)?)
+}
+
+fn to_db(level: f32) -> f32 {
+    if level > 0.0 {
+        (20.0 * level.log10()).max(FLOOR_DB)
+    } else {
+        FLOOR_DB
+    }
+}
+
+/// nearest-rank percentile of sorted values
+fn percentile(sorted: &[f32], p: usize) -> f32 {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank.min(sorted.len()) - 1]
+}
+
+fn mean_var(values: &[f32]) -> (f32, f32) {
+    let n = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+    (mean, var)
+}
+
+/// two-cluster split of levels sampled every VAD tick
+///
+/// Splits the dB levels at the point of largest between-class variance
+/// (Otsu), puts the threshold between the loud end of silence and the quiet
+/// end of speech, the release a quarter of that gap above silence, and
+/// derives hold from the pauses between words.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn propose(levels: &[f32]) -> Option<Proposal> {
+    let min_cluster = (MIN_CLUSTER_S * 1000.0 / VAD_TICK_MS as f32) as usize;
+    let mut sorted: Vec<f32> = levels.iter().map(|l| to_db(*l)).collect();
+    sorted.sort_by(f32::total_cmp);
+    let n = sorted.len();
+    // a flat level has nothing to split, rounding would still pick a point
+    if n < 2 * min_cluster || sorted[0] == sorted[n - 1] {
+        return None;
+    }
+    let total: f32 = sorted.iter().sum();
+    let mut below = 0.0;
+    let mut split = None;
+    let mut best = 0.0;
+    for k in 1..n {
+        below += sorted[k - 1];
+        let (w0, w1) = (k as f32 / n as f32, (n - k) as f32 / n as f32);
+        let between = w0 * w1 * (below / k as f32 - (total - below) / (n - k) as f32).powi(2);
+        if between > best {
+            best = between;
+            split = Some(k);
+        }
+    }
+    let k = split?;
+    let (noise, speech) = sorted.split_at(k);
+    if noise.len() < min_cluster || speech.len() < min_cluster {
+        return None;
+    }
+    let noise_top = percentile(noise, 95);
+    let speech_bottom = percentile(speech, 10);
+    let threshold_db = if noise_top < speech_bottom {
+        (noise_top + speech_bottom) / 2.0
+    } else {
+        (noise[k - 1] + speech[0]) / 2.0
+    };
+    let threshold = 10f32.powf(threshold_db / 20.0).clamp(SENS_MIN, SENS_MAX);
+    let release = (speech_bottom - noise_top >= MIN_GAP_DB).then(|| {
+        10f32
+            .powf((noise_top + threshold_db) / 40.0)
+            .clamp(SENS_MIN, threshold)
+    });
+
+    // pauses between speech runs, ended by the release level like the VAD
+    let mut gaps: Vec<u64> = Vec::new();
+    let mut since_speech: Option<u64> = None;
+    for level in levels {
+        let gate = match release {
+            Some(release) if since_speech.is_some_and(|g| g <= MAX_WORD_GAP_MS) => release,
+            _ => threshold,
+        };
+        if *level > gate {
+            if let Some(gap) = since_speech.filter(|g| *g > 0 && *g <= MAX_WORD_GAP_MS) {
+                gaps.push(gap);
+            }
+            since_speech = Some(0);
+        } else if let Some(gap) = since_speech.as_mut() {
+            *gap += VAD_TICK_MS;
+        }
+    }
+    let hold_ms = (!gaps.is_empty()).then(|| {
+        let hold = analysis::hold_for_gaps(gaps).min(HOLD_MAX_MS);
+        hold.div_ceil(HOLD_STEP_MS) * HOLD_STEP_MS
+    });
+
+    let (noise_mean, noise_var) = mean_var(noise);
+    let (speech_mean, speech_var) = mean_var(speech);
+    let separation = (speech_mean - noise_mean) / ((noise_var + speech_var) / 2.0).sqrt().max(0.1);
+    let speech_s = speech.len() as f32 * VAD_TICK_MS as f32 / 1000.0;
+    let score = ((separation - 1.0) / 3.0).clamp(0.0, 1.0)
+        * (0.5 + 0.5 * (speech_s / FULL_SPEECH_S).min(1.0));
+    Some(Proposal {
+        threshold,
+        release,
+        hold_ms,
+        gap_db: speech_bottom - noise_top,
+        speech_share: speech.len() as f32 / n as f32,
+        confidence: (score * 100.0).round() as u8,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `talk` ticks at `speech`, `pause` ticks at `noise`, each within ±20%
+    fn levels(noise: f32, speech: f32, talk: usize, pause: usize, ticks: usize) -> Vec<f32> {
+        let mut seed = 1_u32;
+        (0..ticks)
+            .map(|i| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                let jitter = 0.8 + 0.4 * f32::from((seed >> 16) as u16) / 65_536.0;
+                let level = if i % (talk + pause) < talk {
+                    speech
+                } else {
+                    noise
+                };
+                level * jitter
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_silence_from_speech() {
+        // two minutes, 1 s words with 300 ms pauses
+        let p = propose(&levels(0.001, 0.05, 20, 6, 2400)).unwrap();
+        assert!(p.threshold > 0.0012 && p.threshold < 0.04, "{p:?}");
+        let release = p.release.unwrap();
+        assert!(release > 0.0012 && release < p.threshold, "{p:?}");
+        assert!((p.speech_share - 20.0 / 26.0).abs() < 0.01, "{p:?}");
+        assert!(p.gap_db > 20.0, "{p:?}");
+        let hold = p.hold_ms.unwrap();
+        assert!((300..=HOLD_MAX_MS).contains(&hold), "{p:?}");
+        assert_eq!(hold % HOLD_STEP_MS, 0);
+        assert!(p.confidence >= 90, "{p:?}");
+    }
+
+    #[test]
+    fn no_release_when_clusters_overlap() {
+        let p = propose(&levels(0.01, 0.012, 20, 6, 2400)).unwrap();
+        assert!(p.gap_db < MIN_GAP_DB, "{p:?}");
+        assert_eq!(p.release, None);
+    }
+
+    #[test]
+    fn rejects_short_or_flat_levels() {
+        assert!(propose(&levels(0.001, 0.05, 20, 6, 30)).is_none());
+        assert!(propose(&[0.02; 2400]).is_none());
+        // talking all the time leaves no silence cluster
+        assert!(propose(&levels(0.001, 0.05, 2000, 10, 2400)).is_none());
+    }
+}
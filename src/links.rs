@@ -0,0 +1,113 @@
+use pipewire as pw;
+use pw::link::Link;
+use pw::properties::properties;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+/// audio port, from the registry
+#[derive(Debug, Clone)]
+struct Port {
+    node: u32,
+    output: bool,
+    /// e.g. FL, MONO
+    channel: String,
+}
+
+/// ports by global id, for linking the capture by hand
+#[derive(Debug, Default)]
+pub struct Ports {
+    ports: RefCell<BTreeMap<u32, Port>>,
+}
+
+impl Ports {
+    pub fn add(&self, id: u32, prop: impl Fn(&str) -> Option<String>) {
+        let Some(node) = prop("node.id").and_then(|n| n.parse().ok()) else {
+            return;
+        };
+        let port = Port {
+            node,
+            output: prop("port.direction").as_deref() == Some("out"),
+            channel: prop("audio.channel").unwrap_or_default(),
+        };
+        self.ports.borrow_mut().insert(id, port);
+    }
+
+    pub fn remove(&self, id: u32) {
+        self.ports.borrow_mut().remove(&id);
+    }
+
+    /// (output, input) port pairs from `from` into `to`
+    ///
+    /// Channels are matched by name; a mono side is linked to every channel
+    /// of the other, anything else in port order.
+    pub fn pairs(&self, from: u32, to: u32) -> Vec<(u32, u32)> {
+        let ports = self.ports.borrow();
+        let side = |node: u32, output: bool| -> Vec<(u32, &str)> {
+            ports
+                .iter()
+                .filter(|(_, p)| p.node == node && p.output == output)
+                .map(|(id, p)| (*id, p.channel.as_str()))
+                .collect()
+        };
+        let outs = side(from, true);
+        let ins = side(to, false);
+        match (outs.len(), ins.len()) {
+            (0, _) | (_, 0) => Vec::new(),
+            (_, 1) => outs.iter().map(|(o, _)| (*o, ins[0].0)).collect(),
+            (1, _) => ins.iter().map(|(i, _)| (outs[0].0, *i)).collect(),
+            _ => {
+                let by_name: Vec<_> = outs
+                    .iter()
+                    .filter_map(|(o, ch)| {
+                        ins.iter()
+                            .find(|(_, c)| !ch.is_empty() && c == ch)
+                            .map(|(i, _)| (*o, *i))
+                    })
+                    .collect();
+                if by_name.is_empty() {
+                    outs.iter()
+                        .zip(&ins)
+                        .map(|((o, _), (i, _))| (*o, *i))
+                        .collect()
+                } else {
+                    by_name
+                }
+            }
+        }
+    }
+}
+
+/// link `from` into `to` through the link factory
+///
+/// The links go away with pw-duck's connection.
+pub fn create(core: &pw::core::CoreRc, ports: &Ports, from: u32, to: u32) -> Vec<Link> {
+    let pairs = ports.pairs(from, to);
+    if pairs.is_empty() {
+        warn!("no ports to link: node {from} -> capture node {to}");
+        return Vec::new();
+    }
+    let links: Vec<Link> = pairs
+        .iter()
+        .filter_map(|(out, inp)| {
+            core.create_object::<Link>(
+                "link-factory",
+                &properties! {
+                    "link.output.node" => from.to_string(),
+                    "link.output.port" => out.to_string(),
+                    "link.input.node" => to.to_string(),
+                    "link.input.port" => inp.to_string(),
+                    "object.linger" => "false",
+                },
+            )
+            .inspect_err(|err| warn!("link {out} -> {inp}: {err}"))
+            .ok()
+        })
+        .collect();
+    info!(
+        "capture linked by hand: node {from} -> {to}, {} of {} ports",
+        links.len(),
+        pairs.len()
+    );
+    links
+}
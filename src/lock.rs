@@ -0,0 +1,68 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// loginctl poll period
+const POLL: Duration = Duration::from_secs(2);
+
+/// logind session watcher
+///
+/// Polls `LockedHint` and `IdleHint` of the current session (set by the
+/// desktop's locker and idle tracking) and keeps the returned flag set while
+/// either is on.
+pub fn spawn_watcher() -> std::io::Result<Arc<AtomicBool>> {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".into());
+    let locked = Arc::new(AtomicBool::new(false));
+    let locked_t = locked.clone();
+    thread::Builder::new()
+        .name("pw-duck-lock".into())
+        .spawn(move || {
+            let mut warned = false;
+            loop {
+                match hints(&session) {
+                    Some(now) => {
+                        if locked_t.swap(now, Ordering::Relaxed) != now {
+                            debug!("session {session}: locked or idle = {now}");
+                        }
+                    }
+                    None if !warned => {
+                        warn!("loginctl: no lock state for session {session}, not pausing on lock");
+                        warned = true;
+                    }
+                    None => {}
+                }
+                thread::sleep(POLL);
+            }
+        })?;
+    info!("pausing while session is locked or idle");
+    Ok(locked)
+}
+
+/// LockedHint || IdleHint, None if logind did not answer
+fn hints(session: &str) -> Option<bool> {
+    let out = Command::new("loginctl")
+        .args([
+            "show-session",
+            session,
+            "-p",
+            "LockedHint",
+            "-p",
+            "IdleHint",
+        ])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    let mut seen = false;
+    let mut on = false;
+    for line in text.lines() {
+        if let Some((_, value)) = line.split_once('=') {
+            seen = true;
+            on |= value.trim() == "yes";
+        }
+    }
+    seen.then_some(on)
+}
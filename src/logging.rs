@@ -1,15 +1,121 @@
-use std::fmt::Display;
+use anyhow::Result;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
 
-pub fn logln(gui_enabled: bool, msg: impl Display) {
-    if gui_enabled {
-        return;
+/// rotated files kept
+const LOG_FILE_KEEP: usize = 3;
+
+/// install subscriber
+///
+/// `RUST_LOG` overrides `level`. Console output is disabled in GUI mode since
+/// the terminal belongs to the TUI; journald and the log file work in both modes.
+pub fn init(
+    gui_enabled: bool,
+    level: &str,
+    journald: bool,
+    log_file: Option<&Path>,
+    log_file_max_kb: u64,
+) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(level))?;
+    let console = (!gui_enabled).then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(io::stderr)
+            .with_target(false)
+    });
+    let journald = if journald {
+        Some(tracing_journald::layer()?.with_syslog_identifier("pw-duck".into()))
+    } else {
+        None
+    };
+    let file = match log_file {
+        Some(path) => {
+            let writer = RotatingFile::open(path, log_file_max_kb.saturating_mul(1024))?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(Mutex::new(writer))
+                    .with_ansi(false)
+                    .with_target(false),
+            )
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(console)
+        .with(journald)
+        .with(file)
+        .try_init()?;
+    Ok(())
+}
+
+/// size-rotated log file
+///
+/// Once `max_bytes` is exceeded the file is moved to `PATH.1` (older ones
+/// shift up to `PATH.{LOG_FILE_KEEP}`) and a fresh file is started.
+#[derive(Debug)]
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            file,
+            written,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for index in (1..LOG_FILE_KEEP).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
     }
-    println!("{msg}");
 }
 
-pub fn elogln(gui_enabled: bool, msg: impl Display) {
-    if gui_enabled {
-        return;
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.max_bytes > 0
+            && self.written > 0
+            && self.written + buf.len() as u64 > self.max_bytes
+        {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
     }
-    eprintln!("{msg}");
 }
@@ -1,51 +1,140 @@
 //! PipeWire voice ducking
 #![cfg_attr(feature = "dev-tools", allow(dead_code))]
 
+mod agents;
 mod analysis;
+mod attach;
+mod candidate;
 mod capture;
+mod config;
+mod control;
+mod ctl;
+mod discord;
+mod doctor;
 mod ducking;
+mod echo;
+mod error;
+mod events;
+mod graph;
+mod helper;
+mod hid;
+mod hooks;
+mod ipc;
+#[cfg(feature = "dev-tools")]
+mod latency;
+mod learn;
+mod links;
+mod lock;
 mod logging;
+mod messages;
+mod metadata;
+mod meter;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod midi;
+mod mqtt;
+mod music;
+mod notify;
+mod once;
+mod osc;
+mod overlay;
+mod plain;
+mod profile;
+mod replay;
+mod reprobe;
+mod rules;
+mod scene;
+#[cfg(feature = "rules")]
+mod script;
+mod session;
+mod sidechain;
+mod snapshot;
+mod spectrum;
+mod tap;
+mod teststream;
+mod trace;
 mod ui;
+mod units;
+mod volumes;
+#[cfg(feature = "web-ui")]
+mod web;
 
 use anyhow::{anyhow, Result};
-use clap::Parser;
+use clap::builder::BoolishValueParser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use pipewire as pw;
-use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use signal_hook::consts::signal::{SIGINT, SIGTERM, SIGTSTP};
 use signal_hook::flag;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex, Weak};
+use std::sync::{mpsc, Arc, Mutex, Weak};
 use std::time::{Duration, Instant};
+use tracing::{debug, error, info, trace, warn};
 
-use crate::analysis::{auto_vad_step, AtomicF32, VadState};
-use crate::capture::{probe_candidate_energy, setup_capture};
-use crate::ducking::{is_voice_candidate, wpctl_get_volume, OutputStream, RestoreGuard};
-use crate::logging::{elogln, logln};
+use crate::agents::{Agent, Agents, ConflictPolicy};
+use crate::analysis::{auto_vad_step, AtomicF32, VadState, MIN_HOLD_MS, VAD_TICK_MS};
+use crate::capture::{
+    probe_candidate_energy, setup_capture, CaptureFormat, ChannelSelect, Targeting, CAPTURE_NAME,
+};
+use crate::control::Control;
+use crate::ducking::{
+    detect_backend, linked_targets, DuckCurve, DuckEngine, NewStreamPolicy, OutputStream,
+    RestoreGuard, VolumeBackend, VolumeScope,
+};
+use crate::error::Error;
+use crate::events::Event;
+use crate::messages::Msg;
+use crate::music::{is_music_node, MusicSink};
+use crate::sidechain::{is_sidechain_node, Sidechain, SidechainParams};
 use crate::ui::{
-    enter_gui_mode, handle_gui_input, render_gui, select_voice_source_gui, GuiSelectResult,
+    enter_gui_mode, handle_gui_input, render_gui, select_voice_source_gui, GroupRow,
+    GuiSelectResult,
 };
+use crate::volumes::VolumeWatch;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum ControlMode {
     AutoVad,
     ManualDucked,
     ManualRestored,
+    /// ducked regardless of the VAD, allowlist excepted
+    Focus,
 }
 
 impl ControlMode {
-    #[cfg(feature = "dev-tools")]
     const fn as_str(self) -> &'static str {
         match self {
             Self::AutoVad => "AutoVad",
             Self::ManualDucked => "ManualDucked",
             Self::ManualRestored => "ManualRestored",
+            Self::Focus => "Focus",
         }
     }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::AutoVad,
+            Self::ManualDucked,
+            Self::ManualRestored,
+            Self::Focus,
+        ]
+        .into_iter()
+        .find(|m| m.as_str() == name)
+    }
 }
 
+/// reappearance window for moved streams
+const STREAM_MOVE_GRACE: Duration = Duration::from_secs(10);
+
+/// callback filled in once its state exists
+type Hook = Rc<RefCell<Option<Box<dyn Fn()>>>>;
+
 /// CLI opts
 #[derive(Parser, Debug)]
 #[command(
@@ -56,24 +145,335 @@ impl ControlMode {
     long_about = None
 )]
 struct Opts {
-    /// vad threshold
-    #[arg(long, default_value_t = 0.02)]
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// vad threshold (level or dB, e.g. -35dB)
+    #[arg(
+        long,
+        default_value_t = 0.02,
+        value_parser = units::threshold,
+        allow_hyphen_values = true,
+        env = "PW_DUCK_THRESHOLD"
+    )]
     threshold: f32,
-    /// attack ms
-    #[arg(long, default_value_t = 0)]
+    /// attack (ms or s)
+    #[arg(long, default_value_t = 0, value_parser = units::millis, env = "PW_DUCK_ATTACK")]
     attack: u64,
-    /// hold ms
-    #[arg(long, default_value_t = 350)]
+    /// hold (ms or s)
+    #[arg(long, default_value_t = 350, value_parser = units::millis, env = "PW_DUCK_HOLD")]
     hold: u64,
-    /// duck factor
-    #[arg(long, default_value_t = 0.45)]
+    /// voice stops below this level instead of the threshold (level or dB)
+    #[arg(
+        long,
+        value_parser = units::threshold,
+        allow_hyphen_values = true,
+        env = "PW_DUCK_RELEASE"
+    )]
+    release: Option<f32>,
+    /// duck factor (0..1, % quieter or dB, e.g. 45% or -10dB)
+    #[arg(
+        long,
+        visible_alias = "duck",
+        default_value_t = 0.45,
+        value_parser = units::duck_factor,
+        allow_hyphen_values = true,
+        env = "PW_DUCK_DUCK"
+    )]
     duck_factor: f32,
     /// debug
     #[arg(long)]
     debug: bool,
+    /// headless auto ducking
+    #[arg(
+        long,
+        conflicts_with = "debug",
+        env = "PW_DUCK_DAEMON",
+        value_parser = BoolishValueParser::new()
+    )]
+    daemon: bool,
+    /// line-based interface for screen readers
+    #[arg(
+        long,
+        conflicts_with_all = ["debug", "daemon"],
+        env = "PW_DUCK_PLAIN",
+        value_parser = BoolishValueParser::new()
+    )]
+    plain: bool,
+    /// JSON events on stdout
+    #[arg(long, env = "PW_DUCK_EVENTS_JSON", value_parser = BoolishValueParser::new())]
+    events_json: bool,
+    /// desktop notifications
+    #[arg(long, env = "PW_DUCK_NOTIFY", value_parser = BoolishValueParser::new())]
+    notify: bool,
+    /// replace running instance
+    #[arg(long, env = "PW_DUCK_TAKEOVER", value_parser = BoolishValueParser::new())]
+    takeover: bool,
+    /// duck sinks instead of streams
+    #[arg(
+        long,
+        conflicts_with = "engine",
+        env = "PW_DUCK_DUCK_SINK",
+        value_parser = BoolishValueParser::new()
+    )]
+    duck_sink: bool,
+    /// duck only the "pw-duck Music" sink
+    #[arg(
+        long,
+        conflicts_with_all = ["engine", "duck_sink"],
+        env = "PW_DUCK_MUSIC_SINK",
+        value_parser = BoolishValueParser::new()
+    )]
+    music_sink: bool,
+    /// move matching app/node/media onto the music sink
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        requires = "music_sink",
+        env = "PW_DUCK_MUSIC_ROUTE"
+    )]
+    music_route: Option<String>,
+    /// ducking engine
+    #[arg(long, value_enum, default_value_t = DuckEngine::Volume, env = "PW_DUCK_ENGINE")]
+    engine: DuckEngine,
+    /// sidechain compressor ratio
+    #[arg(long, default_value_t = 10.0, env = "PW_DUCK_SC_RATIO")]
+    sc_ratio: f32,
+    /// voice gain while ducked
+    #[arg(long, default_value_t = 1.0, env = "PW_DUCK_VOICE_BOOST")]
+    voice_boost: f32,
+    /// duck curve
+    #[arg(long, value_enum, default_value_t = DuckCurve::Linear, env = "PW_DUCK_DUCK_CURVE")]
+    duck_curve: DuckCurve,
+    /// proportional ducking, full depth at threshold * N
+    #[arg(long, value_name = "N", env = "PW_DUCK_SOFT_KNEE")]
+    soft_knee: Option<f32>,
+    /// fade volumes at most this fast, e.g. 20dB/s [default: instant]
+    #[arg(long, value_name = "DB_PER_S", value_parser = units::gain_rate, env = "PW_DUCK_GAIN_RATE")]
+    gain_rate: Option<f32>,
+    /// only count speech-like zero crossing rates as voice
+    #[arg(long, env = "PW_DUCK_ZCR_GATE", value_parser = BoolishValueParser::new())]
+    zcr_gate: bool,
+    /// voice starts after N of the last M ticks above threshold, e.g. 3/5
+    #[arg(long, value_name = "N/M", value_parser = units::votes, env = "PW_DUCK_ONSET_VOTES")]
+    onset_votes: Option<(usize, usize)>,
+    /// follow the speaker's pauses between words instead of a fixed hold
+    #[arg(long, env = "PW_DUCK_ADAPTIVE_HOLD", value_parser = BoolishValueParser::new())]
+    adaptive_hold: bool,
+    /// command run on voice start
+    #[arg(long, value_name = "CMD", env = "PW_DUCK_ON_VOICE_START")]
+    on_voice_start: Option<String>,
+    /// command run on voice stop
+    #[arg(long, value_name = "CMD", env = "PW_DUCK_ON_VOICE_STOP")]
+    on_voice_stop: Option<String>,
+    /// overlay file for OBS text/browser sources
+    #[arg(long, value_name = "PATH", env = "PW_DUCK_OVERLAY")]
+    overlay: Option<PathBuf>,
+    /// OSC listen addr
+    #[arg(long, value_name = "ADDR", env = "PW_DUCK_OSC_ADDR")]
+    osc_addr: Option<String>,
+    /// ALSA rawmidi device
+    #[arg(long, value_name = "DEVICE", env = "PW_DUCK_MIDI_DEVICE")]
+    midi_device: Option<PathBuf>,
+    /// hidraw device for button decks
+    #[arg(long, value_name = "DEVICE", env = "PW_DUCK_HID_DEVICE")]
+    hid_device: Option<PathBuf>,
+    /// live parameters in the "pw-duck" PipeWire metadata object
+    #[arg(long, env = "PW_DUCK_METADATA", value_parser = BoolishValueParser::new())]
+    metadata: bool,
+    /// MQTT broker host:port
+    #[arg(long, value_name = "ADDR", env = "PW_DUCK_MQTT_BROKER")]
+    mqtt_broker: Option<String>,
+    /// only duck automatically while in a Discord voice channel, or while Discord is unreachable
+    #[arg(long, env = "PW_DUCK_DISCORD_GATE", value_parser = BoolishValueParser::new())]
+    discord_gate: bool,
+    /// PipeWire instance to control (socket name or path)
+    #[arg(long, value_name = "NAME", env = "PIPEWIRE_REMOTE")]
+    remote: Option<String>,
+    /// config file
+    #[arg(long, value_name = "PATH", env = "PW_DUCK_CONFIG")]
+    config: Option<PathBuf>,
+    /// write observed globals and selection state as JSON, for bug reports
+    #[arg(long, value_name = "FILE.json", env = "PW_DUCK_DUMP_GRAPH")]
+    dump_graph: Option<PathBuf>,
+    /// rhai rules script
+    #[cfg(feature = "rules")]
+    #[arg(long, value_name = "PATH", env = "PW_DUCK_RULES")]
+    rules: Option<PathBuf>,
+    /// voice source pattern, repeat for failover order
+    #[arg(
+        long = "voice-source",
+        value_name = "PATTERN",
+        env = "PW_DUCK_VOICE_SOURCE"
+    )]
+    voice_sources: Vec<String>,
+    /// app/node/media pattern left alone in focus mode, repeatable
+    #[arg(long, value_name = "PATTERN", env = "PW_DUCK_FOCUS_ALLOW")]
+    focus_allow: Vec<String>,
+    /// duck only streams with these media roles, e.g. Music,Video
+    #[arg(
+        long,
+        value_name = "ROLE",
+        value_delimiter = ',',
+        env = "PW_DUCK_DUCK_ROLES"
+    )]
+    duck_roles: Vec<String>,
+    /// switch to a busier voice source without asking
+    #[arg(long, env = "PW_DUCK_AUTO_SWITCH", value_parser = BoolishValueParser::new())]
+    auto_switch: bool,
+    /// restore if ducked for more than N minutes without a break
+    #[arg(long, value_name = "MINUTES", env = "PW_DUCK_MAX_DUCK")]
+    max_duck: Option<u64>,
+    /// also leave automatic ducking when --max-duck fires
+    #[arg(long, env = "PW_DUCK_MAX_DUCK_PAUSE", value_parser = BoolishValueParser::new())]
+    max_duck_pause: bool,
+    /// restore and pause while the session is locked or idle
+    #[arg(long, env = "PW_DUCK_PAUSE_ON_LOCK", value_parser = BoolishValueParser::new())]
+    pause_on_lock: bool,
+    /// other ducking agents running [default: warn]
+    #[arg(long, value_enum, value_name = "POLICY", env = "PW_DUCK_CONFLICT")]
+    conflict: Option<ConflictPolicy>,
+    /// re-read baselines every N seconds while nothing is ducked
+    #[arg(long, value_name = "SECONDS", env = "PW_DUCK_BASELINE_REFRESH")]
+    baseline_refresh: Option<u64>,
+    /// capture latency request, e.g. 256/48000
+    #[arg(long, value_name = "FRAMES/RATE", value_parser = units::quantum, env = "PW_DUCK_CAPTURE_QUANTUM")]
+    capture_quantum: Option<String>,
+    /// capture at this sample rate, resampled if needed [default: graph rate]
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(8000..=192_000), env = "PW_DUCK_CAPTURE_RATE")]
+    capture_rate: Option<u32>,
+    /// capture this many channels, 1 downmixes to mono [default: source's]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=8), env = "PW_DUCK_CAPTURE_CHANNELS")]
+    capture_channels: Option<u32>,
+    /// VAD level from: mix, max or a channel number [default: mix]
+    #[arg(long, value_name = "CHANNELS", env = "PW_DUCK_CAPTURE_SELECT")]
+    capture_select: Option<ChannelSelect>,
+    /// ignore the voice source while it echoes the local mic
+    #[arg(long, env = "PW_DUCK_ECHO_REJECT", value_parser = BoolishValueParser::new())]
+    echo_reject: bool,
+    /// mic for --echo-reject, by node.name [default: default source]
+    #[arg(long, value_name = "NODE", env = "PW_DUCK_ECHO_MIC")]
+    echo_mic: Option<String>,
+    /// only offer echo-cancelled sources, never raw mics
+    #[arg(long, env = "PW_DUCK_REQUIRE_ECHO_CANCEL", value_parser = BoolishValueParser::new())]
+    require_echo_cancel: bool,
+    /// retry the capture if it has no audio after N seconds [default: 3]
+    #[arg(long, value_name = "SECONDS", env = "PW_DUCK_CAPTURE_TIMEOUT")]
+    capture_timeout: Option<u64>,
+    /// streams that appear while ducked [default: duck-after-baseline]
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        env = "PW_DUCK_NEW_STREAM_POLICY"
+    )]
+    new_stream_policy: Option<NewStreamPolicy>,
+    /// set volumes per node or per client [default: node]
+    #[arg(long, value_enum, value_name = "SCOPE", env = "PW_DUCK_VOLUME_SCOPE")]
+    volume_scope: Option<VolumeScope>,
+    /// voice client profile (default: detect, "none" to disable)
+    #[arg(long, value_name = "NAME", env = "PW_DUCK_PROFILE")]
+    profile: Option<String>,
+    /// scene from the config file to start with
+    #[arg(long, value_name = "NAME", env = "PW_DUCK_SCENE")]
+    scene: Option<String>,
+    /// also offer sources and mic clients as voice source
+    #[arg(long, env = "PW_DUCK_INCLUDE_INPUTS", value_parser = BoolishValueParser::new())]
+    include_inputs: bool,
     /// force selection (gui)
     #[arg(long)]
     select: bool,
+    /// voice source by object id, skips detection and the chooser
+    #[arg(
+        long,
+        value_name = "ID",
+        env = "PW_DUCK_SELECT_ID",
+        conflicts_with = "select_serial"
+    )]
+    select_id: Option<u32>,
+    /// voice source by object serial, skips detection and the chooser
+    #[arg(long, value_name = "SERIAL", env = "PW_DUCK_SELECT_SERIAL")]
+    select_serial: Option<String>,
+    /// output language (default: from locale)
+    #[arg(long, value_enum, env = "PW_DUCK_LANG")]
+    lang: Option<messages::Lang>,
+    /// log level
+    #[arg(long, default_value = "info", env = "PW_DUCK_LOG_LEVEL")]
+    log_level: String,
+    /// log to journald
+    #[arg(long, env = "PW_DUCK_JOURNALD", value_parser = BoolishValueParser::new())]
+    journald: bool,
+    /// log file path
+    #[arg(long, env = "PW_DUCK_LOG_FILE")]
+    log_file: Option<PathBuf>,
+    /// log file rotation size (KiB)
+    #[arg(long, default_value_t = 1024, env = "PW_DUCK_LOG_FILE_MAX_KB")]
+    log_file_max_kb: u64,
+    /// write analyzed audio to WAV
+    #[arg(long, value_name = "FILE.wav", env = "PW_DUCK_RECORD_TAP")]
+    record_tap: Option<PathBuf>,
+    /// write energy and VAD state per tick (CSV, or JSON lines for .json)
+    #[arg(long, value_name = "FILE", env = "PW_DUCK_TRACE")]
+    trace: Option<PathBuf>,
+    /// metrics listen addr
+    #[cfg(feature = "metrics")]
+    #[arg(long, default_value = "127.0.0.1:9977", env = "PW_DUCK_METRICS_ADDR")]
+    metrics_addr: String,
+    /// web UI listen addr
+    #[cfg(feature = "web-ui")]
+    #[arg(long, default_value = "127.0.0.1:9978", env = "PW_DUCK_WEB_ADDR")]
+    web_addr: String,
+}
+
+/// subcommands
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// run the VAD over a recorded WAV file
+    Replay(replay::ReplayArgs),
+    /// duck everything once, restore on exit
+    Once(once::OnceArgs),
+    /// control the running instance
+    Ctl(ctl::CtlArgs),
+    /// attach the TUI to the running instance
+    Attach,
+    /// watch the running instance and propose settings
+    Learn(learn::LearnArgs),
+    /// check the PipeWire setup
+    Doctor,
+    /// show the audio graph around pw-duck
+    Graph(graph::GraphArgs),
+    /// restore volumes if the parent is killed
+    #[command(hide = true)]
+    RestoreHelper(helper::HelperArgs),
+    /// measure attack latency with test streams
+    #[cfg(feature = "dev-tools")]
+    Latency(latency::LatencyArgs),
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => error::report(&err),
+    }
+}
+
+/// core on `--remote`, else the default instance
+fn connect_core(context: &pw::context::ContextRc) -> Result<pw::core::CoreRc, pw::Error> {
+    let remote = std::env::var("PIPEWIRE_REMOTE")
+        .ok()
+        .filter(|r| !r.is_empty());
+    context.connect_rc(remote.map(|remote| {
+        pw::properties::properties! {
+            "remote.name" => remote,
+        }
+    }))
+}
+
+/// longest wait for in-process restores on exit
+const NATIVE_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Flatpak or Snap, where host tools like wpctl cannot be spawned
+fn sandboxed() -> bool {
+    Path::new("/.flatpak-info").exists() || std::env::var_os("SNAP").is_some()
 }
 
 #[allow(
@@ -88,21 +488,138 @@ struct Opts {
     clippy::too_many_lines,
     clippy::uninlined_format_args
 )]
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     // cli parse
-    let opts = Opts::parse();
-    let gui_enabled = !opts.debug;
-    let force_select = opts.select && !opts.debug;
-    let mut gui_mode_guard: Option<crate::ui::GuiModeGuard> = None;
-    let duck_factor = if gui_enabled {
-        opts.duck_factor
+    let matches = Opts::command().get_matches();
+    let opts = Opts::from_arg_matches(&matches)?;
+    // flags and PW_DUCK_* variables
+    let from_cli = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    };
+    messages::set_lang(opts.lang.unwrap_or_else(messages::from_locale));
+    // wpctl, pw-dump and the other tools follow the same instance
+    if let Some(remote) = opts.remote.as_deref() {
+        std::env::set_var("PIPEWIRE_REMOTE", remote);
+    }
+    if let Some(command) = opts.command.as_ref() {
+        // no console output under the attached TUI
+        let quiet = matches!(command, Command::Attach);
+        logging::init(quiet, &opts.log_level, false, None, 0)?;
+        return match command {
+            Command::Replay(args) => replay::run(args),
+            Command::Once(args) => once::run(args),
+            Command::Ctl(args) => ctl::run(args),
+            Command::Attach => attach::run(),
+            Command::Learn(args) => learn::run(args),
+            Command::Doctor => doctor::run(),
+            Command::Graph(args) => graph::run(args),
+            Command::RestoreHelper(args) => helper::run(args),
+            #[cfg(feature = "dev-tools")]
+            Command::Latency(args) => latency::run(args),
+        };
+    }
+    let gui_enabled = !opts.debug && !opts.daemon && !opts.plain;
+    let plain = opts.plain;
+    // keep stderr quiet next to plain output
+    let log_level = if plain && !from_cli("log_level") {
+        "warn"
     } else {
-        logln(gui_enabled, "default ducking enabled (duck_factor=0.0)");
+        opts.log_level.as_str()
+    };
+    logging::init(
+        gui_enabled,
+        log_level,
+        opts.journald,
+        opts.log_file.as_deref(),
+        opts.log_file_max_kb,
+    )?;
+    let config = config::load(opts.config.as_deref())?;
+    let rules =
+        rules::RuleSet::new(config.rule.clone()).with_focus_allow(if opts.focus_allow.is_empty() {
+            config.focus_allow.clone()
+        } else {
+            opts.focus_allow.clone()
+        });
+    let rules = rules.with_duck_roles(if opts.duck_roles.is_empty() {
+        config.duck_roles.clone()
+    } else {
+        opts.duck_roles.clone()
+    });
+    let rules = if config.protect_accessibility.unwrap_or(true) {
+        rules.with_builtin(rules::accessibility_rules())
+    } else {
+        rules
+    };
+    #[cfg(feature = "rules")]
+    let rules = rules.with_script(
+        opts.rules
+            .clone()
+            .or(config.rules.clone())
+            .map(|path| script::Script::load(&path))
+            .transpose()?,
+    );
+    let rules = Rc::new(rules);
+    let _instance_lock = ipc::acquire_instance_lock(opts.takeover)?;
+    let force_select = opts.select && (gui_enabled || plain);
+    let mut gui_mode_guard: Option<crate::ui::GuiModeGuard> = None;
+    // saved settings, below flags
+    let duck_factor = if opts.debug {
+        info!("default ducking enabled (duck_factor=0.0)");
         0.0
+    } else {
+        config
+            .duck_factor
+            .filter(|_| !from_cli("duck_factor"))
+            .unwrap_or(opts.duck_factor)
     };
     let duck_factor_live = Rc::new(RefCell::new(duck_factor));
-    let threshold_live = Rc::new(RefCell::new(opts.threshold));
-    let hold_live = Rc::new(RefCell::new(opts.hold));
+    let threshold_live = Rc::new(RefCell::new(
+        config
+            .threshold
+            .filter(|_| !from_cli("threshold"))
+            .unwrap_or(opts.threshold),
+    ));
+    let hold_live = Rc::new(RefCell::new(
+        config
+            .hold
+            .filter(|_| !from_cli("hold"))
+            .unwrap_or(opts.hold),
+    ));
+    let attack_ms = Rc::new(Cell::new(
+        config
+            .attack
+            .filter(|_| !from_cli("attack"))
+            .unwrap_or(opts.attack),
+    ));
+    let profiles = profile::Profiles::new(&config.profile);
+
+    // metrics exporter
+    #[cfg(feature = "metrics")]
+    metrics::spawn_exporter(&opts.metrics_addr)?;
+
+    // event sinks
+    if opts.events_json {
+        if gui_enabled || plain {
+            return Err(anyhow!("--events-json needs --daemon or --debug"));
+        }
+        events::subscribe_json_stdout();
+    }
+    if opts.notify {
+        notify::subscribe_desktop();
+    }
+    let hooks = hooks::Hooks {
+        on_voice_start: opts.on_voice_start.clone().or(config.hooks.on_voice_start),
+        on_voice_stop: opts.on_voice_stop.clone().or(config.hooks.on_voice_stop),
+    };
+    if !hooks.is_empty() {
+        hooks::subscribe(hooks);
+    }
+    if let Some(path) = opts.overlay.clone().or(config.overlay.path.clone()) {
+        overlay::subscribe(path, config.overlay.clone());
+    }
 
     // pipewire init
     pw::init();
@@ -112,10 +629,10 @@ fn main() -> Result<()> {
     }
 
     // core setup
-    let mainloop = pw::main_loop::MainLoopRc::new(None)?;
-    let context = pw::context::ContextRc::new(&mainloop, None)?;
-    let core = context.connect_rc(None)?;
-    let registry = core.get_registry_rc()?;
+    let mainloop = pw::main_loop::MainLoopRc::new(None).map_err(Error::PipeWireConnect)?;
+    let context = pw::context::ContextRc::new(&mainloop, None).map_err(Error::PipeWireConnect)?;
+    let core = connect_core(&context).map_err(Error::PipeWireConnect)?;
+    let registry = core.get_registry_rc().map_err(Error::PipeWireConnect)?;
 
     // shared state
     let outputs: Rc<RefCell<HashMap<u32, OutputStream>>> = Rc::new(RefCell::new(HashMap::new()));
@@ -125,15 +642,193 @@ fn main() -> Result<()> {
     let voice_source_label: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
     let voice_source_reason: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
     let baselines: Rc<RefCell<HashMap<u32, f32>>> = Rc::new(RefCell::new(HashMap::new()));
+    let departed: Rc<RefCell<HashMap<String, (f32, Instant)>>> =
+        Rc::new(RefCell::new(HashMap::new()));
+    // last pre-duck volume per app
+    let app_baselines: Rc<RefCell<HashMap<String, f32>>> = Rc::new(RefCell::new(HashMap::new()));
+    let sinks: Rc<RefCell<HashMap<u32, String>>> = Rc::new(RefCell::new(HashMap::new()));
+    // sink descriptions, for routing in lists
+    let sink_labels: Rc<RefCell<HashMap<u32, String>>> = Rc::new(RefCell::new(HashMap::new()));
+    let links: Rc<RefCell<HashMap<u32, (u32, u32)>>> = Rc::new(RefCell::new(HashMap::new()));
+    let duck_sink = opts.duck_sink;
+    let sidechain: Rc<RefCell<Option<Sidechain>>> = Rc::new(RefCell::new(None));
+    let music_sink = opts.music_sink || config.music_sink;
+    let music: Rc<RefCell<Option<MusicSink>>> = Rc::new(RefCell::new(None));
+    if music_sink {
+        let route = opts.music_route.clone().or(config.music_route.clone());
+        *music.borrow_mut() = Some(MusicSink::start(route)?);
+    }
     let gui_log: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
     let restore_guard: Rc<RefCell<Option<Arc<Mutex<RestoreGuard>>>>> = Rc::new(RefCell::new(None));
     let control_mode: Rc<RefCell<ControlMode>> = Rc::new(RefCell::new(ControlMode::AutoVad));
+    let energy_atomic = Arc::new(AtomicF32::new(0.0));
+    let zcr_atomic = Arc::new(AtomicF32::new(0.0));
+    let capture_node: Rc<Cell<Option<u32>>> = Rc::new(Cell::new(None));
+    // failover when the voice source goes away, set once switching is possible
+    let on_voice_lost: Hook = Rc::new(RefCell::new(None));
+    // sources and mic clients (--include-inputs)
+    let inputs: Rc<RefCell<HashMap<u32, OutputStream>>> = Rc::new(RefCell::new(HashMap::new()));
+    let include_inputs = opts.include_inputs;
+    let new_stream_policy = opts
+        .new_stream_policy
+        .or(config.new_stream_policy)
+        .unwrap_or_default();
+    let volume_scope = opts
+        .volume_scope
+        .or(config.volume_scope)
+        .unwrap_or_default();
+    // audio sources by node.name, for --echo-reject
+    let sources: Rc<RefCell<HashMap<u32, String>>> = Rc::new(RefCell::new(HashMap::new()));
+    // audio ports, for linking the capture by hand
+    let ports: Rc<links::Ports> = Rc::default();
+    // every global, for --dump-graph
+    let graph: Rc<RefCell<BTreeMap<u32, snapshot::Global>>> = Rc::default();
+    // another pw-duck, wireplumber role ducking
+    let agents: Rc<Agents> = Rc::default();
+    let conflict = opts.conflict.or(config.conflict).unwrap_or_default();
+    // streams whose baseline comes from their first Props update
+    let awaiting_baseline: Rc<RefCell<HashSet<u32>>> = Rc::new(RefCell::new(HashSet::new()));
+    // volumes from Props, external changes become baselines
+    let volume_watch = {
+        let guard = restore_guard.clone();
+        let baselines = baselines.clone();
+        let app_baselines = app_baselines.clone();
+        let outputs = outputs.clone();
+        let duck_factor_live = duck_factor_live.clone();
+        let awaiting = awaiting_baseline.clone();
+        let rules = rules.clone();
+        let energy = energy_atomic.clone();
+        Rc::new(VolumeWatch::new(move |id, vol| {
+            let Some(guard) = guard.borrow().clone() else {
+                return;
+            };
+            let mut guard = guard.lock().unwrap();
+            if awaiting.borrow_mut().remove(&id) {
+                let base = ducking::mix_channels(&vol.channels);
+                debug!("baseline from props: id={id} -> {base}");
+                baselines.borrow_mut().insert(id, base);
+                let outputs = outputs.borrow();
+                if let Some(s) = outputs.get(&id) {
+                    app_baselines.borrow_mut().insert(s.app_key(), base);
+                }
+                let late = new_stream_policy == NewStreamPolicy::LeaveAlone;
+                let level = energy.load();
+                let factor = *duck_factor_live.borrow();
+                let client = outputs
+                    .get(&id)
+                    .filter(|_| volume_scope == VolumeScope::Client)
+                    .map(OutputStream::group);
+                join_stream(
+                    &mut guard, id, base, late, false, client, &rules, &outputs, level, factor,
+                );
+                guard.set_muted(id, vol.mute);
+                return;
+            }
+            if let Some(base) = guard.external_change(id, &vol.channels) {
+                info!(
+                    "volume changed externally: id={id} -> {:?}, baseline {base}",
+                    vol.channels
+                );
+                baselines.borrow_mut().insert(id, base);
+                if let Some(s) = outputs.borrow().get(&id) {
+                    app_baselines.borrow_mut().insert(s.app_key(), base);
+                }
+            }
+            if guard.set_muted(id, vol.mute) {
+                debug!("mute changed: id={id} -> {}", vol.mute);
+                // unmuted while ducked
+                if !vol.mute && guard.ducked {
+                    guard.apply_duck(*duck_factor_live.borrow());
+                }
+            }
+        }))
+    };
+    // refined from registry clients later
+    let session_manager = Rc::new(Cell::new(session::SessionManager::detect()));
+    info!("session manager: {}", session_manager.get().as_str());
+    // wpctl, pactl, or in-process where they cannot run
+    let backend = match sandboxed() {
+        true => {
+            info!("sandboxed, setting volumes in-process");
+            None
+        }
+        // wpctl needs WirePlumber
+        false if session_manager.get() == session::SessionManager::MediaSession => {
+            info!("pipewire-media-session, setting volumes in-process");
+            None
+        }
+        false => match detect_backend() {
+            Err(Error::VolumeBackend) => {
+                warn!("wpctl and pactl unavailable, setting volumes in-process");
+                None
+            }
+            other => Some(other?),
+        },
+    };
+    let native = backend.is_none();
+    let backend: Arc<dyn VolumeBackend> =
+        backend.unwrap_or_else(|| Arc::new(volume_watch.native()));
 
     // signals
     let quit_requested = Arc::new(AtomicBool::new(false));
     // signal handlers
     flag::register(SIGINT, Arc::clone(&quit_requested))?;
     flag::register(SIGTERM, Arc::clone(&quit_requested))?;
+    // Ctrl+Z outside raw mode, or kill -TSTP
+    let suspend_requested = Arc::new(AtomicBool::new(false));
+    flag::register(SIGTSTP, Arc::clone(&suspend_requested))?;
+    // remote control
+    let (control_tx, control_rx) = mpsc::channel::<Control>();
+    if let Some(name) = opts.scene.clone() {
+        if !config.scene.contains_key(&name) {
+            return Err(anyhow!("unknown scene '{name}'"));
+        }
+        // applied on the first tick
+        let _ = control_tx.send(Control::Scene(name));
+    }
+    // control socket
+    let ipc_status = Arc::new(Mutex::new(ipc::Status::default()));
+    // capture spectrum, for confidence, the TUI panel and attached clients
+    let spectrum = spectrum::Spectrum::default();
+    ipc::spawn_server(
+        Arc::clone(&quit_requested),
+        control_tx.clone(),
+        Arc::clone(&ipc_status),
+        spectrum.clone(),
+    )?;
+    if let Some(addr) = opts.osc_addr.as_deref().or(config.osc_addr.as_deref()) {
+        osc::spawn_server(addr, control_tx.clone())?;
+    }
+    if let Some(device) = opts.midi_device.as_ref().or(config.midi.device.as_ref()) {
+        midi::spawn_reader(device, config.midi.clone(), control_tx.clone())?;
+    }
+    if let Some(device) = opts.hid_device.as_ref().or(config.hid.device.as_ref()) {
+        hid::spawn_reader(device, config.hid.clone(), control_tx.clone())?;
+    }
+    if let Some(broker) = opts.mqtt_broker.as_ref().or(config.mqtt.broker.as_ref()) {
+        mqtt::spawn_client(
+            broker,
+            config.mqtt.clone(),
+            control_tx.clone(),
+            Arc::clone(&ipc_status),
+        )?;
+    }
+    let session_locked = (opts.pause_on_lock || config.pause_on_lock)
+        .then(lock::spawn_watcher)
+        .transpose()?;
+    let discord_gate = (opts.discord_gate || config.discord.gate)
+        .then(|| discord::spawn_gate(&config.discord))
+        .transpose()?;
+    #[cfg(feature = "web-ui")]
+    web::spawn_server(&opts.web_addr, control_tx.clone(), Arc::clone(&ipc_status))?;
+    // pipewire-native state, read and written by other clients
+    let published = (opts.metadata || config.metadata)
+        .then(|| {
+            metadata::Published::create(&core, control_tx.clone())
+                .map_err(|err| warn!("metadata object: {err}"))
+                .ok()
+        })
+        .flatten();
 
     // registry listener
     let _registry_listener = {
@@ -144,11 +839,46 @@ fn main() -> Result<()> {
         let voice_r = voice_source_id.clone();
         let voice_node_r = voice_source_node.clone();
         let voice_serial_r = voice_source_serial.clone();
+        let voice_lost_r = on_voice_lost.clone();
         let baselines_g = baselines.clone();
         let baselines_r = baselines.clone();
+        let departed_g = departed.clone();
+        let departed_r = departed.clone();
+        let app_baselines_g = app_baselines.clone();
+        let backend_g = backend.clone();
+        let sinks_g = sinks.clone();
+        let sinks_r = sinks.clone();
+        let sink_labels_g = sink_labels.clone();
+        let sink_labels_r = sink_labels.clone();
+        let links_g = links.clone();
+        let links_r = links.clone();
         let guard_g = restore_guard.clone();
         let guard_r = restore_guard.clone();
+        let sidechain_g = sidechain.clone();
+        let sidechain_r = sidechain.clone();
+        let music_g = music.clone();
+        let music_r = music.clone();
+        let rules_g = rules.clone();
+        let energy_g = energy_atomic.clone();
         let duck_factor_live = duck_factor_live.clone();
+        let capture_node_g = capture_node.clone();
+        let own_pid = std::process::id().to_string();
+        let inputs_g = inputs.clone();
+        let inputs_r = inputs.clone();
+        let watch_g = volume_watch.clone();
+        let watch_r = volume_watch.clone();
+        let registry_g = registry.downgrade();
+        let awaiting_g = awaiting_baseline.clone();
+        let awaiting_r = awaiting_baseline.clone();
+        let agents_g = agents.clone();
+        let agents_r = agents.clone();
+        let ports_g = ports.clone();
+        let ports_r = ports.clone();
+        let sources_g = sources.clone();
+        let sources_r = sources.clone();
+        let session_g = session_manager.clone();
+        let graph_g = opts.dump_graph.is_some().then(|| graph.clone());
+        let graph_r = graph.clone();
 
         registry
             .add_listener_local()
@@ -157,6 +887,42 @@ fn main() -> Result<()> {
                     Some(p) => p,
                     None => return,
                 };
+                if let Some(graph) = &graph_g {
+                    graph.borrow_mut().insert(
+                        global.id,
+                        snapshot::Global {
+                            id: global.id,
+                            kind: global.type_.clone(),
+                            props: snapshot::dump_props(
+                                props.iter().map(|(k, v)| (k.to_string(), v.to_string())),
+                            ),
+                        },
+                    );
+                }
+
+                if global.type_ == pw::types::ObjectType::Client {
+                    if let Some(found) = props
+                        .get("application.name")
+                        .and_then(session::SessionManager::from_client)
+                    {
+                        session_g.set(found);
+                    }
+                    return;
+                }
+                if global.type_ == pw::types::ObjectType::Port {
+                    ports_g.add(global.id, |key| props.get(key).map(|v| v.to_string()));
+                    return;
+                }
+                // links
+                if global.type_ == pw::types::ObjectType::Link {
+                    let node = |key: &str| props.get(key).and_then(|v| v.parse::<u32>().ok());
+                    if let (Some(out), Some(inp)) =
+                        (node("link.output.node"), node("link.input.node"))
+                    {
+                        links_g.borrow_mut().insert(global.id, (out, inp));
+                    }
+                    return;
+                }
 
                 // media props
                 let media_class = props
@@ -167,8 +933,72 @@ fn main() -> Result<()> {
                     .get("application.name")
                     .map(|v| v.to_string())
                     .unwrap_or_default();
-                // skip inputs
-                if media_class == "Stream/Input/Audio" {
+                // other ducking agents
+                if let Some(agent) = Agent::from_props(&media_class, &own_pid, |key| {
+                    props.get(key).map(|v| v.to_string())
+                }) {
+                    agents_g.add(global.id, agent);
+                }
+                // sinks
+                if media_class == "Audio/Sink" {
+                    let name = props
+                        .get("node.name")
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| "unknown-node".into());
+                    debug!("+ sink added: id={} node=\"{}\"", global.id, name);
+                    sinks_g.borrow_mut().insert(global.id, name.clone());
+                    sink_labels_g.borrow_mut().insert(
+                        global.id,
+                        props.get("node.description").unwrap_or(&name).to_string(),
+                    );
+                    if let Some(registry) = registry_g.upgrade() {
+                        watch_g.watch(&registry, global);
+                    }
+                    let Some(voice) = *voice_g.borrow() else {
+                        return;
+                    };
+                    let duckable = if music_sink {
+                        is_music_node(&name)
+                    } else {
+                        duck_sink && !linked_targets(&links_g.borrow(), voice).contains(&global.id)
+                    };
+                    if !duckable {
+                        return;
+                    }
+                    if let Some(v) = backend_g.get(global.id) {
+                        baselines_g.borrow_mut().insert(global.id, v);
+                        debug!("sink baseline captured: id={} -> {}", global.id, v);
+                        if let Some(guard) = guard_g.borrow().as_ref() {
+                            let mut guard = guard.lock().unwrap();
+                            guard.add_stream(global.id, v);
+                            if guard.ducked {
+                                guard.apply_duck(*duck_factor_live.borrow());
+                            }
+                        }
+                    }
+                    return;
+                }
+                // inputs
+                if media_class == "Stream/Input/Audio" || media_class == "Audio/Source" {
+                    let own = props.get("application.process.id") == Some(own_pid.as_str());
+                    // own capture, for retargeting
+                    if own && props.get("media.name") == Some(CAPTURE_NAME) {
+                        capture_node_g.set(Some(global.id));
+                        agents_g.set_own_serial(|key| props.get(key).map(|v| v.to_string()));
+                    }
+                    if media_class == "Audio/Source" {
+                        if let Some(name) = props.get("node.name") {
+                            sources_g.borrow_mut().insert(global.id, name.to_string());
+                        }
+                    }
+                    if include_inputs && !own {
+                        let info =
+                            OutputStream::from_props(global.id, media_class.clone(), |key| {
+                                props.get(key).map(|v| v.to_string())
+                            });
+                        debug!("+ input added: {:?}", info);
+                        inputs_g.borrow_mut().insert(info.id, info);
+                    }
                     return;
                 }
                 // output filter
@@ -176,113 +1006,183 @@ fn main() -> Result<()> {
                     return;
                 }
 
-                let info = OutputStream {
-                    id: global.id,
-                    serial: props
-                        .get("object.serial")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "-".into()),
-                    app: props
-                        .get("application.name")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "unknown-app".into()),
-                    bin: props
-                        .get("application.process.binary")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "-".into()),
-                    pid: props
-                        .get("application.process.id")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "-".into()),
-                    role: props
-                        .get("media.role")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "-".into()),
-                    media: props
-                        .get("media.name")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "unknown-media".into()),
-                    media_class: media_class.clone(),
-                    node: props
-                        .get("node.name")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "unknown-node".into()),
-                    client: props
-                        .get("client.id")
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| "-".into()),
-                };
+                let info = OutputStream::from_props(global.id, media_class.clone(), |key| {
+                    props.get(key).map(|v| v.to_string())
+                });
 
                 outputs_g.borrow_mut().insert(info.id, info.clone());
+                debug!("+ output stream added: {:?}", info);
+                if let Some(registry) = registry_g.upgrade() {
+                    watch_g.watch(&registry, global);
+                }
+                events::emit(Event::StreamAdded {
+                    id: info.id,
+                    app: info.app.clone(),
+                    node: info.node.clone(),
+                    media: info.media.clone(),
+                });
 
-                if voice_g.borrow().is_none() {
-                    if !gui_enabled {
-                        logln(gui_enabled, format!("+ output stream added: {:?}", info));
+                // sidechain routing
+                if let Some(sc) = sidechain_g.borrow_mut().as_mut() {
+                    if Some(info.id) != *voice_g.borrow() && !is_sidechain_node(&info.node) {
+                        sc.route(info.id);
                     }
                     return;
                 }
-                if !gui_enabled {
-                    logln(gui_enabled, format!("+ output stream added: {:?}", info));
+                // music sink routing
+                if let Some(m) = music_g.borrow_mut().as_mut() {
+                    if Some(info.id) != *voice_g.borrow()
+                        && !is_music_node(&info.node)
+                        && m.matches(&info)
+                    {
+                        m.route(info.id);
+                    }
+                }
+                // sinks cover streams
+                if voice_g.borrow().is_none() || duck_sink || music_sink {
+                    return;
                 }
                 // baseline+duck
                 let voice_id_opt = *voice_g.borrow();
                 if let Some(voice) = voice_id_opt {
                     // non-voice baseline
                     if info.id != voice {
-                        if let Some(v) = wpctl_get_volume(info.id) {
+                        // moved stream keeps its baseline
+                        let moved = departed_g
+                            .borrow_mut()
+                            .remove(&info.identity())
+                            .filter(|(_, at)| at.elapsed() < STREAM_MOVE_GRACE)
+                            .map(|(v, _)| v);
+                        // reconnect while ducked: current volume may be ducked
+                        let ducked = guard_g
+                            .borrow()
+                            .as_ref()
+                            .is_some_and(|g| g.lock().unwrap().ducked);
+                        let remembered = if ducked {
+                            app_baselines_g.borrow().get(&info.app_key()).copied()
+                        } else {
+                            None
+                        };
+                        let known = moved.or(remembered);
+                        let sibling = if volume_scope == VolumeScope::Client {
+                            client_baseline(&outputs_g.borrow(), &baselines_g.borrow(), &info)
+                        } else {
+                            None
+                        };
+                        if let (None, Some(v)) = (known, sibling) {
+                            debug!("client baseline: id={} -> {}", info.id, v);
+                        }
+                        let mut baseline = known.or(sibling).or_else(|| backend_g.get(info.id));
+                        if baseline.is_none()
+                            && ducked
+                            && new_stream_policy == NewStreamPolicy::CapAtDuckLevel
+                        {
+                            info!("baseline unknown: id={} capped at duck level", info.id);
+                            baseline = Some(1.0);
+                        }
+                        if let Some(v) = baseline {
                             baselines_g.borrow_mut().insert(info.id, v);
-                            if !gui_enabled {
-                                logln(
-                                    gui_enabled,
-                                    format!("baseline captured: id={} -> {}", info.id, v),
-                                );
+                            app_baselines_g.borrow_mut().insert(info.app_key(), v);
+                            if moved.is_some() {
+                                info!("stream moved: id={} keeps baseline {}", info.id, v);
+                            } else if remembered.is_some() {
+                                info!("stream reconnected: id={} restores to {}", info.id, v);
+                            } else {
+                                debug!("baseline captured: id={} -> {}", info.id, v);
                             }
+                            // known streams and clients were ducked before, new ones follow
+                            // the policy
+                            let late = known.is_none()
+                                && sibling.is_none()
+                                && new_stream_policy == NewStreamPolicy::LeaveAlone;
                             if let Some(guard) = guard_g.borrow().as_ref() {
-                                let mut guard = guard.lock().unwrap();
-                                guard.add_stream(info.id, v);
-                                if guard.ducked {
-                                    let factor = if gui_enabled {
-                                        *duck_factor_live.borrow()
-                                    } else {
-                                        duck_factor
-                                    };
-                                    guard.apply_duck(factor);
-                                }
+                                join_stream(
+                                    &mut guard.lock().unwrap(),
+                                    info.id,
+                                    v,
+                                    late,
+                                    known.is_none() && sibling.is_some(),
+                                    (volume_scope == VolumeScope::Client).then(|| info.group()),
+                                    &rules_g,
+                                    &outputs_g.borrow(),
+                                    energy_g.load(),
+                                    *duck_factor_live.borrow(),
+                                );
                             }
-                        } else if !gui_enabled {
-                            logln(
-                                gui_enabled,
-                                format!("baseline capture failed: id={}", info.id),
-                            );
+                        } else if guard_g.borrow().is_some() {
+                            debug!("baseline capture failed: id={}, waiting for props", info.id);
+                            awaiting_g.borrow_mut().insert(info.id);
+                        } else {
+                            warn!("baseline capture failed: id={}", info.id);
+                            events::emit(Event::Error {
+                                message: format!("baseline capture failed: id={}", info.id),
+                            });
                         }
                     }
                 }
             })
             .global_remove(move |id| {
-                if let Some(info) = outputs_r.borrow_mut().remove(&id) {
+                // voice source or mic client gone: stop ducking, then fail over
+                let voice_lost = |info: &OutputStream| {
+                    warn!("! voice source disappeared: {:?}", info);
+                    events::emit(Event::VoiceSourceLost { id });
+                    *voice_r.borrow_mut() = None;
+                    *voice_node_r.borrow_mut() = None;
+                    *voice_serial_r.borrow_mut() = None;
+                    if let Some(guard) = guard_r.borrow().as_ref() {
+                        let mut guard = guard.lock().unwrap();
+                        guard.clear_voice_boost();
+                        if guard.ducked {
+                            guard.restore();
+                        }
+                    }
+                    if let Some(failover) = voice_lost_r.borrow().as_ref() {
+                        failover();
+                    }
+                };
+                graph_r.borrow_mut().remove(&id);
+                agents_r.remove(id);
+                ports_r.remove(id);
+                sources_r.borrow_mut().remove(&id);
+                links_r.borrow_mut().remove(&id);
+                watch_r.forget(id);
+                awaiting_r.borrow_mut().remove(&id);
+                if let Some(sc) = sidechain_r.borrow_mut().as_mut() {
+                    sc.forget(id);
+                }
+                if let Some(m) = music_r.borrow_mut().as_mut() {
+                    m.forget(id);
+                }
+                sink_labels_r.borrow_mut().remove(&id);
+                if sinks_r.borrow_mut().remove(&id).is_some() {
+                    debug!("- sink removed: id={}", id);
+                    baselines_r.borrow_mut().remove(&id);
+                    if let Some(guard) = guard_r.borrow().as_ref() {
+                        guard.lock().unwrap().remove_stream(id);
+                    }
+                    return;
+                }
+                let removed = inputs_r.borrow_mut().remove(&id);
+                if let Some(info) = removed {
+                    debug!("- input removed: {:?}", info);
+                    if Some(id) == *voice_r.borrow() {
+                        voice_lost(&info);
+                    }
+                    return;
+                }
+                let removed = outputs_r.borrow_mut().remove(&id);
+                if let Some(info) = removed {
+                    events::emit(Event::StreamRemoved { id });
                     // voice removed
                     if Some(id) == *voice_r.borrow() {
-                        if !gui_enabled {
-                            logln(
-                                gui_enabled,
-                                format!("! voice source disappeared: {:?}", info),
-                            );
-                        }
-                        *voice_r.borrow_mut() = None;
-                        *voice_node_r.borrow_mut() = None;
-                        *voice_serial_r.borrow_mut() = None;
-                        // stop ducking
-                        if let Some(guard) = guard_r.borrow().as_ref() {
-                            let mut guard = guard.lock().unwrap();
-                            if guard.ducked {
-                                guard.restore();
-                            }
-                        }
+                        voice_lost(&info);
                     } else {
-                        if !gui_enabled {
-                            logln(gui_enabled, format!("- output stream removed: {:?}", info));
+                        debug!("- output stream removed: {:?}", info);
+                        if let Some(v) = baselines_r.borrow_mut().remove(&id) {
+                            let mut departed = departed_r.borrow_mut();
+                            departed.retain(|_, (_, at)| at.elapsed() < STREAM_MOVE_GRACE);
+                            departed.insert(info.identity(), (v, Instant::now()));
                         }
-                        baselines_r.borrow_mut().remove(&id);
                         if let Some(guard) = guard_r.borrow().as_ref() {
                             guard.lock().unwrap().remove_stream(id);
                         }
@@ -302,63 +1202,95 @@ fn main() -> Result<()> {
 
     // phase A list
     run_mainloop_for(Duration::from_millis(250))?;
+    if conflict == ConflictPolicy::Refuse && !agents.is_empty() {
+        return Err(Error::Conflict(agents.describe()).into());
+    }
+    if sinks.borrow().is_empty() {
+        warn!("{}", messages::registry_restricted(sandboxed()));
+    }
 
     // voice selection
+    let capture_monitor;
+    let require_echo_cancel = opts.require_echo_cancel || config.require_echo_cancel;
+    let scorer = candidate::Scorer::new(&config.voice_scores, require_echo_cancel)?;
+    let failover = Rc::new(candidate::Failover::new(
+        match opts
+            .scene
+            .as_deref()
+            .and_then(|name| config.scene.get(name))
+        {
+            Some(scene) if !scene.voice_source.is_empty() => scene.voice_source.clone(),
+            _ if opts.voice_sources.is_empty() => config.voice_sources.clone(),
+            _ => opts.voice_sources.clone(),
+        },
+    ));
+    // what the voice profile set, kept out of `save`
+    let mut profiled = profile::Profile::default();
     {
-        let score_voice_candidate = |s: &OutputStream| -> i32 {
-            let mut score = 0;
-            if s.app == "WEBRTC VoiceEngine" {
-                score += 100;
-            }
-            if s.node == "WEBRTC VoiceEngine" {
-                score += 30;
-            }
-            if s.node != "unknown-node" {
-                score += 5;
-            }
-            if s.client != "-" {
-                score += 3;
-            }
-            if s.media != "unknown-media" {
-                score += 1;
-            }
-            if s.role != "-" {
-                score += 1;
-            }
-            score
-        };
+        let score_voice_candidate = |s: &OutputStream| scorer.score(s);
         let build_list = || {
             let mut list: Vec<OutputStream> = outputs.borrow().values().cloned().collect();
             list.sort_by_key(|s| s.id);
             list.dedup_by_key(|s| s.id);
+            // inputs after outputs
+            let mut extra: Vec<OutputStream> = inputs
+                .borrow()
+                .values()
+                .filter(|s| scorer.allows(s))
+                .cloned()
+                .collect();
+            extra.sort_by_key(|s| (s.media_class.clone(), s.id));
+            list.extend(extra);
             list
         };
         let mut list = build_list();
         let mut selected: Option<(OutputStream, String)> = None;
 
-        if !force_select {
+        // pinned by a script
+        let pin = match (opts.select_id, opts.select_serial.as_deref()) {
+            (Some(id), _) => Some(("--select-id", format!("id {id}"))),
+            (None, Some(serial)) => Some(("--select-serial", format!("serial {serial}"))),
+            (None, None) => None,
+        };
+        if let Some((flag, what)) = pin {
+            let pinned = list.iter().find(|s| {
+                Some(s.id) == opts.select_id || Some(&s.serial) == opts.select_serial.as_ref()
+            });
+            let Some(s) = pinned else {
+                return Err(Error::NoCandidates(format!(
+                    "no stream with {what} (sources and mic clients need --include-inputs)"
+                ))
+                .into());
+            };
+            selected = Some((s.clone(), flag.into()));
+        }
+
+        if !force_select && selected.is_none() {
+            if let Some((rank, s)) = failover.pick(&outputs.borrow()) {
+                selected = Some((s, format!("voice source #{}", rank + 1)));
+            }
+        }
+        if !force_select && selected.is_none() {
             let candidates: Vec<OutputStream> = list
                 .iter()
-                .filter(|s| s.app == "WEBRTC VoiceEngine" && s.media_class == "Stream/Output/Audio")
+                .filter(|s| scorer.is_candidate(s) && s.media_class == "Stream/Output/Audio")
                 .cloned()
                 .collect();
-            if !gui_enabled {
-                logln(
-                    gui_enabled,
-                    format!(
-                        "WEBRTC VoiceEngine candidates (Stream/Output/Audio): {}",
-                        candidates.len()
-                    ),
+            info!(
+                "voice candidates (Stream/Output/Audio): {}",
+                candidates.len()
+            );
+            for s in candidates.iter() {
+                info!(
+                    "  id={} client={} node=\"{}\" serial={} pid={} media=\"{}\" role=\"{}\"",
+                    s.id,
+                    scorer.client(s).map_or("-", |(name, _)| name),
+                    s.node,
+                    s.serial,
+                    s.pid,
+                    s.media,
+                    s.role
                 );
-                for s in candidates.iter() {
-                    logln(
-                        gui_enabled,
-                        format!(
-                            "  id={} node=\"{}\" serial={} pid={} media=\"{}\" role=\"{}\"",
-                            s.id, s.node, s.serial, s.pid, s.media, s.role
-                        ),
-                    );
-                }
             }
 
             if candidates.len() == 1 {
@@ -384,14 +1316,11 @@ fn main() -> Result<()> {
                     if let Some(best) = best {
                         selected = Some((best, format!("probe rms={:.4}", best_score)));
                     }
-                } else if !gui_enabled {
-                    logln(
-                        gui_enabled,
-                        "no usable WEBRTC output signal found (probe below floor)",
-                    );
+                } else {
+                    info!("no usable voice output signal found (probe below floor)");
                 }
-            } else if !gui_enabled {
-                logln(gui_enabled, "no WEBRTC VoiceEngine output candidates found");
+            } else {
+                info!("no voice output candidates found");
             }
         }
 
@@ -402,7 +1331,7 @@ fn main() -> Result<()> {
             let default_candidate_index = list
                 .iter()
                 .enumerate()
-                .filter(|(_, s)| s.app == "WEBRTC VoiceEngine")
+                .filter(|(_, s)| scorer.is_candidate(s))
                 .max_by_key(|(_, s)| score_voice_candidate(s))
                 .map(|(idx, _)| idx);
             if gui_enabled {
@@ -410,11 +1339,22 @@ fn main() -> Result<()> {
                     let default_candidate_index = list
                         .iter()
                         .enumerate()
-                        .filter(|(_, s)| s.app == "WEBRTC VoiceEngine")
+                        .filter(|(_, s)| scorer.is_candidate(s))
                         .max_by_key(|(_, s)| score_voice_candidate(s))
                         .map(|(idx, _)| idx);
                     let default_index = default_candidate_index.unwrap_or(0);
-                    match select_voice_source_gui(&list, default_index)? {
+                    let routes: HashMap<u32, String> = list
+                        .iter()
+                        .map(|s| {
+                            let sink = routing(
+                                &links.borrow(),
+                                &sink_labels.borrow(),
+                                std::iter::once(s.id),
+                            );
+                            (s.id, sink)
+                        })
+                        .collect();
+                    match select_voice_source_gui(&list, &routes, default_index, &quit_requested)? {
                         GuiSelectResult::Selected(idx) => {
                             selected = Some((list[idx].clone(), "gui selection".into()));
                             break;
@@ -424,56 +1364,57 @@ fn main() -> Result<()> {
                             list = build_list();
                             continue;
                         }
-                        GuiSelectResult::Quit => return Ok(()),
+                        GuiSelectResult::Quit => return Err(Error::SelectionCancelled.into()),
                     }
                 }
+            } else if opts.daemon {
+                // no prompt
+                let idx = default_candidate_index
+                    .ok_or_else(|| Error::NoCandidates("no voice source candidate found".into()))?;
+                selected = Some((list[idx].clone(), "best score".into()));
             } else {
                 if list.is_empty() {
-                    logln(gui_enabled, "Keine aktiven Ausgabeströme gefunden.");
-                    return Ok(());
+                    return Err(Error::NoCandidates(Msg::NoOutputs.text().into()).into());
                 }
-                logln(gui_enabled, "Aktive Ausgabeströme (Stream/Output/Audio):");
-                logln(
-                    gui_enabled,
-                    "  [*] = wahrscheinlicher Remote‑Voice‑Kandidat (nur Hinweis)\n",
-                );
+                // stdout carries only JSON lines with --events-json
+                let mut out: Box<dyn Write> = if opts.events_json {
+                    Box::new(io::stderr())
+                } else {
+                    Box::new(io::stdout())
+                };
+                if include_inputs {
+                    writeln!(out, "{}", Msg::ActiveStreams.text())?;
+                } else {
+                    writeln!(out, "{}", Msg::ActiveOutputs.text())?;
+                }
+                writeln!(out, "{}", Msg::CandidateHint.text())?;
                 for (i, s) in list.iter().enumerate() {
-                    let mark = if is_voice_candidate(s) { "[*]" } else { "[ ]" };
-                    logln(
-                        gui_enabled,
-                        format!(
-                            "  {} [{:02}] id={}  app=\"{}\"  role=\"{}\"  media=\"{}\"  node=\"{}\"  bin=\"{}\"  pid={}  client={}  serial={}",
-                            mark,
-                            i + 1,
-                            s.id,
-                            s.app,
-                            s.role,
-                            s.media,
-                            s.node,
-                            s.bin,
-                            s.pid,
-                            s.client,
-                            s.serial
-                        ),
-                    );
+                    let mark = if scorer.is_candidate(s) { "[*]" } else { "[ ]" };
+                    writeln!(
+                        out,
+                        "  {} [{:02}] id={}  class={}  app=\"{}\"  role=\"{}\"  media=\"{}\"  node=\"{}\"  bin=\"{}\"  pid={}  client={}  serial={}  sink=\"{}\"",
+                        mark,
+                        i + 1,
+                        s.id,
+                        s.media_class,
+                        s.app,
+                        s.role,
+                        s.media,
+                        s.node,
+                        s.bin,
+                        s.pid,
+                        s.client,
+                        s.serial,
+                        routing(&links.borrow(), &sink_labels.borrow(), std::iter::once(s.id))
+                    )?;
                 }
                 // prompt
                 if let Some(idx) = default_candidate_index {
-                    logln(
-                        gui_enabled,
-                        format!(
-                            "\nNummer der VOICE SOURCE wählen (1-{}), Enter für bevorzugten Kandidaten [{}]: ",
-                            list.len(),
-                            idx + 1
-                        ),
-                    );
+                    writeln!(out, "{}", messages::pick_voice_default(list.len(), idx + 1))?;
                 } else {
-                    logln(
-                        gui_enabled,
-                        format!("\nNummer der VOICE SOURCE wählen (1-{}): ", list.len()),
-                    );
+                    writeln!(out, "{}", messages::pick_voice(list.len()))?;
                 }
-                io::stdout().flush().ok();
+                out.flush().ok();
                 let mut line = String::new();
                 io::stdin().read_line(&mut line)?;
                 let trimmed = line.trim();
@@ -485,10 +1426,12 @@ fn main() -> Result<()> {
                     }
                 } else {
                     // parse selection
-                    trimmed.parse().map_err(|_| anyhow!("Ungültige Zahl"))?
+                    trimmed
+                        .parse()
+                        .map_err(|_| anyhow!(Msg::InvalidNumber.text()))?
                 };
                 if sel == 0 || sel > list.len() {
-                    return Err(anyhow!("Auswahl außerhalb des gültigen Bereichs"));
+                    return Err(anyhow!(Msg::OutOfRange.text()));
                 }
                 selected = Some((list[sel - 1].clone(), "manual selection".into()));
             }
@@ -496,100 +1439,494 @@ fn main() -> Result<()> {
 
         let (chosen, reason) = selected.expect("voice selection missing");
         *voice_source_id.borrow_mut() = Some(chosen.id);
-        *voice_source_node.borrow_mut() = Some(chosen.node.clone());
-        *voice_source_serial.borrow_mut() = Some(chosen.serial.clone());
+        // mic clients are captured at their source
+        let capture_from = if chosen.media_class == "Stream/Input/Audio" {
+            let upstream = links
+                .borrow()
+                .values()
+                .find(|(_, inp)| *inp == chosen.id)
+                .and_then(|(out, _)| inputs.borrow().get(out).cloned());
+            if upstream.is_none() {
+                warn!("no source linked to input stream id={}", chosen.id);
+            }
+            upstream.unwrap_or_else(|| chosen.clone())
+        } else {
+            chosen.clone()
+        };
+        capture_monitor = capture_from.media_class == "Stream/Output/Audio";
+        *voice_source_node.borrow_mut() = Some(capture_from.node.clone());
+        *voice_source_serial.borrow_mut() = Some(capture_from.serial.clone());
         *voice_source_label.borrow_mut() = chosen.app.clone();
         *voice_source_reason.borrow_mut() = reason.clone();
-        if !gui_enabled {
-            logln(
-                gui_enabled,
-                format!(
-                    "\nVoice Source ausgewählt: id={} app=\"{}\" role=\"{}\" media=\"{}\" node=\"{}\" serial={} ({})",
-                    chosen.id, chosen.app, chosen.role, chosen.media, chosen.node, chosen.serial, reason
-                ),
+        info!(
+            "{}",
+            messages::voice_selected(
+                chosen.id,
+                &chosen.app,
+                &chosen.role,
+                &chosen.media,
+                &chosen.node,
+                &chosen.serial,
+                &reason
+            )
+        );
+        // voice client profile
+        let profile = match opts.profile.as_deref() {
+            Some("none") => None,
+            Some(name) => Some((
+                name,
+                profiles
+                    .get(name)
+                    .ok_or_else(|| anyhow!("unknown profile '{name}'"))?,
+            )),
+            None => profiles.detect(&chosen),
+        };
+        if let Some((name, p)) = profile {
+            if let Some(v) = p
+                .threshold
+                .filter(|_| !from_cli("threshold") && config.threshold.is_none())
+            {
+                *threshold_live.borrow_mut() = v;
+                profiled.threshold = Some(v);
+            }
+            if let Some(v) = p
+                .hold
+                .filter(|_| !from_cli("hold") && config.hold.is_none())
+            {
+                *hold_live.borrow_mut() = v;
+                profiled.hold = Some(v);
+            }
+            if let Some(v) = p
+                .attack
+                .filter(|_| !from_cli("attack") && config.attack.is_none())
+            {
+                attack_ms.set(v);
+                profiled.attack = Some(v);
+            }
+            if let Some(v) = p
+                .duck_factor
+                .filter(|_| !from_cli("duck_factor") && config.duck_factor.is_none() && !opts.debug)
+            {
+                *duck_factor_live.borrow_mut() = v;
+                profiled.duck_factor = Some(v);
+            }
+            info!(
+                "profile {}: threshold={} hold={}ms attack={}ms duck_factor={}",
+                name,
+                threshold_live.borrow(),
+                hold_live.borrow(),
+                attack_ms.get(),
+                duck_factor_live.borrow()
             );
         }
         // capture baselines
         {
             let voice = chosen.id;
+            let targets: Vec<u32> = if opts.engine == DuckEngine::Sidechain {
+                Vec::new()
+            } else if music_sink {
+                // added once it shows up otherwise
+                sinks
+                    .borrow()
+                    .iter()
+                    .filter(|(_, name)| is_music_node(name))
+                    .map(|(id, _)| *id)
+                    .collect()
+            } else if duck_sink {
+                let voice_sinks = linked_targets(&links.borrow(), voice);
+                if !voice_sinks.is_empty() {
+                    info!("voice plays to sinks {:?}, not ducking those", voice_sinks);
+                }
+                sinks
+                    .borrow()
+                    .keys()
+                    .copied()
+                    .filter(|id| !voice_sinks.contains(id))
+                    .collect()
+            } else {
+                outputs
+                    .borrow()
+                    .keys()
+                    .copied()
+                    .filter(|id| *id != voice)
+                    .collect()
+            };
+            if duck_sink && targets.is_empty() {
+                warn!("--duck-sink: every sink carries the voice stream, nothing to duck");
+            }
             let mut b = baselines.borrow_mut();
-            for (id, _) in outputs.borrow().iter() {
-                if *id == voice {
-                    continue;
+            for id in targets.iter() {
+                if volume_scope == VolumeScope::Client {
+                    let outputs = outputs.borrow();
+                    if let Some(s) = outputs.get(id) {
+                        if let Some(v) = client_baseline(&outputs, &b, s) {
+                            b.insert(*id, v);
+                            debug!("client baseline: id={} -> {}", id, v);
+                            continue;
+                        }
+                    }
                 }
-                if let Some(v) = wpctl_get_volume(*id) {
+                if let Some(v) = volume_watch.volume(*id).or_else(|| backend.get(*id)) {
                     b.insert(*id, v);
-                    logln(
-                        gui_enabled,
-                        format!("baseline captured: id={} -> {}", id, v),
-                    );
+                    if let Some(s) = outputs.borrow().get(id) {
+                        app_baselines.borrow_mut().insert(s.app_key(), v);
+                    }
+                    debug!("baseline captured: id={} -> {}", id, v);
                 } else {
-                    logln(gui_enabled, format!("baseline capture failed: id={}", id));
+                    warn!("baseline capture failed: id={}", id);
                 }
             }
         }
         {
             let guard = Arc::new(Mutex::new(RestoreGuard::new(
+                backend.clone(),
                 &baselines.borrow(),
                 Some(chosen.id),
-                gui_enabled,
+                opts.duck_curve,
             )));
+            guard
+                .lock()
+                .unwrap()
+                .set_helper(helper::RestoreHelper::spawn(native));
+            if volume_scope == VolumeScope::Client {
+                let mut guard = guard.lock().unwrap();
+                for s in outputs.borrow().values() {
+                    guard.set_stream_client(s.id, Some(s.group()));
+                }
+            }
+            rules.apply(
+                &mut guard.lock().unwrap(),
+                &outputs.borrow(),
+                rules::VadInfo {
+                    active: false,
+                    level: 0.0,
+                },
+            );
+            if (opts.voice_boost - 1.0).abs() > f32::EPSILON {
+                if let Some(v) = backend.get(chosen.id) {
+                    debug!("voice baseline captured: id={} -> {}", chosen.id, v);
+                    guard.lock().unwrap().set_voice_boost(v, opts.voice_boost);
+                } else {
+                    warn!("voice baseline capture failed, --voice-boost disabled");
+                }
+            }
             *restore_guard.borrow_mut() = Some(guard.clone());
+            if opts.engine == DuckEngine::Sidechain {
+                let mut sc = Sidechain::start(SidechainParams {
+                    threshold_db: 20.0 * threshold_live.borrow().max(1e-6).log10(),
+                    ratio: opts.sc_ratio,
+                    attack_ms: attack_ms.get() as f32,
+                    release_ms: *hold_live.borrow() as f32,
+                })?;
+                // wait for node
+                run_mainloop_for(Duration::from_millis(500))?;
+                sc.link_key(&chosen.node);
+                let ids: Vec<u32> = outputs
+                    .borrow()
+                    .values()
+                    .filter(|s| s.id != chosen.id && !is_sidechain_node(&s.node))
+                    .map(|s| s.id)
+                    .collect();
+                for id in ids {
+                    sc.route(id);
+                }
+                *sidechain.borrow_mut() = Some(sc);
+            }
             let weak_guard: Weak<Mutex<RestoreGuard>> = Arc::downgrade(&guard);
             std::panic::set_hook(Box::new(move |_| {
-                elogln(gui_enabled, "panic: restoring volumes");
+                error!("panic: restoring volumes");
                 if let Some(guard) = weak_guard.upgrade() {
                     let mut guard = guard.lock().unwrap();
                     let _ = guard.restore();
                 }
             }));
-            if !gui_enabled {
+            if opts.debug {
                 *control_mode.borrow_mut() = ControlMode::ManualDucked;
                 let mut guard = guard.lock().unwrap();
                 guard.apply_duck_logged(duck_factor, "duck init", true);
+            } else if opts.daemon {
+                *control_mode.borrow_mut() = ControlMode::AutoVad;
             } else {
                 *control_mode.borrow_mut() = ControlMode::ManualRestored;
             }
         }
     }
 
-    // energy init
-    let energy_atomic = Arc::new(AtomicF32::new(0.0));
-    let vad_state: Rc<RefCell<VadState>> = Rc::new(RefCell::new(VadState::new(!gui_enabled)));
+    // vad init
+    let vad_state: Rc<RefCell<VadState>> = Rc::new(RefCell::new(VadState::new(opts.debug)));
 
     // capture setup
     let audio_seen = Arc::new(AtomicBool::new(false));
     let capture_frames = Arc::new(AtomicU64::new(0));
-    let voice_id_opt = *voice_source_id.borrow();
-    let voice_node_opt = voice_source_node.borrow().clone();
-    let voice_serial_opt = voice_source_serial.borrow().clone();
-    let _capture = setup_capture(
-        &core,
-        voice_id_opt,
-        voice_node_opt,
-        voice_serial_opt,
-        energy_atomic.clone(),
-        audio_seen.clone(),
-        capture_frames.clone(),
-        gui_enabled,
-    )?;
+    let capture_failed = Arc::new(AtomicBool::new(false));
+    // tui spectrum panel
+    let show_spectrum: Rc<Cell<bool>> = Rc::new(Cell::new(false));
+    let start_capture = {
+        let core = core.clone();
+        let voice = voice_source_id.clone();
+        let node = voice_source_node.clone();
+        let serial = voice_source_serial.clone();
+        let energy = energy_atomic.clone();
+        let zcr = zcr_atomic.clone();
+        let audio_seen = audio_seen.clone();
+        let frames = capture_frames.clone();
+        let failed = capture_failed.clone();
+        let tap = opts
+            .record_tap
+            .as_deref()
+            .map(tap::Tap::spawn)
+            .transpose()?;
+        let spectrum = spectrum.clone();
+        let quantum = match opts.capture_quantum.clone() {
+            Some(q) => Some(q),
+            None => config
+                .capture_quantum
+                .as_deref()
+                .map(units::quantum)
+                .transpose()
+                .map_err(|err| anyhow!("capture_quantum: {err}"))?,
+        };
+        let fixed = CaptureFormat {
+            rate: opts.capture_rate.or(config.capture_rate),
+            channels: opts.capture_channels.or(config.capture_channels),
+        };
+        if let Some(rate) = fixed.rate.filter(|r| !(8000..=192_000).contains(r)) {
+            return Err(anyhow!(
+                "capture_rate {rate} is out of range, use 8000 to 192000"
+            ));
+        }
+        if let Some(n) = fixed.channels.filter(|n| !(1..=8).contains(n)) {
+            return Err(anyhow!("capture_channels {n} is out of range, use 1 to 8"));
+        }
+        let select = opts
+            .capture_select
+            .or(config.capture_select)
+            .unwrap_or_default();
+        move |targeting: Targeting| {
+            failed.store(false, Ordering::Relaxed);
+            setup_capture(
+                &core,
+                CAPTURE_NAME,
+                *voice.borrow(),
+                node.borrow().clone(),
+                serial.borrow().clone(),
+                targeting,
+                quantum.as_deref(),
+                fixed,
+                select,
+                capture_monitor,
+                energy.clone(),
+                zcr.clone(),
+                audio_seen.clone(),
+                frames.clone(),
+                failed.clone(),
+                tap.clone(),
+                Some(spectrum.clone()),
+            )
+        }
+    };
+    // recreated with other targeting if it stays idle
+    let capture = Rc::new(RefCell::new(
+        start_capture(Targeting::Serial).map_err(Error::CaptureSetup)?,
+    ));
+    // local mic, to tell own voice echoes from the remote side
+    let mic_energy = Arc::new(AtomicF32::new(0.0));
+    let mic_capture = if opts.echo_reject || config.echo_reject {
+        // echo-cancelled first, a raw mic also hears the speakers
+        let cancelled = || {
+            sources
+                .borrow()
+                .values()
+                .filter(|n| candidate::is_echo_cancelled(n))
+                .min()
+                .cloned()
+        };
+        let name = opts
+            .echo_mic
+            .clone()
+            .or(config.echo_mic.clone())
+            .or_else(cancelled)
+            .or_else(|| session_manager.get().default_node(true))
+            .ok_or_else(|| anyhow!("--echo-reject: no default source, set --echo-mic"))?;
+        if require_echo_cancel && !candidate::is_echo_cancelled(&name) {
+            return Err(anyhow!(
+                "--echo-reject: mic '{name}' is not echo-cancelled (--require-echo-cancel)"
+            ));
+        }
+        let id = sources
+            .borrow()
+            .iter()
+            .find(|(_, n)| **n == name)
+            .map(|(id, _)| *id)
+            .ok_or_else(|| anyhow!("--echo-reject: no source named '{name}'"))?;
+        info!("echo rejection against mic {name} (id={id})");
+        setup_capture(
+            &core,
+            echo::MIC_CAPTURE_NAME,
+            Some(id),
+            Some(name),
+            None,
+            Targeting::NodeName,
+            None,
+            CaptureFormat {
+                rate: None,
+                channels: Some(1),
+            },
+            ChannelSelect::Mix,
+            false,
+            mic_energy.clone(),
+            Arc::new(AtomicF32::new(0.0)),
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicU64::new(0)),
+            Arc::new(AtomicBool::new(false)),
+            None,
+            None,
+        )
+        .map_err(Error::CaptureSetup)?
+        .map(|capture| (capture, mic_energy.clone()))
+    } else {
+        None
+    };
+    // paused while nothing would be ducked
+    let capture_active: Rc<Cell<bool>> = Rc::new(Cell::new(true));
+
+    // plain commands
+    if plain {
+        plain::spawn_reader(
+            control_tx.clone(),
+            Arc::clone(&ipc_status),
+            Arc::clone(&quit_requested),
+        )?;
+        plain::say(&format!(
+            "voice source {} ({})",
+            voice_source_label.borrow(),
+            voice_source_reason.borrow()
+        ));
+        plain::help();
+    }
+
+    // switch suggestion
+    let switch_pending: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let switch_accept: Rc<Cell<bool>> = Rc::new(Cell::new(false));
 
     // VAD timer
     let vad_timer = {
+        let session_manager = session_manager.clone();
+        let switch_pending_t = switch_pending.clone();
+        let switch_accept_t = switch_accept.clone();
+        let show_spectrum_t = show_spectrum.clone();
+        let spectrum_t = spectrum.clone();
         let voice_label_t = voice_source_label.clone();
         let voice_reason_t = voice_source_reason.clone();
         let vad_t = vad_state.clone();
         let guard_t = restore_guard.clone();
         let mode_t = control_mode.clone();
-        let threshold = opts.threshold;
-        let attack_ms = opts.attack;
-        let hold_ms = opts.hold;
+        let soft_knee = opts.soft_knee;
+        // stuck duck watchdog
+        let max_duck = opts.max_duck.or(config.max_duck).filter(|m| *m > 0);
+        let max_duck_pause = opts.max_duck_pause || config.max_duck_pause;
+        let ducked_since: Cell<Option<Instant>> = Cell::new(None);
+        // slow baseline drift
+        let baseline_refresh = opts
+            .baseline_refresh
+            .or(config.baseline_refresh)
+            .filter(|s| *s > 0)
+            .map(Duration::from_secs);
+        let refreshed_at = Cell::new(Instant::now());
+        // restores given up on, for the quit dialog
+        let restores_failed = Cell::new(0);
+        let rules_t = rules.clone();
+        let outputs_t = outputs.clone();
+        let links_t = links.clone();
+        let sink_labels_t = sink_labels.clone();
+        let attack_t = attack_ms.clone();
+        let release = match opts.release {
+            Some(level) => Some(level),
+            None => config
+                .release
+                .map(|r| units::threshold(&r.to_string()))
+                .transpose()
+                .map_err(|err| anyhow!("release: {err}"))?,
+        };
+        let session = control::Session {
+            rules: rules.clone(),
+            outputs: outputs.clone(),
+            profiles,
+            scenes: config.scene.clone(),
+            scene: RefCell::new(None),
+            failover: failover.clone(),
+            attack_ms: attack_ms.clone(),
+            release: Cell::new(release),
+            config_path: config::path(opts.config.as_deref()),
+            profiled: RefCell::new(profiled),
+        };
+        // tui command line and tabs
+        let prompt_t: RefCell<Option<String>> = RefCell::new(None);
+        let view_t = ui::View::default();
+        let control_tx_t = control_tx.clone();
+        let voice_t = voice_source_id.clone();
         let energy_t = energy_atomic.clone();
+        let discord_gate_t = discord_gate.clone();
+        let mic_t = mic_capture.as_ref().map(|(_, energy)| energy.clone());
+        let echo_reject = RefCell::new(echo::EchoReject::default());
+        let locked_t = session_locked.clone();
+        let agents_t = agents.clone();
+        let backend_t = backend.clone();
+        let watch_t = volume_watch.clone();
+        let capture_t = capture.clone();
+        let capture_timeout = Duration::from_secs(
+            opts.capture_timeout
+                .or(config.capture_timeout)
+                .unwrap_or(3)
+                .max(1),
+        );
+        // strategy in use, None once all failed
+        let targeting = Cell::new(Some(Targeting::Serial));
+        let capture_failed_t = capture_failed.clone();
+        let core_t = core.clone();
+        let ports_t = ports.clone();
+        let capture_node_t = capture_node.clone();
+        let manual_links: RefCell<Vec<pw::link::Link>> = RefCell::new(Vec::new());
+        let capture_active_t = capture_active.clone();
+        let was_locked = Cell::new(false);
+        let zcr_t = zcr_atomic.clone();
+        let zcr_gate = opts.zcr_gate || config.zcr_gate;
+        let gain_rate = match opts.gain_rate {
+            Some(rate) => Some(rate),
+            None => config
+                .gain_rate
+                .map(|r| units::gain_rate(&r.to_string()))
+                .transpose()
+                .map_err(|err| anyhow!("gain_rate: {err}"))?,
+        };
+        let onset_votes = match opts.onset_votes {
+            Some(votes) => Some(votes),
+            None => config
+                .onset_votes
+                .as_deref()
+                .map(units::votes)
+                .transpose()
+                .map_err(|err| anyhow!("onset_votes: {err}"))?,
+        };
+        let adaptive_hold = opts.adaptive_hold || config.adaptive_hold;
+        let adapted_hold: Cell<Option<u64>> = Cell::new(None);
         let quit_flag_t = quit_requested.clone();
+        let suspend_t = suspend_requested.clone();
+        // stop once the restores are confirmed
+        let suspend_at: Cell<Option<Instant>> = Cell::new(None);
         let gui_log_t = gui_log.clone();
         let duck_factor_live = duck_factor_live.clone();
         let threshold_live = threshold_live.clone();
         let hold_live = hold_live.clone();
+        let ipc_status_t = ipc_status.clone();
+        let state_file = RefCell::new(ipc::StateFile::default());
+        let trace = RefCell::new(
+            opts.trace
+                .as_deref()
+                .map(trace::Trace::create)
+                .transpose()?,
+        );
+        let plain_last: Cell<Option<(bool, bool)>> = Cell::new(None);
         // heartbeat source
         let audio_seen_t = audio_seen.clone();
         let capture_frames_t = capture_frames.clone();
@@ -599,115 +1936,588 @@ fn main() -> Result<()> {
         let idle_warned_t = idle_warned.clone();
         let last_log = Rc::new(RefCell::new(Instant::now()));
         let last_log_t = last_log.clone();
-        let start_time = Rc::new(Instant::now());
-        let start_time_t = start_time.clone();
+        // capture (re)created or resumed
+        let capture_started = Cell::new(Instant::now());
         let timer = mainloop.loop_().add_timer(move |_| {
             if gui_enabled {
                 handle_gui_input(
                     &guard_t,
-                    &mode_t,
-                    &vad_t,
                     &gui_log_t,
                     &quit_flag_t,
+                    &suspend_t,
                     &threshold_live,
                     &duck_factor_live,
                     &hold_live,
-                    gui_enabled,
+                    &switch_accept_t,
+                    &show_spectrum_t,
+                    &view_t,
+                    &prompt_t,
+                    &control_tx_t,
                 );
             }
+            // remote control
+            while let Ok(ctl) = control_rx.try_recv() {
+                let msg = control::apply(
+                    ctl,
+                    &guard_t,
+                    &mode_t,
+                    &vad_t,
+                    &threshold_live,
+                    &duck_factor_live,
+                    &hold_live,
+                    &session,
+                );
+                info!("{msg}");
+                say_line(&gui_log_t, gui_enabled, plain, msg);
+            }
+            let say = |msg: String| {
+                info!("{msg}");
+                say_line(&gui_log_t, gui_enabled, plain, msg);
+            };
+            let pause = || {
+                if let Some(guard) = guard_t.borrow().as_ref() {
+                    let mut guard = guard.lock().unwrap();
+                    if guard.ducked {
+                        guard.restore();
+                    }
+                }
+                let mut vad = vad_t.borrow_mut();
+                vad.voice_active = false;
+                vad.above_start = None;
+                vad.last_above = None;
+                vad.stop_ramp();
+            };
+            // baselines may have moved meanwhile, manual modes duck again
+            let resume = || {
+                let refreshed = guard_t.borrow().as_ref().map_or(0, |guard| {
+                    let mut guard = guard.lock().unwrap();
+                    let refreshed = guard.refresh_baselines();
+                    if matches!(
+                        *mode_t.borrow(),
+                        ControlMode::ManualDucked | ControlMode::Focus
+                    ) {
+                        guard.apply_duck(*duck_factor_live.borrow());
+                    }
+                    refreshed
+                });
+                say(messages::resumed(refreshed));
+            };
+            // volume tool broken, already logged
+            if let Some(alert) = backend_t.take_alert() {
+                say_line(&gui_log_t, gui_enabled, plain, alert);
+            }
+            // nothing stays ducked while stopped
+            if suspend_t.swap(false, Ordering::Relaxed) {
+                pause();
+                // in-process restores only reach PipeWire from the loop
+                if native {
+                    watch_t.flush();
+                }
+                suspend_at.set(Some(Instant::now() + NATIVE_FLUSH_TIMEOUT));
+            }
+            if suspend_at
+                .get()
+                .is_some_and(|deadline| watch_t.settled() || Instant::now() >= deadline)
+            {
+                suspend_at.set(None);
+                info!("suspending, volumes restored");
+                if let Err(err) = ui::suspend(gui_enabled) {
+                    warn!("suspend failed: {err}");
+                }
+                resume();
+            }
+            // or while the screen is locked
+            // or while another agent has the volumes
+            if agents_t.take_changed() && conflict == ConflictPolicy::Coordinate {
+                rules_t.set_coordinated(agents_t.ducked_roles());
+                if let Some(guard) = guard_t.borrow().as_ref() {
+                    let vad = rules::VadInfo {
+                        active: vad_t.borrow().voice_active,
+                        level: 0.0,
+                    };
+                    rules_t.apply(&mut guard.lock().unwrap(), &outputs_t.borrow(), vad);
+                }
+            }
+            let deferred = agents_t.defers(conflict).then(|| agents_t.describe());
+            let locked = locked_t.as_ref().is_some_and(|l| l.load(Ordering::Relaxed));
+            let hands_off = locked || deferred.is_some();
+            if hands_off != was_locked.replace(hands_off) {
+                if hands_off {
+                    pause();
+                    say(match &deferred {
+                        Some(agents) if !locked => messages::deferring(agents),
+                        _ => messages::session_locked(),
+                    });
+                } else {
+                    resume();
+                }
+            }
+            // capture only while a duck could follow
+            let needed = !hands_off
+                && *mode_t.borrow() != ControlMode::ManualRestored
+                && guard_t
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|g| g.lock().unwrap().has_duckable());
+            if needed != capture_active_t.get() {
+                if let Some((stream, _)) = capture_t.borrow().as_ref() {
+                    match stream.set_active(needed) {
+                        Ok(()) => {
+                            capture_active_t.set(needed);
+                            if needed {
+                                debug!("capture resumed");
+                                capture_started.set(Instant::now());
+                                let mut vad = vad_t.borrow_mut();
+                                vad.voice_active = false;
+                                vad.above_start = None;
+                                vad.last_above = None;
+                            } else {
+                                debug!("capture paused, nothing to duck");
+                                energy_t.store(0.0);
+                            }
+                        }
+                        Err(err) => warn!("capture set_active({needed}): {err}"),
+                    }
+                }
+            }
             if audio_seen_t.load(Ordering::Relaxed) && !audio_logged_t.swap(true, Ordering::Relaxed)
             {
-                logln(gui_enabled, "Audio-Frames empfangen (Capture aktiv).");
+                info!("{}", Msg::AudioSeen.text());
             }
             let now = Instant::now();
             if now.duration_since(*last_log_t.borrow()) >= Duration::from_secs(1) {
                 let seen = capture_frames_t.load(Ordering::Relaxed);
-                logln(gui_enabled, format!("capture frames seen = {}", seen));
+                trace!("capture frames seen = {}", seen);
                 *last_log_t.borrow_mut() = now;
             }
-            if now.duration_since(*start_time_t) >= Duration::from_secs(3)
+            // idle: link by hand, then retry with the next targeting
+            let idle = now.duration_since(capture_started.get()) >= capture_timeout
                 && capture_frames_t.load(Ordering::Relaxed) == 0
-                && !idle_warned_t.swap(true, Ordering::Relaxed)
-                && !gui_enabled
-            {
-                logln(gui_enabled, "CAPTURE IDLE (no frames) -> likely not linked");
+                && capture_active_t.get();
+            let failed = capture_failed_t.load(Ordering::Relaxed);
+            if let Some(current) = targeting.get().filter(|_| idle || failed) {
+                if failed || idle_warned_t.swap(true, Ordering::Relaxed) {
+                    manual_links.borrow_mut().clear();
+                    drop(capture_t.borrow_mut().take());
+                    targeting.set(current.next());
+                    if let Some(next) = current.next() {
+                        warn!("capture retry: targeting by {}", next.name());
+                        match start_capture(next) {
+                            Ok(stream) => *capture_t.borrow_mut() = stream,
+                            Err(err) => {
+                                warn!("capture retry: {err}");
+                                capture_failed_t.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        capture_active_t.set(true);
+                        idle_warned_t.store(false, Ordering::Relaxed);
+                    } else {
+                        let msg = messages::capture_gave_up();
+                        warn!("{msg}");
+                        say(msg.clone());
+                        events::emit(Event::Error { message: msg });
+                    }
+                } else {
+                    warn!("CAPTURE IDLE (no frames) -> likely not linked, linking by hand");
+                    events::emit(Event::Error {
+                        message: "capture idle (no frames)".into(),
+                    });
+                    if let (Some(voice), Some(capture)) = (*voice_t.borrow(), capture_node_t.get())
+                    {
+                        *manual_links.borrow_mut() =
+                            links::create(&core_t, &ports_t, voice, capture);
+                    }
+                }
+                capture_started.set(now);
             }
 
             let energy = energy_t.load();
             let now = Instant::now();
-            let mode = *mode_t.borrow();
-            let threshold = if gui_enabled {
-                *threshold_live.borrow()
-            } else {
-                threshold
-            };
-            let hold_ms = if gui_enabled {
-                *hold_live.borrow()
-            } else {
-                hold_ms
-            };
-            let hold_ms_effective = if hold_ms < 300 { 300 } else { hold_ms };
-            let duck_factor_now = if gui_enabled {
-                *duck_factor_live.borrow()
+            // locked, deferring or about to stop: hands off
+            let mode = if hands_off || suspend_at.get().is_some() {
+                ControlMode::ManualRestored
             } else {
-                duck_factor
+                *mode_t.borrow()
             };
+            let threshold = *threshold_live.borrow();
+            let hold_ms = *hold_live.borrow();
+            let adapted = adaptive_hold
+                .then(|| vad_t.borrow().adaptive_hold())
+                .flatten();
+            if adapted != adapted_hold.replace(adapted) {
+                if let Some(ms) = adapted {
+                    debug!("adaptive hold -> {ms} ms");
+                }
+            }
+            let hold_ms_effective = adapted.unwrap_or(hold_ms).max(MIN_HOLD_MS);
+            let duck_factor_now = *duck_factor_live.borrow();
 
             let snapshot = if let Some(guard_ref) = guard_t.borrow().as_ref() {
                 let mut guard = guard_ref.lock().unwrap();
                 let mut vad = vad_t.borrow_mut();
+                vad.speech_ratio = spectrum_t.speech_ratio();
+                vad.zcr = zcr_gate.then(|| zcr_t.load());
+                vad.onset_votes = onset_votes;
+                vad.gain_rate = gain_rate;
+                vad.release = session.release.get();
                 let mut log_fn = |msg: String| {
-                    if gui_enabled {
-                        gui_log_t.borrow_mut().push(msg);
-                    } else {
-                        logln(gui_enabled, msg);
+                    info!("{msg}");
+                    say_line(&gui_log_t, gui_enabled, false, msg);
+                };
+                // re-run rules before applying
+                let mut on_transition = |guard: &mut RestoreGuard, active: bool, level: f32| {
+                    if rules_t.is_dynamic() {
+                        let vad = rules::VadInfo { active, level };
+                        rules_t.apply(guard, &outputs_t.borrow(), vad);
                     }
                 };
+                // outside a discord call, nothing counts as voice
+                let gated = discord_gate_t
+                    .as_ref()
+                    .is_some_and(|g| !g.load(Ordering::Relaxed));
+                // own voice echoed by the remote side
+                let gated = mic_t
+                    .as_ref()
+                    .is_some_and(|mic| echo_reject.borrow_mut().step(mic.load(), energy))
+                    || gated;
                 auto_vad_step(
                     mode,
-                    energy,
+                    if gated { 0.0 } else { energy },
                     threshold,
                     now,
                     &mut vad,
                     &mut guard,
                     duck_factor_now,
                     &mut log_fn,
-                    attack_ms,
+                    attack_t.get(),
                     hold_ms_effective,
+                    soft_knee,
+                    &mut on_transition,
                 )
             } else {
                 return;
             };
 
+            // stuck duck watchdog
+            let mut snapshot = snapshot;
+            match max_duck {
+                Some(minutes) if snapshot.applied_duck && mode == ControlMode::AutoVad => {
+                    let since = *ducked_since.get().get_or_insert(now);
+                    ducked_since.set(Some(since));
+                    if now.duration_since(since) >= Duration::from_secs(minutes * 60) {
+                        warn!("ducked for {minutes} min without a break, restoring (stuck VAD?)");
+                        events::emit(Event::Watchdog { minutes });
+                        if let Some(guard) = guard_t.borrow().as_ref() {
+                            guard.lock().unwrap().restore();
+                        }
+                        *vad_t.borrow_mut() = VadState::new(false);
+                        if max_duck_pause {
+                            *mode_t.borrow_mut() = ControlMode::ManualRestored;
+                        }
+                        let msg = messages::watchdog(minutes, max_duck_pause);
+                        say_line(&gui_log_t, gui_enabled, plain, msg);
+                        snapshot.voice_active = false;
+                        snapshot.applied_duck = false;
+                        snapshot.armed = false;
+                        ducked_since.set(None);
+                    }
+                }
+                _ => ducked_since.set(None),
+            }
+
+            // restore retries
+            if let Some(guard) = guard_t.borrow().as_ref() {
+                for id in guard.lock().unwrap().retry_restores(now) {
+                    restores_failed.set(restores_failed.get() + 1);
+                    let msg = messages::restore_failed(id);
+                    say_line(&gui_log_t, gui_enabled, plain, msg);
+                }
+            }
+
+            // periodic baseline refresh
+            if let Some(every) = baseline_refresh {
+                if !snapshot.applied_duck && now.duration_since(refreshed_at.get()) >= every {
+                    refreshed_at.set(now);
+                    if let Some(guard) = guard_t.borrow().as_ref() {
+                        let changed = guard.lock().unwrap().refresh_baselines();
+                        if changed > 0 {
+                            debug!("baseline refresh: {changed} changed");
+                        }
+                    }
+                }
+            }
+
+            // plain transitions
+            if plain {
+                let now_state = (snapshot.voice_active, snapshot.applied_duck);
+                if plain_last.replace(Some(now_state)) != Some(now_state) {
+                    plain::say(&messages::voice_ducking(now_state.0, now_state.1));
+                }
+            }
+
+            let reason = switch_pending_t
+                .borrow()
+                .clone()
+                .unwrap_or_else(|| voice_reason_t.borrow().clone());
+            let groups = stream_groups(
+                &outputs_t.borrow(),
+                *voice_t.borrow(),
+                guard_t.borrow().as_ref(),
+                &links_t.borrow(),
+                &sink_labels_t.borrow(),
+            );
+
+            let scene = session.scene.borrow().clone().unwrap_or_default();
+
+            // ipc status
+            {
+                let mut status = ipc_status_t.lock().unwrap();
+                status.mode = mode.as_str().to_string();
+                status.voice_source.clone_from(&voice_label_t.borrow());
+                status.voice_source_id = *voice_t.borrow();
+                status.voice_active = snapshot.voice_active;
+                status.ducked = snapshot.applied_duck;
+                status.armed = snapshot.armed;
+                status.level = energy;
+                status.confidence = snapshot.confidence;
+                status.threshold = threshold;
+                status.duck_factor = duck_factor_now;
+                status.hold_ms = hold_ms;
+                status.streams = outputs_t.borrow().len();
+                status.reason.clone_from(&reason);
+                status.scene.clone_from(&scene);
+                status.session_manager = session_manager.get().as_str().to_string();
+                status.groups.clone_from(&groups);
+                state_file.borrow_mut().update(&status, now);
+                if let Some(published) = &published {
+                    published.update(&status);
+                }
+            }
+            if let Some(trace) = trace.borrow_mut().as_mut() {
+                trace.sample(
+                    energy,
+                    threshold,
+                    snapshot.voice_active,
+                    snapshot.applied_duck,
+                );
+            }
+            if native {
+                watch_t.flush();
+            }
+
+            #[cfg(feature = "metrics")]
+            {
+                let m = &metrics::METRICS;
+                m.energy.store(energy);
+                m.voice_active
+                    .store(snapshot.voice_active, Ordering::Relaxed);
+                m.ducked.store(snapshot.applied_duck, Ordering::Relaxed);
+                m.capture_frames_total
+                    .store(capture_frames_t.load(Ordering::Relaxed), Ordering::Relaxed);
+            }
+
             if gui_enabled {
                 let duck_factor_live = *duck_factor_live.borrow();
                 let threshold_live = *threshold_live.borrow();
                 let label = voice_label_t.borrow().clone();
-                let reason = voice_reason_t.borrow().clone();
                 let log = gui_log_t.borrow();
+                let quit = view_t.confirming_quit().then(|| ui::QuitSummary {
+                    restoring: groups
+                        .iter()
+                        .filter(|g| g.state.starts_with("duck"))
+                        .map(|g| g.label.clone())
+                        .collect(),
+                    failed: restores_failed.get()
+                        + guard_t
+                            .borrow()
+                            .as_ref()
+                            .map_or(0, |g| g.lock().unwrap().pending_restores()),
+                });
                 render_gui(
                     label,
                     reason,
+                    &scene,
                     mode,
                     &snapshot,
                     energy,
                     threshold_live,
                     duck_factor_live,
                     hold_ms,
+                    &groups,
+                    show_spectrum_t
+                        .get()
+                        .then(|| spectrum_t.bands(ui::spectrum_bands()))
+                        .as_deref(),
+                    &view_t,
+                    quit.as_ref(),
+                    Msg::Keys,
+                    prompt_t.borrow().as_deref(),
                     &log,
                 );
             }
         });
         timer
             .update_timer(
-                Some(Duration::from_millis(50)),
-                Some(Duration::from_millis(50)),
+                Some(Duration::from_millis(VAD_TICK_MS)),
+                Some(Duration::from_millis(VAD_TICK_MS)),
             )
             .into_result()?;
         timer
     };
     let _vad_timer = vad_timer;
 
+    // voice source switching, for failover and re-probing
+    let switcher = Rc::new(VoiceSwitch {
+        voice: voice_source_id.clone(),
+        node: voice_source_node.clone(),
+        serial: voice_source_serial.clone(),
+        label: voice_source_label.clone(),
+        reason: voice_source_reason.clone(),
+        outputs: outputs.clone(),
+        baselines: baselines.clone(),
+        backend: backend.clone(),
+        guard: restore_guard.clone(),
+        vad: vad_state.clone(),
+        capture_node: capture_node.clone(),
+        sidechain: sidechain.clone(),
+        voice_boost: opts.voice_boost,
+        switched_at: Cell::new(Instant::now()),
+    });
+    // failover right when the voice source goes away
+    *on_voice_lost.borrow_mut() = Some({
+        let switcher = switcher.clone();
+        let failover = failover.clone();
+        let gui_log_t = gui_log.clone();
+        Box::new(move || {
+            if let Some(msg) = switcher.fail_over(&failover) {
+                info!("{msg}");
+                say_line(&gui_log_t, gui_enabled, plain, msg);
+            }
+        })
+    });
+    // failover back to a higher-ranked source, or to new scene patterns
+    let failover_timer = {
+        let switcher = switcher.clone();
+        let failover = failover.clone();
+        let gui_log_t = gui_log.clone();
+        let timer = mainloop.loop_().add_timer(move |_| {
+            if let Some(msg) = switcher.fail_over(&failover) {
+                info!("{msg}");
+                say_line(&gui_log_t, gui_enabled, plain, msg);
+            }
+        });
+        timer
+            .update_timer(Some(Duration::from_secs(1)), Some(Duration::from_secs(1)))
+            .into_result()?;
+        timer
+    };
+    let _failover_timer = failover_timer;
+
+    // background re-probing
+    let reprobe_timer = if opts.engine == DuckEngine::Sidechain || duck_sink || music_sink {
+        debug!("background re-probing off for this engine");
+        None
+    } else {
+        let reprober = reprobe::Reprober::spawn()?;
+        let watch = RefCell::new(reprobe::SwitchWatch::default());
+        let switcher = switcher.clone();
+        let outputs_t = outputs.clone();
+        let voice_t = voice_source_id.clone();
+        let vad_t = vad_state.clone();
+        let threshold_live = threshold_live.clone();
+        let gui_log_t = gui_log.clone();
+        let switch_pending_t = switch_pending.clone();
+        let switch_accept_t = switch_accept.clone();
+        let auto_switch = opts.auto_switch;
+        // last switch the probe state was reset for
+        let reset_at = Cell::new(switcher.switched_at.get());
+        let capture_active_t = capture_active.clone();
+        let scorer = scorer.clone();
+        let timer = mainloop.loop_().add_timer(move |_| {
+            // switched, by failover or here: start over
+            if switcher.switched_at.get() != reset_at.get() {
+                reset_at.set(switcher.switched_at.get());
+                watch.borrow_mut().reset();
+                *switch_pending_t.borrow_mut() = None;
+                switch_accept_t.set(false);
+            }
+            let current = *voice_t.borrow();
+            let Some(voice) = current else {
+                return;
+            };
+
+            // re-probing, only once the voice is quiet and the capture runs
+            let last_voice = vad_t
+                .borrow()
+                .last_above
+                .map_or(switcher.switched_at.get(), |t| {
+                    t.max(switcher.switched_at.get())
+                });
+            if !capture_active_t.get() || last_voice.elapsed() < reprobe::PROBE_AFTER {
+                reprober.set_targets(Vec::new());
+                reprober.readings().for_each(drop);
+                watch.borrow_mut().reset();
+                *switch_pending_t.borrow_mut() = None;
+                switch_accept_t.set(false);
+                return;
+            }
+            let targets: Vec<reprobe::Target> = outputs_t
+                .borrow()
+                .values()
+                .filter(|s| s.id != voice && scorer.is_candidate(s))
+                .map(|s| reprobe::Target {
+                    id: s.id,
+                    serial: s.serial.clone(),
+                    node: s.node.clone(),
+                })
+                .collect();
+            let suggestion = {
+                let mut watch = watch.borrow_mut();
+                watch.retain(&targets);
+                reprober.set_targets(targets);
+                let threshold = *threshold_live.borrow();
+                for reading in reprober.readings() {
+                    watch.reading(reading, threshold);
+                }
+                watch.suggestion(last_voice.elapsed())
+            };
+            let Some(id) = suggestion else {
+                *switch_pending_t.borrow_mut() = None;
+                switch_accept_t.set(false);
+                return;
+            };
+            let Some(next) = outputs_t.borrow().get(&id).cloned() else {
+                return;
+            };
+            if !auto_switch && !switch_accept_t.get() {
+                if switch_pending_t.borrow().is_none() {
+                    let msg = messages::switch_offered(&next.app, next.id, false);
+                    info!("{msg}");
+                    if plain {
+                        plain::say(&messages::switch_offered(&next.app, next.id, true));
+                    }
+                    *switch_pending_t.borrow_mut() = Some(msg);
+                }
+                return;
+            }
+            let reason = if auto_switch {
+                "auto-switch"
+            } else {
+                "switch accepted"
+            };
+            if switcher.switch_to(&next, reason) {
+                let msg = messages::voice_switched(voice, next.id, &next.app);
+                info!("{msg}");
+                say_line(&gui_log_t, gui_enabled, plain, msg);
+            }
+        });
+        timer
+            .update_timer(Some(Duration::from_secs(1)), Some(Duration::from_secs(1)))
+            .into_result()?;
+        Some(timer)
+    };
+    let _reprobe_timer = reprobe_timer;
+
     // heartbeat
     let heartbeat_timer = {
         let mainloop_t = mainloop.clone();
@@ -716,16 +2526,11 @@ fn main() -> Result<()> {
         let pid = std::process::id();
         let frames_t = capture_frames.clone();
         let timer = mainloop.loop_().add_timer(move |_| {
-            if !gui_enabled {
-                let elapsed = start_time.elapsed().as_secs();
-                let frames = frames_t.load(Ordering::Relaxed);
-                elogln(
-                    gui_enabled,
-                    format!("HEARTBEAT pid={} t={} frames={}", pid, elapsed, frames),
-                );
-            }
+            let elapsed = start_time.elapsed().as_secs();
+            let frames = frames_t.load(Ordering::Relaxed);
+            debug!("HEARTBEAT pid={} t={} frames={}", pid, elapsed, frames);
             if quit_flag_t.load(Ordering::Relaxed) {
-                elogln(gui_enabled, "quit requested");
+                info!("quit requested");
                 mainloop_t.quit();
             }
         });
@@ -738,8 +2543,285 @@ fn main() -> Result<()> {
 
     let _gui_mode_guard = if gui_enabled { gui_mode_guard } else { None };
 
-    logln(gui_enabled, "\nLive‑Betrieb … (Ctrl+C zum Beenden)\n");
+    // once running and again on exit
+    let dump_graph = || {
+        let Some(path) = opts.dump_graph.as_deref() else {
+            return;
+        };
+        let mut candidates: Vec<snapshot::DumpCandidate> = outputs
+            .borrow()
+            .values()
+            .map(|s| snapshot::DumpCandidate {
+                id: s.id,
+                app: s.app.clone(),
+                node: s.node.clone(),
+                client: scorer.client(s).map(|(name, _)| name.to_string()),
+                score: scorer.score(s),
+            })
+            .collect();
+        candidates.sort_by_key(|c| (std::cmp::Reverse(c.score), c.id));
+        let state = snapshot::DumpState {
+            session_manager: session_manager.get().as_str().to_string(),
+            voice_source_id: *voice_source_id.borrow(),
+            voice_source: voice_source_label.borrow().clone(),
+            voice_source_reason: voice_source_reason.borrow().clone(),
+            capture_node: capture_node.get(),
+            ducked: restore_guard
+                .borrow()
+                .as_ref()
+                .is_some_and(|g| g.lock().unwrap().ducked),
+            candidates,
+            baselines: baselines.borrow().iter().map(|(k, v)| (*k, *v)).collect(),
+        };
+        if let Err(err) = snapshot::write_dump(path, &graph.borrow(), &state) {
+            warn!("{err:#}");
+        }
+    };
+    dump_graph();
+
+    info!("Live‑Betrieb … (Ctrl+C zum Beenden)");
     mainloop.run();
-    elogln(gui_enabled, "mainloop exited");
+    info!("mainloop exited");
+    dump_graph();
+    if native {
+        // in-process restores need the loop to reach the server
+        if let Some(guard) = restore_guard.borrow().as_ref() {
+            guard.lock().unwrap().restore();
+        }
+        volume_watch.flush();
+        let deadline = Instant::now() + NATIVE_FLUSH_TIMEOUT;
+        while !volume_watch.settled() && Instant::now() < deadline {
+            mainloop.loop_().iterate(Duration::from_millis(50));
+        }
+        if !volume_watch.settled() {
+            warn!("some volumes were not confirmed before exit");
+        }
+    }
     Ok(())
 }
+
+/// voice source state, for switching at runtime
+struct VoiceSwitch {
+    voice: Rc<RefCell<Option<u32>>>,
+    node: Rc<RefCell<Option<String>>>,
+    serial: Rc<RefCell<Option<String>>>,
+    label: Rc<RefCell<String>>,
+    reason: Rc<RefCell<String>>,
+    outputs: Rc<RefCell<HashMap<u32, OutputStream>>>,
+    baselines: Rc<RefCell<HashMap<u32, f32>>>,
+    backend: Arc<dyn VolumeBackend>,
+    guard: Rc<RefCell<Option<Arc<Mutex<RestoreGuard>>>>>,
+    vad: Rc<RefCell<VadState>>,
+    capture_node: Rc<Cell<Option<u32>>>,
+    sidechain: Rc<RefCell<Option<Sidechain>>>,
+    voice_boost: f32,
+    /// selection or last switch
+    switched_at: Cell<Instant>,
+}
+
+impl VoiceSwitch {
+    /// switch to the best `--voice-source` match
+    ///
+    /// Taken when the voice source is gone, when a higher-ranked one is
+    /// back, or when a scene brought new patterns. Returns the log line.
+    fn fail_over(&self, failover: &candidate::Failover) -> Option<String> {
+        let current = *self.voice.borrow();
+        let (rank, next) = {
+            let outputs = self.outputs.borrow();
+            // new scene patterns: pick as if the voice was gone
+            let current_rank = if failover.take_replaced() {
+                None
+            } else {
+                current.map(|id| outputs.get(&id).and_then(|c| failover.rank(c)))
+            };
+            match current_rank {
+                // voice gone
+                None => failover.pick(&outputs),
+                // back to a higher-ranked source
+                Some(Some(rank)) => failover.pick(&outputs).filter(|(r, _)| *r < rank),
+                Some(None) => None,
+            }
+        }
+        .filter(|(_, n)| Some(n.id) != current)?;
+        let reason = format!("voice source #{}", rank + 1);
+        self.switch_to(&next, &reason)
+            .then(|| messages::voice_failover(next.id, &next.app, rank + 1))
+    }
+
+    /// retarget capture and guard
+    fn switch_to(&self, next: &OutputStream, reason: &str) -> bool {
+        let Some(capture) = self.capture_node.get() else {
+            warn!("capture node unknown, cannot switch voice source");
+            return false;
+        };
+        if !capture::retarget(capture, &next.serial, &next.node) {
+            return false;
+        }
+        if let Some(sc) = self.sidechain.borrow_mut().as_mut() {
+            sc.unroute(next.id);
+            sc.link_key(&next.node);
+        }
+        let old = *self.voice.borrow();
+        if let Some(guard) = self.guard.borrow().as_ref() {
+            let mut guard = guard.lock().unwrap();
+            if guard.ducked {
+                guard.restore();
+            }
+            guard.set_voice(Some(next.id));
+            // old voice is ducked from now on
+            if let Some(old) = old.filter(|id| self.outputs.borrow().contains_key(id)) {
+                if let Some(v) = self.backend.get(old) {
+                    self.baselines.borrow_mut().insert(old, v);
+                    guard.add_stream(old, v);
+                }
+            }
+            if (self.voice_boost - 1.0).abs() > f32::EPSILON {
+                if let Some(v) = self.backend.get(next.id) {
+                    guard.set_voice_boost(v, self.voice_boost);
+                }
+            }
+        }
+        self.baselines.borrow_mut().remove(&next.id);
+        *self.voice.borrow_mut() = Some(next.id);
+        *self.node.borrow_mut() = Some(next.node.clone());
+        *self.serial.borrow_mut() = Some(next.serial.clone());
+        *self.label.borrow_mut() = next.app.clone();
+        *self.reason.borrow_mut() = reason.to_string();
+        let mut vad = self.vad.borrow_mut();
+        vad.voice_active = false;
+        vad.above_start = None;
+        vad.last_above = None;
+        vad.reset_cadence();
+        self.switched_at.set(Instant::now());
+        true
+    }
+}
+
+/// line for the TUI log or plain mode, also sent as a `log` event
+fn say_line(gui_log: &RefCell<Vec<String>>, gui_enabled: bool, plain: bool, msg: String) {
+    events::emit(Event::Log {
+        message: msg.clone(),
+    });
+    if gui_enabled {
+        gui_log.borrow_mut().push(msg);
+    } else if plain {
+        plain::say(&msg);
+    }
+}
+
+/// start tracking a non-voice stream
+///
+/// `late` streams are left alone until the next restore; `shared` baselines
+/// come from another stream of the client. `client` is set under client
+/// scope, the client's streams are then written together.
+#[allow(clippy::too_many_arguments)]
+fn join_stream(
+    guard: &mut RestoreGuard,
+    id: u32,
+    baseline: f32,
+    late: bool,
+    shared: bool,
+    client: Option<String>,
+    rules: &rules::RuleSet,
+    outputs: &HashMap<u32, OutputStream>,
+    level: f32,
+    factor: f32,
+) {
+    if shared {
+        guard.add_client_stream(id, baseline);
+    } else {
+        guard.add_stream(id, baseline);
+    }
+    guard.set_stream_client(id, client);
+    let vad = rules::VadInfo {
+        active: guard.ducked,
+        level,
+    };
+    rules.apply(guard, outputs, vad);
+    if guard.ducked {
+        if late {
+            info!("new stream while ducked: id={id} left alone");
+            guard.leave_until_restore(id);
+        } else {
+            guard.apply_duck(factor);
+        }
+    }
+}
+
+/// baseline of another stream of the same client
+fn client_baseline(
+    outputs: &HashMap<u32, OutputStream>,
+    baselines: &HashMap<u32, f32>,
+    stream: &OutputStream,
+) -> Option<f32> {
+    let group = stream.group();
+    outputs
+        .values()
+        .filter(|s| s.id != stream.id && s.group() == group)
+        .find_map(|s| baselines.get(&s.id).copied())
+}
+
+/// sinks the given nodes play to, empty when unlinked
+fn routing(
+    links: &HashMap<u32, (u32, u32)>,
+    sink_labels: &HashMap<u32, String>,
+    nodes: impl Iterator<Item = u32>,
+) -> String {
+    let mut sinks: Vec<&str> = nodes
+        .flat_map(|id| linked_targets(links, id))
+        .filter_map(|id| sink_labels.get(&id).map(String::as_str))
+        .collect();
+    sinks.sort_unstable();
+    sinks.dedup();
+    sinks.join(", ")
+}
+
+/// stream pane rows
+fn stream_groups(
+    outputs: &HashMap<u32, OutputStream>,
+    voice: Option<u32>,
+    guard: Option<&Arc<Mutex<RestoreGuard>>>,
+    links: &HashMap<u32, (u32, u32)>,
+    sink_labels: &HashMap<u32, String>,
+) -> Vec<GroupRow> {
+    let guard = guard.map(|g| g.lock().unwrap());
+    rules::groups(outputs)
+        .values()
+        .map(|members| {
+            let first = members[0];
+            let label = if first.pid == "-" {
+                format!("{} (id {})", first.app, first.id)
+            } else {
+                format!("{} (pid {})", first.app, first.pid)
+            };
+            let tracked = guard.as_ref().and_then(|g| {
+                members
+                    .iter()
+                    .find(|s| g.is_tracked(s.id))
+                    .map(|s| (g, s.id))
+            });
+            let state = if members.iter().any(|s| Some(s.id) == voice) {
+                "voice".to_string()
+            } else {
+                match tracked.map(|(g, id)| g.stream_factor(id)) {
+                    None => "-".to_string(),
+                    Some(None) => "duck".to_string(),
+                    Some(Some(f)) if f >= 0.999 => "excluded".to_string(),
+                    Some(Some(f)) => format!("duck {:.0}%", (1.0 - f) * 100.0),
+                }
+            };
+            let volume = tracked.and_then(|(g, id)| g.stream_volume(id));
+            GroupRow {
+                label,
+                streams: members.len(),
+                state,
+                volume: volume.map_or_else(String::new, |v| match v.current {
+                    Some(now) => format!("{:>3.0}% -> {:>3.0}%", v.baseline * 100.0, now * 100.0),
+                    None => format!("{:>3.0}%", v.baseline * 100.0),
+                }),
+                overridden: volume.is_some_and(|v| v.overridden),
+                sink: routing(links, sink_labels, members.iter().map(|s| s.id)),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,573 @@
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// output language
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Lang {
+    De,
+    En,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// from LC_ALL, LC_MESSAGES or LANG
+pub fn from_locale() -> Lang {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|v| !v.is_empty())
+        .filter(|v| v.starts_with("de"))
+        .map_or(Lang::En, |_| Lang::De)
+}
+
+/// set once at startup
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+fn lang() -> Lang {
+    *LANG.get_or_init(from_locale)
+}
+
+fn pick<T>(de: T, en: T) -> T {
+    match lang() {
+        Lang::De => de,
+        Lang::En => en,
+    }
+}
+
+/// fixed user-facing text
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Msg {
+    NoOutputs,
+    ActiveOutputs,
+    ActiveStreams,
+    CandidateHint,
+    InvalidNumber,
+    OutOfRange,
+    AudioSeen,
+    SelectVoiceSource,
+    NoOutputsYet,
+    SelectKeys,
+    NoMatches,
+    Keys,
+    AttachKeys,
+    KeysLabel,
+    Selected,
+    AutoDucking,
+    Reason,
+    Scene,
+    On,
+    Off,
+    Focus,
+    Active,
+    Inactive,
+    Voice,
+    Sensitivity,
+    DuckAmount,
+    Hold,
+    Streams,
+    Spectrum,
+    Timeline,
+    Ducked,
+    Dashboard,
+    Log,
+    Settings,
+    SettingsKeys,
+    QuitTitle,
+    Overridden,
+    QuitKeys,
+    TagSource,
+    TagMicClient,
+    QuitViaGui,
+    PlainHelp,
+    Quitting,
+}
+
+impl Msg {
+    #[allow(clippy::too_many_lines)]
+    pub fn text(self) -> &'static str {
+        let (de, en) = match self {
+            Self::NoOutputs => (
+                "Keine aktiven Ausgabeströme gefunden.",
+                "No active output streams found.",
+            ),
+            Self::ActiveOutputs => (
+                "Aktive Ausgabeströme (Stream/Output/Audio):",
+                "Active output streams (Stream/Output/Audio):",
+            ),
+            Self::ActiveStreams => (
+                "Aktive Ströme (Stream/Output/Audio, Audio/Source, Stream/Input/Audio):",
+                "Active streams (Stream/Output/Audio, Audio/Source, Stream/Input/Audio):",
+            ),
+            Self::CandidateHint => (
+                "  [*] = wahrscheinlicher Remote‑Voice‑Kandidat (nur Hinweis)\n",
+                "  [*] = likely remote voice candidate (hint only)\n",
+            ),
+            Self::InvalidNumber => ("Ungültige Zahl", "invalid number"),
+            Self::OutOfRange => (
+                "Auswahl außerhalb des gültigen Bereichs",
+                "selection out of range",
+            ),
+            Self::AudioSeen => (
+                "Audio-Frames empfangen (Capture aktiv).",
+                "audio frames received (capture active)",
+            ),
+            Self::SelectVoiceSource => ("Voice Source wählen", "Select voice source"),
+            Self::NoOutputsYet => (
+                "Noch keine Ausgaben. r zum Aktualisieren.",
+                "No outputs yet. Press r to refresh.",
+            ),
+            Self::SelectKeys => (
+                "Hoch/Runter, Enter=wählen, /=filtern, r=aktualisieren, Esc/Q=beenden",
+                "Up/Down, Enter=select, /=filter, r=refresh, Esc/Q=quit",
+            ),
+            Self::NoMatches => ("Keine Treffer, Esc löscht den Filter.", "No matches, Esc clears the filter."),
+            Self::Keys => (
+                "Tab/1-4=Ansicht  W/S=Empf.  A/D=Absenkung  Q/E=Halten  Leertaste=Auto  O=Fokus  V=Wechseln  F=Spektrum  :=Befehl  Strg+S=Speichern  Esc/x=Beenden",
+                "Tab/1-4=view  W/S=sens  A/D=duck  Q/E=hold  Space=auto  O=focus  V=switch  F=spectrum  :=command  Ctrl+S=save  Esc/x=quit",
+            ),
+            Self::AttachKeys => (
+                "Tab/1-4=Ansicht  W/S=Empf.  A/D=Absenkung  Q/E=Halten  Leertaste=Auto  O=Fokus  F=Spektrum  :=Befehl  Strg+S=Speichern  Esc/x=Trennen",
+                "Tab/1-4=view  W/S=sens  A/D=duck  Q/E=hold  Space=auto  O=focus  F=spectrum  :=command  Ctrl+S=save  Esc/x=detach",
+            ),
+            Self::KeysLabel => ("Tasten: ", "keys: "),
+            Self::Selected => ("Ausgewählt: ", "Selected: "),
+            Self::AutoDucking => ("Auto-Ducking: ", "Auto Ducking: "),
+            Self::Reason => ("Grund: ", "reason: "),
+            Self::Scene => ("Szene: ", "Scene: "),
+            Self::On => ("AN", "ON"),
+            Self::Off => ("AUS", "OFF"),
+            Self::Focus => ("FOKUS", "FOCUS"),
+            Self::Active => ("AKTIV", "ACTIVE"),
+            Self::Inactive => ("INAKTIV", "INACTIVE"),
+            Self::Voice => ("STIMME", "VOICE"),
+            Self::Sensitivity => ("Empfindlichkeit", "Sensitivity"),
+            Self::DuckAmount => ("Absenkung", "Duck Amount"),
+            Self::Hold => ("Halten", "Hold"),
+            Self::Streams => ("Ströme", "Streams"),
+            Self::Spectrum => ("Spektrum", "Spectrum"),
+            Self::Timeline => ("Verlauf (60 s)", "Timeline (60 s)"),
+            Self::Ducked => ("ABGESENKT", "DUCKED"),
+            Self::Dashboard => ("Übersicht", "Dashboard"),
+            Self::Log => ("Protokoll", "Log"),
+            Self::Settings => ("Einstellungen", "Settings"),
+            Self::SettingsKeys => (
+                "Hoch/Runter=wählen  Links/Rechts=ändern  Enter=eingeben  Strg+S=speichern",
+                "Up/Down=select  Left/Right=change  Enter=type a value  Ctrl+S=save",
+            ),
+            Self::Overridden => ("  ! von der Anwendung geändert", "  ! changed by the app"),
+            Self::QuitTitle => ("Beenden?", "Quit?"),
+            Self::QuitKeys => ("Enter/J=beenden  Esc/N=abbrechen", "Enter/Y=quit  Esc/N=cancel"),
+            Self::TagSource => (" [Quelle]", " [source]"),
+            Self::TagMicClient => (" [Mikrofon-Client]", " [mic client]"),
+            Self::QuitViaGui => ("Beenden über GUI", "quit requested via gui"),
+            Self::PlainHelp => (
+                "Befehle: a = automatisch, d = absenken, r = wiederherstellen, t WERT = Schwelle, \
+                 f WERT = Absenkfaktor, h MS = Halten, s = Status, q = beenden, ? = Hilfe",
+                "commands: a = automatic, d = duck, r = restore, t VALUE = threshold, \
+                 f VALUE = duck factor, h MS = hold, s = status, q = quit, ? = help",
+            ),
+            Self::Quitting => ("beende", "quitting"),
+        };
+        pick(de, en)
+    }
+}
+
+pub fn pick_voice_default(count: usize, default: usize) -> String {
+    pick(
+        format!("\nNummer der VOICE SOURCE wählen (1-{count}), Enter für bevorzugten Kandidaten [{default}]: "),
+        format!("\nPick the VOICE SOURCE number (1-{count}), Enter for the preferred candidate [{default}]: "),
+    )
+}
+
+pub fn pick_voice(count: usize) -> String {
+    pick(
+        format!("\nNummer der VOICE SOURCE wählen (1-{count}): "),
+        format!("\nPick the VOICE SOURCE number (1-{count}): "),
+    )
+}
+
+pub fn voice_selected(
+    id: u32,
+    app: &str,
+    role: &str,
+    media: &str,
+    node: &str,
+    serial: &str,
+    reason: &str,
+) -> String {
+    let fields =
+        format!("id={id} app=\"{app}\" role=\"{role}\" media=\"{media}\" node=\"{node}\" serial={serial} ({reason})");
+    pick(
+        format!("Voice Source ausgewählt: {fields}"),
+        format!("voice source selected: {fields}"),
+    )
+}
+
+pub fn capture_started(rate: u32, channels: u32, format: impl Debug) -> String {
+    pick(
+        format!("Überwachung gestartet: rate={rate} channels={channels} format={format:?}"),
+        format!("monitoring started: rate={rate} channels={channels} format={format:?}"),
+    )
+}
+
+/// every targeting strategy left the capture idle
+pub fn capture_gave_up() -> String {
+    pick(
+        "Capture-Stream bekommt kein Audio, alle Verbindungsarten versucht. Stimme wird nicht erkannt.".into(),
+        "capture stream gets no audio with any targeting, voice will not be detected".into(),
+    )
+}
+
+pub fn capture_connected(id: u32) -> String {
+    pick(
+        format!("Capture-Stream verbunden mit Voice-Node id={id}"),
+        format!("capture stream connected to voice node id={id}"),
+    )
+}
+
+pub fn stream_count(n: usize) -> String {
+    pick(format!("{n:>2} Ströme  "), format!("{n:>2} streams  "))
+}
+
+pub fn watchdog(minutes: u64, paused: bool) -> String {
+    let (de, en) = if paused {
+        (" Automatik aus.", " Automatic ducking off.")
+    } else {
+        ("", "")
+    };
+    pick(
+        format!("Seit {minutes} min durchgehend abgesenkt, Lautstärken wiederhergestellt.{de}"),
+        format!("Ducked for {minutes} min without a break, volumes restored.{en}"),
+    )
+}
+
+/// session locked or idle
+pub fn session_locked() -> String {
+    pick(
+        "Sitzung gesperrt, Lautstärken wiederhergestellt und Automatik pausiert.".into(),
+        "Session locked, volumes restored and automatic ducking paused.".into(),
+    )
+}
+
+/// another ducking agent has the volumes
+pub fn deferring(agents: &str) -> String {
+    pick(
+        format!("{agents} läuft, Lautstärken wiederhergestellt und Automatik pausiert."),
+        format!("Deferring to {agents}, volumes restored and automatic ducking paused."),
+    )
+}
+
+/// no sinks in the registry, e.g. a Flatpak without full PipeWire access
+pub fn registry_restricted(sandboxed: bool) -> String {
+    if sandboxed {
+        pick(
+            "Keine Audio-Ausgänge sichtbar: die Sandbox schränkt den PipeWire-Zugriff ein. \
+             Abhilfe: flatpak override --user --filesystem=xdg-run/pipewire-0 <app-id>"
+                .into(),
+            "No audio sinks visible: the sandbox restricts PipeWire access. \
+             Grant it with: flatpak override --user --filesystem=xdg-run/pipewire-0 <app-id>"
+                .into(),
+        )
+    } else {
+        pick(
+            "Keine Audio-Ausgänge sichtbar: läuft der Session-Manager (WirePlumber), \
+             und darf dieser Client alle Objekte sehen?"
+                .into(),
+            "No audio sinks visible: is the session manager (WirePlumber) running, \
+             and may this client see all objects?"
+                .into(),
+        )
+    }
+}
+
+/// volume tool failing on every call, switched
+pub fn backend_switched(from: &str, to: &str) -> String {
+    pick(
+        format!("{from} schlägt bei jedem Aufruf fehl, Lautstärken laufen jetzt über {to}."),
+        format!("{from} fails on every call, volumes now go through {to}."),
+    )
+}
+
+/// volume tool failing on every call, nothing to switch to
+pub fn backend_broken(name: &str) -> String {
+    pick(
+        format!(
+            "{name} schlägt bei jedem Aufruf fehl, Lautstärken werden nicht geändert. \
+             `pw-duck doctor` zeigt die Ursache; pactl (pipewire-pulse) dient als Ersatz."
+        ),
+        format!(
+            "{name} fails on every call, volumes are not changed. \
+             Run `pw-duck doctor` to see why; pactl (pipewire-pulse) works as a fallback."
+        ),
+    )
+}
+
+/// back from Ctrl+Z
+pub fn resumed(refreshed: usize) -> String {
+    pick(
+        format!("Fortgesetzt, {refreshed} Ausgangslautstärken neu eingelesen."),
+        format!("Resumed, {refreshed} baselines re-read."),
+    )
+}
+
+/// quit dialog
+pub fn restoring(n: usize) -> String {
+    pick(
+        format!("Beim Beenden werden {n} Anwendungen wiederhergestellt:"),
+        format!("Quitting restores {n} applications:"),
+    )
+}
+
+pub fn failed_restores(n: usize) -> String {
+    if n == 0 {
+        pick(
+            "Bisher keine fehlgeschlagenen Wiederherstellungen.",
+            "No restore has failed so far.",
+        )
+        .to_string()
+    } else {
+        pick(
+            format!("{n} Wiederherstellungen sind fehlgeschlagen, Lautstärken prüfen!"),
+            format!("{n} restores failed, check the volumes afterwards!"),
+        )
+    }
+}
+
+pub fn restore_failed(id: u32) -> String {
+    pick(
+        format!("Lautstärke von Strom id={id} konnte nicht wiederhergestellt werden!"),
+        format!("could not restore the volume of stream id={id}!"),
+    )
+}
+
+/// plain mode transition
+pub fn voice_ducking(voice: bool, ducking: bool) -> String {
+    let on_off = |v: bool| {
+        if v {
+            pick("an", "on")
+        } else {
+            pick("aus", "off")
+        }
+    };
+    pick(
+        format!("Stimme {}, Absenkung {}", on_off(voice), on_off(ducking)),
+        format!("voice {}, ducking {}", on_off(voice), on_off(ducking)),
+    )
+}
+
+/// command line without its argument
+pub fn usage(command: &str, arg: &str) -> String {
+    pick(
+        format!("Aufruf: {command} <{arg}>"),
+        format!("usage: {command} <{arg}>"),
+    )
+}
+
+pub fn unknown_command(line: &str) -> String {
+    pick(
+        format!("Unbekannter Befehl '{line}'"),
+        format!("unknown command '{line}'"),
+    )
+}
+
+pub fn unknown_parameter(param: &str) -> String {
+    pick(
+        format!("Unbekannter Parameter '{param}'"),
+        format!("unknown parameter '{param}'"),
+    )
+}
+
+/// GUI log after a live change
+pub fn threshold_set(threshold: f32) -> String {
+    pick(
+        format!("Schwelle -> {threshold:.4}"),
+        format!("threshold -> {threshold:.4}"),
+    )
+}
+
+pub fn duck_set(pct: f32) -> String {
+    pick(
+        format!("Absenkung -> {pct:.0}%"),
+        format!("duck -> {pct:.0}%"),
+    )
+}
+
+pub fn hold_set(ms: u64) -> String {
+    pick(format!("Halten -> {ms} ms"), format!("hold -> {ms} ms"))
+}
+
+pub fn release_set(level: f32) -> String {
+    pick(
+        format!("Loslassen -> {level:.4}"),
+        format!("release -> {level:.4}"),
+    )
+}
+
+pub fn mode_set(mode: &str) -> String {
+    pick(format!("Modus -> {mode}"), format!("mode -> {mode}"))
+}
+
+pub fn excluded(app: &str) -> String {
+    pick(format!("'{app}' ausgenommen"), format!("excluded '{app}'"))
+}
+
+pub fn unknown_profile(name: &str) -> String {
+    pick(
+        format!("Unbekanntes Profil '{name}'"),
+        format!("unknown profile '{name}'"),
+    )
+}
+
+pub fn unknown_scene(name: &str) -> String {
+    pick(
+        format!("Unbekannte Szene '{name}'"),
+        format!("unknown scene '{name}'"),
+    )
+}
+
+/// profile or scene switched, with the resulting settings
+pub fn settings_switched(
+    scene: bool,
+    name: &str,
+    threshold: f32,
+    hold_ms: u64,
+    attack_ms: u64,
+    duck_pct: f32,
+) -> String {
+    let values = format!(
+        "threshold={threshold:.4} hold={hold_ms}ms attack={attack_ms}ms duck={duck_pct:.0}%"
+    );
+    match (scene, lang()) {
+        (true, Lang::De) => format!("Szene {name}: {values}"),
+        (true, Lang::En) => format!("scene {name}: {values}"),
+        (false, Lang::De) => format!("Profil {name}: {values}"),
+        (false, Lang::En) => format!("profile {name}: {values}"),
+    }
+}
+
+pub fn save_no_path() -> String {
+    pick(
+        "Speichern: kein Konfigurationspfad ($HOME nicht gesetzt)".into(),
+        "save: no config path ($HOME unset)".into(),
+    )
+}
+
+pub fn saved(path: &Path) -> String {
+    pick(
+        format!("Gespeichert in {}", path.display()),
+        format!("saved to {}", path.display()),
+    )
+}
+
+pub fn save_failed(err: &anyhow::Error) -> String {
+    pick(
+        format!("Speichern fehlgeschlagen: {err:#}"),
+        format!("save failed: {err:#}"),
+    )
+}
+
+/// VAD transition
+pub fn voice_changed(active: bool, level: f32) -> String {
+    match (active, lang()) {
+        (true, Lang::De) => format!("STIMME AKTIV (Pegel={level:.4}) → Absenkung ein"),
+        (true, Lang::En) => format!("VOICE ACTIVE (level={level:.4}) → ducking on"),
+        (false, Lang::De) => format!("STIMME INAKTIV (Pegel={level:.4}) → Absenkung aus"),
+        (false, Lang::En) => format!("VOICE INACTIVE (level={level:.4}) → ducking off"),
+    }
+}
+
+/// moved to the next voice source in the failover list
+pub fn voice_failover(id: u32, app: &str, rank: usize) -> String {
+    pick(
+        format!("Voice Source gewechselt: id={id} app=\"{app}\" (Voice Source #{rank})"),
+        format!("voice source failover: id={id} app=\"{app}\" (voice source #{rank})"),
+    )
+}
+
+/// the voice is silent while another candidate plays
+///
+/// The TUI switches with V, plain mode only with `--auto-switch`.
+pub fn switch_offered(app: &str, id: u32, plain: bool) -> String {
+    match (plain, lang()) {
+        (false, Lang::De) => {
+            format!("Voice Source stumm, \"{app}\" (id={id}) ist aktiv: V zum Umschalten")
+        }
+        (false, Lang::En) => {
+            format!("voice source silent, \"{app}\" (id={id}) is active: press V to switch")
+        }
+        (true, Lang::De) => {
+            format!("Voice Source stumm, \"{app}\" ist aktiv (--auto-switch folgt ihr)")
+        }
+        (true, Lang::En) => {
+            format!("voice source silent, \"{app}\" is active (--auto-switch follows it)")
+        }
+    }
+}
+
+/// the probe found a louder candidate
+pub fn voice_switched(from: u32, to: u32, app: &str) -> String {
+    pick(
+        format!("Voice Source umgeschaltet: id={from} -> id={to} app=\"{app}\""),
+        format!("voice source switched: id={from} -> id={to} app=\"{app}\""),
+    )
+}
+
+/// desktop notification, summary and body
+pub fn notify_duck_on(pct: f32) -> (String, String) {
+    pick(
+        (
+            "Absenkung an".into(),
+            format!("Andere Wiedergabe um {pct:.0}% leiser"),
+        ),
+        (
+            "Ducking on".into(),
+            format!("Other audio lowered by {pct:.0}%"),
+        ),
+    )
+}
+
+pub fn notify_duck_off() -> (String, String) {
+    pick(
+        (
+            "Absenkung aus".into(),
+            "Lautstärken wiederhergestellt".into(),
+        ),
+        ("Ducking off".into(), "Volumes restored".into()),
+    )
+}
+
+pub fn notify_restore_failed(failures: usize) -> (String, String) {
+    pick(
+        (
+            "Wiederherstellung fehlgeschlagen".into(),
+            format!("{failures} Ströme konnten nicht wiederhergestellt werden"),
+        ),
+        (
+            "Volume restore failed".into(),
+            format!("{failures} streams could not be restored"),
+        ),
+    )
+}
+
+pub fn notify_watchdog(minutes: u64) -> (String, String) {
+    (
+        pick("Absenkung hängt", "Ducking stuck").into(),
+        watchdog(minutes, false),
+    )
+}
+
+pub fn notify_voice_lost(id: u32) -> (String, String) {
+    pick(
+        (
+            "Voice Source verschwunden".into(),
+            format!("Strom id={id} ist weg, Absenkung beendet"),
+        ),
+        (
+            "Voice source disappeared".into(),
+            format!("Stream id={id} is gone, ducking stopped"),
+        ),
+    )
+}
@@ -0,0 +1,128 @@
+use crate::control::{self, Control};
+use crate::ipc::Status;
+use crate::ControlMode;
+use pipewire as pw;
+use pw::metadata::{Metadata, MetadataListener};
+use pw::properties::properties;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use tracing::{debug, info, warn};
+
+/// metadata object name
+pub const NAME: &str = "pw-duck";
+
+/// key prefix, e.g. `pw-duck.threshold`
+const PREFIX: &str = "pw-duck.";
+
+/// one-shot command key, cleared once handled
+const COMMAND_KEY: &str = "pw-duck.command";
+
+/// live state in a PipeWire metadata object
+///
+/// Readable with `pw-metadata -n pw-duck` and from WirePlumber scripts.
+/// Writes to `threshold`, `duck_factor`, `hold`, `mode` and `command` are
+/// applied like socket commands; `ducked` and `voice_active` are read-only.
+pub struct Published {
+    metadata: Metadata,
+    _listener: MetadataListener,
+    /// last value per key, set or seen
+    last: Rc<RefCell<HashMap<String, String>>>,
+    /// a command was handled, clear the key on the next update
+    clear_command: Rc<Cell<bool>>,
+}
+
+impl Published {
+    pub fn create(core: &pw::core::CoreRc, control_tx: Sender<Control>) -> Result<Self, pw::Error> {
+        let metadata: Metadata = core.create_object(
+            "metadata",
+            &properties! {
+                "metadata.name" => NAME,
+            },
+        )?;
+        let last: Rc<RefCell<HashMap<String, String>>> = Rc::default();
+        let last_t = last.clone();
+        let clear_command: Rc<Cell<bool>> = Rc::default();
+        let clear_command_t = clear_command.clone();
+        let listener = metadata
+            .add_listener_local()
+            .property(move |subject, key, _type, value| {
+                let (0, Some(key), Some(value)) = (subject, key, value) else {
+                    return 0;
+                };
+                let Some(name) = key.strip_prefix(PREFIX) else {
+                    return 0;
+                };
+                if key == COMMAND_KEY {
+                    // the same command may be sent twice in a row
+                    clear_command_t.set(true);
+                } else {
+                    // echo of our own write
+                    if last_t.borrow().get(key).is_some_and(|v| v == value) {
+                        return 0;
+                    }
+                    last_t
+                        .borrow_mut()
+                        .insert(key.to_string(), value.to_string());
+                }
+                match external(name, value) {
+                    Some(Ok(ctl)) => {
+                        debug!("metadata: {key}={value} -> {ctl:?}");
+                        let _ = control_tx.send(ctl);
+                    }
+                    Some(Err(err)) => warn!("metadata: {key}={value}: {err}"),
+                    None => {}
+                }
+                0
+            })
+            .register();
+        info!("live state in metadata '{NAME}'");
+        Ok(Self {
+            metadata,
+            _listener: listener,
+            last,
+            clear_command,
+        })
+    }
+
+    /// write changed values
+    pub fn update(&self, status: &Status) {
+        if self.clear_command.take() {
+            self.metadata.set_property(0, COMMAND_KEY, None, None);
+        }
+        let values = [
+            ("threshold", format!("{:.4}", status.threshold)),
+            ("duck_factor", format!("{:.2}", status.duck_factor)),
+            ("hold", status.hold_ms.to_string()),
+            ("mode", status.mode.clone()),
+            ("ducked", status.ducked.to_string()),
+            ("voice_active", status.voice_active.to_string()),
+        ];
+        for (name, value) in values {
+            let key = format!("{PREFIX}{name}");
+            if self.last.borrow().get(&key) == Some(&value) {
+                continue;
+            }
+            self.metadata.set_property(0, &key, None, Some(&value));
+            self.last.borrow_mut().insert(key, value);
+        }
+    }
+}
+
+/// command for a write by another client, None for read-only keys
+fn external(name: &str, value: &str) -> Option<Result<Control, String>> {
+    let line = match name {
+        "threshold" | "duck_factor" | "hold" => format!("set {name} {value}"),
+        "mode" => match ControlMode::from_name(value) {
+            Some(ControlMode::AutoVad) => "auto".into(),
+            Some(ControlMode::ManualDucked) => "duck".into(),
+            Some(ControlMode::ManualRestored) => "restore".into(),
+            Some(ControlMode::Focus) => "focus".into(),
+            None => value.to_string(),
+        },
+        "command" => value.to_string(),
+        _ => return None,
+    };
+    Some(control::parse(&line))
+}
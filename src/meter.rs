@@ -0,0 +1,100 @@
+use crate::analysis::AtomicF32;
+use crate::ducking::OutputStream;
+use crate::reprobe::{self, Target};
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::Child;
+use std::sync::Arc;
+use std::thread;
+use tracing::debug;
+
+/// meter rate, mono
+const METER_RATE: u32 = 8000;
+/// samples per level update, 50 ms
+const WINDOW: usize = METER_RATE as usize / 20;
+
+/// live level of one stream
+///
+/// Records with `pw-record` so it keeps running while the chooser blocks the
+/// main loop; the reader thread ends when the child is killed.
+#[derive(Debug)]
+struct Meter {
+    child: Child,
+    level: Arc<AtomicF32>,
+}
+
+impl Meter {
+    #[allow(clippy::cast_precision_loss)]
+    fn spawn(s: &OutputStream) -> std::io::Result<Self> {
+        let target = Target {
+            id: s.id,
+            serial: s.serial.clone(),
+            node: s.node.clone(),
+        };
+        let monitor = s.media_class == "Stream/Output/Audio";
+        let mut child = reprobe::record(&target, METER_RATE, monitor).spawn()?;
+        let level = Arc::new(AtomicF32::new(0.0));
+        if let Some(mut out) = child.stdout.take() {
+            let level_t = Arc::clone(&level);
+            thread::Builder::new()
+                .name("pw-duck-meter".into())
+                .spawn(move || {
+                    let mut raw = vec![0u8; WINDOW * 4];
+                    while out.read_exact(&mut raw).is_ok() {
+                        let sum_sq: f32 = raw
+                            .chunks_exact(4)
+                            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).powi(2))
+                            .sum();
+                        level_t.store((sum_sq / WINDOW as f32).sqrt());
+                    }
+                })?;
+        }
+        Ok(Self { child, level })
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// meters for the rows on screen
+#[derive(Debug, Default)]
+pub struct Meters {
+    running: HashMap<u32, Meter>,
+    /// pw-record failed to start, no more tries
+    failed: bool,
+}
+
+impl Meters {
+    /// meter exactly `visible`
+    ///
+    /// Recording clients are not metered, they have no signal of their own.
+    pub fn show(&mut self, visible: &[&OutputStream]) {
+        self.running
+            .retain(|id, _| visible.iter().any(|s| s.id == *id));
+        for s in visible {
+            if self.failed
+                || s.media_class == "Stream/Input/Audio"
+                || self.running.contains_key(&s.id)
+            {
+                continue;
+            }
+            match Meter::spawn(s) {
+                Ok(meter) => {
+                    self.running.insert(s.id, meter);
+                }
+                Err(err) => {
+                    debug!("meter id={} failed: {err}", s.id);
+                    self.failed = true;
+                }
+            }
+        }
+    }
+
+    pub fn level(&self, id: u32) -> Option<f32> {
+        self.running.get(&id).map(|m| m.level.load())
+    }
+}
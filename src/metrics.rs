@@ -0,0 +1,118 @@
+use crate::analysis::AtomicF32;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// per read or write on a scrape connection
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// exported values
+#[derive(Debug)]
+pub struct Metrics {
+    pub energy: AtomicF32,
+    pub voice_active: AtomicBool,
+    pub ducked: AtomicBool,
+    pub transitions_total: AtomicU64,
+    pub wpctl_failures_total: AtomicU64,
+    pub capture_frames_total: AtomicU64,
+}
+
+pub static METRICS: Metrics = Metrics {
+    energy: AtomicF32::new(0.0),
+    voice_active: AtomicBool::new(false),
+    ducked: AtomicBool::new(false),
+    transitions_total: AtomicU64::new(0),
+    wpctl_failures_total: AtomicU64::new(0),
+    capture_frames_total: AtomicU64::new(0),
+};
+
+impl Metrics {
+    /// text exposition format
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP pw_duck_{name} {help}");
+            let _ = writeln!(out, "# TYPE pw_duck_{name} {kind}");
+            let _ = writeln!(out, "pw_duck_{name} {value}");
+        };
+        metric(
+            "energy",
+            "gauge",
+            "Current RMS level of the voice source.",
+            self.energy.load().to_string(),
+        );
+        metric(
+            "voice_active",
+            "gauge",
+            "Whether voice activity is currently detected.",
+            u8::from(self.voice_active.load(Ordering::Relaxed)).to_string(),
+        );
+        metric(
+            "ducked",
+            "gauge",
+            "Whether other streams are currently ducked.",
+            u8::from(self.ducked.load(Ordering::Relaxed)).to_string(),
+        );
+        metric(
+            "transitions_total",
+            "counter",
+            "Duck/restore transitions triggered by the VAD.",
+            self.transitions_total.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "wpctl_failures_total",
+            "counter",
+            "Failed wpctl volume operations.",
+            self.wpctl_failures_total
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
+        metric(
+            "capture_frames_total",
+            "counter",
+            "Audio buffers received from the voice source.",
+            self.capture_frames_total
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
+        out
+    }
+}
+
+/// serve /metrics
+pub fn spawn_exporter(addr: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    thread::Builder::new()
+        .name("pw-duck-metrics".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = handle_request(stream);
+            }
+        })?;
+    Ok(())
+}
+
+/// one scrape, served in turn
+///
+/// The timeouts keep an idle connection from holding up the next scrape.
+fn handle_request(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", METRICS.render())
+    } else {
+        ("404 Not Found", String::from("not found\n"))
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
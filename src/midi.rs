@@ -0,0 +1,267 @@
+use crate::control::Control;
+use crate::ui::{HOLD_MAX_MS, SENS_MAX, SENS_MIN};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+use tracing::{debug, info, warn};
+
+/// [midi] table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiConfig {
+    /// ALSA rawmidi device
+    pub device: Option<PathBuf>,
+    /// 1..16, all when unset
+    pub channel: Option<u8>,
+    pub threshold_cc: Option<u8>,
+    pub duck_cc: Option<u8>,
+    pub hold_cc: Option<u8>,
+    pub auto_note: Option<u8>,
+}
+
+impl Default for MidiConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            channel: None,
+            threshold_cc: Some(20),
+            duck_cc: Some(21),
+            hold_cc: Some(22),
+            auto_note: Some(60),
+        }
+    }
+}
+
+/// MIDI input thread
+///
+/// Reads an ALSA rawmidi device (e.g. `/dev/snd/midiC1D0`) and maps control
+/// changes onto the same live parameters as the W/S/A/D/Q/E keys; the note
+/// toggles Auto like Space.
+pub fn spawn_reader(device: &Path, map: MidiConfig, tx: Sender<Control>) -> io::Result<()> {
+    let mut file = File::open(device)?;
+    info!("midi input: {}", device.display());
+    thread::Builder::new()
+        .name("pw-duck-midi".into())
+        .spawn(move || {
+            let mut parser = Parser::default();
+            let mut buf = [0u8; 64];
+            loop {
+                let len = match file.read(&mut buf) {
+                    Ok(0) => {
+                        warn!("midi device closed");
+                        return;
+                    }
+                    Ok(len) => len,
+                    Err(err) => {
+                        warn!("midi read failed: {err}");
+                        return;
+                    }
+                };
+                for byte in &buf[..len] {
+                    let Some(msg) = parser.feed(*byte) else {
+                        continue;
+                    };
+                    if let Some(ctl) = to_control(&map, msg) {
+                        if tx.send(ctl).is_err() {
+                            return;
+                        }
+                    } else {
+                        debug!("midi: ignored {msg:?}");
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// channel message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Message {
+    status: u8,
+    data: [u8; 2],
+}
+
+impl Message {
+    const fn kind(self) -> u8 {
+        self.status & 0xF0
+    }
+
+    const fn channel(self) -> u8 {
+        (self.status & 0x0F) + 1
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn to_control(map: &MidiConfig, msg: Message) -> Option<Control> {
+    if map.channel.is_some_and(|ch| ch != msg.channel()) {
+        return None;
+    }
+    let [num, value] = msg.data;
+    let t = f32::from(value) / 127.0;
+    match msg.kind() {
+        0xB0 if Some(num) == map.threshold_cc => {
+            Some(Control::Threshold(SENS_MIN + (SENS_MAX - SENS_MIN) * t))
+        }
+        0xB0 if Some(num) == map.duck_cc => Some(Control::DuckFactor(1.0 - t)),
+        0xB0 if Some(num) == map.hold_cc => {
+            Some(Control::Hold(u64::from(value) * HOLD_MAX_MS / 127))
+        }
+        // note on, velocity 0 is note off
+        0x90 if Some(num) == map.auto_note && value > 0 => Some(Control::Toggle),
+        _ => None,
+    }
+}
+
+/// rawmidi byte stream parser
+#[derive(Debug, Default)]
+struct Parser {
+    running: Option<u8>,
+    data: Vec<u8>,
+    in_sysex: bool,
+}
+
+impl Parser {
+    fn feed(&mut self, byte: u8) -> Option<Message> {
+        match byte {
+            // realtime
+            0xF8..=0xFF => None,
+            0xF0 => {
+                self.in_sysex = true;
+                self.running = None;
+                None
+            }
+            0xF7 => {
+                self.in_sysex = false;
+                None
+            }
+            // system common
+            0xF1..=0xF6 => {
+                self.running = None;
+                None
+            }
+            0x80..=0xEF => {
+                self.in_sysex = false;
+                self.running = Some(byte);
+                self.data.clear();
+                None
+            }
+            _ => {
+                if self.in_sysex {
+                    return None;
+                }
+                let status = self.running?;
+                self.data.push(byte);
+                let needed = if matches!(status & 0xF0, 0xC0 | 0xD0) {
+                    1
+                } else {
+                    2
+                };
+                if self.data.len() < needed {
+                    return None;
+                }
+                let msg = Message {
+                    status,
+                    data: [self.data[0], self.data.get(1).copied().unwrap_or(0)],
+                };
+                self.data.clear();
+                Some(msg)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Vec<Message> {
+        let mut parser = Parser::default();
+        bytes.iter().filter_map(|b| parser.feed(*b)).collect()
+    }
+
+    fn controls(map: &MidiConfig, bytes: &[u8]) -> Vec<Option<Control>> {
+        parse(bytes)
+            .into_iter()
+            .map(|m| to_control(map, m))
+            .collect()
+    }
+
+    #[test]
+    fn note_on_toggles_note_off_does_not() {
+        let map = MidiConfig::default();
+        assert_eq!(
+            controls(&map, &[0x90, 60, 100, 0x80, 60, 64]),
+            [Some(Control::Toggle), None]
+        );
+        // velocity 0 is note off
+        assert_eq!(controls(&map, &[0x90, 60, 0]), [None]);
+        assert_eq!(controls(&map, &[0x90, 61, 100]), [None]);
+    }
+
+    #[test]
+    fn control_changes_map_to_settings() {
+        let map = MidiConfig::default();
+        assert_eq!(
+            controls(&map, &[0xB0, 20, 0, 0xB0, 21, 127, 0xB0, 22, 127]),
+            [
+                Some(Control::Threshold(SENS_MIN)),
+                Some(Control::DuckFactor(0.0)),
+                Some(Control::Hold(HOLD_MAX_MS)),
+            ]
+        );
+        assert_eq!(controls(&map, &[0xB0, 23, 64]), [None]);
+    }
+
+    #[test]
+    fn channel_filter() {
+        let map = MidiConfig {
+            channel: Some(2),
+            ..MidiConfig::default()
+        };
+        assert_eq!(
+            controls(&map, &[0x90, 60, 100, 0x91, 60, 100]),
+            [None, Some(Control::Toggle)]
+        );
+    }
+
+    #[test]
+    fn running_status() {
+        let cc = |num, value| Message {
+            status: 0xB0,
+            data: [num, value],
+        };
+        assert_eq!(
+            parse(&[0xB0, 21, 0, 21, 127, 22, 5]),
+            [cc(21, 0), cc(21, 127), cc(22, 5)]
+        );
+        // realtime bytes may come between data bytes
+        assert_eq!(parse(&[0xB0, 21, 0xF8, 64]), [cc(21, 64)]);
+        // program change takes one data byte
+        let pc = |program| Message {
+            status: 0xC0,
+            data: [program, 0],
+        };
+        assert_eq!(parse(&[0xC0, 3, 4]), [pc(3), pc(4)]);
+    }
+
+    #[test]
+    fn truncated_and_stray_bytes() {
+        // cut off by the next status byte
+        assert_eq!(
+            parse(&[0xB0, 21, 0x90, 60, 100]),
+            [Message {
+                status: 0x90,
+                data: [60, 100],
+            }]
+        );
+        assert!(parse(&[0xB0, 21]).is_empty());
+        // data without a status, and sysex contents
+        assert!(parse(&[21, 64]).is_empty());
+        assert!(parse(&[0xF0, 0x7E, 60, 100, 0xF7, 60, 100]).is_empty());
+        // system common clears running status
+        assert_eq!(parse(&[0x90, 60, 100, 0xF6, 60, 100]).len(), 1);
+    }
+}
@@ -0,0 +1,546 @@
+use crate::control::{self, Control};
+use crate::ipc::Status;
+use crate::ui::{HOLD_MAX_MS, HOLD_STEP_MS, SENS_MAX, SENS_MIN, SENS_STEP};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// keepalive announced to the broker, pinged at half of it
+const KEEPALIVE_S: u16 = 60;
+/// state poll and socket read period
+const POLL: Duration = Duration::from_millis(100);
+/// wait before reconnecting
+const RECONNECT: Duration = Duration::from_secs(5);
+/// wait for CONNACK
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// [mqtt] table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    /// host:port
+    pub broker: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// topic prefix
+    pub topic: String,
+    /// Home Assistant discovery prefix, empty to disable
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker: None,
+            username: None,
+            password: None,
+            topic: "pw-duck".into(),
+            discovery_prefix: "homeassistant".into(),
+        }
+    }
+}
+
+/// MQTT client thread
+///
+/// Publishes retained `voice`, `ducked`, `auto`, `threshold`, `duck_factor`
+/// and `hold` topics under the prefix whenever they change, and takes
+/// `set/<name>` plus `command` (duck, restore, auto, toggle, focus) messages.
+/// Reconnects when the broker goes away.
+pub fn spawn_client(
+    broker: &str,
+    config: MqttConfig,
+    tx: Sender<Control>,
+    status: Arc<Mutex<Status>>,
+) -> io::Result<()> {
+    // MQTT-3.1.2-22
+    if config.password.is_some() && config.username.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "mqtt password set without a username",
+        ));
+    }
+    let broker = broker.to_string();
+    thread::Builder::new()
+        .name("pw-duck-mqtt".into())
+        .spawn(move || loop {
+            match session(&broker, &config, &tx, &status) {
+                Ok(()) => return,
+                Err(err) => warn!("mqtt {broker}: {err}, reconnecting"),
+            }
+            thread::sleep(RECONNECT);
+        })?;
+    Ok(())
+}
+
+/// one broker connection, Ok once the main loop is gone
+fn session(
+    broker: &str,
+    config: &MqttConfig,
+    tx: &Sender<Control>,
+    status: &Mutex<Status>,
+) -> io::Result<()> {
+    let topic = |name: &str| format!("{}/{name}", config.topic);
+    let mut conn = Conn::open(broker)?;
+    conn.send(&connect_packet(config, &topic("available")))?;
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        if let Some(Packet::ConnAck(code)) = conn.next_packet()? {
+            if code != 0 {
+                return Err(io::Error::other(format!(
+                    "connection refused (code {code})"
+                )));
+            }
+            break;
+        }
+        if Instant::now() > deadline {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+    }
+    info!("mqtt connected to {broker}");
+    conn.send(&publish_packet(&topic("available"), b"online", true))?;
+    if !config.discovery_prefix.is_empty() {
+        for (topic, payload) in discovery(config) {
+            conn.send(&publish_packet(
+                &topic,
+                payload.to_string().as_bytes(),
+                true,
+            ))?;
+        }
+    }
+    conn.send(&subscribe_packet(1, &[&topic("set/+"), &topic("command")]))?;
+
+    let mut published: HashMap<&str, String> = HashMap::new();
+    let mut last_ping = Instant::now();
+    loop {
+        while let Some(packet) = conn.next_packet()? {
+            let Packet::Publish {
+                topic: t, payload, ..
+            } = packet
+            else {
+                continue;
+            };
+            let Some(name) = t
+                .strip_prefix(&config.topic)
+                .and_then(|t| t.strip_prefix('/'))
+            else {
+                continue;
+            };
+            let payload = String::from_utf8_lossy(&payload);
+            match to_control(name, payload.trim()) {
+                Some(ctl) => {
+                    debug!("mqtt control: {ctl:?}");
+                    if tx.send(ctl).is_err() {
+                        return Ok(());
+                    }
+                }
+                None => debug!("mqtt: ignored {t} {payload}"),
+            }
+        }
+        let state = state_values(&status.lock().unwrap());
+        for (name, value) in state {
+            if published.get(name) != Some(&value) {
+                conn.send(&publish_packet(&topic(name), value.as_bytes(), true))?;
+                published.insert(name, value);
+            }
+        }
+        if last_ping.elapsed() >= Duration::from_secs(u64::from(KEEPALIVE_S / 2)) {
+            conn.send(&[0xc0, 0])?;
+            last_ping = Instant::now();
+        }
+    }
+}
+
+const fn on_off(on: bool) -> &'static str {
+    if on {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+/// published topics and their payloads
+fn state_values(s: &Status) -> [(&'static str, String); 6] {
+    [
+        ("voice", on_off(s.voice_active).into()),
+        ("ducked", on_off(s.ducked).into()),
+        ("auto", on_off(s.mode == "AutoVad").into()),
+        ("threshold", s.threshold.to_string()),
+        ("duck_factor", s.duck_factor.to_string()),
+        ("hold", s.hold_ms.to_string()),
+    ]
+}
+
+/// `set/<name>` or `command` message
+fn to_control(name: &str, payload: &str) -> Option<Control> {
+    match (name, payload) {
+        ("set/auto", "ON") => Some(Control::Auto),
+        ("set/auto", "OFF") => Some(Control::Restore),
+        ("set/threshold" | "set/duck_factor" | "set/hold", value) => {
+            control::parse(&format!("set {} {value}", &name[4..])).ok()
+        }
+        ("command", "duck" | "restore" | "auto" | "toggle" | "focus") => {
+            control::parse(payload).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Home Assistant discovery topics and payloads
+fn discovery(config: &MqttConfig) -> Vec<(String, serde_json::Value)> {
+    let id = config.topic.replace(['/', '-'], "_");
+    let topic = |name: &str| format!("{}/{name}", config.topic);
+    let device = json!({ "identifiers": [id], "name": "pw-duck" });
+    let entity = |component: &str, name: &str, title: &str, extra: serde_json::Value| {
+        let mut payload = json!({
+            "name": title,
+            "unique_id": format!("{id}_{name}"),
+            "state_topic": topic(name),
+            "availability_topic": topic("available"),
+            "device": device,
+        });
+        if let (Some(payload), Some(extra)) = (payload.as_object_mut(), extra.as_object()) {
+            payload.extend(extra.clone());
+        }
+        (
+            format!("{}/{component}/{id}/{name}/config", config.discovery_prefix),
+            payload,
+        )
+    };
+    let number = |name: &str, min: f64, max: f64, step: f64| {
+        json!({
+            "command_topic": topic(&format!("set/{name}")),
+            "min": min,
+            "max": max,
+            "step": step,
+            "mode": "slider",
+        })
+    };
+    vec![
+        entity(
+            "binary_sensor",
+            "voice",
+            "Voice",
+            json!({ "device_class": "sound" }),
+        ),
+        entity("binary_sensor", "ducked", "Ducked", json!({})),
+        entity(
+            "switch",
+            "auto",
+            "Auto ducking",
+            json!({ "command_topic": topic("set/auto") }),
+        ),
+        entity(
+            "number",
+            "threshold",
+            "Threshold",
+            number(
+                "threshold",
+                f64::from(SENS_MIN),
+                f64::from(SENS_MAX),
+                f64::from(SENS_STEP),
+            ),
+        ),
+        entity(
+            "number",
+            "duck_factor",
+            "Duck factor",
+            number("duck_factor", 0.0, 1.0, 0.05),
+        ),
+        entity("number", "hold", "Hold", {
+            let mut hold = number("hold", 0.0, HOLD_MAX_MS as f64, HOLD_STEP_MS as f64);
+            hold["unit_of_measurement"] = "ms".into();
+            hold
+        }),
+    ]
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    let len = u16::try_from(s.len()).unwrap_or(u16::MAX);
+    buf.extend_from_slice(&len.to_be_bytes());
+    buf.extend_from_slice(&s.as_bytes()[..usize::from(len)]);
+}
+
+/// fixed header plus body
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind];
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    out.extend_from_slice(body);
+    out
+}
+
+/// CONNECT with an "offline" will on the availability topic
+fn connect_packet(config: &MqttConfig, will_topic: &str) -> Vec<u8> {
+    let mut flags = 0x02 | 0x04 | 0x20;
+    if config.username.is_some() {
+        flags |= 0x80;
+    }
+    if config.password.is_some() {
+        flags |= 0x40;
+    }
+    let mut body = Vec::new();
+    put_str(&mut body, "MQTT");
+    body.push(4);
+    body.push(flags);
+    body.extend_from_slice(&KEEPALIVE_S.to_be_bytes());
+    put_str(&mut body, &format!("pw-duck-{}", std::process::id()));
+    put_str(&mut body, will_topic);
+    put_str(&mut body, "offline");
+    for field in [&config.username, &config.password].into_iter().flatten() {
+        put_str(&mut body, field);
+    }
+    packet(0x10, &body)
+}
+
+/// QoS 0 PUBLISH
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_str(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(0x30 | u8::from(retain), &body)
+}
+
+/// QoS 0 SUBSCRIBE
+fn subscribe_packet(id: u16, filters: &[&str]) -> Vec<u8> {
+    let mut body = id.to_be_bytes().to_vec();
+    for filter in filters {
+        put_str(&mut body, filter);
+        body.push(0);
+    }
+    packet(0x82, &body)
+}
+
+/// broker packet
+#[derive(Debug, PartialEq)]
+enum Packet {
+    ConnAck(u8),
+    Publish {
+        topic: String,
+        payload: Vec<u8>,
+        /// packet id of a QoS 1 message, to acknowledge
+        id: Option<[u8; 2]>,
+    },
+    Other,
+}
+
+struct Conn {
+    stream: TcpStream,
+    buf: Vec<u8>,
+}
+
+impl Conn {
+    fn open(broker: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(broker)?;
+        stream.set_read_timeout(Some(POLL))?;
+        Ok(Self {
+            stream,
+            buf: Vec::new(),
+        })
+    }
+
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.stream.write_all(packet)
+    }
+
+    /// next complete packet, None after waiting up to `POLL` for one
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            if let Some(next) = take_packet(&mut self.buf)? {
+                if let Packet::Publish { id: Some(id), .. } = &next {
+                    // PUBACK, QoS 2 is not subscribed to
+                    self.send(&packet(0x40, id))?;
+                }
+                return Ok(Some(next));
+            }
+            let mut chunk = [0u8; 1024];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => self.buf.extend_from_slice(&chunk[..len]),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// first complete packet in `buf`, removed from it
+fn take_packet(buf: &mut Vec<u8>) -> io::Result<Option<Packet>> {
+    let mut len = 0usize;
+    let mut pos = 1;
+    loop {
+        let Some(&byte) = buf.get(pos) else {
+            return Ok(None);
+        };
+        len |= usize::from(byte & 0x7f) << (7 * (pos - 1));
+        pos += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if pos > 4 {
+            return Err(io::Error::other("malformed packet length"));
+        }
+    }
+    if buf.len() < pos + len {
+        return Ok(None);
+    }
+    let kind = buf[0];
+    let body: Vec<u8> = buf.drain(..pos + len).skip(pos).collect();
+    let packet = match kind >> 4 {
+        2 => Packet::ConnAck(body.get(1).copied().unwrap_or(0xff)),
+        3 => {
+            let qos = (kind >> 1) & 3;
+            let topic_len = body
+                .get(..2)
+                .map_or(0, |b| usize::from(u16::from_be_bytes([b[0], b[1]])));
+            let Some(topic) = body.get(2..2 + topic_len) else {
+                return Err(io::Error::other("malformed publish"));
+            };
+            let mut rest = 2 + topic_len;
+            let mut id = None;
+            if qos > 0 {
+                id = body.get(rest..rest + 2).map(|b| [b[0], b[1]]);
+                rest += 2;
+            }
+            Packet::Publish {
+                topic: String::from_utf8_lossy(topic).into_owned(),
+                payload: body.get(rest..).unwrap_or_default().to_vec(),
+                id,
+            }
+        }
+        _ => Packet::Other,
+    };
+    Ok(Some(packet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// remaining length bytes of an encoded packet
+    fn length_bytes(body_len: usize) -> Vec<u8> {
+        let out = packet(0x30, &vec![0; body_len]);
+        out[1..out.len() - body_len].to_vec()
+    }
+
+    #[test]
+    fn remaining_length() {
+        assert_eq!(length_bytes(0), [0x00]);
+        assert_eq!(length_bytes(127), [0x7f]);
+        assert_eq!(length_bytes(128), [0x80, 0x01]);
+        assert_eq!(length_bytes(16_383), [0xff, 0x7f]);
+        assert_eq!(length_bytes(16_384), [0x80, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn encoded_packets_parse_back() {
+        for len in [0, 5, 127, 128, 300, 20_000] {
+            let payload = vec![7u8; len];
+            let mut buf = publish_packet("pw-duck/set/hold", &payload, true);
+            assert_eq!(
+                take_packet(&mut buf).unwrap(),
+                Some(Packet::Publish {
+                    topic: "pw-duck/set/hold".into(),
+                    payload,
+                    id: None,
+                })
+            );
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn partial_and_joined_packets() {
+        let one = publish_packet("a", b"1", false);
+        let two = [0x20, 0x02, 0x00, 0x05];
+        let mut buf = one[..one.len() - 1].to_vec();
+        assert_eq!(take_packet(&mut buf).unwrap(), None);
+        buf.push(one[one.len() - 1]);
+        buf.extend_from_slice(&two);
+        assert!(matches!(
+            take_packet(&mut buf).unwrap(),
+            Some(Packet::Publish { .. })
+        ));
+        assert_eq!(take_packet(&mut buf).unwrap(), Some(Packet::ConnAck(5)));
+        assert_eq!(take_packet(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn qos1_publish_keeps_its_id() {
+        let mut body = Vec::new();
+        put_str(&mut body, "t");
+        body.extend_from_slice(&[0x12, 0x34]);
+        body.extend_from_slice(b"on");
+        let mut buf = packet(0x32, &body);
+        assert_eq!(
+            take_packet(&mut buf).unwrap(),
+            Some(Packet::Publish {
+                topic: "t".into(),
+                payload: b"on".to_vec(),
+                id: Some([0x12, 0x34]),
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_length_is_an_error() {
+        let mut buf = vec![0x30, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert!(take_packet(&mut buf).is_err());
+    }
+
+    #[test]
+    fn connect_flags() {
+        let flags = |username: Option<&str>, password: Option<&str>| {
+            let config = MqttConfig {
+                username: username.map(Into::into),
+                password: password.map(Into::into),
+                ..MqttConfig::default()
+            };
+            // fixed header, "MQTT", level, then flags
+            connect_packet(&config, "pw-duck/available")[2 + 6 + 1]
+        };
+        assert_eq!(flags(None, None), 0x26);
+        assert_eq!(flags(Some("u"), None), 0xa6);
+        assert_eq!(flags(Some("u"), Some("p")), 0xe6);
+    }
+
+    #[test]
+    fn password_needs_a_username() {
+        let config = MqttConfig {
+            password: Some("secret".into()),
+            ..MqttConfig::default()
+        };
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let err = spawn_client(
+            "127.0.0.1:1",
+            config,
+            tx,
+            Arc::new(Mutex::new(Status::default())),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::ducking::{contains_ci, OutputStream};
+use crate::ipc::create_runtime_dir;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use tracing::{debug, info, warn};
+
+/// loopback sink name
+pub const SINK_NAME: &str = "pw_duck_music";
+
+/// duckable music sink
+///
+/// Runs a loopback (`pipewire -c`) whose sink side shows up as "pw-duck Music"
+/// and plays into the default output. Only this sink's volume is ducked, so
+/// streams come and go without baselines, and users can move players onto it
+/// in pavucontrol. Streams matching the route pattern are moved there.
+#[derive(Debug)]
+pub struct MusicSink {
+    child: Child,
+    conf: PathBuf,
+    pattern: Option<String>,
+    routed: Vec<u32>,
+}
+
+impl MusicSink {
+    pub fn start(pattern: Option<String>) -> io::Result<Self> {
+        let dir = create_runtime_dir()?;
+        let conf = dir.join("music.conf");
+        fs::write(&conf, render_conf())?;
+        let child = Command::new("pipewire")
+            .arg("-c")
+            .arg(&conf)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        info!("music sink started (pid={})", child.id());
+        Ok(Self {
+            child,
+            conf,
+            pattern,
+            routed: Vec::new(),
+        })
+    }
+
+    /// stream matches the route pattern
+    pub fn matches(&self, s: &OutputStream) -> bool {
+        self.pattern.as_deref().is_some_and(|p| {
+            contains_ci(&s.app, p) || contains_ci(&s.node, p) || contains_ci(&s.media, p)
+        })
+    }
+
+    /// move stream onto the sink
+    pub fn route(&mut self, id: u32) -> bool {
+        if self.routed.contains(&id) {
+            return true;
+        }
+        let ok = Command::new("pw-metadata")
+            .args([&id.to_string(), "target.object", SINK_NAME])
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if ok {
+            debug!("music sink: routed id={id}");
+            self.routed.push(id);
+        } else {
+            warn!("music sink: routing id={id} failed");
+        }
+        ok
+    }
+
+    /// stream gone
+    pub fn forget(&mut self, id: u32) {
+        self.routed.retain(|v| *v != id);
+    }
+}
+
+impl Drop for MusicSink {
+    fn drop(&mut self) {
+        for id in self.routed.drain(..) {
+            let _ = Command::new("pw-metadata")
+                .args(["-d", &id.to_string(), "target.object"])
+                .stdout(Stdio::null())
+                .status();
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_file(&self.conf);
+        info!("music sink stopped");
+    }
+}
+
+/// is our own node
+pub fn is_music_node(node: &str) -> bool {
+    node.starts_with(SINK_NAME)
+}
+
+fn render_conf() -> String {
+    format!(
+        r#"# generated by pw-duck
+context.spa-libs = {{
+    audio.convert.* = audioconvert/libspa-audioconvert
+    support.*       = support/libspa-support
+}}
+context.modules = [
+    {{ name = libpipewire-module-rt flags = [ ifexists nofail ] }}
+    {{ name = libpipewire-module-protocol-native }}
+    {{ name = libpipewire-module-client-node }}
+    {{ name = libpipewire-module-adapter }}
+    {{ name = libpipewire-module-loopback
+        args = {{
+            node.description = "pw-duck Music"
+            media.name       = "pw-duck Music"
+            audio.position   = [ FL FR ]
+            capture.props = {{
+                node.name   = "{SINK_NAME}"
+                media.class = Audio/Sink
+            }}
+            playback.props = {{
+                node.name    = "{SINK_NAME}.output"
+                node.passive = true
+            }}
+        }}
+    }}
+]
+"#
+    )
+}
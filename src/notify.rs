@@ -0,0 +1,72 @@
+use crate::ducking::tool_available;
+use crate::events::{self, Event};
+use crate::messages;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use tracing::warn;
+
+/// notification urgency
+#[derive(Debug, Copy, Clone)]
+enum Urgency {
+    Low,
+    Critical,
+}
+
+impl Urgency {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// desktop notification sink
+///
+/// Needs `notify-send` (libnotify); without it `--notify` only warns.
+pub fn subscribe_desktop() {
+    if !tool_available("notify-send") {
+        warn!("--notify: notify-send not found, install libnotify to get desktop notifications");
+        return;
+    }
+    let ducked = AtomicBool::new(false);
+    events::subscribe(move |event| match event {
+        // state changes only
+        Event::DuckApplied { factor, .. } if !ducked.swap(true, Ordering::Relaxed) => {
+            send(
+                Urgency::Low,
+                messages::notify_duck_on((1.0 - factor) * 100.0),
+            );
+        }
+        Event::DuckRestored { failures, .. } => {
+            let was_ducked = ducked.swap(false, Ordering::Relaxed);
+            if *failures > 0 {
+                send(
+                    Urgency::Critical,
+                    messages::notify_restore_failed(*failures),
+                );
+            } else if was_ducked {
+                send(Urgency::Low, messages::notify_duck_off());
+            }
+        }
+        Event::Watchdog { minutes } => {
+            send(Urgency::Critical, messages::notify_watchdog(*minutes));
+        }
+        Event::VoiceSourceLost { id } => {
+            send(Urgency::Critical, messages::notify_voice_lost(*id));
+        }
+        _ => {}
+    });
+}
+
+/// notify-send
+fn send(urgency: Urgency, (summary, body): (String, String)) {
+    let mut cmd = Command::new("notify-send");
+    cmd.args(["-a", "pw-duck", "-u", urgency.as_str()])
+        .arg(summary)
+        .arg(body);
+    thread::spawn(move || {
+        let _ = cmd.status();
+    });
+}
@@ -0,0 +1,154 @@
+use crate::ducking::{
+    contains_ci, detect_backend, DuckCurve, OutputStream, RestoreGuard, VolumeBackend,
+};
+use crate::helper::RestoreHelper;
+use crate::ipc;
+use crate::snapshot;
+use crate::units;
+use anyhow::{anyhow, Result};
+use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// once args
+#[derive(clap::Args, Debug)]
+pub struct OnceArgs {
+    /// volume factor while ducked (0..1, % quieter or dB)
+    #[arg(
+        long,
+        default_value_t = 0.3,
+        value_parser = units::duck_factor,
+        allow_hyphen_values = true
+    )]
+    duck: f32,
+    /// leave streams matching app/node/media alone
+    #[arg(long, value_name = "PATTERN")]
+    except: Option<String>,
+    /// duck curve
+    #[arg(long, value_enum, default_value_t = DuckCurve::Linear)]
+    duck_curve: DuckCurve,
+    /// run this and restore when it exits
+    #[arg(last = true, value_name = "COMMAND")]
+    command: Vec<String>,
+}
+
+/// `pw-duck once`
+///
+/// Ducks the output streams present at start, then restores when the wrapped
+/// command exits or on SIGINT/SIGTERM. Exits with the command's status.
+pub fn run(args: &OnceArgs) -> Result<()> {
+    let quit = Arc::new(AtomicBool::new(false));
+    flag::register(SIGINT, Arc::clone(&quit))?;
+    flag::register(SIGTERM, Arc::clone(&quit))?;
+    // a running instance owns the volumes, duck through it
+    let _instance_lock = match ipc::acquire_instance_lock(false) {
+        Ok(lock) => lock,
+        Err(_) if ipc::request("status").is_ok() => return through_daemon(args, &quit),
+        Err(err) => return Err(err),
+    };
+
+    let streams: Vec<OutputStream> = snapshot::collect()?
+        .into_iter()
+        .filter(|g| {
+            g.kind == pipewire::types::ObjectType::Node && g.media_class() == "Stream/Output/Audio"
+        })
+        .map(|g| OutputStream::from_props(g.id, g.media_class().to_string(), |k| g.prop(k)))
+        .filter(|s| {
+            !args.except.as_deref().is_some_and(|p| {
+                contains_ci(&s.app, p) || contains_ci(&s.node, p) || contains_ci(&s.media, p)
+            })
+        })
+        .collect();
+
+    let backend: Arc<dyn VolumeBackend> = detect_backend()?;
+    let mut baselines = HashMap::new();
+    for s in &streams {
+        match backend.get(s.id) {
+            Some(v) => {
+                baselines.insert(s.id, v);
+            }
+            None => warn!("baseline capture failed: id={}", s.id),
+        }
+    }
+    let mut guard = RestoreGuard::new(backend, &baselines, None, args.duck_curve);
+    guard.set_helper(RestoreHelper::spawn(false));
+    let failures = guard.apply_duck(args.duck);
+    info!(
+        "ducked {} streams to {} ({failures} failed)",
+        baselines.len(),
+        args.duck
+    );
+
+    let mut code = wait(args, &quit)?;
+
+    let failures = guard.restore();
+    if failures > 0 {
+        warn!("restore: failed for {failures} streams");
+        code = code.max(1);
+    }
+    drop(guard);
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// `once` against a running instance
+///
+/// Sends `duck`, then `auto` when done. The instance's own duck factor and
+/// exclusions apply.
+fn through_daemon(args: &OnceArgs, quit: &AtomicBool) -> Result<()> {
+    info!(
+        "pw-duck is running, ducking through it (--duck, --except and --duck-curve do not apply)"
+    );
+    send("duck")?;
+    let mut code = wait(args, quit)?;
+    if let Err(err) = send("auto") {
+        warn!("restore: {err}");
+        code = code.max(1);
+    }
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// one ipc command, rejected replies as errors
+fn send(command: &str) -> Result<()> {
+    let reply = ipc::request(command)?;
+    match reply.strip_prefix("error: ") {
+        Some(err) => Err(anyhow!("{command}: {err}")),
+        None => Ok(()),
+    }
+}
+
+/// run the command, or wait for a signal, and return the exit code
+fn wait(args: &OnceArgs, quit: &AtomicBool) -> Result<i32> {
+    let Some((program, rest)) = args.command.split_first() else {
+        while !quit.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        return Ok(0);
+    };
+    let mut child = Command::new(program)
+        .args(rest)
+        .spawn()
+        .map_err(|err| anyhow!("{program}: {err}"))?;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status.code().unwrap_or(1));
+        }
+        if quit.load(Ordering::Relaxed) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(130);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
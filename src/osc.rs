@@ -0,0 +1,264 @@
+use crate::control::Control;
+use crate::units;
+use std::io;
+use std::net::UdpSocket;
+use std::sync::mpsc::Sender;
+use std::thread;
+use tracing::{debug, info, warn};
+
+/// address prefix
+const PREFIX: &str = "/pwduck/";
+
+/// OSC argument
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Arg {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+}
+
+impl Arg {
+    #[allow(clippy::cast_precision_loss)]
+    fn as_f32(self) -> f32 {
+        match self {
+            Self::Int(i) => i as f32,
+            Self::Float(f) => f,
+            Self::Bool(b) => f32::from(u8::from(b)),
+        }
+    }
+}
+
+/// OSC server
+///
+/// Accepts `/pwduck/threshold f`, `/pwduck/duck f` (duck amount 0..1),
+/// `/pwduck/hold f` (ms), `/pwduck/manual f` (non-zero ducks, zero restores),
+/// `/pwduck/auto` and `/pwduck/toggle`. Button presses that send 0 on release
+/// are ignored for the trigger addresses.
+pub fn spawn_server(addr: &str, tx: Sender<Control>) -> io::Result<()> {
+    let socket = UdpSocket::bind(addr)?;
+    info!("osc server listening on udp://{}", socket.local_addr()?);
+    thread::Builder::new()
+        .name("pw-duck-osc".into())
+        .spawn(move || {
+            let mut buf = [0u8; 1536];
+            loop {
+                let len = match socket.recv_from(&mut buf) {
+                    Ok((len, _)) => len,
+                    Err(err) => {
+                        warn!("osc recv failed: {err}");
+                        continue;
+                    }
+                };
+                let mut messages = Vec::new();
+                if parse_packet(&buf[..len], &mut messages).is_none() {
+                    debug!("osc: malformed packet ({len} bytes)");
+                    continue;
+                }
+                for (addr, args) in messages {
+                    match to_control(&addr, &args) {
+                        Some(ctl) => {
+                            if tx.send(ctl).is_err() {
+                                return;
+                            }
+                        }
+                        None => debug!("osc: ignored {addr} {args:?}"),
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn to_control(addr: &str, args: &[Arg]) -> Option<Control> {
+    let cmd = addr.strip_prefix(PREFIX)?;
+    let value = args.first().map(|a| a.as_f32());
+    let pressed = value.is_none_or(|v| v != 0.0);
+    // same ranges as on the command line
+    let number = value.filter(|v| v.is_finite());
+    match cmd {
+        "threshold" => units::threshold(&number?.to_string())
+            .ok()
+            .map(Control::Threshold),
+        "duck" => Some(Control::DuckFactor(1.0 - number?.clamp(0.0, 1.0))),
+        "hold" => units::millis(&number?.to_string()).ok().map(Control::Hold),
+        "manual" => Some(if pressed {
+            Control::Duck
+        } else {
+            Control::Restore
+        }),
+        "auto" => pressed.then_some(Control::Auto),
+        "toggle" => pressed.then_some(Control::Toggle),
+        _ => None,
+    }
+}
+
+/// message or bundle
+fn parse_packet(data: &[u8], out: &mut Vec<(String, Vec<Arg>)>) -> Option<()> {
+    if let Some(mut rest) = data.strip_prefix(b"#bundle\0") {
+        // skip timetag
+        rest = rest.get(8..)?;
+        while !rest.is_empty() {
+            let size = usize::try_from(i32::from_be_bytes(rest.get(..4)?.try_into().ok()?)).ok()?;
+            parse_packet(rest.get(4..4 + size)?, out)?;
+            rest = &rest[4 + size..];
+        }
+        return Some(());
+    }
+    let (addr, rest) = read_str(data)?;
+    let (tags, mut rest) = read_str(rest)?;
+    let mut args = Vec::new();
+    for tag in tags.strip_prefix(',')?.chars() {
+        let arg = match tag {
+            'i' | 'f' => {
+                let bytes: [u8; 4] = rest.get(..4)?.try_into().ok()?;
+                rest = &rest[4..];
+                if tag == 'i' {
+                    Arg::Int(i32::from_be_bytes(bytes))
+                } else {
+                    Arg::Float(f32::from_be_bytes(bytes))
+                }
+            }
+            'T' => Arg::Bool(true),
+            'F' => Arg::Bool(false),
+            _ => return None,
+        };
+        args.push(arg);
+    }
+    out.push((addr.to_string(), args));
+    Some(())
+}
+
+/// padded OSC string
+fn read_str(data: &[u8]) -> Option<(&str, &[u8])> {
+    let end = data.iter().position(|b| *b == 0)?;
+    let s = std::str::from_utf8(&data[..end]).ok()?;
+    let padded = (end + 4) & !3;
+    Some((s, data.get(padded..)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// NUL-terminated, padded to four bytes
+    fn osc_str(s: &str) -> Vec<u8> {
+        let mut out = s.as_bytes().to_vec();
+        out.resize((s.len() + 4) & !3, 0);
+        out
+    }
+
+    fn message(addr: &str, tags: &str, args: &[u8]) -> Vec<u8> {
+        let mut out = osc_str(addr);
+        out.extend(osc_str(tags));
+        out.extend_from_slice(args);
+        out
+    }
+
+    fn bundle(messages: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = osc_str("#bundle");
+        out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        for m in messages {
+            out.extend_from_slice(&i32::try_from(m.len()).unwrap().to_be_bytes());
+            out.extend_from_slice(m);
+        }
+        out
+    }
+
+    fn parse(data: &[u8]) -> Option<Vec<(String, Vec<Arg>)>> {
+        let mut out = Vec::new();
+        parse_packet(data, &mut out).map(|()| out)
+    }
+
+    #[test]
+    fn parses_messages() {
+        let packet = message("/pwduck/threshold", ",f", &0.02f32.to_be_bytes());
+        assert_eq!(
+            parse(&packet),
+            Some(vec![("/pwduck/threshold".into(), vec![Arg::Float(0.02)])])
+        );
+        let packet = message("/pwduck/manual", ",iTF", &7i32.to_be_bytes());
+        assert_eq!(
+            parse(&packet).unwrap()[0].1,
+            [Arg::Int(7), Arg::Bool(true), Arg::Bool(false)]
+        );
+        assert_eq!(
+            parse(&message("/pwduck/toggle", ",", &[])).unwrap()[0].1,
+            []
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        // no terminating NUL
+        assert_eq!(parse(b"/pwduck/toggle"), None);
+        // padding cut off
+        assert_eq!(parse(b"/pwduck/toggle\0"), None);
+        // tag string without a comma
+        assert_eq!(parse(&message("/pwduck/toggle", "f", &[0; 4])), None);
+        // argument missing or short
+        assert_eq!(parse(&message("/pwduck/hold", ",f", &[])), None);
+        assert_eq!(parse(&message("/pwduck/hold", ",i", &[0, 1])), None);
+        // unsupported type
+        assert_eq!(parse(&message("/pwduck/hold", ",s", &osc_str("x"))), None);
+        // address not UTF-8
+        assert_eq!(parse(&[0xff, 0, 0, 0, b',', 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn parses_bundles() {
+        let packet = bundle(&[
+            message("/pwduck/auto", ",", &[]),
+            bundle(&[message("/pwduck/hold", ",i", &300i32.to_be_bytes())]),
+        ]);
+        let messages = parse(&packet).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1], ("/pwduck/hold".into(), vec![Arg::Int(300)]));
+        // element longer than the packet
+        let mut packet = bundle(&[message("/pwduck/auto", ",", &[])]);
+        packet.truncate(packet.len() - 2);
+        assert_eq!(parse(&packet), None);
+        // negative size
+        let mut packet = osc_str("#bundle");
+        packet.extend_from_slice(&[0; 8]);
+        packet.extend_from_slice(&(-4i32).to_be_bytes());
+        assert_eq!(parse(&packet), None);
+        // timetag cut off
+        assert_eq!(parse(&osc_str("#bundle")), None);
+    }
+
+    #[test]
+    fn maps_addresses_to_controls() {
+        let f = |addr: &str, v: f32| to_control(addr, &[Arg::Float(v)]);
+        assert_eq!(f("/pwduck/threshold", 0.02), Some(Control::Threshold(0.02)));
+        assert_eq!(f("/pwduck/hold", 499.6), Some(Control::Hold(500)));
+        assert_eq!(
+            to_control("/pwduck/hold", &[Arg::Int(250)]),
+            Some(Control::Hold(250))
+        );
+        assert!(matches!(
+            f("/pwduck/duck", 0.7),
+            Some(Control::DuckFactor(d)) if (d - 0.3).abs() < 1e-6
+        ));
+        assert_eq!(f("/pwduck/duck", 2.0), Some(Control::DuckFactor(0.0)));
+        assert_eq!(f("/pwduck/manual", 1.0), Some(Control::Duck));
+        assert_eq!(f("/pwduck/manual", 0.0), Some(Control::Restore));
+        assert_eq!(to_control("/pwduck/toggle", &[]), Some(Control::Toggle));
+        assert_eq!(to_control("/pwduck/auto", &[Arg::Bool(false)]), None);
+        assert_eq!(to_control("/other/toggle", &[]), None);
+        assert_eq!(to_control("/pwduck/unknown", &[]), None);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let f = |addr: &str, v: f32| to_control(addr, &[Arg::Float(v)]);
+        for v in [f32::NAN, f32::INFINITY, -0.01, 0.0, 1.5] {
+            assert_eq!(f("/pwduck/threshold", v), None, "threshold {v}");
+        }
+        for v in [f32::NAN, f32::NEG_INFINITY, -1.0, 60_001.0, 1e12] {
+            assert_eq!(f("/pwduck/hold", v), None, "hold {v}");
+        }
+        assert_eq!(f("/pwduck/duck", f32::NAN), None);
+        // value missing
+        assert_eq!(to_control("/pwduck/threshold", &[]), None);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::events::{self, Event};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// reload period of the HTML page, ms
+const HTML_REFRESH_MS: u32 = 250;
+
+/// [overlay] table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverlayConfig {
+    pub path: Option<PathBuf>,
+    /// text while someone speaks, `{level}` and `{confidence}` are replaced
+    pub voice: String,
+    /// text otherwise
+    pub idle: String,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            voice: "🎙 VOICE".into(),
+            idle: String::new(),
+        }
+    }
+}
+
+/// overlay sink
+///
+/// Rewrites `path` on every voice start and stop. A `.html` path gets a page
+/// that reloads itself, for OBS browser sources; anything else is plain text
+/// for text sources.
+pub fn subscribe(path: PathBuf, config: OverlayConfig) {
+    if let Err(err) = write(&path, &config.idle) {
+        warn!("overlay {}: {err}", path.display());
+    }
+    events::subscribe(move |event| {
+        let (template, level, confidence) = match event {
+            Event::VoiceStart { level, confidence } => (&config.voice, level, confidence),
+            Event::VoiceStop { level, confidence } => (&config.idle, level, confidence),
+            _ => return,
+        };
+        let text = template
+            .replace("{level}", &format!("{level:.3}"))
+            .replace("{confidence}", &confidence.to_string());
+        if let Err(err) = write(&path, &text) {
+            warn!("overlay {}: {err}", path.display());
+        }
+    });
+}
+
+/// replace atomically, sources never read a half-written file
+fn write(path: &Path, text: &str) -> io::Result<()> {
+    let html = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"));
+    let contents = if html {
+        format!(
+            "<!doctype html>\n<html><head><meta charset=\"utf-8\">\
+             <script>setTimeout(() => location.reload(), {HTML_REFRESH_MS})</script>\
+             </head><body>{text}</body></html>\n"
+        )
+    } else {
+        format!("{text}\n")
+    };
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
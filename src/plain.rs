@@ -0,0 +1,89 @@
+use crate::control::Control;
+use crate::ipc::Status;
+use crate::messages::Msg;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// print one line
+///
+/// Plain mode talks on stdout, one complete line per update.
+pub fn say(line: &str) {
+    let mut out = io::stdout().lock();
+    let _ = writeln!(out, "{line}");
+    let _ = out.flush();
+}
+
+pub fn help() {
+    say(Msg::PlainHelp.text());
+}
+
+/// one-line status
+pub fn status_line(s: &Status) -> String {
+    format!(
+        "source {}, mode {}, voice {} ({}%), ducking {}, threshold {:.4}, duck factor {:.2}, hold {} ms",
+        s.voice_source,
+        s.mode,
+        if s.voice_active { "on" } else { "off" },
+        s.confidence,
+        match (s.ducked, s.armed) {
+            (true, true) => "armed",
+            (true, false) => "on",
+            _ => "off",
+        },
+        s.threshold,
+        s.duck_factor,
+        s.hold_ms
+    )
+}
+
+/// stdin command reader
+///
+/// Line based so it works without raw mode and with screen readers.
+pub fn spawn_reader(
+    tx: Sender<Control>,
+    status: Arc<Mutex<Status>>,
+    quit_requested: Arc<AtomicBool>,
+) -> io::Result<()> {
+    thread::Builder::new()
+        .name("pw-duck-plain".into())
+        .spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let mut words = line.split_whitespace();
+                let (Some(cmd), arg) = (words.next(), words.next()) else {
+                    continue;
+                };
+                let ctl = match (cmd, arg.map(str::parse::<f32>)) {
+                    ("a", None) => Control::Auto,
+                    ("d", None) => Control::Duck,
+                    ("r", None) => Control::Restore,
+                    ("t", Some(Ok(v))) => Control::Threshold(v),
+                    ("f", Some(Ok(v))) => Control::DuckFactor(v),
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    ("h", Some(Ok(v))) => Control::Hold(v.max(0.0) as u64),
+                    ("s", None) => {
+                        say(&status_line(&status.lock().unwrap()));
+                        continue;
+                    }
+                    ("q", None) => {
+                        say(Msg::Quitting.text());
+                        quit_requested.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                    _ => {
+                        help();
+                        continue;
+                    }
+                };
+                if tx.send(ctl).is_err() {
+                    break;
+                }
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use crate::ducking::{contains_ci, OutputStream};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// [profile.NAME] table
+///
+/// Parameters picked by the voice application; unset values keep the
+/// command line (or default) setting.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// app/binary/node substrings
+    pub app: Vec<String>,
+    pub threshold: Option<f32>,
+    pub attack: Option<u64>,
+    pub hold: Option<u64>,
+    pub duck_factor: Option<f32>,
+}
+
+impl Profile {
+    fn matches(&self, s: &OutputStream) -> bool {
+        self.app
+            .iter()
+            .any(|p| contains_ci(&s.app, p) || contains_ci(&s.bin, p) || contains_ci(&s.node, p))
+    }
+
+    /// overlay set fields
+    fn merge(&mut self, other: &Self) {
+        if !other.app.is_empty() {
+            self.app.clone_from(&other.app);
+        }
+        self.threshold = other.threshold.or(self.threshold);
+        self.attack = other.attack.or(self.attack);
+        self.hold = other.hold.or(self.hold);
+        self.duck_factor = other.duck_factor.or(self.duck_factor);
+    }
+}
+
+fn builtin(app: &[&str], threshold: f32, hold: u64) -> Profile {
+    Profile {
+        app: app.iter().map(|a| (*a).to_string()).collect(),
+        threshold: Some(threshold),
+        hold: Some(hold),
+        ..Profile::default()
+    }
+}
+
+/// known voice clients
+///
+/// Checked in order, so specific clients come before the generic WebRTC
+/// engine that browsers share with Discord.
+fn builtins() -> Vec<(String, Profile)> {
+    vec![
+        ("zoom".into(), builtin(&["zoom"], 0.015, 500)),
+        ("teams".into(), builtin(&["teams"], 0.02, 450)),
+        ("mumble".into(), builtin(&["mumble"], 0.03, 300)),
+        (
+            "teamspeak".into(),
+            builtin(&["teamspeak", "ts3client"], 0.03, 300),
+        ),
+        (
+            "discord".into(),
+            builtin(&["discord", "WEBRTC VoiceEngine"], 0.02, 350),
+        ),
+    ]
+}
+
+/// built-in profiles plus config overrides
+#[derive(Debug)]
+pub struct Profiles {
+    list: Vec<(String, Profile)>,
+}
+
+impl Profiles {
+    /// own profiles are checked before the built-in ones
+    pub fn new(config: &BTreeMap<String, Profile>) -> Self {
+        let mut known = builtins();
+        let mut list = Vec::new();
+        for (name, profile) in config {
+            if let Some((_, p)) = known.iter_mut().find(|(n, _)| n == name) {
+                p.merge(profile);
+            } else {
+                list.push((name.clone(), profile.clone()));
+            }
+        }
+        list.extend(known);
+        Self { list }
+    }
+
+    /// profile by name
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.list.iter().find(|(n, _)| n == name).map(|(_, p)| p)
+    }
+
+    /// profile for a voice source
+    pub fn detect(&self, voice: &OutputStream) -> Option<(&str, &Profile)> {
+        self.list
+            .iter()
+            .find(|(_, p)| p.matches(voice))
+            .map(|(n, p)| (n.as_str(), p))
+    }
+}
@@ -0,0 +1,230 @@
+use crate::analysis::{auto_vad_step, zero_crossing_rate, VadState, MIN_HOLD_MS, VAD_TICK_MS};
+use crate::ducking::{DuckCurve, RestoreGuard, Wpctl};
+use crate::tap::read_wav;
+use crate::units;
+use crate::ControlMode;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// replay args
+#[derive(clap::Args, Debug)]
+pub struct ReplayArgs {
+    /// recorded capture (e.g. from --record-tap)
+    file: PathBuf,
+    /// vad threshold (level or dB)
+    #[arg(
+        long,
+        default_value_t = 0.02,
+        value_parser = units::threshold,
+        allow_hyphen_values = true
+    )]
+    threshold: f32,
+    /// attack (ms or s)
+    #[arg(long, default_value_t = 0, value_parser = units::millis)]
+    attack: u64,
+    /// hold (ms or s)
+    #[arg(long, default_value_t = 350, value_parser = units::millis)]
+    hold: u64,
+    /// frames per analyzed buffer
+    #[arg(long, default_value_t = 1024)]
+    block: usize,
+    /// print an ASCII timeline
+    #[arg(long)]
+    plot: bool,
+    /// only count speech-like zero crossing rates as voice
+    #[arg(long)]
+    zcr_gate: bool,
+    /// follow the speaker's pauses between words instead of a fixed hold
+    #[arg(long)]
+    adaptive_hold: bool,
+    /// voice starts after N of the last M ticks above threshold
+    #[arg(long, value_name = "N/M", value_parser = units::votes)]
+    onset_votes: Option<(usize, usize)>,
+}
+
+/// detected voice span
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Segment {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// replay parameters
+#[derive(Debug, Copy, Clone)]
+pub struct ReplayParams {
+    pub threshold: f32,
+    pub attack_ms: u64,
+    pub hold_ms: u64,
+    pub block: usize,
+    pub zcr_gate: bool,
+    pub adaptive_hold: bool,
+    pub onset_votes: Option<(usize, usize)>,
+}
+
+/// one tick of the replayed timeline
+#[derive(Debug, Copy, Clone)]
+pub struct Tick {
+    pub energy: f32,
+    pub voice_active: bool,
+}
+
+/// run `auto_vad_step` over interleaved samples
+///
+/// Mirrors the live pipeline: RMS per capture buffer, sampled by the VAD timer
+/// every `VAD_TICK_MS` on a simulated clock, with no streams to duck.
+#[allow(clippy::cast_precision_loss)]
+pub fn replay(
+    samples: &[f32],
+    rate: u32,
+    channels: u16,
+    params: ReplayParams,
+) -> (Vec<Segment>, Vec<Tick>) {
+    let block = params.block.max(1) * usize::from(channels);
+    let energies: Vec<f32> = samples
+        .chunks(block)
+        .map(|b| (b.iter().map(|s| s * s).sum::<f32>() / b.len() as f32).sqrt())
+        .collect();
+    let zcrs: Vec<f32> = samples
+        .chunks(block)
+        .map(|b| zero_crossing_rate(b, usize::from(channels), rate))
+        .collect();
+    let block_dur = Duration::from_secs_f64(params.block.max(1) as f64 / f64::from(rate.max(1)));
+    let total = block_dur * u32::try_from(energies.len()).unwrap_or(u32::MAX);
+    let tick = Duration::from_millis(VAD_TICK_MS);
+
+    // no streams, the backend is never called
+    let mut guard = RestoreGuard::new(
+        Arc::new(Wpctl::default()),
+        &HashMap::new(),
+        None,
+        DuckCurve::Linear,
+    );
+    let mut state = VadState::new(false);
+    let start = Instant::now();
+    let mut segments = Vec::new();
+    let mut ticks = Vec::new();
+    let mut open: Option<Duration> = None;
+    let mut t = Duration::ZERO;
+    while t < total {
+        // latest buffer the callback would have delivered
+        let idx = (t.as_secs_f64() / block_dur.as_secs_f64()) as usize;
+        let energy = energies.get(idx).copied().unwrap_or(0.0);
+        state.zcr = params
+            .zcr_gate
+            .then(|| zcrs.get(idx).copied().unwrap_or(0.0));
+        state.onset_votes = params.onset_votes;
+        let hold_ms = params
+            .adaptive_hold
+            .then(|| state.adaptive_hold())
+            .flatten()
+            .unwrap_or(params.hold_ms);
+        let snap = auto_vad_step(
+            ControlMode::AutoVad,
+            energy,
+            params.threshold,
+            start + t,
+            &mut state,
+            &mut guard,
+            0.0,
+            &mut |_| {},
+            params.attack_ms,
+            hold_ms.max(MIN_HOLD_MS),
+            None,
+            &mut |_, _, _| {},
+        );
+        match (snap.voice_active, open) {
+            (true, None) => open = Some(t),
+            (false, Some(s)) => {
+                segments.push(Segment { start: s, end: t });
+                open = None;
+            }
+            _ => {}
+        }
+        ticks.push(Tick {
+            energy,
+            voice_active: snap.voice_active,
+        });
+        t += tick;
+    }
+    if let Some(s) = open {
+        segments.push(Segment {
+            start: s,
+            end: total,
+        });
+    }
+    (segments, ticks)
+}
+
+/// `pw-duck replay`
+pub fn run(args: &ReplayArgs) -> Result<()> {
+    let wav = read_wav(&args.file).with_context(|| format!("reading {}", args.file.display()))?;
+    let params = ReplayParams {
+        threshold: args.threshold,
+        attack_ms: args.attack,
+        hold_ms: args.hold,
+        block: args.block,
+        zcr_gate: args.zcr_gate,
+        adaptive_hold: args.adaptive_hold,
+        onset_votes: args.onset_votes,
+    };
+    let (segments, ticks) = replay(&wav.samples, wav.rate, wav.channels, params);
+    println!(
+        "{}: {} Hz, {} ch, threshold={} attack={}ms hold={}ms",
+        args.file.display(),
+        wav.rate,
+        wav.channels,
+        args.threshold,
+        args.attack,
+        args.hold.max(MIN_HOLD_MS)
+    );
+    let mut voiced = Duration::ZERO;
+    for s in &segments {
+        let len = s.end - s.start;
+        voiced += len;
+        println!(
+            "voice {:>9.3}s - {:>9.3}s  ({:.3}s)",
+            s.start.as_secs_f64(),
+            s.end.as_secs_f64(),
+            len.as_secs_f64()
+        );
+    }
+    println!(
+        "{} segments, {:.3}s voice",
+        segments.len(),
+        voiced.as_secs_f64()
+    );
+    if args.plot {
+        plot(&ticks, args.threshold);
+    }
+    Ok(())
+}
+
+/// ticks per plot row
+const PLOT_WIDTH: usize = 100;
+
+/// energy bars plus voice row
+#[allow(clippy::cast_precision_loss)]
+fn plot(ticks: &[Tick], threshold: f32) {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    // full scale at 4x threshold
+    let top = threshold * 4.0;
+    for (row, chunk) in ticks.chunks(PLOT_WIDTH).enumerate() {
+        let secs = (row * PLOT_WIDTH) as f64 * VAD_TICK_MS as f64 / 1000.0;
+        let level: String = chunk
+            .iter()
+            .map(|t| {
+                let i = ((t.energy / top).clamp(0.0, 1.0) * 7.0).round() as usize;
+                BARS[i]
+            })
+            .collect();
+        let voice: String = chunk
+            .iter()
+            .map(|t| if t.voice_active { '#' } else { '.' })
+            .collect();
+        println!("{secs:>8.1}s {level}");
+        println!("{:>9} {voice}", "");
+    }
+}
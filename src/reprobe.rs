@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// pause between probe rounds
+pub const PROBE_EVERY: Duration = Duration::from_secs(20);
+/// capture per candidate
+const PROBE_WINDOW: Duration = Duration::from_millis(800);
+/// probe rate, keeps one window below the pipe buffer
+const PROBE_RATE: u32 = 8000;
+/// voice silence before suggesting
+pub const SILENT_FOR: Duration = Duration::from_secs(120);
+/// loud rounds in a row
+const SUSTAINED_PROBES: u32 = 3;
+/// voice silence before probing, leaves room for the loud rounds
+pub const PROBE_AFTER: Duration =
+    Duration::from_secs(SILENT_FOR.as_secs() - PROBE_EVERY.as_secs() * SUSTAINED_PROBES as u64);
+
+/// unselected candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub id: u32,
+    pub serial: String,
+    pub node: String,
+}
+
+/// probe result
+#[derive(Debug, Copy, Clone)]
+pub struct Reading {
+    pub id: u32,
+    pub rms: f32,
+}
+
+/// background candidate prober
+///
+/// Samples every target for a short window with `pw-record` once per round,
+/// off the main loop so the VAD keeps running. Without targets nothing is
+/// recorded.
+#[derive(Debug)]
+pub struct Reprober {
+    targets: Arc<Mutex<Vec<Target>>>,
+    rx: Receiver<Reading>,
+}
+
+impl Reprober {
+    pub fn spawn() -> io::Result<Self> {
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let (tx, rx) = mpsc::channel();
+        let targets_t = Arc::clone(&targets);
+        thread::Builder::new()
+            .name("pw-duck-reprobe".into())
+            .spawn(move || run(&targets_t, &tx))?;
+        Ok(Self { targets, rx })
+    }
+
+    pub fn set_targets(&self, targets: Vec<Target>) {
+        *self.targets.lock().unwrap() = targets;
+    }
+
+    pub fn readings(&self) -> impl Iterator<Item = Reading> + '_ {
+        self.rx.try_iter()
+    }
+}
+
+fn run(targets: &Mutex<Vec<Target>>, tx: &Sender<Reading>) {
+    loop {
+        thread::sleep(PROBE_EVERY);
+        let round = targets.lock().unwrap().clone();
+        for target in round {
+            match probe(&target) {
+                Ok(rms) => {
+                    debug!("reprobe id={} rms={:.4}", target.id, rms);
+                    if tx.send(Reading { id: target.id, rms }).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => warn!("reprobe id={} failed: {err}", target.id),
+            }
+        }
+    }
+}
+
+/// `pw-record` of a target as raw mono f32 on stdout
+///
+/// `monitor` records what an output stream plays rather than a source.
+pub fn record(target: &Target, rate: u32, monitor: bool) -> Command {
+    let object = if target.serial == "-" {
+        &target.node
+    } else {
+        &target.serial
+    };
+    let mut cmd = Command::new("pw-record");
+    cmd.args(["--raw", "--format", "f32", "--channels", "1"])
+        .args(["--rate", &rate.to_string()])
+        .args(["--target", object]);
+    if monitor {
+        cmd.args(["-P", "{ stream.capture.sink = true }"]);
+    }
+    cmd.arg("-").stdout(Stdio::piped()).stderr(Stdio::null());
+    cmd
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn probe(target: &Target) -> io::Result<f32> {
+    let mut child = record(target, PROBE_RATE, true).spawn()?;
+    thread::sleep(PROBE_WINDOW);
+    let _ = child.kill();
+    let mut raw = Vec::new();
+    if let Some(mut out) = child.stdout.take() {
+        out.read_to_end(&mut raw)?;
+    }
+    let _ = child.wait();
+    let samples: Vec<f32> = raw
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    if samples.is_empty() {
+        return Ok(0.0);
+    }
+    let sum_sq: f32 = samples.iter().map(|s| s * s).sum();
+    Ok((sum_sq / samples.len() as f32).sqrt())
+}
+
+/// switch decision
+///
+/// Suggests a candidate once the voice source has been silent for
+/// [`SILENT_FOR`] while the candidate was loud in several rounds in a row.
+#[derive(Debug, Default)]
+pub struct SwitchWatch {
+    loud: HashMap<u32, u32>,
+}
+
+impl SwitchWatch {
+    pub fn reading(&mut self, r: Reading, threshold: f32) {
+        let streak = self.loud.entry(r.id).or_default();
+        if r.rms >= threshold {
+            *streak += 1;
+        } else {
+            *streak = 0;
+        }
+    }
+
+    /// drop gone candidates
+    pub fn retain(&mut self, targets: &[Target]) {
+        self.loud
+            .retain(|id, _| targets.iter().any(|t| t.id == *id));
+    }
+
+    pub fn suggestion(&self, voice_silent: Duration) -> Option<u32> {
+        if voice_silent < SILENT_FOR {
+            return None;
+        }
+        self.loud
+            .iter()
+            .filter(|(_, streak)| **streak >= SUSTAINED_PROBES)
+            .max_by_key(|(_, streak)| **streak)
+            .map(|(id, _)| *id)
+    }
+
+    pub fn reset(&mut self) {
+        self.loud.clear();
+    }
+}
@@ -0,0 +1,344 @@
+use crate::ducking::{contains_ci, OutputStream, RestoreGuard};
+use serde::Deserialize;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+/// per-stream rule result
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Verdict {
+    /// global duck factor
+    Default,
+    /// never touch
+    Exclude,
+    /// own duck factor
+    Factor(f32),
+}
+
+impl Verdict {
+    /// guard override
+    pub fn factor(self) -> Option<f32> {
+        match self {
+            Self::Default => None,
+            Self::Exclude => Some(1.0),
+            Self::Factor(f) => Some(f.clamp(0.0, 1.0)),
+        }
+    }
+}
+
+/// per-rule attack/hold in ms
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Timing {
+    pub attack: Option<u64>,
+    pub hold: Option<u64>,
+}
+
+impl Timing {
+    pub fn is_default(self) -> bool {
+        self.attack.is_none() && self.hold.is_none()
+    }
+}
+
+/// vad state seen by rules
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(not(feature = "rules"), allow(dead_code))]
+pub struct VadInfo {
+    pub active: bool,
+    pub level: f32,
+}
+
+/// [[rule]] table
+///
+/// All given match keys must match (case-insensitive substring); `app` is
+/// checked against `application.name` and the process binary.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppRule {
+    pub app: Option<String>,
+    pub node: Option<String>,
+    pub media: Option<String>,
+    pub role: Option<String>,
+    pub exclude: bool,
+    pub factor: Option<f32>,
+    /// own attack in ms
+    pub attack: Option<u64>,
+    /// own hold in ms
+    pub hold: Option<u64>,
+    /// never ducked below this volume
+    pub min_volume: Option<f32>,
+}
+
+impl AppRule {
+    fn matches(&self, s: &OutputStream) -> bool {
+        let hit = |pat: &Option<String>, fields: &[&str]| {
+            pat.as_deref()
+                .is_none_or(|p| fields.iter().any(|f| contains_ci(f, p)))
+        };
+        hit(&self.app, &[&s.app, &s.bin])
+            && hit(&self.node, &[&s.node])
+            && hit(&self.media, &[&s.media])
+            && hit(&self.role, &[&s.role])
+    }
+
+    fn verdict(&self) -> Verdict {
+        if self.exclude {
+            Verdict::Exclude
+        } else if let Some(f) = self.factor {
+            Verdict::Factor(f)
+        } else {
+            Verdict::Default
+        }
+    }
+
+    fn timing(&self) -> Timing {
+        Timing {
+            attack: self.attack,
+            hold: self.hold,
+        }
+    }
+}
+
+/// screen readers and speech synthesis
+///
+/// Never ducked unless `protect_accessibility = false` or an own rule or the
+/// script decides otherwise.
+pub fn accessibility_rules() -> Vec<AppRule> {
+    let app = |name: &str| AppRule {
+        app: Some(name.into()),
+        exclude: true,
+        ..AppRule::default()
+    };
+    let mut rules: Vec<AppRule> = [
+        "speech-dispatcher",
+        "orca",
+        "espeak",
+        "sd_generic",
+        "festival",
+    ]
+    .into_iter()
+    .map(app)
+    .collect();
+    rules.push(AppRule {
+        node: Some("speech-dispatcher".into()),
+        exclude: true,
+        ..AppRule::default()
+    });
+    rules.push(AppRule {
+        role: Some("Accessibility".into()),
+        exclude: true,
+        ..AppRule::default()
+    });
+    rules
+}
+
+/// static rules plus optional script
+#[derive(Default)]
+pub struct RuleSet {
+    /// added at runtime, checked first
+    live: RefCell<Vec<AppRule>>,
+    /// app/node/media patterns left alone in focus mode
+    allow: Vec<String>,
+    focus: Cell<bool>,
+    /// media roles ducked at all, empty for every role
+    roles: Vec<String>,
+    /// media roles another agent ducks, left alone
+    coordinated: RefCell<Vec<String>>,
+    /// active scene, checked before the static rules
+    scene: RefCell<Vec<AppRule>>,
+    rules: Vec<AppRule>,
+    /// checked last
+    builtin: Vec<AppRule>,
+    #[cfg(feature = "rules")]
+    script: Option<crate::script::Script>,
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<AppRule>) -> Self {
+        Self {
+            live: RefCell::default(),
+            allow: Vec::new(),
+            focus: Cell::new(false),
+            roles: Vec::new(),
+            coordinated: RefCell::default(),
+            scene: RefCell::default(),
+            rules,
+            builtin: Vec::new(),
+            #[cfg(feature = "rules")]
+            script: None,
+        }
+    }
+
+    pub fn with_builtin(mut self, builtin: Vec<AppRule>) -> Self {
+        self.builtin = builtin;
+        self
+    }
+
+    pub fn with_focus_allow(mut self, allow: Vec<String>) -> Self {
+        self.allow = allow;
+        self
+    }
+
+    pub fn with_duck_roles(mut self, roles: Vec<String>) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    /// while on, allowlisted streams are excluded before any other rule
+    pub fn set_focus(&self, on: bool) {
+        self.focus.set(on);
+    }
+
+    /// roles WirePlumber's role-based policy ducks on its own
+    pub fn set_coordinated(&self, roles: Vec<String>) {
+        *self.coordinated.borrow_mut() = roles;
+    }
+
+    #[cfg(feature = "rules")]
+    pub fn with_script(mut self, script: Option<crate::script::Script>) -> Self {
+        self.script = script;
+        self
+    }
+
+    /// exclude an app until restart
+    pub fn exclude(&self, app: &str) {
+        self.live.borrow_mut().push(AppRule {
+            app: Some(app.into()),
+            exclude: true,
+            ..AppRule::default()
+        });
+    }
+
+    /// rules of the active scene
+    pub fn set_scene(&self, rules: Vec<AppRule>) {
+        *self.scene.borrow_mut() = rules;
+    }
+
+    /// apps excluded at runtime
+    pub fn exclusions(&self) -> Vec<String> {
+        self.live
+            .borrow()
+            .iter()
+            .filter_map(|r| r.app.clone())
+            .collect()
+    }
+
+    /// has dynamic rules
+    pub fn is_dynamic(&self) -> bool {
+        #[cfg(feature = "rules")]
+        return self.script.is_some();
+        #[cfg(not(feature = "rules"))]
+        false
+    }
+
+    /// focus allowlist, roles outside --duck-roles, roles ducked by another
+    /// agent, first matching live, scene or static rule, then the script,
+    /// then built-in rules
+    pub fn evaluate(&self, s: &OutputStream, vad: VadInfo) -> Verdict {
+        if self.focus.get()
+            && self.allow.iter().any(|p| {
+                [&s.app, &s.bin, &s.node, &s.media]
+                    .iter()
+                    .any(|f| contains_ci(f, p))
+            })
+        {
+            return Verdict::Exclude;
+        }
+        if !self.roles.is_empty() && !self.roles.iter().any(|r| r.eq_ignore_ascii_case(&s.role)) {
+            return Verdict::Exclude;
+        }
+        if self
+            .coordinated
+            .borrow()
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(&s.role))
+        {
+            return Verdict::Exclude;
+        }
+        if let Some(rule) = self.live.borrow().iter().find(|r| r.matches(s)) {
+            return rule.verdict();
+        }
+        if let Some(rule) = self.scene.borrow().iter().find(|r| r.matches(s)) {
+            return rule.verdict();
+        }
+        if let Some(rule) = self.rules.iter().find(|r| r.matches(s)) {
+            return rule.verdict();
+        }
+        #[cfg(feature = "rules")]
+        if let Some(script) = self.script.as_ref() {
+            let verdict = script.evaluate(s, vad);
+            if verdict != Verdict::Default {
+                return verdict;
+            }
+        }
+        let _ = vad;
+        self.builtin
+            .iter()
+            .find(|r| r.matches(s))
+            .map_or(Verdict::Default, AppRule::verdict)
+    }
+
+    /// attack/hold of the first matching scene, static or built-in rule
+    pub fn timing(&self, s: &OutputStream) -> Timing {
+        self.scene
+            .borrow()
+            .iter()
+            .chain(&self.rules)
+            .chain(&self.builtin)
+            .find(|r| r.matches(s))
+            .map_or(Timing::default(), AppRule::timing)
+    }
+
+    /// floor of the first matching scene, static or built-in rule that sets one
+    pub fn min_volume(&self, s: &OutputStream) -> Option<f32> {
+        self.scene
+            .borrow()
+            .iter()
+            .chain(&self.rules)
+            .chain(&self.builtin)
+            .filter(|r| r.matches(s))
+            .find_map(|r| r.min_volume)
+            .map(|v| v.clamp(0.0, 1.0))
+    }
+
+    /// evaluate per application
+    ///
+    /// Streams of one process form a unit: the first stream (lowest id) with a
+    /// non-default verdict decides for all of its siblings.
+    pub fn apply(
+        &self,
+        guard: &mut RestoreGuard,
+        outputs: &HashMap<u32, OutputStream>,
+        vad: VadInfo,
+    ) {
+        for members in groups(outputs).values() {
+            let verdict = members
+                .iter()
+                .map(|s| self.evaluate(s, vad))
+                .find(|v| *v != Verdict::Default)
+                .unwrap_or(Verdict::Default);
+            let timing = members
+                .iter()
+                .map(|s| self.timing(s))
+                .find(|t| !t.is_default())
+                .unwrap_or_default();
+            let floor = members.iter().find_map(|s| self.min_volume(s));
+            for s in members {
+                guard.set_stream_factor(s.id, verdict.factor());
+                guard.set_stream_timing(s.id, timing);
+                guard.set_stream_floor(s.id, floor);
+            }
+        }
+    }
+}
+
+/// streams by application group, sorted by id
+pub fn groups(outputs: &HashMap<u32, OutputStream>) -> BTreeMap<String, Vec<&OutputStream>> {
+    let mut groups: BTreeMap<String, Vec<&OutputStream>> = BTreeMap::new();
+    for s in outputs.values() {
+        groups.entry(s.group()).or_default().push(s);
+    }
+    for members in groups.values_mut() {
+        members.sort_unstable_by_key(|s| s.id);
+    }
+    groups
+}
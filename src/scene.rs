@@ -0,0 +1,19 @@
+use crate::rules::AppRule;
+use serde::Deserialize;
+
+/// [scene.NAME] table
+///
+/// Voice target, rules and parameters switched together at runtime; unset
+/// values keep the current setting.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    /// voice source patterns, replace `--voice-source`
+    pub voice_source: Vec<String>,
+    pub threshold: Option<f32>,
+    pub attack: Option<u64>,
+    pub hold: Option<u64>,
+    pub duck_factor: Option<f32>,
+    /// checked before the config rules
+    pub rule: Vec<AppRule>,
+}
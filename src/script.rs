@@ -0,0 +1,145 @@
+use crate::ducking::OutputStream;
+use crate::rules::{VadInfo, Verdict};
+use anyhow::{anyhow, Result};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use std::path::Path;
+use tracing::warn;
+
+/// script entry point
+const ENTRY: &str = "duck";
+
+/// limits per call, scripts run on the mainloop
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 4096;
+
+/// rhai rules engine
+///
+/// The script defines `fn duck(stream, vad)` returning `()` for the global
+/// factor, `false` to exclude the stream, or a number used as its factor.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_string_size(MAX_STRING_SIZE);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|err| anyhow!("rules {}: {err}", path.display()))?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == ENTRY && f.params.len() == 2)
+        {
+            return Err(anyhow!(
+                "rules {}: missing fn {ENTRY}(stream, vad)",
+                path.display()
+            ));
+        }
+        Ok(Self { engine, ast })
+    }
+
+    pub fn evaluate(&self, stream: &OutputStream, vad: VadInfo) -> Verdict {
+        let mut s = Map::new();
+        s.insert("id".into(), Dynamic::from_int(i64::from(stream.id)));
+        s.insert("app".into(), stream.app.clone().into());
+        s.insert("bin".into(), stream.bin.clone().into());
+        s.insert("pid".into(), stream.pid.clone().into());
+        s.insert("role".into(), stream.role.clone().into());
+        s.insert("media".into(), stream.media.clone().into());
+        s.insert("class".into(), stream.media_class.clone().into());
+        s.insert("node".into(), stream.node.clone().into());
+        let mut v = Map::new();
+        v.insert("active".into(), vad.active.into());
+        v.insert("level".into(), Dynamic::from_float(f64::from(vad.level)));
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, ENTRY, (s, v));
+        match result {
+            Ok(r) if r.is_unit() => Verdict::Default,
+            Ok(r) if r.as_bool() == Ok(false) => Verdict::Exclude,
+            Ok(r) if r.as_bool() == Ok(true) => Verdict::Default,
+            #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+            Ok(r) => match (r.as_float(), r.as_int()) {
+                (Ok(f), _) => Verdict::Factor(f as f32),
+                (_, Ok(i)) => Verdict::Factor(i as f32),
+                _ => {
+                    warn!(
+                        "rules: id={} unexpected result {}",
+                        stream.id,
+                        r.type_name()
+                    );
+                    Verdict::Default
+                }
+            },
+            // limits hit included, the rule did not match
+            Err(err) => {
+                warn!("rules: id={} {err}", stream.id);
+                Verdict::Default
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, source: &str) -> Script {
+        let path = std::env::temp_dir().join(format!(
+            "pw-duck-script-test-{}-{name}.rhai",
+            std::process::id()
+        ));
+        std::fs::write(&path, source).unwrap();
+        let script = Script::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        script
+    }
+
+    fn evaluate(script: &Script, app: &str) -> Verdict {
+        let stream = OutputStream::from_props(1, "Stream/Output/Audio".into(), |key| {
+            (key == "application.name").then(|| app.to_string())
+        });
+        script.evaluate(
+            &stream,
+            VadInfo {
+                active: true,
+                level: 0.1,
+            },
+        )
+    }
+
+    #[test]
+    fn verdicts() {
+        let script = load(
+            "verdicts",
+            r#"fn duck(stream, vad) {
+                if stream.app == "game" { return false; }
+                if stream.app == "music" { return 0.5; }
+                if stream.app == "video" { return 1; }
+            }"#,
+        );
+        assert_eq!(evaluate(&script, "game"), Verdict::Exclude);
+        assert_eq!(evaluate(&script, "music"), Verdict::Factor(0.5));
+        assert_eq!(evaluate(&script, "video"), Verdict::Factor(1.0));
+        assert_eq!(evaluate(&script, "other"), Verdict::Default);
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        let script = load("loop", "fn duck(stream, vad) { loop {} }");
+        assert_eq!(evaluate(&script, "any"), Verdict::Default);
+        let script = load("recurse", "fn duck(stream, vad) { duck(stream, vad) }");
+        assert_eq!(evaluate(&script, "any"), Verdict::Default);
+        let script = load(
+            "string",
+            r#"fn duck(stream, vad) { let s = "x"; loop { s += s; } }"#,
+        );
+        assert_eq!(evaluate(&script, "any"), Verdict::Default);
+    }
+}
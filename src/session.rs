@@ -0,0 +1,147 @@
+use crate::ducking::contains_ci;
+use std::fs;
+use std::process::Command;
+
+/// PipeWire session manager
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SessionManager {
+    WirePlumber,
+    MediaSession,
+    #[default]
+    Unknown,
+}
+
+impl SessionManager {
+    /// from a client's application.name or a process name
+    pub fn from_client(name: &str) -> Option<Self> {
+        if contains_ci(name, "wireplumber") {
+            Some(Self::WirePlumber)
+        } else if contains_ci(name, "media-session") {
+            Some(Self::MediaSession)
+        } else {
+            None
+        }
+    }
+
+    /// running session manager, from /proc
+    ///
+    /// Used before the registry is up, when the volume backend is picked.
+    /// Sees nothing of a remote instance or from inside a sandbox.
+    pub fn detect() -> Self {
+        let Ok(dir) = fs::read_dir("/proc") else {
+            return Self::Unknown;
+        };
+        dir.flatten()
+            .filter_map(|entry| fs::read_to_string(entry.path().join("comm")).ok())
+            .find_map(|comm| Self::from_client(comm.trim()))
+            .unwrap_or_default()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::WirePlumber => "wireplumber",
+            Self::MediaSession => "pipewire-media-session",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// node.name of the default source or sink
+    ///
+    /// wpctl under WirePlumber, else the `default` metadata that
+    /// pipewire-media-session keeps as well.
+    pub fn default_node(self, source: bool) -> Option<String> {
+        if self == Self::WirePlumber {
+            return wpctl_default(source);
+        }
+        metadata_default(source).or_else(|| wpctl_default(source))
+    }
+}
+
+fn wpctl_default(source: bool) -> Option<String> {
+    let target = if source {
+        "@DEFAULT_AUDIO_SOURCE@"
+    } else {
+        "@DEFAULT_AUDIO_SINK@"
+    };
+    let out = Command::new("wpctl")
+        .args(["inspect", target])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.trim().trim_start_matches('*').split_once('=')?;
+            (key.trim() == "node.name").then(|| value.trim().trim_matches('"').to_string())
+        })
+}
+
+fn metadata_default(source: bool) -> Option<String> {
+    let key = if source {
+        "default.audio.source"
+    } else {
+        "default.audio.sink"
+    };
+    let out = Command::new("pw-metadata")
+        .args(["-n", "default", "0", key])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    parse_metadata_default(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// name from `pw-metadata` output, e.g.
+/// `update: id:0 key:'default.audio.sink' value:'{"name":"alsa_output.x"}' type:'Spa:String:JSON'`
+fn parse_metadata_default(text: &str) -> Option<String> {
+    let (_, rest) = text.split_once("\"name\":")?;
+    let rest = rest.trim_start().strip_prefix('"')?;
+    let (name, _) = rest.split_once('"')?;
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_sink_name() {
+        let out = "Found \"default\" metadata 31\n\
+            update: id:0 key:'default.audio.sink' value:'{\"name\":\"alsa_output.pci-0000_00_1f.3.analog-stereo\"}' type:'Spa:String:JSON'\n";
+        assert_eq!(
+            parse_metadata_default(out).as_deref(),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo")
+        );
+        assert_eq!(
+            parse_metadata_default("value:'{ \"name\": \"bluez_output.1\" }'").as_deref(),
+            Some("bluez_output.1")
+        );
+    }
+
+    #[test]
+    fn missing_name() {
+        assert_eq!(parse_metadata_default(""), None);
+        assert_eq!(
+            parse_metadata_default("Found \"default\" metadata 31\n"),
+            None
+        );
+        assert_eq!(
+            parse_metadata_default(
+                "update: id:0 key:'default.audio.sink' value:'{}' type:'Spa:String:JSON'"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn malformed_json() {
+        assert_eq!(
+            parse_metadata_default("value:'{\"name\":alsa_output.x}'"),
+            None
+        );
+        assert_eq!(
+            parse_metadata_default("value:'{\"name\":\"alsa_output.x"),
+            None
+        );
+        assert_eq!(parse_metadata_default("value:'{\"name\":'"), None);
+    }
+}
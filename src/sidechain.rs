@@ -0,0 +1,181 @@
+use crate::ipc::create_runtime_dir;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use tracing::{debug, info, warn};
+
+/// filter-chain sink name
+pub const SINK_NAME: &str = "pw_duck_sidechain";
+
+/// compressor settings
+#[derive(Debug, Copy, Clone)]
+pub struct SidechainParams {
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+/// sidechain compressor engine
+///
+/// Runs a filter-chain (`pipewire -c`) with two swh `sc2` compressors keyed by
+/// a third input channel. The voice stream is linked into that key channel and
+/// other streams are moved onto the sink, so ducking happens sample-accurately
+/// inside the graph and no volume ever needs restoring.
+#[derive(Debug)]
+pub struct Sidechain {
+    child: Child,
+    conf: PathBuf,
+    routed: Vec<u32>,
+}
+
+impl Sidechain {
+    pub fn start(params: SidechainParams) -> io::Result<Self> {
+        let dir = create_runtime_dir()?;
+        let conf = dir.join("sidechain.conf");
+        fs::write(&conf, render_conf(params))?;
+        let child = Command::new("pipewire")
+            .arg("-c")
+            .arg(&conf)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        info!("sidechain filter-chain started (pid={})", child.id());
+        Ok(Self {
+            child,
+            conf,
+            routed: Vec::new(),
+        })
+    }
+
+    /// voice -> key input
+    pub fn link_key(&self, voice_node: &str) -> bool {
+        let mut linked = false;
+        for port in ["output_FL", "output_FR", "output_MONO"] {
+            let ok = Command::new("pw-link")
+                .arg(format!("{voice_node}:{port}"))
+                .arg(format!("{SINK_NAME}:playback_AUX0"))
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success());
+            linked |= ok;
+        }
+        if !linked {
+            warn!("sidechain: could not link voice node \"{voice_node}\" to key input");
+        }
+        linked
+    }
+
+    /// move stream onto the sink
+    pub fn route(&mut self, id: u32) -> bool {
+        if self.routed.contains(&id) {
+            return true;
+        }
+        let ok = Command::new("pw-metadata")
+            .args([&id.to_string(), "target.object", SINK_NAME])
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if ok {
+            debug!("sidechain: routed id={id}");
+            self.routed.push(id);
+        } else {
+            warn!("sidechain: routing id={id} failed");
+        }
+        ok
+    }
+
+    /// stream gone
+    pub fn forget(&mut self, id: u32) {
+        self.routed.retain(|v| *v != id);
+    }
+
+    /// back to its own target, e.g. a stream that became the voice
+    pub fn unroute(&mut self, id: u32) {
+        if !self.routed.contains(&id) {
+            return;
+        }
+        let _ = Command::new("pw-metadata")
+            .args(["-d", &id.to_string(), "target.object"])
+            .stdout(Stdio::null())
+            .status();
+        self.forget(id);
+    }
+}
+
+impl Drop for Sidechain {
+    fn drop(&mut self) {
+        for id in self.routed.drain(..) {
+            let _ = Command::new("pw-metadata")
+                .args(["-d", &id.to_string(), "target.object"])
+                .stdout(Stdio::null())
+                .status();
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_file(&self.conf);
+        info!("sidechain filter-chain stopped");
+    }
+}
+
+/// is our own node
+pub fn is_sidechain_node(node: &str) -> bool {
+    node.starts_with(SINK_NAME)
+}
+
+fn render_conf(p: SidechainParams) -> String {
+    // sc2 control ranges
+    let threshold = p.threshold_db.clamp(-30.0, 0.0);
+    let ratio = p.ratio.clamp(1.0, 10.0);
+    let attack = p.attack_ms.clamp(1.5, 400.0);
+    let release = p.release_ms.clamp(2.0, 800.0);
+    let control = format!(
+        "\"Attack time (ms)\" = {attack} \"Release time (ms)\" = {release} \"Threshold level (dB)\" = {threshold} \"Ratio (1:n)\" = {ratio} \"Knee radius (dB)\" = 3 \"Makeup gain (dB)\" = 0"
+    );
+    format!(
+        r#"# generated by pw-duck
+context.spa-libs = {{
+    audio.convert.* = audioconvert/libspa-audioconvert
+    support.*       = support/libspa-support
+}}
+context.modules = [
+    {{ name = libpipewire-module-rt flags = [ ifexists nofail ] }}
+    {{ name = libpipewire-module-protocol-native }}
+    {{ name = libpipewire-module-client-node }}
+    {{ name = libpipewire-module-adapter }}
+    {{ name = libpipewire-module-filter-chain
+        args = {{
+            node.description = "pw-duck sidechain"
+            media.name       = "pw-duck sidechain"
+            filter.graph = {{
+                nodes = [
+                    {{ type = builtin name = key label = copy }}
+                    {{ type = ladspa name = sc_l plugin = sc2_1403 label = sc2 control = {{ {control} }} }}
+                    {{ type = ladspa name = sc_r plugin = sc2_1403 label = sc2 control = {{ {control} }} }}
+                ]
+                links = [
+                    {{ output = "key:Out" input = "sc_l:Sidechain" }}
+                    {{ output = "key:Out" input = "sc_r:Sidechain" }}
+                ]
+                inputs  = [ "sc_l:Input" "sc_r:Input" "key:In" ]
+                outputs = [ "sc_l:Output" "sc_r:Output" ]
+            }}
+            capture.props = {{
+                node.name      = "{SINK_NAME}"
+                media.class    = Audio/Sink
+                audio.channels = 3
+                audio.position = [ FL FR AUX0 ]
+            }}
+            playback.props = {{
+                node.name      = "{SINK_NAME}.output"
+                node.passive   = true
+                audio.channels = 2
+                audio.position = [ FL FR ]
+            }}
+        }}
+    }}
+]
+"#
+    )
+}
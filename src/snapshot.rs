@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use pipewire as pw;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+use tracing::info;
+
+/// registry roundtrip wait
+const COLLECT_FOR: Duration = Duration::from_millis(250);
+
+/// registry global
+#[derive(Debug, Clone)]
+pub struct Global {
+    pub id: u32,
+    pub kind: pw::types::ObjectType,
+    pub props: BTreeMap<String, String>,
+}
+
+impl Global {
+    pub fn prop(&self, key: &str) -> Option<String> {
+        self.props.get(key).cloned()
+    }
+
+    pub fn media_class(&self) -> &str {
+        self.props.get("media.class").map_or("", String::as_str)
+    }
+}
+
+/// one-shot registry snapshot
+///
+/// Connects, lets the registry announce its globals and disconnects again,
+/// for subcommands that don't run the main loop.
+pub fn collect() -> Result<Vec<Global>> {
+    pw::init();
+    let mainloop = pw::main_loop::MainLoopRc::new(None)?;
+    let context = pw::context::ContextRc::new(&mainloop, None)?;
+    let core = crate::connect_core(&context)?;
+    let registry = core.get_registry_rc()?;
+    let globals: Rc<RefCell<Vec<Global>>> = Rc::new(RefCell::new(Vec::new()));
+    let _listener = {
+        let globals = globals.clone();
+        registry
+            .add_listener_local()
+            .global(move |global| {
+                let props = global
+                    .props
+                    .as_ref()
+                    .map(|p| {
+                        p.iter()
+                            .map(|(k, v)| (k.to_string(), v.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                globals.borrow_mut().push(Global {
+                    id: global.id,
+                    kind: global.type_.clone(),
+                    props,
+                });
+            })
+            .register()
+    };
+    let quit = mainloop.clone();
+    let timer = mainloop.loop_().add_timer(move |_| quit.quit());
+    timer.update_timer(Some(COLLECT_FOR), None).into_result()?;
+    mainloop.run();
+    let mut globals = globals.take();
+    globals.sort_by_key(|g| g.id);
+    Ok(globals)
+}
+
+/// property prefixes kept in a graph dump
+///
+/// Leaves out host, user and security properties.
+const DUMP_KEYS: &[&str] = &[
+    "object.",
+    "node.",
+    "port.",
+    "link.",
+    "client.api",
+    "factory.name",
+    "device.",
+    "audio.",
+    "media.class",
+    "media.name",
+    "media.role",
+    "media.category",
+    "application.name",
+    "application.process.binary",
+    "application.process.id",
+    "target.object",
+    "stream.",
+];
+
+/// props worth keeping for a bug report
+pub fn dump_props(prop: impl IntoIterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    prop.into_iter()
+        .filter(|(k, _)| DUMP_KEYS.iter().any(|p| k.starts_with(p)))
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+struct DumpGlobal<'a> {
+    id: u32,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    props: &'a BTreeMap<String, String>,
+}
+
+/// scored voice candidate
+#[derive(Debug, Serialize)]
+pub struct DumpCandidate {
+    pub id: u32,
+    pub app: String,
+    pub node: String,
+    pub client: Option<String>,
+    pub score: i32,
+}
+
+/// pw-duck's side of a graph dump
+#[derive(Debug, Default, Serialize)]
+pub struct DumpState {
+    pub session_manager: String,
+    pub voice_source_id: Option<u32>,
+    pub voice_source: String,
+    pub voice_source_reason: String,
+    pub capture_node: Option<u32>,
+    pub ducked: bool,
+    pub candidates: Vec<DumpCandidate>,
+    pub baselines: BTreeMap<u32, f32>,
+}
+
+/// `--dump-graph`: observed globals and selection state as JSON
+pub fn write_dump(path: &Path, globals: &BTreeMap<u32, Global>, state: &DumpState) -> Result<()> {
+    #[derive(Serialize)]
+    struct Dump<'a> {
+        version: &'a str,
+        globals: Vec<DumpGlobal<'a>>,
+        state: &'a DumpState,
+    }
+    let dump = Dump {
+        version: env!("CARGO_PKG_VERSION"),
+        globals: globals
+            .values()
+            .map(|g| DumpGlobal {
+                id: g.id,
+                kind: g.kind.to_str(),
+                props: &g.props,
+            })
+            .collect(),
+        state,
+    };
+    fs::write(path, serde_json::to_string_pretty(&dump)?)
+        .with_context(|| format!("--dump-graph {}", path.display()))?;
+    info!("graph dumped to {}", path.display());
+    Ok(())
+}
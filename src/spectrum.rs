@@ -0,0 +1,155 @@
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+
+/// fft size
+const SIZE: usize = 2048;
+/// lowest band edge
+const F_MIN: f32 = 40.0;
+/// highest band edge
+const F_MAX: f32 = 16000.0;
+/// floor of the display range
+const DB_FLOOR: f32 = -80.0;
+/// speech band
+const SPEECH_LO: f32 = 300.0;
+const SPEECH_HI: f32 = 3400.0;
+/// below this total power there is nothing to judge
+const POWER_FLOOR: f32 = 1e-8;
+
+#[derive(Debug)]
+struct Window {
+    rate: u32,
+    channels: usize,
+    ring: Vec<f32>,
+    pos: usize,
+}
+
+/// capture spectrum
+///
+/// Keeps the last `SIZE` mono frames of the capture. The realtime callback
+/// skips a buffer instead of waiting when the UI holds the lock.
+#[derive(Debug, Clone)]
+pub struct Spectrum {
+    window: Arc<Mutex<Window>>,
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Self {
+            window: Arc::new(Mutex::new(Window {
+                rate: 48000,
+                channels: 1,
+                ring: vec![0.0; SIZE],
+                pos: 0,
+            })),
+        }
+    }
+}
+
+impl Spectrum {
+    pub fn format(&self, rate: u32, channels: u32) {
+        if let Ok(mut w) = self.window.lock() {
+            w.rate = rate.max(1);
+            w.channels = (channels as usize).max(1);
+        }
+    }
+
+    /// interleaved samples
+    pub fn samples(&self, samples: &[f32]) {
+        let Ok(mut w) = self.window.try_lock() else {
+            return;
+        };
+        let channels = w.channels;
+        for frame in samples.chunks_exact(channels) {
+            let pos = w.pos;
+            w.ring[pos] = frame.iter().sum::<f32>() / channels as f32;
+            w.pos = (pos + 1) % SIZE;
+        }
+    }
+
+    /// `n` log-spaced bands, 0..1 over -80..0 dBFS
+    pub fn bands(&self, n: usize) -> Vec<f32> {
+        if n == 0 {
+            return Vec::new();
+        }
+        let (power, bin_hz) = self.power();
+        let top = F_MAX.min(bin_hz * SIZE as f32 / 2.0);
+        (0..n)
+            .map(|i| {
+                let lo = F_MIN * (top / F_MIN).powf(i as f32 / n as f32);
+                let hi = F_MIN * (top / F_MIN).powf((i + 1) as f32 / n as f32);
+                let first = ((lo / bin_hz) as usize).clamp(1, SIZE / 2 - 1);
+                let last = ((hi / bin_hz) as usize).clamp(first, SIZE / 2 - 1);
+                let peak = power[first..=last].iter().copied().fold(0.0, f32::max);
+                let db = 10.0 * peak.max(1e-18).log10();
+                ((db - DB_FLOOR) / -DB_FLOOR).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    /// share of power in the speech band, None when silent
+    pub fn speech_ratio(&self) -> Option<f32> {
+        let (power, bin_hz) = self.power();
+        let sum = |lo: f32, hi: f32| -> f32 {
+            let first = ((lo / bin_hz) as usize).clamp(1, SIZE / 2 - 1);
+            let last = ((hi / bin_hz) as usize).clamp(first, SIZE / 2 - 1);
+            power[first..=last].iter().sum()
+        };
+        let total = sum(F_MIN, F_MAX);
+        (total > POWER_FLOOR).then(|| (sum(SPEECH_LO, SPEECH_HI) / total).clamp(0.0, 1.0))
+    }
+
+    /// amplitude squared per bin up to nyquist, and bin width
+    fn power(&self) -> (Vec<f32>, f32) {
+        let (mut buf, rate) = {
+            let w = self.window.lock().unwrap();
+            let mut buf: Vec<(f32, f32)> = Vec::with_capacity(SIZE);
+            buf.extend(w.ring[w.pos..].iter().map(|s| (*s, 0.0)));
+            buf.extend(w.ring[..w.pos].iter().map(|s| (*s, 0.0)));
+            (buf, w.rate as f32)
+        };
+        // hann
+        for (i, s) in buf.iter_mut().enumerate() {
+            s.0 *= 0.5 - 0.5 * (2.0 * PI * i as f32 / SIZE as f32).cos();
+        }
+        fft(&mut buf);
+        // hann gain
+        let scale = 4.0 / SIZE as f32;
+        let power = buf[..SIZE / 2]
+            .iter()
+            .map(|(re, im)| (re * re + im * im) * scale * scale)
+            .collect();
+        (power, rate / SIZE as f32)
+    }
+}
+
+/// in-place radix-2 fft
+fn fft(buf: &mut [(f32, f32)]) {
+    let n = buf.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let step = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (step * k as f32).sin_cos();
+                let (re, im) = buf[start + k + len / 2];
+                let t = (re * cos - im * sin, re * sin + im * cos);
+                let u = buf[start + k];
+                buf[start + k] = (u.0 + t.0, u.1 + t.1);
+                buf[start + k + len / 2] = (u.0 - t.0, u.1 - t.1);
+            }
+        }
+        len <<= 1;
+    }
+}
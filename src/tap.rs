@@ -0,0 +1,186 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// queued buffers before dropping
+const TAP_QUEUE: usize = 64;
+
+/// how often the header sizes are brought up to date
+const HEADER_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+enum TapMsg {
+    Format { rate: u32, channels: u32 },
+    Samples(Vec<f32>),
+}
+
+/// capture tap
+///
+/// Hands analyzed buffers from the realtime callback to a writer thread that
+/// stores them as 32-bit float WAV. Buffers are dropped when the writer lags.
+#[derive(Debug, Clone)]
+pub struct Tap {
+    tx: SyncSender<TapMsg>,
+}
+
+impl Tap {
+    pub fn spawn(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let (tx, rx) = mpsc::sync_channel(TAP_QUEUE);
+        let name = path.display().to_string();
+        thread::Builder::new()
+            .name("pw-duck-tap".into())
+            .spawn(move || {
+                if let Err(err) = write_wav(file, &rx) {
+                    warn!("record tap {name}: {err}");
+                }
+            })?;
+        info!("recording capture tap to {}", path.display());
+        Ok(Self { tx })
+    }
+
+    pub fn format(&self, rate: u32, channels: u32) {
+        let _ = self.tx.try_send(TapMsg::Format { rate, channels });
+    }
+
+    pub fn samples(&self, samples: Vec<f32>) {
+        // dropped when the writer lags
+        let _ = self.tx.try_send(TapMsg::Samples(samples));
+    }
+}
+
+fn write_wav(file: File, rx: &Receiver<TapMsg>) -> io::Result<()> {
+    let mut out = BufWriter::new(file);
+    let mut format: Option<(u32, u32)> = None;
+    let mut data_bytes: u32 = 0;
+    let mut header_at = Instant::now();
+    for msg in rx {
+        match msg {
+            TapMsg::Format { rate, channels } => match format {
+                None => {
+                    write_header(&mut out, rate, channels, 0)?;
+                    format = Some((rate, channels));
+                }
+                Some(f) if f != (rate, channels) => {
+                    warn!("record tap: format changed to {rate} Hz/{channels} ch, keeping {} Hz/{} ch", f.0, f.1);
+                }
+                Some(_) => {}
+            },
+            TapMsg::Samples(samples) => {
+                let Some(f) = format else {
+                    continue;
+                };
+                for s in &samples {
+                    out.write_all(&s.to_le_bytes())?;
+                }
+                data_bytes = data_bytes.saturating_add((samples.len() * 4) as u32);
+                // keep sizes valid if we get killed
+                if header_at.elapsed() >= HEADER_INTERVAL {
+                    rewrite_header(&mut out, f, data_bytes)?;
+                    header_at = Instant::now();
+                }
+            }
+        }
+    }
+    if let Some(f) = format {
+        rewrite_header(&mut out, f, data_bytes)?;
+    }
+    out.flush()
+}
+
+/// sizes in place, then back to the end
+fn rewrite_header(
+    out: &mut BufWriter<File>,
+    (rate, channels): (u32, u32),
+    data_bytes: u32,
+) -> io::Result<()> {
+    out.seek(SeekFrom::Start(0))?;
+    write_header(out, rate, channels, data_bytes)?;
+    out.seek(SeekFrom::End(0))?;
+    Ok(())
+}
+
+/// RIFF header for IEEE float PCM
+fn write_header(out: &mut impl Write, rate: u32, channels: u32, data_bytes: u32) -> io::Result<()> {
+    let channels = u16::try_from(channels).unwrap_or(1);
+    let block_align = channels * 4;
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_bytes).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&3u16.to_le_bytes())?;
+    out.write_all(&channels.to_le_bytes())?;
+    out.write_all(&rate.to_le_bytes())?;
+    out.write_all(&(rate * u32::from(block_align)).to_le_bytes())?;
+    out.write_all(&block_align.to_le_bytes())?;
+    out.write_all(&32u16.to_le_bytes())?;
+    out.write_all(b"data")?;
+    out.write_all(&data_bytes.to_le_bytes())
+}
+
+/// decoded WAV
+#[derive(Debug)]
+pub struct Wav {
+    pub rate: u32,
+    pub channels: u16,
+    /// interleaved
+    pub samples: Vec<f32>,
+}
+
+/// read 16-bit PCM or 32-bit float WAV
+pub fn read_wav(path: &Path) -> io::Result<Wav> {
+    let bad = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let data = std::fs::read(path)?;
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(bad("not a RIFF/WAVE file"));
+    }
+    let mut fmt: Option<(u16, u16, u32, u16)> = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let len = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let body = data.get(pos + 8..pos + 8 + len).unwrap_or(&data[pos + 8..]);
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let u16_at = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+                let mut tag = u16_at(0);
+                // WAVE_FORMAT_EXTENSIBLE
+                if tag == 0xFFFE && body.len() >= 26 {
+                    tag = u16_at(24);
+                }
+                let rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                fmt = Some((tag, u16_at(2), rate, u16_at(14)));
+            }
+            b"data" => {
+                let (tag, channels, rate, bits) = fmt.ok_or_else(|| bad("data before fmt"))?;
+                let samples = match (tag, bits) {
+                    (3, 32) => body
+                        .chunks_exact(4)
+                        .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+                        .collect(),
+                    (1, 16) => body
+                        .chunks_exact(2)
+                        .map(|c| f32::from(i16::from_le_bytes([c[0], c[1]])) / f32::from(i16::MAX))
+                        .collect(),
+                    _ => return Err(bad("only 16-bit PCM and 32-bit float WAV are supported")),
+                };
+                if channels == 0 {
+                    return Err(bad("zero channels"));
+                }
+                return Ok(Wav {
+                    rate,
+                    channels,
+                    samples,
+                });
+            }
+            _ => {}
+        }
+        // chunks are word aligned
+        pos += 8 + len + (len & 1);
+    }
+    Err(bad("no data chunk"))
+}
@@ -0,0 +1,76 @@
+use anyhow::{anyhow, Result};
+use pipewire as pw;
+use pw::spa::param::audio::{AudioFormat, AudioInfoRaw};
+use pw::spa::pod::Pod;
+use pw::{properties::properties, spa};
+
+/// test stream rate
+pub const RATE: u32 = 48000;
+
+pub type TestStream<'c> = (pw::stream::StreamBox<'c>, pw::stream::StreamListener<()>);
+
+/// mono F32 playback stream filled by `fill`
+#[allow(clippy::cast_possible_truncation)]
+pub fn test_stream<'c>(
+    core: &'c pw::core::CoreRc,
+    name: &str,
+    fill: impl Fn(&mut [f32]) + 'static,
+) -> Result<TestStream<'c>> {
+    let props = properties! {
+        *pw::keys::MEDIA_TYPE => "Audio",
+        *pw::keys::MEDIA_CATEGORY => "Playback",
+        *pw::keys::MEDIA_ROLE => "Music",
+        *pw::keys::NODE_NAME => name,
+    };
+    let stream = pw::stream::StreamBox::new(core, name, props)?;
+    let listener = stream
+        .add_local_listener_with_user_data(())
+        .process(move |stream, ()| {
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                return;
+            };
+            let datas = buffer.datas_mut();
+            let Some(data) = datas.first_mut() else {
+                return;
+            };
+            let stride = std::mem::size_of::<f32>();
+            let frames = data.data().map_or(0, |bytes| {
+                let mut frame = vec![0.0f32; bytes.len() / stride];
+                fill(&mut frame);
+                for (dst, s) in bytes.chunks_exact_mut(stride).zip(&frame) {
+                    dst.copy_from_slice(&s.to_le_bytes());
+                }
+                frame.len()
+            });
+            let chunk = data.chunk_mut();
+            *chunk.offset_mut() = 0;
+            *chunk.stride_mut() = stride as i32;
+            *chunk.size_mut() = (frames * stride) as u32;
+        })
+        .register()?;
+
+    let mut audio_info = AudioInfoRaw::new();
+    audio_info.set_format(AudioFormat::F32LE);
+    audio_info.set_rate(RATE);
+    audio_info.set_channels(1);
+    let obj = pw::spa::pod::Object {
+        type_: pw::spa::utils::SpaTypes::ObjectParamFormat.as_raw(),
+        id: pw::spa::param::ParamType::EnumFormat.as_raw(),
+        properties: audio_info.into(),
+    };
+    let serialized = pw::spa::pod::serialize::PodSerializer::serialize(
+        std::io::Cursor::new(Vec::new()),
+        &pw::spa::pod::Value::Object(obj),
+    )
+    .map_err(|_| anyhow!("format pod serialization failed"))?
+    .0
+    .into_inner();
+    let mut params = [Pod::from_bytes(&serialized).ok_or_else(|| anyhow!("invalid format pod"))?];
+    stream.connect(
+        spa::utils::Direction::Output,
+        None,
+        pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+        &mut params,
+    )?;
+    Ok((stream, listener))
+}
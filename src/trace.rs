@@ -0,0 +1,94 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// rows between flushes, about a second of VAD ticks
+const FLUSH_ROWS: u64 = 20;
+
+#[derive(Serialize)]
+struct Row {
+    ts_ms: u128,
+    energy: f32,
+    threshold: f32,
+    voice_active: bool,
+    applied_duck: bool,
+}
+
+/// energy and VAD timeline
+///
+/// One row per VAD tick, CSV unless the path ends in `.json` or `.jsonl`,
+/// which gives one JSON object per line.
+#[derive(Debug)]
+pub struct Trace {
+    out: BufWriter<File>,
+    json: bool,
+    rows: u64,
+    failed: bool,
+}
+
+impl Trace {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let json = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json") || e.eq_ignore_ascii_case("jsonl"));
+        let mut out = BufWriter::new(File::create(path)?);
+        if !json {
+            writeln!(out, "ts_ms,energy,threshold,voice_active,applied_duck")?;
+        }
+        info!("tracing VAD timeline to {}", path.display());
+        Ok(Self {
+            out,
+            json,
+            rows: 0,
+            failed: false,
+        })
+    }
+
+    pub fn sample(&mut self, energy: f32, threshold: f32, voice_active: bool, applied_duck: bool) {
+        if self.failed {
+            return;
+        }
+        let row = Row {
+            ts_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default(),
+            energy,
+            threshold,
+            voice_active,
+            applied_duck,
+        };
+        let result = self.write(&row).and_then(|()| {
+            self.rows += 1;
+            if self.rows.is_multiple_of(FLUSH_ROWS) {
+                self.out.flush()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = result {
+            warn!("trace: {err}, stopped");
+            self.failed = true;
+        }
+    }
+
+    fn write(&mut self, row: &Row) -> io::Result<()> {
+        if self.json {
+            serde_json::to_writer(&mut self.out, row)?;
+            writeln!(self.out)
+        } else {
+            writeln!(
+                self.out,
+                "{},{:.6},{:.6},{},{}",
+                row.ts_ms,
+                row.energy,
+                row.threshold,
+                u8::from(row.voice_active),
+                u8::from(row.applied_duck)
+            )
+        }
+    }
+}
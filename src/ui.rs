@@ -1,9 +1,11 @@
-use crate::analysis::{VadSnapshot, VadState};
-use crate::ducking::{OutputStream, RestoreGuard};
-use crate::logging::elogln;
+use crate::analysis::VadSnapshot;
+use crate::control::{self, Control};
+use crate::ducking::{contains_ci, OutputStream, RestoreGuard};
+use crate::messages::{self, Msg};
+use crate::meter::Meters;
 use crate::ControlMode;
 use crossterm::cursor::{Hide, MoveTo, Show};
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
 };
@@ -12,35 +14,55 @@ use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Gauge, Paragraph};
+use ratatui::widgets::{Block, Borders, Clear as ClearWidget, Gauge, Paragraph, Sparkline, Tabs};
 use ratatui::Terminal;
-use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use signal_hook::consts::signal::SIGSTOP;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::io;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tracing::trace;
+
+/// live parameter ranges
+pub const SENS_MIN: f32 = 0.0025;
+pub const SENS_MAX: f32 = 0.2;
+pub const HOLD_MAX_MS: u64 = 2000;
+/// key steps
+pub const SENS_STEP: f32 = 0.0025;
+pub const DUCK_STEP_PCT: f32 = 5.0;
+pub const HOLD_STEP_MS: u64 = 50;
+
+/// span of the timeline widget
+const TIMELINE_SPAN: Duration = Duration::from_secs(60);
 
 thread_local! {
     static UI_TERMINAL: RefCell<Option<Terminal<CrosstermBackend<io::Stdout>>>> =
         const { RefCell::new(None) };
+    /// (time, voice active, ducked) per rendered frame
+    static TIMELINE: RefCell<VecDeque<(Instant, bool, bool)>> =
+        const { RefCell::new(VecDeque::new()) };
 }
 
 pub struct GuiModeGuard;
 
 impl Drop for GuiModeGuard {
     fn drop(&mut self) {
-        let _ = disable_raw_mode();
-        let mut stdout = io::stdout();
-        let _ = stdout.execute(Show);
-        UI_TERMINAL.with(|term| {
-            let _ = term.borrow_mut().take();
-        });
-        let _ = stdout.execute(LeaveAlternateScreen);
+        leave_terminal();
     }
 }
 
 pub fn enter_gui_mode() -> io::Result<GuiModeGuard> {
+    enter_terminal()?;
+    Ok(GuiModeGuard)
+}
+
+/// raw mode and alternate screen
+fn enter_terminal() -> io::Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     let _ = stdout.execute(EnterAlternateScreen);
@@ -53,7 +75,144 @@ pub fn enter_gui_mode() -> io::Result<GuiModeGuard> {
     UI_TERMINAL.with(|term| {
         *term.borrow_mut() = Some(terminal);
     });
-    Ok(GuiModeGuard)
+    Ok(())
+}
+
+fn leave_terminal() {
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    let _ = stdout.execute(Show);
+    UI_TERMINAL.with(|term| {
+        let _ = term.borrow_mut().take();
+    });
+    let _ = stdout.execute(LeaveAlternateScreen);
+}
+
+/// stop the process like Ctrl+Z in a cooked terminal
+///
+/// Leaves raw mode first and re-enters it once continued. Returns after
+/// SIGCONT.
+pub fn suspend(gui: bool) -> io::Result<()> {
+    if gui {
+        leave_terminal();
+    }
+    signal_hook::low_level::raise(SIGSTOP)?;
+    if gui {
+        enter_terminal()?;
+    }
+    Ok(())
+}
+
+/// stream pane row, one per application
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupRow {
+    pub label: String,
+    pub streams: usize,
+    pub state: String,
+    /// baseline -> live volume
+    #[serde(default)]
+    pub volume: String,
+    /// live volume is not what pw-duck set
+    #[serde(default)]
+    pub overridden: bool,
+    /// sinks it plays to
+    #[serde(default)]
+    pub sink: String,
+}
+
+/// TUI views
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Tab {
+    #[default]
+    Dashboard,
+    Streams,
+    Log,
+    Settings,
+}
+
+impl Tab {
+    const ALL: [Self; 4] = [Self::Dashboard, Self::Streams, Self::Log, Self::Settings];
+
+    const fn title(self) -> Msg {
+        match self {
+            Self::Dashboard => Msg::Dashboard,
+            Self::Streams => Msg::Streams,
+            Self::Log => Msg::Log,
+            Self::Settings => Msg::Settings,
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|t| *t == self).unwrap_or(0)
+    }
+}
+
+/// settings tab rows: title, `set` parameter, keys that step down and up
+const SETTINGS: [(Msg, &str, char, char); 3] = [
+    (Msg::Sensitivity, "threshold", 's', 'w'),
+    (Msg::DuckAmount, "duck", 'a', 'd'),
+    (Msg::Hold, "hold", 'q', 'e'),
+];
+
+/// selected tab and settings row
+#[derive(Debug, Default)]
+pub struct View {
+    tab: Cell<Tab>,
+    row: Cell<usize>,
+    confirm_quit: Cell<bool>,
+}
+
+/// quit dialog contents
+#[derive(Debug)]
+pub struct QuitSummary {
+    /// apps that get their volume back
+    pub restoring: Vec<String>,
+    /// restores that failed or are still retried
+    pub failed: usize,
+}
+
+impl View {
+    /// quit dialog open
+    pub fn confirming_quit(&self) -> bool {
+        self.confirm_quit.get()
+    }
+
+    /// Tab, Shift+Tab and 1-4 switch views, Up/Down pick a settings row;
+    /// false for any other key
+    pub fn handle_key(&self, code: KeyCode) -> bool {
+        let tab = self.tab.get();
+        let count = Tab::ALL.len();
+        match code {
+            KeyCode::Tab => self.tab.set(Tab::ALL[(tab.index() + 1) % count]),
+            KeyCode::BackTab => self.tab.set(Tab::ALL[(tab.index() + count - 1) % count]),
+            KeyCode::Char(c @ '1'..='4') => {
+                self.tab.set(Tab::ALL[usize::from(c as u8 - b'1')]);
+            }
+            KeyCode::Up if tab == Tab::Settings => {
+                self.row.set(self.row.get().saturating_sub(1));
+            }
+            KeyCode::Down if tab == Tab::Settings => {
+                self.row.set((self.row.get() + 1).min(SETTINGS.len() - 1));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Left/Right on the settings tab as the W/S, A/D or Q/E key of the row
+    pub fn setting_key(&self, code: KeyCode) -> Option<char> {
+        let (_, _, down, up) = SETTINGS[self.row.get()];
+        match (self.tab.get(), code) {
+            (Tab::Settings, KeyCode::Left) => Some(down),
+            (Tab::Settings, KeyCode::Right) => Some(up),
+            _ => None,
+        }
+    }
+
+    /// `set` parameter of the selected settings row
+    pub fn setting_param(&self) -> Option<&'static str> {
+        (self.tab.get() == Tab::Settings).then(|| SETTINGS[self.row.get()].1)
+    }
 }
 
 pub enum GuiSelectResult {
@@ -62,156 +221,282 @@ pub enum GuiSelectResult {
     Quit,
 }
 
+/// `routes` maps stream ids to the sinks they play to; `quit` (a signal or
+/// `--takeover`) leaves the chooser like `q`
 pub fn select_voice_source_gui(
     list: &[OutputStream],
+    routes: &HashMap<u32, String>,
     default_index: usize,
+    quit: &AtomicBool,
 ) -> io::Result<GuiSelectResult> {
-    let mut cursor = default_index.min(list.len().saturating_sub(1));
+    let mut filter = String::new();
+    // typing after `/`
+    let mut typing = false;
+    let mut selected = default_index;
+    let mut meters = Meters::default();
     loop {
-        render_gui_selection(list, cursor);
+        if quit.load(Ordering::Relaxed) {
+            return Ok(GuiSelectResult::Quit);
+        }
+        let shown: Vec<usize> = (0..list.len())
+            .filter(|i| matches_filter(&list[*i], &filter))
+            .collect();
+        let mut cursor = shown.iter().position(|i| *i == selected).unwrap_or(0);
+        let rows = selection_rows();
+        let offset = cursor.saturating_sub(rows.saturating_sub(1));
+        let visible = &shown[offset..shown.len().min(offset + rows)];
+        meters.show(&visible.iter().map(|i| &list[*i]).collect::<Vec<_>>());
+        render_gui_selection(
+            list,
+            routes,
+            visible,
+            cursor - offset,
+            &meters,
+            typing.then_some(filter.as_str()),
+        );
         if event::poll(Duration::from_millis(50))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind != KeyEventKind::Press {
                     continue;
                 }
                 match key.code {
+                    KeyCode::Char(c) if typing => filter.push(c),
+                    KeyCode::Backspace if typing => {
+                        filter.pop();
+                    }
+                    KeyCode::Char('/') => typing = true,
                     KeyCode::Up | KeyCode::Char('k') => {
                         cursor = cursor.saturating_sub(1);
                     }
                     KeyCode::Down | KeyCode::Char('j') => {
-                        if cursor + 1 < list.len() {
+                        if cursor + 1 < shown.len() {
                             cursor += 1;
                         }
                     }
                     KeyCode::Enter => {
-                        if !list.is_empty() {
-                            return Ok(GuiSelectResult::Selected(cursor));
+                        if let Some(i) = shown.get(cursor) {
+                            return Ok(GuiSelectResult::Selected(*i));
                         }
                     }
+                    // clear the filter first
+                    KeyCode::Esc if typing || !filter.is_empty() => {
+                        typing = false;
+                        filter.clear();
+                    }
                     KeyCode::Char('r') => return Ok(GuiSelectResult::Refresh),
                     KeyCode::Esc | KeyCode::Char('q') => return Ok(GuiSelectResult::Quit),
                     _ => {}
                 }
+                if let Some(i) = shown.get(cursor) {
+                    selected = *i;
+                }
             }
         }
     }
 }
 
+/// mini meter, scaled like the voice gauge
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn level_bar(level: Option<f32>) -> Vec<Span<'static>> {
+    const WIDTH: usize = 8;
+    let Some(level) = level else {
+        return vec![Span::raw(format!(" {} ", " ".repeat(WIDTH)))];
+    };
+    let filled = ((level * 20.0).clamp(0.0, 1.0) * WIDTH as f32).round() as usize;
+    let color = if filled > 0 {
+        Color::Green
+    } else {
+        Color::DarkGray
+    };
+    vec![
+        Span::styled(
+            format!(" {}", "█".repeat(filled)),
+            Style::default().fg(color),
+        ),
+        Span::styled(
+            format!("{} ", "·".repeat(WIDTH - filled)),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]
+}
+
+/// list rows that fit under the title, above the help line
+fn selection_rows() -> usize {
+    crossterm::terminal::size().map_or(20, |(_, h)| usize::from(h.saturating_sub(6)).max(1))
+}
+
+/// case-insensitive substring of app, node or media name
+fn matches_filter(s: &OutputStream, filter: &str) -> bool {
+    filter.is_empty()
+        || contains_ci(&s.app, filter)
+        || contains_ci(&s.node, filter)
+        || contains_ci(&s.media, filter)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn handle_gui_input(
     guard_t: &Rc<RefCell<Option<Arc<Mutex<RestoreGuard>>>>>,
-    mode_t: &Rc<RefCell<ControlMode>>,
-    vad_t: &Rc<RefCell<VadState>>,
     gui_log_t: &Rc<RefCell<Vec<String>>>,
     quit_flag_t: &Arc<AtomicBool>,
+    suspend_flag: &AtomicBool,
     threshold_live: &Rc<RefCell<f32>>,
     duck_factor_live: &Rc<RefCell<f32>>,
     hold_live: &Rc<RefCell<u64>>,
-    gui_enabled: bool,
+    switch_accept: &Cell<bool>,
+    show_spectrum: &Cell<bool>,
+    view: &View,
+    prompt: &RefCell<Option<String>>,
+    control_tx: &Sender<Control>,
 ) {
-    const SENS_STEP: f32 = 0.0025;
-    const SENS_MIN: f32 = 0.0025;
-    const SENS_MAX: f32 = 0.2;
-    const DUCK_STEP_PCT: f32 = 5.0;
-    const HOLD_STEP_MS: u64 = 50;
-    const HOLD_MAX_MS: u64 = 2000;
-
+    let quit = || {
+        gui_log_t.borrow_mut().push(Msg::QuitViaGui.text().into());
+        quit_flag_t.store(true, Ordering::Relaxed);
+    };
+    // confirm first while streams are lowered
+    let ask_quit = || {
+        let ducked = guard_t.borrow().as_ref().is_some_and(|g| {
+            let g = g.lock().unwrap();
+            g.ducked && !g.is_armed()
+        });
+        if ducked {
+            view.confirm_quit.set(true);
+        } else {
+            quit();
+        }
+    };
     while event::poll(std::time::Duration::from_millis(0)).unwrap_or(false) {
         if let Ok(Event::Key(key)) = event::read() {
-            elogln(
-                gui_enabled,
-                format!(
-                    "key={:?} mods={:?} kind={:?}",
-                    key.code, key.modifiers, key.kind
-                ),
+            trace!(
+                "key={:?} mods={:?} kind={:?}",
+                key.code,
+                key.modifiers,
+                key.kind
             );
             if key.kind != KeyEventKind::Press {
                 continue;
             }
-            match key.code {
+            if view.confirm_quit.get() {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Char('y' | 'Y' | 'j' | 'J') => quit(),
+                    KeyCode::Esc | KeyCode::Char('n' | 'N') => view.confirm_quit.set(false),
+                    _ => {}
+                }
+                continue;
+            }
+            if prompt.borrow().is_some() {
+                if let Some(line) = edit_prompt(&mut prompt.borrow_mut(), key.code) {
+                    match control::parse(&line) {
+                        Ok(ctl) => {
+                            let _ = control_tx.send(ctl);
+                        }
+                        Err(err) => gui_log_t.borrow_mut().push(format!(":{line}: {err}")),
+                    }
+                }
+                continue;
+            }
+            if view.handle_key(key.code) {
+                continue;
+            }
+            if is_save_key(key) {
+                let _ = control_tx.send(Control::Save);
+                continue;
+            }
+            if is_suspend_key(key) {
+                suspend_flag.store(true, Ordering::Relaxed);
+                continue;
+            }
+            if key.code == KeyCode::Enter {
+                if let Some(param) = view.setting_param() {
+                    *prompt.borrow_mut() = Some(format!("set {param} "));
+                }
+                continue;
+            }
+            match view.setting_key(key.code).map_or(key.code, KeyCode::Char) {
+                KeyCode::Char(':') => {
+                    *prompt.borrow_mut() = Some(String::new());
+                }
                 KeyCode::Char(c) => {
                     if c == ' ' {
-                        let mut mode = mode_t.borrow_mut();
-                        if *mode == ControlMode::AutoVad {
-                            *mode = ControlMode::ManualRestored;
-                            if let Some(guard) = guard_t.borrow().as_ref() {
-                                let mut guard = guard.lock().unwrap();
-                                if guard.ducked {
-                                    guard.restore();
-                                }
-                            }
-                            gui_log_t.borrow_mut().push("mode -> ManualRestored".into());
-                        } else {
-                            // reset VAD
-                            *mode = ControlMode::AutoVad;
-                            {
-                                let mut vad = vad_t.borrow_mut();
-                                vad.voice_active = false;
-                                vad.above_start = None;
-                                vad.last_above = None;
-                            }
-                            if let Some(guard) = guard_t.borrow().as_ref() {
-                                let mut guard = guard.lock().unwrap();
-                                if guard.ducked {
-                                    guard.restore();
-                                }
-                            }
-                            gui_log_t.borrow_mut().push("mode -> AutoVad".into());
-                        }
+                        let _ = control_tx.send(Control::Toggle);
                         continue;
                     }
                     let lower = c.to_ascii_lowercase();
                     if lower == 'w' {
                         let mut thr = threshold_live.borrow_mut();
                         *thr = (*thr + SENS_STEP).clamp(SENS_MIN, SENS_MAX);
-                        gui_log_t
-                            .borrow_mut()
-                            .push(format!("threshold -> {:.4}", *thr));
+                        gui_log_t.borrow_mut().push(messages::threshold_set(*thr));
                     } else if lower == 's' {
                         let mut thr = threshold_live.borrow_mut();
                         *thr = (*thr - SENS_STEP).clamp(SENS_MIN, SENS_MAX);
-                        gui_log_t
-                            .borrow_mut()
-                            .push(format!("threshold -> {:.4}", *thr));
+                        gui_log_t.borrow_mut().push(messages::threshold_set(*thr));
                     } else if lower == 'a' {
                         let mut factor = duck_factor_live.borrow_mut();
                         let mut pct = (1.0 - *factor) * 100.0;
                         pct = (pct - DUCK_STEP_PCT).clamp(0.0, 100.0);
                         *factor = (1.0 - pct / 100.0).clamp(0.0, 1.0);
-                        gui_log_t
-                            .borrow_mut()
-                            .push(format!("duck -> {pct:.0}%"));
+                        gui_log_t.borrow_mut().push(messages::duck_set(pct));
                     } else if lower == 'd' {
                         let mut factor = duck_factor_live.borrow_mut();
                         let mut pct = (1.0 - *factor) * 100.0;
                         pct = (pct + DUCK_STEP_PCT).clamp(0.0, 100.0);
                         *factor = (1.0 - pct / 100.0).clamp(0.0, 1.0);
-                        gui_log_t
-                            .borrow_mut()
-                            .push(format!("duck -> {pct:.0}%"));
+                        gui_log_t.borrow_mut().push(messages::duck_set(pct));
                     } else if lower == 'q' {
                         let mut hold = hold_live.borrow_mut();
                         *hold = hold.saturating_sub(HOLD_STEP_MS);
-                        gui_log_t.borrow_mut().push(format!("hold -> {} ms", *hold));
+                        gui_log_t.borrow_mut().push(messages::hold_set(*hold));
                     } else if lower == 'e' {
                         let mut hold = hold_live.borrow_mut();
                         *hold = (*hold + HOLD_STEP_MS).min(HOLD_MAX_MS);
-                        gui_log_t.borrow_mut().push(format!("hold -> {} ms", *hold));
+                        gui_log_t.borrow_mut().push(messages::hold_set(*hold));
+                    } else if lower == 'v' {
+                        switch_accept.set(true);
+                    } else if lower == 'f' {
+                        show_spectrum.set(!show_spectrum.get());
+                    } else if lower == 'o' {
+                        let _ = control_tx.send(Control::Focus);
                     } else if lower == 'x' {
-                        gui_log_t.borrow_mut().push("quit requested via gui".into());
-                        quit_flag_t.store(true, Ordering::Relaxed);
+                        ask_quit();
                     }
                 }
-                KeyCode::Esc => {
-                    gui_log_t.borrow_mut().push("quit requested via gui".into());
-                    quit_flag_t.store(true, Ordering::Relaxed);
-                }
+                KeyCode::Esc => ask_quit(),
                 _ => {}
             }
         }
     }
 }
 
+/// Ctrl+Z, raw mode swallows SIGTSTP
+pub fn is_suspend_key(key: KeyEvent) -> bool {
+    key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// Ctrl+S
+pub fn is_save_key(key: KeyEvent) -> bool {
+    key.code == KeyCode::Char('s') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// one key at the `:` prompt, the line once Enter is pressed
+///
+/// Esc, or Backspace on an empty line, closes the prompt.
+pub fn edit_prompt(prompt: &mut Option<String>, code: KeyCode) -> Option<String> {
+    let buf = prompt.as_mut()?;
+    match code {
+        KeyCode::Char(c) => buf.push(c),
+        KeyCode::Backspace if !buf.is_empty() => {
+            buf.pop();
+        }
+        KeyCode::Backspace | KeyCode::Esc => *prompt = None,
+        KeyCode::Enter => return prompt.take().filter(|line| !line.trim().is_empty()),
+        _ => {}
+    }
+    None
+}
+
 #[allow(
     clippy::cast_lossless,
     clippy::cast_precision_loss,
@@ -223,58 +508,112 @@ pub fn handle_gui_input(
 pub fn render_gui(
     label: String,
     reason: String,
+    scene: &str,
     mode: ControlMode,
     snapshot: &VadSnapshot,
     energy: f32,
     threshold_live: f32,
     duck_factor_live: f32,
     hold_ms: u64,
+    groups: &[GroupRow],
+    spectrum: Option<&[f32]>,
+    view: &View,
+    quit: Option<&QuitSummary>,
+    keys: Msg,
+    prompt: Option<&str>,
     log: &[String],
 ) {
-    let _ = log;
     UI_TERMINAL.with(|term| {
         let mut term_ref = term.borrow_mut();
         let Some(terminal) = term_ref.as_mut() else {
             return;
         };
-        let auto_on = mode == ControlMode::AutoVad;
         let voice_active = snapshot.voice_active;
         let ducking_on = snapshot.applied_duck;
         let level = (energy * 20.0).clamp(0.0, 1.0);
         let sens_fill = (1.0 - threshold_live).clamp(0.0, 1.0);
         let duck_fill = (1.0 - duck_factor_live).clamp(0.0, 1.0);
         let hold_fill = (hold_ms as f32 / 1000.0).clamp(0.0, 1.0);
+        let now = Instant::now();
+        TIMELINE.with(|t| {
+            let mut t = t.borrow_mut();
+            t.push_back((now, voice_active, ducking_on));
+            while t.front().is_some_and(|(at, ..)| now - *at > TIMELINE_SPAN) {
+                t.pop_front();
+            }
+        });
 
         let _ = terminal.draw(|f| {
-            let size = f.size();
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
+                    Constraint::Length(1),
                     Constraint::Length(3),
-                    Constraint::Length(6),
-                    Constraint::Length(9),
+                    Constraint::Min(3),
                     Constraint::Length(3),
                 ])
-                .split(size);
+                .split(f.size());
 
-            draw_status(f, chunks[0], &label, &reason, auto_on, ducking_on);
-            draw_voice(f, chunks[1], level, voice_active);
-            draw_controls(
-                f,
-                chunks[2],
-                threshold_live,
-                duck_factor_live,
-                hold_ms,
-                sens_fill,
-                duck_fill,
-                hold_fill,
-            );
-            draw_help(f, chunks[3]);
+            draw_tabs(f, chunks[0], view.tab.get());
+            draw_status(f, chunks[1], &label, &reason, scene, mode, ducking_on);
+            let body = chunks[2];
+            match view.tab.get() {
+                Tab::Dashboard => {
+                    let spectrum_rows = if spectrum.is_some() { 8 } else { 0 };
+                    let rows = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([
+                            Constraint::Length(6),
+                            Constraint::Length(4),
+                            Constraint::Length(9),
+                            Constraint::Length(spectrum_rows),
+                            Constraint::Min(0),
+                        ])
+                        .split(body);
+                    draw_voice(f, rows[0], level, voice_active, snapshot.confidence);
+                    TIMELINE.with(|t| draw_timeline(f, rows[1], &t.borrow(), now));
+                    draw_controls(
+                        f,
+                        rows[2],
+                        threshold_live,
+                        duck_factor_live,
+                        hold_ms,
+                        sens_fill,
+                        duck_fill,
+                        hold_fill,
+                    );
+                    if let Some(spectrum) = spectrum {
+                        draw_spectrum(f, rows[3], spectrum);
+                    }
+                }
+                Tab::Streams => draw_streams(f, body, groups),
+                Tab::Log => draw_log(f, body, log),
+                Tab::Settings => {
+                    let values = [
+                        format!("{:.4}", threshold_live),
+                        format!("{:.0}%", (1.0 - duck_factor_live) * 100.0),
+                        format!("{} ms", hold_ms),
+                    ];
+                    draw_settings(f, body, &values, view.row.get());
+                }
+            }
+            draw_help(f, chunks[3], keys, prompt);
+            if let Some(quit) = quit {
+                draw_quit(f, body, quit);
+            }
         });
     });
 }
 
-fn render_gui_selection(list: &[OutputStream], cursor: usize) {
+/// `shown` indexes `list`, `filter` is set while typing one
+fn render_gui_selection(
+    list: &[OutputStream],
+    routes: &HashMap<u32, String>,
+    shown: &[usize],
+    cursor: usize,
+    meters: &Meters,
+    filter: Option<&str>,
+) {
     UI_TERMINAL.with(|term| {
         let mut term_ref = term.borrow_mut();
         let Some(terminal) = term_ref.as_mut() else {
@@ -288,47 +627,90 @@ fn render_gui_selection(list: &[OutputStream], cursor: usize) {
                 .split(size);
 
             let mut lines: Vec<Line> = Vec::with_capacity(list.len() + 2);
-            lines.push(Line::from(vec![
-                Span::styled("Select voice source", Style::default().fg(Color::Yellow)),
-            ]));
+            lines.push(Line::from(vec![Span::styled(
+                Msg::SelectVoiceSource.text(),
+                Style::default().fg(Color::Yellow),
+            )]));
             if list.is_empty() {
-                lines.push(Line::from(vec![
-                    Span::styled("No outputs yet. Press r to refresh.", Style::default()),
-                ]));
+                lines.push(Line::from(vec![Span::styled(
+                    Msg::NoOutputsYet.text(),
+                    Style::default(),
+                )]));
+            } else if shown.is_empty() {
+                lines.push(Line::from(vec![Span::styled(
+                    Msg::NoMatches.text(),
+                    Style::default().fg(Color::DarkGray),
+                )]));
             }
-            for (i, s) in list.iter().enumerate() {
-                let marker = if i == cursor { ">" } else { " " };
+            for (row, &i) in shown.iter().enumerate() {
+                let s = &list[i];
+                let marker = if row == cursor { ">" } else { " " };
+                let tag = match s.media_class.as_str() {
+                    "Audio/Source" => Msg::TagSource.text(),
+                    "Stream/Input/Audio" => Msg::TagMicClient.text(),
+                    _ => "",
+                };
                 let text = format!(
-                    "{} [{:02}] id={} app=\"{}\" media=\"{}\" node=\"{}\"",
+                    "{} [{:02}] id={} app=\"{}\" media=\"{}\" node=\"{}\"{}",
                     marker,
                     i + 1,
                     s.id,
                     s.app,
                     s.media,
-                    s.node
+                    s.node,
+                    tag
                 );
-                lines.push(Line::raw(text));
+                let mut spans = level_bar(meters.level(s.id));
+                spans.push(Span::raw(text));
+                if let Some(sink) = routes.get(&s.id).filter(|r| !r.is_empty()) {
+                    spans.push(Span::styled(
+                        format!(" -> {sink}"),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                lines.push(Line::from(spans));
             }
             let block = Block::default().borders(Borders::ALL);
             let paragraph = Paragraph::new(lines).block(block);
             f.render_widget(paragraph, chunks[0]);
 
-            let help = Line::from(vec![
-                Span::styled("keys: ", Style::default().fg(Color::DarkGray)),
-                Span::raw("Up/Down, Enter=select, r=refresh, Esc/Q=quit"),
-            ]);
+            let help = match filter {
+                Some(filter) => Line::from(vec![
+                    Span::styled("/", Style::default().fg(Color::Yellow)),
+                    Span::raw(filter),
+                    Span::styled("█", Style::default().fg(Color::DarkGray)),
+                ]),
+                None => Line::from(vec![
+                    Span::styled(Msg::KeysLabel.text(), Style::default().fg(Color::DarkGray)),
+                    Span::raw(Msg::SelectKeys.text()),
+                ]),
+            };
             let help_block = Paragraph::new(help).block(Block::default().borders(Borders::TOP));
             f.render_widget(help_block, chunks[1]);
         });
     });
 }
 
+fn draw_tabs(f: &mut ratatui::Frame<'_>, area: Rect, selected: Tab) {
+    let titles: Vec<Line> = Tab::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, t)| Line::raw(format!("{} {}", i + 1, t.title().text())))
+        .collect();
+    let tabs = Tabs::new(titles)
+        .select(selected.index())
+        .style(Style::default().fg(Color::DarkGray))
+        .highlight_style(Style::default().fg(Color::Yellow));
+    f.render_widget(tabs, area);
+}
+
 fn draw_status(
     f: &mut ratatui::Frame<'_>,
     area: Rect,
     label: &str,
     reason: &str,
-    auto_on: bool,
+    scene: &str,
+    mode: ControlMode,
     ducking_on: bool,
 ) {
     let auto_color = if ducking_on {
@@ -336,16 +718,33 @@ fn draw_status(
     } else {
         Color::DarkGray
     };
-    let auto_text = if auto_on { "ON" } else { "OFF" };
-    let line1 = Line::from(vec![
-        Span::styled("Selected: ", Style::default().fg(Color::DarkGray)),
+    let auto_text = match mode {
+        ControlMode::AutoVad => Msg::On,
+        ControlMode::Focus => Msg::Focus,
+        _ => Msg::Off,
+    }
+    .text();
+    let mut line1 = vec![
+        Span::styled(Msg::Selected.text(), Style::default().fg(Color::DarkGray)),
         Span::raw(label),
         Span::raw("    "),
-        Span::styled("Auto Ducking: ", Style::default().fg(Color::DarkGray)),
+        Span::styled(
+            Msg::AutoDucking.text(),
+            Style::default().fg(Color::DarkGray),
+        ),
         Span::styled(auto_text, Style::default().fg(auto_color)),
-    ]);
+    ];
+    if !scene.is_empty() {
+        line1.push(Span::raw("    "));
+        line1.push(Span::styled(
+            Msg::Scene.text(),
+            Style::default().fg(Color::DarkGray),
+        ));
+        line1.push(Span::styled(scene, Style::default().fg(Color::Cyan)));
+    }
+    let line1 = Line::from(line1);
     let line2 = Line::from(vec![
-        Span::styled("reason: ", Style::default().fg(Color::DarkGray)),
+        Span::styled(Msg::Reason.text(), Style::default().fg(Color::DarkGray)),
         Span::raw(reason),
     ]);
     let block = Block::default().borders(Borders::BOTTOM);
@@ -354,15 +753,22 @@ fn draw_status(
 }
 
 #[allow(clippy::cast_lossless)]
-fn draw_voice(f: &mut ratatui::Frame<'_>, area: Rect, level: f32, active: bool) {
+fn draw_voice(f: &mut ratatui::Frame<'_>, area: Rect, level: f32, active: bool, confidence: u8) {
     let color = if active {
         Color::Green
     } else {
         Color::DarkGray
     };
-    let label = if active { "ACTIVE" } else { "INACTIVE" };
+    let label = format!(
+        "{} {confidence}%",
+        if active { Msg::Active } else { Msg::Inactive }.text()
+    );
     let gauge = Gauge::default()
-        .block(Block::default().title("VOICE").borders(Borders::ALL))
+        .block(
+            Block::default()
+                .title(Msg::Voice.text())
+                .borders(Borders::ALL),
+        )
         .gauge_style(Style::default().fg(color))
         .ratio(level as f64)
         .label(Span::raw(label));
@@ -394,12 +800,20 @@ fn draw_controls(
         .split(area);
 
     let sens = Gauge::default()
-        .block(Block::default().title("Sensitivity").borders(Borders::ALL))
+        .block(
+            Block::default()
+                .title(Msg::Sensitivity.text())
+                .borders(Borders::ALL),
+        )
         .gauge_style(Style::default().fg(Color::Gray))
         .ratio(sens_fill as f64)
         .label(Span::raw(format!("{:.3}", threshold_live)));
     let duck = Gauge::default()
-        .block(Block::default().title("Duck Amount").borders(Borders::ALL))
+        .block(
+            Block::default()
+                .title(Msg::DuckAmount.text())
+                .borders(Borders::ALL),
+        )
         .gauge_style(Style::default().fg(Color::Yellow))
         .ratio(duck_fill as f64)
         .label(Span::raw(format!(
@@ -407,7 +821,11 @@ fn draw_controls(
             (1.0 - duck_factor_live) * 100.0
         )));
     let hold = Gauge::default()
-        .block(Block::default().title("Hold").borders(Borders::ALL))
+        .block(
+            Block::default()
+                .title(Msg::Hold.text())
+                .borders(Borders::ALL),
+        )
         .gauge_style(Style::default().fg(Color::Gray))
         .ratio(hold_fill as f64)
         .label(Span::raw(format!("{} ms", hold_ms)));
@@ -417,11 +835,202 @@ fn draw_controls(
     f.render_widget(hold, rows[2]);
 }
 
-fn draw_help(f: &mut ratatui::Frame<'_>, area: Rect) {
-    let line = Line::from(vec![
-        Span::styled("keys: ", Style::default().fg(Color::DarkGray)),
-        Span::raw("W/S=sens  A/D=duck  Q/E=hold  Space=auto  Esc/x=quit"),
-    ]);
+/// spectrum bands that fit the panel
+pub fn spectrum_bands() -> usize {
+    crossterm::terminal::size().map_or(80, |(w, _)| usize::from(w.saturating_sub(2)))
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn draw_spectrum(f: &mut ratatui::Frame<'_>, area: Rect, bands: &[f32]) {
+    let data: Vec<u64> = bands.iter().map(|b| (b * 100.0) as u64).collect();
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .title(Msg::Spectrum.text())
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::Cyan))
+        .max(100)
+        .data(&data);
+    f.render_widget(sparkline, area);
+}
+
+/// voice and duck rows over the last `TIMELINE_SPAN`, newest on the right
+///
+/// Gaps in the voice row between words show a flickering hold; a duck row
+/// running long after the voice row shows a hold that is too long.
+fn draw_timeline(
+    f: &mut ratatui::Frame<'_>,
+    area: Rect,
+    history: &VecDeque<(Instant, bool, bool)>,
+    now: Instant,
+) {
+    const LABEL: usize = 10;
+    let cols = usize::from(area.width.saturating_sub(2)).saturating_sub(LABEL);
+    let mut voice = vec![false; cols];
+    let mut ducked = vec![false; cols];
+    if cols > 0 {
+        let span = TIMELINE_SPAN.as_secs_f32();
+        for (at, v, d) in history {
+            let age = (now - *at).as_secs_f32();
+            let col = ((1.0 - age / span) * cols as f32) as usize;
+            let col = col.min(cols - 1);
+            voice[col] |= v;
+            ducked[col] |= d;
+        }
+    }
+    let row = |label: Msg, cells: &[bool], color: Color| {
+        let bar: String = cells.iter().map(|on| if *on { '█' } else { '·' }).collect();
+        Line::from(vec![
+            Span::styled(
+                format!("{:<LABEL$}", label.text()),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(bar, Style::default().fg(color)),
+        ])
+    };
+    let paragraph = Paragraph::new(vec![
+        row(Msg::Voice, &voice, Color::Green),
+        row(Msg::Ducked, &ducked, Color::Yellow),
+    ])
+    .block(
+        Block::default()
+            .title(Msg::Timeline.text())
+            .borders(Borders::ALL),
+    );
+    f.render_widget(paragraph, area);
+}
+
+fn draw_streams(f: &mut ratatui::Frame<'_>, area: Rect, groups: &[GroupRow]) {
+    let lines: Vec<Line> = groups
+        .iter()
+        .map(|g| {
+            let mut spans = vec![
+                Span::raw(format!("{:<36} ", g.label)),
+                Span::styled(
+                    messages::stream_count(g.streams),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(format!("{:<12}", g.state)),
+                Span::raw(format!("{:<16}", g.volume)),
+                Span::styled(g.sink.clone(), Style::default().fg(Color::DarkGray)),
+            ];
+            if g.overridden {
+                spans.push(Span::styled(
+                    Msg::Overridden.text(),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(Msg::Streams.text())
+            .borders(Borders::ALL),
+    );
+    f.render_widget(paragraph, area);
+}
+
+/// newest lines that fit, oldest first
+fn draw_log(f: &mut ratatui::Frame<'_>, area: Rect, log: &[String]) {
+    let rows = usize::from(area.height.saturating_sub(2));
+    let lines: Vec<Line> = log[log.len().saturating_sub(rows)..]
+        .iter()
+        .map(|l| Line::raw(l.as_str()))
+        .collect();
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(Msg::Log.text())
+            .borders(Borders::ALL),
+    );
+    f.render_widget(paragraph, area);
+}
+
+fn draw_settings(f: &mut ratatui::Frame<'_>, area: Rect, values: &[String], row: usize) {
+    let mut lines: Vec<Line> = SETTINGS
+        .iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, ((title, ..), value))| {
+            let (marker, style) = if i == row {
+                ("> ", Style::default().fg(Color::Yellow))
+            } else {
+                ("  ", Style::default())
+            };
+            Line::from(vec![
+                Span::styled(format!("{marker}{:<20}", title.text()), style),
+                Span::styled(value.clone(), style),
+            ])
+        })
+        .collect();
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(
+        Msg::SettingsKeys.text(),
+        Style::default().fg(Color::DarkGray),
+    ));
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(Msg::Settings.text())
+            .borders(Borders::ALL),
+    );
+    f.render_widget(paragraph, area);
+}
+
+fn draw_quit(f: &mut ratatui::Frame<'_>, area: Rect, quit: &QuitSummary) {
+    let mut lines = vec![Line::raw(messages::restoring(quit.restoring.len()))];
+    lines.extend(
+        quit.restoring
+            .iter()
+            .map(|app| Line::raw(format!("  {app}"))),
+    );
+    lines.push(Line::raw(""));
+    let failed_color = if quit.failed > 0 {
+        Color::Red
+    } else {
+        Color::DarkGray
+    };
+    lines.push(Line::styled(
+        messages::failed_restores(quit.failed),
+        Style::default().fg(failed_color),
+    ));
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(
+        Msg::QuitKeys.text(),
+        Style::default().fg(Color::Yellow),
+    ));
+    let width = area.width.min(60);
+    let height = u16::try_from(lines.len() + 2)
+        .unwrap_or(u16::MAX)
+        .min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(Msg::QuitTitle.text())
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(ClearWidget, popup);
+    f.render_widget(paragraph, popup);
+}
+
+fn draw_help(f: &mut ratatui::Frame<'_>, area: Rect, keys: Msg, prompt: Option<&str>) {
+    let line = match prompt {
+        Some(buf) => Line::from(vec![
+            Span::styled(":", Style::default().fg(Color::Yellow)),
+            Span::raw(buf),
+            Span::styled("█", Style::default().fg(Color::DarkGray)),
+        ]),
+        None => Line::from(vec![
+            Span::styled(Msg::KeysLabel.text(), Style::default().fg(Color::DarkGray)),
+            Span::raw(keys.text()),
+        ]),
+    };
     let paragraph = Paragraph::new(line).block(Block::default().borders(Borders::TOP));
     f.render_widget(paragraph, area);
 }
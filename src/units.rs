@@ -0,0 +1,200 @@
+/// longest accepted duration
+const MAX_MS: f64 = 60_000.0;
+
+/// known units, longest first
+const UNITS: &[&str] = &["db/s", "dbps", "db", "ms", "s", "%"];
+
+/// number and lowercase unit, e.g. `-35dB` -> (-35.0, "db")
+///
+/// Units are matched from the end, so exponents like `2e-2` stay numbers.
+fn split_unit(s: &str) -> Result<(f64, String), String> {
+    let s = s.trim();
+    let lower = s.to_ascii_lowercase();
+    let number = |n: &str| -> Result<f64, String> {
+        n.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("'{s}' is not a number"))
+    };
+    if let Ok(value) = number(&lower) {
+        return Ok((value, String::new()));
+    }
+    for unit in UNITS {
+        if let Some(value) = lower.strip_suffix(unit).and_then(|n| number(n).ok()) {
+            return Ok((value, (*unit).to_string()));
+        }
+    }
+    // report the trailing letters as the unit when the rest is a number
+    let at = lower
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%' || c == '/')
+        .len();
+    let (value, unit) = lower.split_at(at);
+    Ok((number(value)?, unit.trim().to_string()))
+}
+
+/// duration: `350`, `350ms` or `1.5s`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn millis(s: &str) -> Result<u64, String> {
+    let (value, unit) = split_unit(s)?;
+    let ms = match unit.as_str() {
+        "" | "ms" => value,
+        "s" => value * 1000.0,
+        other => return Err(format!("unknown unit '{other}', use ms or s")),
+    };
+    if !(0.0..=MAX_MS).contains(&ms) {
+        return Err(format!("'{s}' is out of range, use 0ms to 60s"));
+    }
+    Ok(ms.round() as u64)
+}
+
+/// RMS level: `0.02` or `-35dB`
+#[allow(clippy::cast_possible_truncation)]
+pub fn threshold(s: &str) -> Result<f32, String> {
+    let (value, unit) = split_unit(s)?;
+    let level = match unit.as_str() {
+        "" => value,
+        "db" => 10f64.powf(value / 20.0),
+        other => return Err(format!("unknown unit '{other}', use a level or dB")),
+    };
+    if level <= 0.0 || level > 1.0 {
+        return Err(format!(
+            "'{s}' is out of range, use a level above 0 up to 1 (0dB), e.g. 0.02 or -35dB"
+        ));
+    }
+    Ok(level as f32)
+}
+
+/// volume factor while ducked: `0.55`, `45%` (how much quieter) or `-10dB`
+#[allow(clippy::cast_possible_truncation)]
+pub fn duck_factor(s: &str) -> Result<f32, String> {
+    let (value, unit) = split_unit(s)?;
+    let factor = match unit.as_str() {
+        "" => value,
+        "%" if (0.0..=100.0).contains(&value) => 1.0 - value / 100.0,
+        "%" => return Err(format!("'{s}' is out of range, use 0% to 100%")),
+        "db" if value <= 0.0 => 10f64.powf(value / 20.0),
+        "db" => return Err(format!("'{s}' would make streams louder, use 0dB or less")),
+        other => return Err(format!("unknown unit '{other}', use a factor, % or dB")),
+    };
+    if !(0.0..=1.0).contains(&factor) {
+        return Err(format!(
+            "'{s}' is out of range, use a factor from 0 to 1, e.g. 0.55, 45% or -10dB"
+        ));
+    }
+    Ok(factor as f32)
+}
+
+/// node.latency request: `256/48000`, or `256` at 48 kHz
+pub fn quantum(s: &str) -> Result<String, String> {
+    let (frames, rate) = s.trim().split_once('/').unwrap_or((s.trim(), "48000"));
+    let parse = |v: &str| v.trim().parse::<u32>().ok().filter(|v| *v > 0);
+    match (parse(frames), parse(rate)) {
+        (Some(frames), Some(rate)) if frames <= 8192 => Ok(format!("{frames}/{rate}")),
+        (Some(_), Some(_)) => Err(format!("'{s}' is out of range, use at most 8192 frames")),
+        _ => Err(format!(
+            "'{s}' is not a quantum, use frames/rate, e.g. 256/48000"
+        )),
+    }
+}
+
+/// onset vote: `3/5`, 3 of the last 5 VAD ticks above threshold
+pub fn votes(s: &str) -> Result<(usize, usize), String> {
+    let bad = || format!("'{s}' is not a vote, use n/m, e.g. 3/5");
+    let (n, m) = s.trim().split_once('/').ok_or_else(bad)?;
+    let n: usize = n.trim().parse().map_err(|_| bad())?;
+    let m: usize = m.trim().parse().map_err(|_| bad())?;
+    if n == 0 || n > m || m > 20 {
+        return Err(format!("'{s}' is out of range, use 1 <= n <= m <= 20"));
+    }
+    Ok((n, m))
+}
+
+/// volume change limit: `20` or `20dB/s`
+#[allow(clippy::cast_possible_truncation)]
+pub fn gain_rate(s: &str) -> Result<f32, String> {
+    let (value, unit) = split_unit(s)?;
+    if !matches!(unit.as_str(), "" | "db/s" | "dbps") {
+        return Err(format!("unknown unit '{unit}', use dB/s"));
+    }
+    if !(1.0..=1000.0).contains(&value) {
+        return Err(format!("'{s}' is out of range, use 1 to 1000 dB/s"));
+    }
+    Ok(value as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn units_are_split_from_the_end() {
+        assert_eq!(split_unit("-35dB").unwrap(), (-35.0, "db".into()));
+        assert_eq!(split_unit(" 350 ms ").unwrap(), (350.0, "ms".into()));
+        assert_eq!(split_unit("1.5s").unwrap(), (1.5, "s".into()));
+        assert_eq!(split_unit("45%").unwrap(), (45.0, "%".into()));
+        assert_eq!(split_unit("20dB/s").unwrap(), (20.0, "db/s".into()));
+        assert_eq!(split_unit("2e-2").unwrap(), (0.02, String::new()));
+        assert_eq!(split_unit("1E-3").unwrap(), (0.001, String::new()));
+        assert_eq!(split_unit("1e3ms").unwrap(), (1000.0, "ms".into()));
+        assert_eq!(split_unit("3xyz").unwrap(), (3.0, "xyz".into()));
+        assert!(split_unit("abc").is_err());
+        assert!(split_unit("nan").is_err());
+        assert!(split_unit("inf").is_err());
+        assert!(split_unit("").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(millis("350"), Ok(350));
+        assert_eq!(millis("350ms"), Ok(350));
+        assert_eq!(millis("1.5s"), Ok(1500));
+        assert_eq!(millis("2e2"), Ok(200));
+        assert!(millis("61s").is_err());
+        assert!(millis("-1").is_err());
+        assert!(millis("3min").is_err());
+    }
+
+    #[test]
+    fn thresholds() {
+        assert!(close(threshold("0.02").unwrap(), 0.02));
+        assert!(close(threshold("2e-2").unwrap(), 0.02));
+        assert!(close(threshold("1e-3").unwrap(), 0.001));
+        assert!(close(threshold("-20dB").unwrap(), 0.1));
+        assert!(close(threshold("0dB").unwrap(), 1.0));
+        assert!(threshold("0").is_err());
+        assert!(threshold("1dB").is_err());
+        assert!(threshold("3ms").is_err());
+    }
+
+    #[test]
+    fn duck_factors() {
+        assert!(close(duck_factor("0.55").unwrap(), 0.55));
+        assert!(close(duck_factor("45%").unwrap(), 0.55));
+        assert!(close(duck_factor("-20dB").unwrap(), 0.1));
+        assert!(duck_factor("101%").is_err());
+        assert!(duck_factor("3dB").is_err());
+        assert!(duck_factor("1.5").is_err());
+        assert!(duck_factor("nan").is_err());
+    }
+
+    #[test]
+    fn quantums_votes_and_rates() {
+        assert_eq!(quantum("256"), Ok("256/48000".into()));
+        assert_eq!(quantum("128/44100"), Ok("128/44100".into()));
+        assert!(quantum("0").is_err());
+        assert!(quantum("9000/48000").is_err());
+        assert_eq!(votes("3/5"), Ok((3, 5)));
+        assert!(votes("6/5").is_err());
+        assert!(votes("0/5").is_err());
+        assert!(votes("3").is_err());
+        assert!(close(gain_rate("20").unwrap(), 20.0));
+        assert!(close(gain_rate("20dB/s").unwrap(), 20.0));
+        assert!(gain_rate("0.5").is_err());
+        assert!(gain_rate("20ms").is_err());
+    }
+}
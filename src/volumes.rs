@@ -0,0 +1,268 @@
+use crate::ducking::{is_balanced, mix_channels, VolumeBackend};
+use pipewire as pw;
+use pw::node::{Node, NodeListener};
+use pw::registry::{GlobalObject, Registry};
+use pw::spa::param::ParamType;
+use pw::spa::pod::deserialize::PodDeserializer;
+use pw::spa::pod::serialize::PodSerializer;
+use pw::spa::pod::{Object, Pod, Property, Value, ValueArray};
+use pw::spa::utils::dict::DictRef;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// volume change callback
+type OnChange = dyn Fn(u32, &NodeVolume);
+
+/// Props volume state
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NodeVolume {
+    /// wpctl scale
+    pub channels: Vec<f32>,
+    pub mute: bool,
+}
+
+/// bound node, kept alive for its listener
+struct Watched {
+    _node: Node,
+    _listener: NodeListener,
+}
+
+/// node volumes from Props params
+///
+/// Binds each watched node and subscribes to its Props, so volumes are known
+/// without running wpctl and changes made in other mixers show up right away.
+/// Channel volumes use the wpctl scale (cube root of the channel gain).
+pub struct VolumeWatch {
+    nodes: RefCell<HashMap<u32, Watched>>,
+    volumes: Rc<RefCell<HashMap<u32, NodeVolume>>>,
+    on_change: Rc<OnChange>,
+    /// shared with the native backend
+    native: Native,
+}
+
+/// in-process volume backend
+///
+/// For sandboxes (Flatpak, Snap) where wpctl and pactl cannot be run. Reads
+/// come from the Props the watch has seen, so a write only reads back once
+/// PipeWire has confirmed it; writes are queued and sent as Props params by
+/// `VolumeWatch::flush` on the main loop.
+#[derive(Debug, Clone, Default)]
+pub struct Native {
+    volumes: Arc<Mutex<HashMap<u32, NodeVolume>>>,
+    pending: Arc<Mutex<HashMap<u32, Vec<f32>>>>,
+    /// sent, not yet confirmed by a Props update
+    sent: Arc<Mutex<HashMap<u32, Vec<f32>>>>,
+}
+
+impl VolumeBackend for Native {
+    fn get(&self, id: u32) -> Option<f32> {
+        self.get_channels(id).map(|ch| mix_channels(&ch))
+    }
+
+    fn set(&self, id: u32, vol: f32) -> bool {
+        let n = self
+            .volumes
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map_or(1, |v| v.channels.len());
+        self.set_channels(id, &vec![vol; n])
+    }
+
+    fn get_channels(&self, id: u32) -> Option<Vec<f32>> {
+        self.volumes
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|v| v.channels.clone())
+    }
+
+    fn set_channels(&self, id: u32, channels: &[f32]) -> bool {
+        if !self.volumes.lock().unwrap().contains_key(&id) {
+            return false;
+        }
+        self.pending.lock().unwrap().insert(id, channels.to_vec());
+        true
+    }
+
+    /// queued together, sent in the same flush
+    fn set_group(&self, streams: &[(u32, Vec<f32>)]) -> bool {
+        let volumes = self.volumes.lock().unwrap();
+        let mut pending = self.pending.lock().unwrap();
+        let mut ok = true;
+        for (id, channels) in streams {
+            match volumes.get(id) {
+                Some(v) if is_balanced(channels) => {
+                    let vol = channels.first().copied().unwrap_or(0.0);
+                    pending.insert(*id, vec![vol; v.channels.len().max(1)]);
+                }
+                Some(_) => {
+                    pending.insert(*id, channels.clone());
+                }
+                None => ok = false,
+            }
+        }
+        ok
+    }
+
+    fn is_muted(&self, id: u32) -> bool {
+        self.volumes
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|v| v.mute)
+    }
+}
+
+impl VolumeWatch {
+    pub fn new(on_change: impl Fn(u32, &NodeVolume) + 'static) -> Self {
+        Self {
+            nodes: RefCell::new(HashMap::new()),
+            volumes: Rc::new(RefCell::new(HashMap::new())),
+            on_change: Rc::new(on_change),
+            native: Native::default(),
+        }
+    }
+
+    /// bind and subscribe to Props
+    pub fn watch(&self, registry: &Registry, global: &GlobalObject<&DictRef>) {
+        let id = global.id;
+        if self.nodes.borrow().contains_key(&id) {
+            return;
+        }
+        let node: Node = match registry.bind(global) {
+            Ok(node) => node,
+            Err(err) => {
+                debug!("bind failed: id={id}: {err}");
+                return;
+            }
+        };
+        let volumes = self.volumes.clone();
+        let native = self.native.volumes.clone();
+        let sent = self.native.sent.clone();
+        let on_change = self.on_change.clone();
+        let listener = node
+            .add_listener_local()
+            .param(move |_seq, kind, _index, _next, pod| {
+                if kind != ParamType::Props {
+                    return;
+                }
+                let Some(vol) = pod.and_then(node_volume) else {
+                    return;
+                };
+                native.lock().unwrap().insert(id, vol.clone());
+                {
+                    let mut sent = sent.lock().unwrap();
+                    if sent
+                        .get(&id)
+                        .is_some_and(|want| same_volume(want, &vol.channels))
+                    {
+                        sent.remove(&id);
+                    }
+                }
+                let prev = volumes.borrow_mut().insert(id, vol.clone());
+                if prev.as_ref() == Some(&vol) {
+                    return;
+                }
+                debug!("props: id={id} {vol:?}");
+                on_change(id, &vol);
+            })
+            .register();
+        node.subscribe_params(&[ParamType::Props]);
+        self.nodes.borrow_mut().insert(
+            id,
+            Watched {
+                _node: node,
+                _listener: listener,
+            },
+        );
+    }
+
+    pub fn forget(&self, id: u32) {
+        self.nodes.borrow_mut().remove(&id);
+        self.volumes.borrow_mut().remove(&id);
+        self.native.volumes.lock().unwrap().remove(&id);
+        self.native.pending.lock().unwrap().remove(&id);
+        self.native.sent.lock().unwrap().remove(&id);
+    }
+
+    /// backend that sets volumes through the watched nodes
+    pub fn native(&self) -> Native {
+        self.native.clone()
+    }
+
+    /// send the native backend's queued volumes
+    pub fn flush(&self) {
+        let pending = std::mem::take(&mut *self.native.pending.lock().unwrap());
+        let nodes = self.nodes.borrow();
+        for (id, channels) in pending {
+            let Some(watched) = nodes.get(&id) else {
+                continue;
+            };
+            let channels: Vec<f32> = channels.iter().map(|c| c.clamp(0.0, 1.5)).collect();
+            let gains = channels.iter().map(|c| c.powi(3)).collect();
+            let props = Value::Object(Object {
+                type_: pw::spa::utils::SpaTypes::ObjectParamProps.as_raw(),
+                id: ParamType::Props.as_raw(),
+                properties: vec![Property::new(
+                    pw::spa::sys::SPA_PROP_channelVolumes,
+                    Value::ValueArray(ValueArray::Float(gains)),
+                )],
+            });
+            let bytes = match PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &props) {
+                Ok((cursor, _)) => cursor.into_inner(),
+                Err(err) => {
+                    warn!("props for id={id}: {err}");
+                    continue;
+                }
+            };
+            if let Some(pod) = Pod::from_bytes(&bytes) {
+                watched._node.set_param(ParamType::Props, 0, pod);
+                self.native.sent.lock().unwrap().insert(id, channels);
+            }
+        }
+    }
+
+    /// every native write sent and confirmed
+    pub fn settled(&self) -> bool {
+        self.native.pending.lock().unwrap().is_empty()
+            && self.native.sent.lock().unwrap().is_empty()
+    }
+
+    /// last reported volume
+    pub fn volume(&self, id: u32) -> Option<f32> {
+        self.volumes
+            .borrow()
+            .get(&id)
+            .map(|v| mix_channels(&v.channels))
+    }
+}
+
+/// channelVolumes and mute of a Props pod
+fn node_volume(pod: &Pod) -> Option<NodeVolume> {
+    let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes()).ok()?;
+    let Value::Object(obj) = value else {
+        return None;
+    };
+    let mut vol = NodeVolume::default();
+    for p in obj.properties {
+        match p.value {
+            Value::ValueArray(ValueArray::Float(v))
+                if p.key == pw::spa::sys::SPA_PROP_channelVolumes =>
+            {
+                vol.channels = v.into_iter().map(f32::cbrt).collect();
+            }
+            Value::Bool(mute) if p.key == pw::spa::sys::SPA_PROP_mute => vol.mute = mute,
+            _ => {}
+        }
+    }
+    (!vol.channels.is_empty()).then_some(vol)
+}
+
+/// Props echo of a write, within rounding
+fn same_volume(a: &[f32], b: &[f32]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 0.01)
+}
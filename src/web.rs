@@ -0,0 +1,163 @@
+use crate::control::{self, Control};
+use crate::ipc::Status;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const PAGE: &str = include_str!("web.html");
+/// largest accepted request body
+const MAX_BODY: usize = 256;
+/// per read or write on a client connection
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// serve the web UI
+///
+/// `GET /` is the page, `GET /status` the same JSON as the `status` socket
+/// command and `POST /control` takes one socket command (`toggle`,
+/// `set threshold 0.01`, ...) as body.
+pub fn spawn_server(addr: &str, tx: Sender<Control>, status: Arc<Mutex<Status>>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let port = listener.local_addr()?.port();
+    info!("web ui on http://{}/", listener.local_addr()?);
+    thread::Builder::new()
+        .name("pw-duck-web".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                let status = Arc::clone(&status);
+                // one thread per client, an idle one times out on its own
+                let spawned = thread::Builder::new()
+                    .name("pw-duck-web-client".into())
+                    .spawn(move || {
+                        if let Err(err) = handle_request(stream, port, &tx, &status) {
+                            debug!("web request failed: {err}");
+                        }
+                    });
+                if let Err(err) = spawned {
+                    warn!("web client thread: {err}");
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// loopback name and our port
+///
+/// Anything else is a DNS-rebinding page that resolved its own name to
+/// 127.0.0.1, whose Origin matches its Host.
+fn allowed_host(host: &str, port: u16) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, p)) if !name.is_empty() && !p.contains(']') => {
+            if p.parse() != Ok(port) {
+                return false;
+            }
+            name
+        }
+        _ if port == 80 => host,
+        _ => return false,
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+fn handle_request(
+    mut stream: TcpStream,
+    port: u16,
+    tx: &Sender<Control>,
+    status: &Mutex<Status>,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut length = 0;
+    let (mut host, mut origin) = (String::new(), None);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("host") {
+                host = value.to_string();
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.to_string());
+            }
+        }
+    }
+    let mut words = request_line.split_whitespace();
+    let (status_line, content_type, body) = match (words.next(), words.next()) {
+        _ if !allowed_host(&host, port) => (
+            "403 Forbidden",
+            "text/plain",
+            "unexpected host\n".to_string(),
+        ),
+        (Some("GET"), Some("/")) => ("200 OK", "text/html; charset=utf-8", PAGE.to_string()),
+        (Some("GET"), Some("/status")) => (
+            "200 OK",
+            "application/json",
+            serde_json::to_string(&*status.lock().unwrap()).unwrap_or_default(),
+        ),
+        // other sites' pages may post to localhost too
+        (Some("POST"), Some("/control"))
+            if origin.is_some_and(|o| o.split_once("://").map(|(_, h)| h) != Some(&host)) =>
+        {
+            (
+                "403 Forbidden",
+                "text/plain",
+                "cross-origin request\n".to_string(),
+            )
+        }
+        (Some("POST"), Some("/control")) if length <= MAX_BODY => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            match control::parse(String::from_utf8_lossy(&body).trim()) {
+                Ok(ctl) => {
+                    debug!("web control: {ctl:?}");
+                    if tx.send(ctl).is_ok() {
+                        ("200 OK", "text/plain", "ok\n".to_string())
+                    } else {
+                        (
+                            "503 Service Unavailable",
+                            "text/plain",
+                            "main loop gone\n".to_string(),
+                        )
+                    }
+                }
+                Err(err) => ("400 Bad Request", "text/plain", format!("{err}\n")),
+            }
+        }
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status_line}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::allowed_host;
+
+    #[test]
+    fn only_loopback_hosts() {
+        assert!(allowed_host("127.0.0.1:9978", 9978));
+        assert!(allowed_host("localhost:9978", 9978));
+        assert!(allowed_host("[::1]:9978", 9978));
+        assert!(!allowed_host("localhost:9979", 9978));
+        assert!(!allowed_host("evil.example:9978", 9978));
+        assert!(!allowed_host("localhost", 9978));
+        assert!(!allowed_host("[::1]", 9978));
+        assert!(!allowed_host("", 9978));
+        assert!(allowed_host("localhost", 80));
+    }
+}